@@ -0,0 +1,108 @@
+//! This benchmark measures what cloning and dropping handles costs, and
+//! how much of that is the `Arc` every handle and contract holds. It
+//! compares `Arc` with a minimal strong-only reference count, which is
+//! the most a slimmer shared-state handle could save.
+//!
+//! Run it with `cargo bench --bench handles`.
+
+extern crate reqchan as chan;
+
+use std::hint::black_box;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 1_000_000;
+
+fn main() {
+    let arc = Arc::new([0u64; 64]);
+    let counted = Counted::new([0u64; 64]);
+    assert_eq!(arc[0], counted[0]);
+
+    let clone_arc = time(|| drop(black_box(arc.clone())));
+    let clone_counted = time(|| drop(black_box(counted.clone())));
+
+    report("pointer clone + drop", &[("Arc", clone_arc), ("strong-only", clone_counted)]);
+
+    let (requester, responder) = chan::channel::<u32>();
+
+    let clone_responder = time(|| drop(black_box(responder.clone())));
+    let downgrade = time(|| drop(black_box(responder.downgrade())));
+    let round_trip = time(|| {
+        let mut contract = requester.try_request().ok().unwrap();
+        responder.try_respond().ok().unwrap().send(1);
+        contract.try_receive().ok().unwrap();
+    });
+
+    report("channel", &[("Responder clone + drop", clone_responder),
+                        ("Responder downgrade + drop", downgrade),
+                        ("request round trip", round_trip)]);
+}
+
+/// This function returns how long `f` takes to run `ROUNDS` times.
+fn time<F>(mut f: F) -> Duration
+    where F: FnMut() {
+    let start = Instant::now();
+
+    for _ in 0..ROUNDS {
+        f();
+    }
+
+    start.elapsed()
+}
+
+fn report(name: &str, results: &[(&str, Duration)]) {
+    let per_round = |d: Duration| d.as_secs_f64() * 1e9 / ROUNDS as f64;
+
+    let results: Vec<String> = results.iter()
+        .map(|&(what, d)| format!("{} {:.1} ns/round", what, per_round(d)))
+        .collect();
+
+    println!("{}: {}", name, results.join(", "));
+}
+
+/// This is a thin pointer to a value with a strong count and nothing
+/// else: no weak count, and no support for weak pointers.
+struct Counted<T> {
+    ptr: NonNull<CountedBox<T>>,
+}
+
+struct CountedBox<T> {
+    strong: AtomicUsize,
+    value: T,
+}
+
+impl<T> Counted<T> {
+    fn new(value: T) -> Counted<T> {
+        let boxed = Box::new(CountedBox { strong: AtomicUsize::new(1), value });
+
+        Counted { ptr: NonNull::from(Box::leak(boxed)) }
+    }
+}
+
+impl<T> Deref for Counted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &unsafe { self.ptr.as_ref() }.value
+    }
+}
+
+impl<T> Clone for Counted<T> {
+    fn clone(&self) -> Self {
+        unsafe { self.ptr.as_ref() }.strong.fetch_add(1, Ordering::Relaxed);
+
+        Counted { ptr: self.ptr }
+    }
+}
+
+impl<T> Drop for Counted<T> {
+    fn drop(&mut self) {
+        if unsafe { self.ptr.as_ref() }.strong.fetch_sub(1, Ordering::Release) == 1 {
+            fence(Ordering::Acquire);
+            drop(unsafe { Box::from_raw(self.ptr.as_ptr()) });
+        }
+    }
+}
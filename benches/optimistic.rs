@@ -0,0 +1,97 @@
+//! This benchmark compares `channel()` with `channel_optimistic()` when
+//! responders prepare their data at uneven speeds. Three responders poll
+//! on their own threads, and each spins for a different number of steps
+//! to prepare its datum. With `channel()` whichever responder claims a
+//! request first makes the requester wait for it to prepare; with
+//! `channel_optimistic()` the first datum prepared serves the request.
+//!
+//! Run it with `cargo bench --bench optimistic`.
+
+extern crate reqchan as chan;
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 20_000;
+const STEPS: [u32; 3] = [10, 1_000, 10_000];
+
+fn main() {
+    let locked = run(|stop| {
+        let (requester, responder) = chan::channel::<u32>();
+
+        let workers = STEPS.iter().map(|&steps| {
+            let (responder, stop) = (responder.clone(), stop.clone());
+
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match responder.try_respond() {
+                        Ok(contract) => { contract.send(prepare(steps)); },
+                        Err(_) => { thread::yield_now(); },
+                    }
+                }
+            })
+        }).collect();
+
+        (requester, workers)
+    });
+
+    let optimistic = run(|stop| {
+        let (requester, responder) = chan::channel_optimistic::<u32>();
+
+        let workers = STEPS.iter().map(|&steps| {
+            let (responder, stop) = (responder.clone(), stop.clone());
+
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match responder.try_respond() {
+                        Ok(contract) => { let _ = contract.send(prepare(steps)); },
+                        Err(_) => { thread::yield_now(); },
+                    }
+                }
+            })
+        }).collect();
+
+        (requester, workers)
+    });
+
+    let per_round = |d: Duration| d.as_secs_f64() * 1e9 / ROUNDS as f64;
+
+    println!("responders of uneven speed: channel() {:.1} ns/round, \
+              channel_optimistic() {:.1} ns/round",
+             per_round(locked), per_round(optimistic));
+}
+
+/// This function stands in for the work of preparing a datum.
+fn prepare(steps: u32) -> u32 {
+    (0..steps).fold(0, |acc, step| black_box(acc ^ step))
+}
+
+/// This function starts the responders `make` spawns, then makes a
+/// request and polls for its answer `ROUNDS` times.
+fn run<F>(make: F) -> Duration
+    where F: FnOnce(&Arc<AtomicBool>) -> (chan::Requester<u32>, Vec<thread::JoinHandle<()>>) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (requester, workers) = make(&stop);
+
+    let start = Instant::now();
+
+    for _ in 0..ROUNDS {
+        let mut contract = requester.try_request().ok().unwrap();
+
+        while contract.try_receive().is_err() {
+            thread::yield_now();
+        }
+    }
+
+    let elapsed = start.elapsed();
+
+    stop.store(true, Ordering::Relaxed);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    elapsed
+}
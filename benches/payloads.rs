@@ -0,0 +1,96 @@
+//! This benchmark shows what a round trip costs for payloads of different
+//! shapes: a `u32`, a `Box<u64>`, and a `Task`, the boxed closure the docs
+//! hand between workers. Each round also makes and drops its payload, so
+//! the benchmark times that alone too, and reports the handoff as the
+//! difference.
+//!
+//! It then times round trips through the backend handles, comparing
+//! `AtomicBackend`, the regular channel's state machine, with the
+//! backends specialized for a payload's shape: `SmallBackend`, which packs
+//! a `u32` into the state, and `BoxedBackend`, which hands a `Box<u64>` or
+//! a boxed `Task` over as a single pointer.
+//!
+//! Run it with `cargo bench --bench payloads`.
+
+extern crate reqchan as chan;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use chan::{AtomicBackend, BackendRequester, BackendResponder, BoxedBackend, ChannelBackend,
+           SmallBackend, Task};
+
+const ROUNDS: u32 = 1_000_000;
+
+fn main() {
+    report("u32", |i| i, |num| { black_box(num); });
+    report("Box<u64>", |i| Box::new(i as u64), |num| { black_box(num); });
+    report("Task", |i| Task::new(move || { black_box(i); }), |task| task.run());
+
+    let per_round = |d: Duration| d.as_secs_f64() * 1e9 / ROUNDS as f64;
+
+    let make_num = |i| i;
+    println!("u32 through backend handles: AtomicBackend {:.1} ns/round, SmallBackend {:.1} ns/round",
+             per_round(backend_round_trip::<_, AtomicBackend<u32>, _>(make_num)),
+             per_round(backend_round_trip::<_, SmallBackend<u32>, _>(make_num)));
+
+    let make_box = |i| Box::new(i as u64);
+    println!("Box<u64> through backend handles: AtomicBackend {:.1} ns/round, BoxedBackend {:.1} ns/round",
+             per_round(backend_round_trip::<_, AtomicBackend<Box<u64>>, _>(make_box)),
+             per_round(backend_round_trip::<_, BoxedBackend<u64>, _>(make_box)));
+
+    let make_task = |i| Box::new(Task::new(move || { black_box(i); }));
+    println!("Box<Task> through backend handles: AtomicBackend {:.1} ns/round, BoxedBackend {:.1} ns/round",
+             per_round(backend_round_trip::<_, AtomicBackend<Box<Task>>, _>(make_task)),
+             per_round(backend_round_trip::<_, BoxedBackend<Task>, _>(make_task)));
+}
+
+/// This function returns how long `ROUNDS` round trips of payloads `make`
+/// creates take on a channel with the backend `B`, on the calling thread.
+fn backend_round_trip<T, B, M>(mut make: M) -> Duration
+    where B: ChannelBackend<T>,
+          M: FnMut(u32) -> T {
+    let (requester, responder): (BackendRequester<T, B>, BackendResponder<T, B>) =
+        chan::channel_with_backend();
+
+    time(|i| {
+        let mut contract = requester.try_request().ok().unwrap();
+        responder.try_respond().ok().unwrap().send(make(i));
+        black_box(contract.try_receive().ok().unwrap());
+    })
+}
+
+/// This function times round trips of payloads `make` creates and `used`
+/// consumes, on the calling thread, and prints the result.
+fn report<T, M, U>(name: &str, mut make: M, mut used: U)
+    where M: FnMut(u32) -> T,
+          U: FnMut(T) {
+    let (requester, responder) = chan::channel::<T>();
+
+    let alone = time(|i| used(make(i)));
+    let round_trip = time(|i| {
+        let mut contract = requester.try_request().ok().unwrap();
+        responder.try_respond().ok().unwrap().send(make(i));
+        used(contract.try_receive().ok().unwrap());
+    });
+
+    let per_round = |d: Duration| d.as_secs_f64() * 1e9 / ROUNDS as f64;
+
+    println!("{}: round trip {:.1} ns/round, payload alone {:.1} ns/round, handoff {:.1} ns/round",
+             name,
+             per_round(round_trip),
+             per_round(alone),
+             per_round(round_trip) - per_round(alone));
+}
+
+/// This function returns how long `f` takes to run `ROUNDS` times.
+fn time<F>(mut f: F) -> Duration
+    where F: FnMut(u32) {
+    let start = Instant::now();
+
+    for i in 0..ROUNDS {
+        f(i);
+    }
+
+    start.elapsed()
+}
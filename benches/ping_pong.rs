@@ -0,0 +1,101 @@
+//! This benchmark compares `channel()` with `channel_spsc()` on a
+//! ping-pong workload: the requester issues a request, the only responder
+//! answers it, and the requester receives the answer before the next round.
+//!
+//! Run it with `cargo bench --bench ping_pong`.
+//!
+//! It does not compare the channel's `Acquire`/`Release` orderings with
+//! `SeqCst`, and no ARM results have been collected for it.
+
+extern crate reqchan as chan;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 1_000_000;
+
+fn main() {
+    let (requester, responder) = chan::channel::<u32>();
+    let general = same_thread_with(&requester, || responder.try_respond());
+
+    let (requester, responder) = chan::channel_spsc::<u32>();
+    let spsc = same_thread_with(&requester, || responder.try_respond());
+
+    report("same thread", general, spsc);
+
+    let general = two_threads(|| {
+        let (requester, responder) = chan::channel::<u32>();
+        (requester, Box::new(move || responder.try_respond()) as Respond)
+    });
+    let spsc = two_threads(|| {
+        let (requester, responder) = chan::channel_spsc::<u32>();
+        (requester, Box::new(move || responder.try_respond()) as Respond)
+    });
+
+    report("two threads", general, spsc);
+}
+
+type Respond = Box<dyn FnMut() -> chan::Result<chan::ResponseContract<u32>> + Send>;
+
+/// This function runs both ends on the calling thread, so it measures
+/// only the cost of the channel's operations.
+fn same_thread_with<F>(requester: &chan::Requester<u32>, mut respond: F) -> Duration
+    where F: FnMut() -> chan::Result<chan::ResponseContract<u32>> {
+    let start = Instant::now();
+
+    for i in 0..ROUNDS {
+        let mut contract = requester.try_request().ok().unwrap();
+        respond().ok().unwrap().send(i);
+        assert_eq!(contract.try_receive().ok().unwrap(), i);
+    }
+
+    start.elapsed()
+}
+
+/// This function runs the responder on its own thread, polling for
+/// requests, while the calling thread polls for the responses. Both
+/// yield between polls, so the benchmark also runs on a single core.
+fn two_threads<F>(make: F) -> Duration
+    where F: FnOnce() -> (chan::Requester<u32>, Respond) {
+    let (requester, mut respond) = make();
+
+    let handle = thread::spawn(move || {
+        let mut sent = 0;
+
+        while sent < ROUNDS {
+            if let Ok(contract) = respond() {
+                contract.send(sent);
+                sent += 1;
+            }
+            else {
+                thread::yield_now();
+            }
+        }
+    });
+
+    let start = Instant::now();
+
+    for i in 0..ROUNDS {
+        let mut contract = requester.try_request().ok().unwrap();
+
+        loop {
+            if let Ok(num) = contract.try_receive() {
+                assert_eq!(num, i);
+                break;
+            }
+
+            thread::yield_now();
+        }
+    }
+
+    let elapsed = start.elapsed();
+    handle.join().unwrap();
+    elapsed
+}
+
+fn report(name: &str, general: Duration, spsc: Duration) {
+    let per_round = |d: Duration| d.as_secs_f64() * 1e9 / ROUNDS as f64;
+
+    println!("{}: channel() {:.1} ns/round, channel_spsc() {:.1} ns/round",
+             name, per_round(general), per_round(spsc));
+}
@@ -0,0 +1,53 @@
+//! This benchmark shows what `Responder::has_request()` saves a worker
+//! that polls many channels. One worker serves 16 channels, but only one
+//! of them ever has a request. It either calls `Responder::try_respond()`
+//! on every channel, or only on the channels `Responder::has_request()`
+//! says look live.
+//!
+//! Run it with `cargo bench --bench poll_many`.
+
+extern crate reqchan as chan;
+
+use std::time::{Duration, Instant};
+
+const CHANNELS: usize = 16;
+const BUSY: usize = 7;
+const ROUNDS: u32 = 200_000;
+
+fn main() {
+    let plain = run(false);
+    let peeking = run(true);
+
+    let per_round = |d: Duration| d.as_secs_f64() * 1e9 / ROUNDS as f64;
+
+    println!("{} channels, 1 busy: try_respond() {:.1} ns/round, \
+              has_request() first {:.1} ns/round",
+             CHANNELS, per_round(plain), per_round(peeking));
+}
+
+/// This function makes a request on the busy channel, sweeps every
+/// channel once to serve it, and receives the answer, `ROUNDS` times.
+fn run(peek: bool) -> Duration {
+    let (requesters, responders): (Vec<_>, Vec<_>) =
+        (0..CHANNELS).map(|_| chan::channel::<u32>()).unzip();
+
+    let start = Instant::now();
+
+    for i in 0..ROUNDS {
+        let mut contract = requesters[BUSY].try_request().ok().unwrap();
+
+        for responder in responders.iter() {
+            if peek && !responder.has_request() {
+                continue;
+            }
+
+            if let Ok(contract) = responder.try_respond() {
+                contract.send(i);
+            }
+        }
+
+        assert_eq!(contract.try_receive().ok().unwrap(), i);
+    }
+
+    start.elapsed()
+}
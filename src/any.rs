@@ -0,0 +1,340 @@
+//! This module defines type-erased handles to a channel, so channels of
+//! different payload types can sit in one collection. They wrap the
+//! ordinary `Requester` and `Responder` and check the payload type at run
+//! time, where the generic handles check it at compile time.
+//!
+//! A datum of the wrong type is never sent or received. `AnyResponseContract`
+//! hands it back, and `AnyRequestContract` refuses to downcast to a type
+//! the channel does not carry before it receives anything.
+
+use std::any::{self, Any, TypeId};
+use std::result;
+
+use super::{Error, RequestContract, Requester, ResponseContract, Responder, Result, SendError};
+
+/// This is a boxed datum of any type.
+pub type AnyDatum = Box<dyn Any + Send>;
+
+/// This is the part of `Requester<T>` that does not depend on `T`.
+#[doc(hidden)]
+trait ErasedRequester: Send + Sync {
+    fn try_request(&self) -> Result<Box<dyn ErasedRequestContract>>;
+}
+
+impl<T: Send + Any> ErasedRequester for Requester<T> {
+    fn try_request(&self) -> Result<Box<dyn ErasedRequestContract>> {
+        let contract = Requester::try_request(self)?;
+        Ok(Box::new(contract))
+    }
+}
+
+/// This is the part of `RequestContract<T>` that does not depend on `T`.
+#[doc(hidden)]
+trait ErasedRequestContract: Send {
+    fn try_receive(&mut self) -> Result<AnyDatum>;
+    fn try_cancel(&mut self) -> Result<()>;
+}
+
+impl<T: Send + Any> ErasedRequestContract for RequestContract<T> {
+    fn try_receive(&mut self) -> Result<AnyDatum> {
+        let datum = RequestContract::try_receive(self)?;
+        Ok(Box::new(datum))
+    }
+
+    fn try_cancel(&mut self) -> Result<()> {
+        RequestContract::try_cancel(self)
+    }
+}
+
+/// This is the part of `Responder<T>` that does not depend on `T`.
+#[doc(hidden)]
+trait ErasedResponder: Send + Sync {
+    fn try_respond(&self) -> Result<Box<dyn ErasedResponseContract>>;
+}
+
+impl<T: Send + Any> ErasedResponder for Responder<T> {
+    fn try_respond(&self) -> Result<Box<dyn ErasedResponseContract>> {
+        let contract = Responder::try_respond(self)?;
+        Ok(Box::new(contract))
+    }
+}
+
+/// This is the part of `ResponseContract<T>` that does not depend on `T`.
+#[doc(hidden)]
+trait ErasedResponseContract: Send {
+    fn try_send(&mut self, datum: AnyDatum) -> result::Result<(), AnyDatum>;
+}
+
+impl<T: Send + Any> ErasedResponseContract for ResponseContract<T> {
+    fn try_send(&mut self, datum: AnyDatum) -> result::Result<(), AnyDatum> {
+        let datum = datum.downcast::<T>()?;
+
+        ResponseContract::try_send(self, *datum)
+            .map_err(|SendError(datum)| Box::new(datum) as AnyDatum)
+    }
+}
+
+/// This is the payload type of a type-erased channel.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+struct Payload {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl Payload {
+    #[inline]
+    fn of<T: Any>() -> Payload {
+        Payload {
+            id: TypeId::of::<T>(),
+            name: any::type_name::<T>(),
+        }
+    }
+}
+
+/// This is a `Requester` whose payload type is only known at run time.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (numbers, number_responder) = chan::channel::<u32>();
+/// let (names, name_responder) = chan::channel::<String>();
+///
+/// let requesters = vec![numbers.into_any(), names.into_any()];
+/// let mut contracts: Vec<_> = requesters.iter()
+///     .map(|requester| requester.try_request().ok().unwrap())
+///     .collect();
+///
+/// number_responder.try_respond().ok().unwrap().send(4);
+/// name_responder.try_respond().ok().unwrap().send("four".to_string());
+///
+/// assert!(requesters[0].is::<u32>());
+/// assert_eq!(contracts[0].try_receive_downcast::<u32>().ok().unwrap(), 4);
+/// assert_eq!(contracts[1].try_receive_downcast::<String>().ok().unwrap(), "four");
+/// ```
+pub struct AnyRequester {
+    requester: Box<dyn ErasedRequester>,
+    payload: Payload,
+}
+
+impl AnyRequester {
+    /// This function erases the payload type of a `Requester`.
+    pub fn new<T: Send + Any>(requester: Requester<T>) -> AnyRequester {
+        AnyRequester {
+            requester: Box::new(requester),
+            payload: Payload::of::<T>(),
+        }
+    }
+
+    /// This method returns whether the channel carries `T`s.
+    #[inline]
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.id == TypeId::of::<T>()
+    }
+
+    /// This method returns the name of the type the channel carries, for
+    /// diagnostics.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.payload.name
+    }
+
+    /// This method works just like `Requester::try_request()`.
+    pub fn try_request(&self) -> Result<AnyRequestContract> {
+        Ok(AnyRequestContract {
+            contract: self.requester.try_request()?,
+            payload: self.payload,
+        })
+    }
+}
+
+/// This is the contract returned by `AnyRequester::try_request()`. It
+/// wraps a `RequestContract`, and panics just like one if it is dropped
+/// before it receives its datum.
+pub struct AnyRequestContract {
+    contract: Box<dyn ErasedRequestContract>,
+    payload: Payload,
+}
+
+impl AnyRequestContract {
+    /// This method works just like `RequestContract::try_receive()`, but
+    /// returns the datum boxed.
+    pub fn try_receive(&mut self) -> Result<AnyDatum> {
+        self.contract.try_receive()
+    }
+
+    /// This method works just like `RequestContract::try_receive()`, if
+    /// the channel carries `T`s. Otherwise it returns
+    /// `Err(Error::WrongType)` without touching the request.
+    pub fn try_receive_downcast<T: Any>(&mut self) -> Result<T> {
+        if self.payload.id != TypeId::of::<T>() {
+            return Err(Error::WrongType);
+        }
+
+        let datum = self.contract.try_receive()?;
+
+        // The check above makes this infallible.
+        match datum.downcast::<T>() {
+            Ok(datum) => Ok(*datum),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// This method works just like `RequestContract::try_cancel()`.
+    pub fn try_cancel(&mut self) -> Result<()> {
+        self.contract.try_cancel()
+    }
+}
+
+/// This is a `Responder` whose payload type is only known at run time.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel::<u32>();
+/// let responder = responder.into_any();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// let mut response = responder.try_respond_boxed().ok().unwrap();
+///
+/// // A datum of the wrong type comes back, and the contract stays open.
+/// let rejected = response.send(Box::new("four")).err().unwrap().0;
+/// assert_eq!(*rejected.downcast::<&str>().ok().unwrap(), "four");
+///
+/// response.send(Box::new(4u32)).ok().unwrap();
+/// assert_eq!(contract.try_receive().ok().unwrap(), 4);
+/// ```
+pub struct AnyResponder {
+    responder: Box<dyn ErasedResponder>,
+    payload: Payload,
+}
+
+impl AnyResponder {
+    /// This function erases the payload type of a `Responder`.
+    pub fn new<T: Send + Any>(responder: Responder<T>) -> AnyResponder {
+        AnyResponder {
+            responder: Box::new(responder),
+            payload: Payload::of::<T>(),
+        }
+    }
+
+    /// This method returns whether the channel carries `T`s.
+    #[inline]
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.id == TypeId::of::<T>()
+    }
+
+    /// This method returns the name of the type the channel carries, for
+    /// diagnostics.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.payload.name
+    }
+
+    /// This method works just like `Responder::try_respond()`, but
+    /// returns a contract that takes boxed data.
+    pub fn try_respond_boxed(&self) -> Result<AnyResponseContract> {
+        Ok(AnyResponseContract {
+            contract: self.responder.try_respond()?,
+        })
+    }
+}
+
+/// This is the contract returned by `AnyResponder::try_respond_boxed()`.
+/// It wraps a `ResponseContract`, and panics just like one if it is
+/// dropped before it sends a datum.
+pub struct AnyResponseContract {
+    contract: Box<dyn ErasedResponseContract>,
+}
+
+impl AnyResponseContract {
+    /// This method sends `datum` if it holds the type the channel
+    /// carries. Otherwise it hands `datum` back and the contract stays
+    /// open, so the caller can still send a datum of the right type. It
+    /// also hands `datum` back whenever `ResponseContract::try_send()`
+    /// would.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The boxed item(s) to send
+    pub fn send(&mut self, datum: AnyDatum) -> result::Result<(), SendError<AnyDatum>> {
+        self.contract.try_send(datum).map_err(SendError)
+    }
+}
+
+impl<T: Send + Any> Requester<T> {
+    /// This method turns this `Requester` into an `AnyRequester`.
+    pub fn into_any(self) -> AnyRequester {
+        AnyRequester::new(self)
+    }
+}
+
+impl<T: Send + Any> Responder<T> {
+    /// This method turns this `Responder` into an `AnyResponder`.
+    pub fn into_any(self) -> AnyResponder {
+        AnyResponder::new(self)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::channel;
+
+    #[test]
+    fn test_any_wrong_type() {
+        let (rqst, resp) = channel::<u32>();
+        let (rqst, resp) = (rqst.into_any(), resp.into_any());
+
+        assert!(rqst.is::<u32>() && resp.is::<u32>());
+        assert!(!rqst.is::<u64>() && !resp.is::<u64>());
+        assert_eq!(resp.type_name(), "u32");
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut resp_contract = resp.try_respond_boxed().ok().unwrap();
+
+        // Neither side acts on the wrong type.
+        match resp_contract.send(Box::new(1u64)) {
+            Err(SendError(datum)) => { assert_eq!(*datum.downcast::<u64>().ok().unwrap(), 1); },
+            _ => { assert!(false); },
+        }
+        match contract.try_receive_downcast::<u32>() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(Box::new(2u32)).ok().unwrap();
+        match contract.try_receive_downcast::<u64>() {
+            Err(Error::WrongType) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(contract.try_receive_downcast::<u32>().ok().unwrap(), 2);
+
+        // A sent contract hands everything back.
+        match resp_contract.send(Box::new(3u32)) {
+            Err(SendError(datum)) => { assert_eq!(*datum.downcast::<u32>().ok().unwrap(), 3); },
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_any_receive_boxed() {
+        let (rqst, resp) = channel::<String>();
+        let rqst = rqst.into_any();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send("one".to_string());
+
+        let datum = contract.try_receive().ok().unwrap();
+        assert_eq!(*datum.downcast::<String>().ok().unwrap(), "one");
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! This module picks the atomic types the rest of the crate uses. By
+//! default they come from `std`. With the `portable-atomic` feature, they
+//! come from the `portable-atomic` crate instead, which also works on
+//! targets without native 64-bit atomics, such as `mips-unknown-linux-gnu`.
+//! Under `cfg(loom)` they come from `loom`, which model-checks them.
+
+#[cfg(all(not(loom), not(feature = "portable-atomic")))]
+pub use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicU16, AtomicU32, AtomicU64,
+                            AtomicU8, AtomicUsize, Ordering};
+
+#[cfg(all(not(loom), feature = "portable-atomic"))]
+pub use portable_atomic::{fence, AtomicBool, AtomicPtr, AtomicU16, AtomicU32, AtomicU64,
+                          AtomicU8, AtomicUsize, Ordering};
+
+#[cfg(loom)]
+pub use loom::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicU16, AtomicU32, AtomicU64,
+                             AtomicU8, AtomicUsize, Ordering};
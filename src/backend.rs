@@ -0,0 +1,566 @@
+//! This module lets users plug their own synchronization scheme into the
+//! channel. A `ChannelBackend` performs the steps of a single request:
+//! locking the requesting side, flagging the request, claiming it,
+//! publishing the datum, taking it, and unlocking again. The handles and
+//! contracts in this module drive any backend through those steps, so a
+//! backend only has to get the synchronization right.
+//!
+//! `AtomicBackend` is the lock-free state machine the regular channel
+//! uses. `MutexBackend` is built on a `Mutex` and a `Condvar` and uses no
+//! `unsafe` code at all.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::thread;
+
+use super::backoff::Backoff;
+use super::{Error, Inner, Result};
+
+/// This trait defines the steps of a single request. The requesting side
+/// calls `try_lock_request()`, `flag_request()`, then `try_take_datum()`
+/// or `try_unflag_request()`, then `unlock_request()`. The responding side
+/// calls `try_claim_request()` and then `publish_datum()`.
+///
+/// Every method must be safe to call from any thread at any time. The
+/// handles only call them in the order above, so a backend can rely on it.
+pub trait ChannelBackend<T> {
+    /// This function creates the state of an idle channel.
+    fn new() -> Self where Self: Sized;
+
+    /// This method locks the requesting side of the channel. It returns
+    /// `Err(Error::AlreadyLocked)` if it is already locked.
+    fn try_lock_request(&self) -> Result<()>;
+
+    /// This method flags a request, which lets a responder claim it.
+    fn flag_request(&self);
+
+    /// This method withdraws the request if no responder has claimed it.
+    /// It returns `Err(Error::TooLate)` if a responder already did.
+    fn try_unflag_request(&self) -> Result<()>;
+
+    /// This method claims the flagged request for one responder. It
+    /// returns `Err(Error::AlreadyLocked)` if another responder already
+    /// claimed it, and `Err(Error::NoRequest)` if there is no request.
+    fn try_claim_request(&self) -> Result<()>;
+
+    /// This method hands the datum for the claimed request to the
+    /// requesting side.
+    fn publish_datum(&self, datum: T);
+
+    /// This method takes the published datum. It returns
+    /// `Err(Error::Empty)` if there is none yet.
+    fn try_take_datum(&self) -> Result<T>;
+
+    /// This method unlocks the requesting side once the request has been
+    /// received or withdrawn.
+    fn unlock_request(&self);
+
+    /// This method waits until a request is flagged and claims it. By
+    /// default, it polls `try_claim_request()`.
+    fn claim_request(&self) {
+        let mut backoff = Backoff::new();
+
+        while self.try_claim_request().is_err() {
+            backoff.snooze();
+        }
+    }
+
+    /// This method waits until the datum is published and takes it. By
+    /// default, it polls `try_take_datum()`.
+    fn take_datum(&self) -> T {
+        let mut backoff = Backoff::new();
+
+        loop {
+            match self.try_take_datum() {
+                Ok(datum) => { return datum; },
+                Err(_) => { backoff.snooze(); },
+            }
+        }
+    }
+}
+
+/// This function creates a channel that uses the backend `B` and returns
+/// a tuple containing its two ends.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel_with_backend::<u32, chan::MutexBackend<u32>>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+///
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub fn channel_with_backend<T, B>() -> (BackendRequester<T, B>, BackendResponder<T, B>)
+    where B: ChannelBackend<T>
+{
+    let backend = Arc::new(B::new());
+
+    (
+        BackendRequester { backend: backend.clone(), _datum: PhantomData },
+        BackendResponder { backend: backend.clone(), _datum: PhantomData },
+    )
+}
+
+/// This end of a channel with a pluggable backend requests and receives
+/// data from its `BackendResponder`(s). It works just like `Requester`.
+pub struct BackendRequester<T, B = AtomicBackend<T>> {
+    backend: Arc<B>,
+    _datum: PhantomData<fn(T) -> T>,
+}
+
+impl<T, B: ChannelBackend<T>> BackendRequester<T, B> {
+    /// This method works just like `Requester::try_request()`.
+    pub fn try_request(&self) -> Result<BackendRequestContract<T, B>> {
+        self.backend.try_lock_request()?;
+        self.backend.flag_request();
+
+        Ok(BackendRequestContract {
+            backend: self.backend.clone(),
+            done: false,
+            _datum: PhantomData,
+        })
+    }
+}
+
+/// This is the contract returned by a successful
+/// `BackendRequester::try_request()`. It works just like `RequestContract`.
+pub struct BackendRequestContract<T, B: ChannelBackend<T> = AtomicBackend<T>> {
+    backend: Arc<B>,
+    done: bool,
+    _datum: PhantomData<fn(T) -> T>,
+}
+
+impl<T, B: ChannelBackend<T>> BackendRequestContract<T, B> {
+    /// This method works just like `RequestContract::try_receive()`.
+    pub fn try_receive(&mut self) -> Result<T> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        let datum = self.backend.try_take_datum()?;
+        self.done = true;
+
+        Ok(datum)
+    }
+
+    /// This method waits for the datum and receives it.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it after either
+    /// receiving a datum or cancelling the request. Otherwise, it waits
+    /// until a responder sends the datum.
+    pub fn receive(&mut self) -> Result<T> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        let datum = self.backend.take_datum();
+        self.done = true;
+
+        Ok(datum)
+    }
+
+    /// This method works just like `RequestContract::try_cancel()`.
+    pub fn try_cancel(&mut self) -> Result<()> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        self.backend.try_unflag_request()?;
+        self.done = true;
+
+        Ok(())
+    }
+}
+
+impl<T, B: ChannelBackend<T>> Drop for BackendRequestContract<T, B> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping BackendRequestContract without receiving data!");
+        }
+
+        self.backend.unlock_request();
+    }
+}
+
+/// This end of a channel with a pluggable backend sends data in response
+/// to requests from its `BackendRequester`. It works just like `Responder`.
+pub struct BackendResponder<T, B = AtomicBackend<T>> {
+    backend: Arc<B>,
+    _datum: PhantomData<fn(T) -> T>,
+}
+
+impl<T, B: ChannelBackend<T>> BackendResponder<T, B> {
+    /// This method works just like `Responder::try_respond()`.
+    pub fn try_respond(&self) -> Result<BackendResponseContract<T, B>> {
+        self.backend.try_claim_request()?;
+
+        Ok(BackendResponseContract {
+            backend: self.backend.clone(),
+            done: false,
+            _datum: PhantomData,
+        })
+    }
+
+    /// This method waits for a request and claims it.
+    ///
+    /// # Warning
+    ///
+    /// It waits forever if the requester never makes a request.
+    pub fn respond(&self) -> BackendResponseContract<T, B> {
+        self.backend.claim_request();
+
+        BackendResponseContract {
+            backend: self.backend.clone(),
+            done: false,
+            _datum: PhantomData,
+        }
+    }
+}
+
+impl<T, B> Clone for BackendResponder<T, B> {
+    fn clone(&self) -> Self {
+        BackendResponder {
+            backend: self.backend.clone(),
+            _datum: PhantomData,
+        }
+    }
+}
+
+/// This is the contract returned by a successful
+/// `BackendResponder::try_respond()`. It works just like `ResponseContract`.
+pub struct BackendResponseContract<T, B = AtomicBackend<T>> {
+    backend: Arc<B>,
+    done: bool,
+    _datum: PhantomData<fn(T) -> T>,
+}
+
+impl<T, B: ChannelBackend<T>> BackendResponseContract<T, B> {
+    /// This method works just like `ResponseContract::send()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send(mut self, datum: T) {
+        self.backend.publish_datum(datum);
+        self.done = true;
+    }
+}
+
+impl<T, B> Drop for BackendResponseContract<T, B> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping BackendResponseContract without sending data!");
+        }
+    }
+}
+
+/// This is the lock-free backend of the regular channel.
+pub struct AtomicBackend<T> {
+    inner: Inner<T>,
+}
+
+impl<T> ChannelBackend<T> for AtomicBackend<T> {
+    #[inline]
+    fn new() -> AtomicBackend<T> {
+        AtomicBackend {
+            inner: Inner::new(),
+        }
+    }
+
+    #[inline]
+    fn try_lock_request(&self) -> Result<()> {
+        self.inner.try_lock_request()
+    }
+
+    #[inline]
+    fn flag_request(&self) {
+        self.inner.flag_request();
+    }
+
+    #[inline]
+    fn try_unflag_request(&self) -> Result<()> {
+        self.inner.try_cancel_request().map_err(|_| Error::TooLate)
+    }
+
+    #[inline]
+    fn try_claim_request(&self) -> Result<()> {
+        self.inner.try_claim_request()
+    }
+
+    #[inline]
+    fn publish_datum(&self, datum: T) {
+        if self.inner.try_set_datum(datum).is_err() {
+            panic!("AtomicBackend found a datum already published for its claim!");
+        }
+    }
+
+    #[inline]
+    fn try_take_datum(&self) -> Result<T> {
+        self.inner.try_get_datum()
+    }
+
+    #[inline]
+    fn unlock_request(&self) {
+        self.inner.finish_request();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::fmt::Debug;
+    use std::thread;
+
+    use super::*;
+    use super::super::{BoxedBackend, MutexBackend, SmallBackend};
+
+    // Every backend must pass the tests below. Add a backend to the
+    // `backend_tests!` invocations at the bottom to run them against it.
+    // The tests send `u32`s, converted into whatever the backend carries.
+
+    trait Datum: From<u32> + PartialEq + Debug + Send + 'static {}
+
+    impl<T: From<u32> + PartialEq + Debug + Send + 'static> Datum for T {}
+
+    fn check_try_request_multiple<T: Datum, B: ChannelBackend<T>>() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        contract.try_cancel().ok().unwrap();
+    }
+
+    fn check_try_respond<T: Datum, B: ChannelBackend<T>>() {
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match resp.clone().try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(T::from(1));
+
+        assert_eq!(contract.try_receive().ok().unwrap(), T::from(1));
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    fn check_try_cancel<T: Datum, B: ChannelBackend<T>>() {
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            contract.try_cancel().ok().unwrap();
+
+            match contract.try_cancel() {
+                Err(Error::Done) => {},
+                _ => { assert!(false); },
+            }
+
+            match resp.try_respond() {
+                Err(Error::NoRequest) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(T::from(2));
+        assert_eq!(contract.try_receive().ok().unwrap(), T::from(2));
+    }
+
+    fn check_reuse<T: Datum, B: ChannelBackend<T>>() {
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        for i in 0..3 {
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(T::from(i));
+            assert_eq!(contract.try_receive().ok().unwrap(), T::from(i));
+        }
+    }
+
+    fn check_multi_threaded<T, B>()
+        where T: Datum,
+              B: ChannelBackend<T> + Send + Sync + 'static
+    {
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        let handles = (0..2).map(|_| {
+            let resp = resp.clone();
+
+            thread::spawn(move || {
+                let mut sent = 0;
+
+                while sent < 50 {
+                    match resp.try_respond() {
+                        Ok(contract) => {
+                            contract.send(T::from(1));
+                            sent += 1;
+                        },
+                        Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {
+                            thread::yield_now();
+                        },
+                        _ => unreachable!(),
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let mut sum = 0;
+
+        for _ in 0..100 {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            loop {
+                match contract.try_receive() {
+                    Ok(datum) => {
+                        assert_eq!(datum, T::from(1));
+                        sum += 1;
+                        break;
+                    },
+                    Err(Error::Empty) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(sum, 100);
+    }
+
+    fn check_blocking<T, B>()
+        where T: Datum,
+              B: ChannelBackend<T> + Send + Sync + 'static
+    {
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        let handle = thread::spawn(move || {
+            for i in 0..50 {
+                resp.respond().send(T::from(i));
+            }
+        });
+
+        for i in 0..50 {
+            let mut contract = rqst.try_request().ok().unwrap();
+            assert_eq!(contract.receive().ok().unwrap(), T::from(i));
+
+            match contract.receive() {
+                Err(Error::Done) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        handle.join().unwrap();
+    }
+
+    fn check_request_contract_drop_without_receiving_data<T: Datum, B: ChannelBackend<T>>() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+
+    fn check_response_contract_drop_without_sending_data<T: Datum, B: ChannelBackend<T>>() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel_with_backend::<T, B>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        contract.done = true;
+
+        #[allow(unused_variables)]
+        let resp_contract = resp.try_respond().ok().unwrap();
+    }
+
+    macro_rules! backend_tests {
+        ($name:ident, $datum:ty, $backend:ty) => {
+            mod $name {
+                use super::*;
+
+                #[test]
+                fn test_try_request_multiple() {
+                    check_try_request_multiple::<$datum, $backend>();
+                }
+
+                #[test]
+                fn test_try_respond() {
+                    check_try_respond::<$datum, $backend>();
+                }
+
+                #[test]
+                fn test_try_cancel() {
+                    check_try_cancel::<$datum, $backend>();
+                }
+
+                #[test]
+                fn test_reuse() {
+                    check_reuse::<$datum, $backend>();
+                }
+
+                #[test]
+                fn test_multi_threaded() {
+                    check_multi_threaded::<$datum, $backend>();
+                }
+
+                #[test]
+                fn test_blocking() {
+                    check_blocking::<$datum, $backend>();
+                }
+
+                #[test]
+                #[should_panic]
+                fn test_request_contract_drop_without_receiving_data() {
+                    check_request_contract_drop_without_receiving_data::<$datum, $backend>();
+                }
+
+                #[test]
+                #[should_panic]
+                fn test_response_contract_drop_without_sending_data() {
+                    check_response_contract_drop_without_sending_data::<$datum, $backend>();
+                }
+            }
+        };
+    }
+
+    backend_tests!(atomic_backend, u32, AtomicBackend<u32>);
+    backend_tests!(mutex_backend, u32, MutexBackend<u32>);
+    backend_tests!(small_backend, u32, SmallBackend<u32>);
+    backend_tests!(boxed_backend, Box<u32>, BoxedBackend<u32>);
+}
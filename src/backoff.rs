@@ -0,0 +1,260 @@
+//! This module defines the backoff used by every blocking helper in the
+//! crate. It spins for a while and then starts yielding the thread. How
+//! long it spins adapts to each channel: a `SpinTuner` remembers whether
+//! recent waits on the channel ended while they were still spinning, and
+//! moves the number of spin rounds within the bounds the channel was built
+//! with.
+
+use std::hint;
+use std::thread;
+
+use super::atomic::{AtomicU32, Ordering};
+
+const SPIN_LIMIT: u32 = 6;
+// How many rounds a waiting thread yields after it stops spinning, before
+// it is considered to have waited long enough to block.
+const YIELD_ROUNDS: u32 = 4;
+
+/// The fewest spin rounds a channel's blocking helpers may settle on by
+/// default.
+pub(crate) const MIN_SPIN_LIMIT: u32 = 2;
+/// The most spin rounds a channel's blocking helpers may settle on by
+/// default.
+pub(crate) const MAX_SPIN_LIMIT: u32 = 10;
+/// The most spin rounds a channel may be configured with. The last round
+/// spins `1 << SPIN_CEILING` times.
+pub(crate) const SPIN_CEILING: u32 = 16;
+// How many waits a `SpinTuner` records before it moves its limit.
+const TUNING_WINDOW: u32 = 16;
+
+/// This struct tracks how long a waiting thread has been waiting.
+pub(crate) struct Backoff<'a> {
+    step: u32,
+    // The last step that spins instead of yielding.
+    limit: u32,
+    // This learns how the wait ended, if it waits on a channel.
+    tuner: Option<&'a SpinTuner>,
+}
+
+impl Backoff<'static> {
+    /// This function creates a backoff with the default spin limit, for a
+    /// wait that is not on a single channel.
+    #[inline]
+    pub(crate) fn new() -> Backoff<'static> {
+        Backoff { step: 0, limit: SPIN_LIMIT, tuner: None }
+    }
+}
+
+impl<'a> Backoff<'a> {
+    /// This function creates a backoff that spins as long as `tuner`
+    /// currently suggests, and reports back to it how the wait ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `tuner` - The spin tuner of the channel the thread waits on
+    #[inline]
+    pub(crate) fn tuned(tuner: &'a SpinTuner) -> Backoff<'a> {
+        Backoff { step: 0, limit: tuner.limit(), tuner: Some(tuner) }
+    }
+
+    /// This method waits a little longer each time it is called.
+    #[inline]
+    pub(crate) fn snooze(&mut self) {
+        if self.step <= self.limit {
+            for _ in 0..1 << self.step {
+                hint::spin_loop();
+            }
+            self.step += 1;
+        }
+        else {
+            thread::yield_now();
+
+            if self.step <= self.limit + YIELD_ROUNDS {
+                self.step += 1;
+            }
+        }
+    }
+
+    /// This method tells whether the waiting thread has yielded long
+    /// enough that it should block instead.
+    #[cfg(feature = "pool")]
+    #[inline]
+    pub(crate) fn is_completed(&self) -> bool {
+        self.step > self.limit + YIELD_ROUNDS
+    }
+}
+
+impl<'a> Drop for Backoff<'a> {
+    fn drop(&mut self) {
+        // A wait that never snoozed tells nothing about spinning.
+        if let Some(tuner) = self.tuner {
+            if self.step > 0 {
+                tuner.record(self.step <= self.limit + 1);
+            }
+        }
+    }
+}
+
+/// This struct adapts how many rounds the blocking helpers of one channel
+/// spin before they start yielding. If most recent waits ended while they
+/// were still spinning, spinning pays off and the limit goes up; if few
+/// did, the spinning was wasted and the limit goes down. The limit never
+/// leaves the bounds the channel was built with.
+pub(crate) struct SpinTuner {
+    limit: AtomicU32,
+    // How many waits of the current window ended while spinning, and how
+    // many had to yield. They are only hints, so relaxed updates that
+    // race with the end of a window are fine.
+    hits: AtomicU32,
+    misses: AtomicU32,
+    min: u32,
+    max: u32,
+}
+
+impl SpinTuner {
+    const_fn! {
+        /// This function creates a tuner whose limit stays between `min`
+        /// and `max`, and starts as close to the default as it can.
+        ///
+        /// # Arguments
+        ///
+        /// * `min` - The fewest spin rounds
+        /// * `max` - The most spin rounds
+        #[inline]
+        pub(crate) fn new(min: u32, max: u32) -> SpinTuner {
+            let start = if SPIN_LIMIT < min {
+                min
+            }
+            else if SPIN_LIMIT > max {
+                max
+            }
+            else {
+                SPIN_LIMIT
+            };
+
+            SpinTuner {
+                limit: AtomicU32::new(start),
+                hits: AtomicU32::new(0),
+                misses: AtomicU32::new(0),
+                min,
+                max,
+            }
+        }
+    }
+
+    /// This method returns how many rounds a wait should spin now.
+    #[inline]
+    pub(crate) fn limit(&self) -> u32 {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// This method records whether a wait ended while it was still
+    /// spinning, and moves the limit once it has recorded a full window
+    /// of waits.
+    ///
+    /// # Arguments
+    ///
+    /// * `spun` - Whether the wait ended before it yielded
+    pub(crate) fn record(&self, spun: bool) {
+        let (hits, misses) = if spun {
+            (self.hits.fetch_add(1, Ordering::Relaxed) + 1, self.misses.load(Ordering::Relaxed))
+        }
+        else {
+            (self.hits.load(Ordering::Relaxed), self.misses.fetch_add(1, Ordering::Relaxed) + 1)
+        };
+        let total = hits + misses;
+
+        if total < TUNING_WINDOW {
+            return;
+        }
+
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+
+        let limit = self.limit();
+        let next = if hits * 2 > total {
+            limit + 1
+        }
+        else if hits * 4 < total {
+            limit.saturating_sub(1)
+        }
+        else {
+            limit
+        };
+
+        self.limit.store(next.clamp(self.min, self.max), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spin_tuner_start() {
+        assert_eq!(SpinTuner::new(MIN_SPIN_LIMIT, MAX_SPIN_LIMIT).limit(), SPIN_LIMIT);
+        assert_eq!(SpinTuner::new(8, 12).limit(), 8);
+        assert_eq!(SpinTuner::new(0, 3).limit(), 3);
+        assert_eq!(SpinTuner::new(4, 4).limit(), 4);
+    }
+
+    #[test]
+    fn test_spin_tuner_stays_within_bounds() {
+        let tuner = SpinTuner::new(3, 9);
+
+        for _ in 0..1000 {
+            tuner.record(true);
+            assert!((3..=9).contains(&tuner.limit()));
+        }
+        assert_eq!(tuner.limit(), 9);
+
+        for _ in 0..1000 {
+            tuner.record(false);
+            assert!((3..=9).contains(&tuner.limit()));
+        }
+        assert_eq!(tuner.limit(), 3);
+
+        // A mix between the two thresholds leaves the limit alone.
+        for i in 0..1000 {
+            tuner.record(i % 3 == 0);
+            assert_eq!(tuner.limit(), 3);
+        }
+    }
+
+    #[test]
+    fn test_spin_tuner_moves_once_per_window() {
+        let tuner = SpinTuner::new(MIN_SPIN_LIMIT, MAX_SPIN_LIMIT);
+
+        for _ in 0..TUNING_WINDOW - 1 {
+            tuner.record(true);
+        }
+        assert_eq!(tuner.limit(), SPIN_LIMIT);
+
+        tuner.record(true);
+        assert_eq!(tuner.limit(), SPIN_LIMIT + 1);
+    }
+
+    #[test]
+    fn test_backoff_tuned_records() {
+        let tuner = SpinTuner::new(1, 1);
+
+        // This wait never snoozed.
+        drop(Backoff::tuned(&tuner));
+
+        // This one ended while spinning.
+        let mut backoff = Backoff::tuned(&tuner);
+        backoff.snooze();
+        backoff.snooze();
+        drop(backoff);
+
+        // This one yielded.
+        let mut backoff = Backoff::tuned(&tuner);
+        for _ in 0..3 {
+            backoff.snooze();
+        }
+        drop(backoff);
+
+        assert_eq!(tuner.hits.load(Ordering::Relaxed), 1);
+        assert_eq!(tuner.misses.load(Ordering::Relaxed), 1);
+    }
+}
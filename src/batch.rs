@@ -0,0 +1,589 @@
+//! This module defines a batch channel. It works like the regular channel,
+//! but a single request opens a window during which responders can push
+//! up to `capacity` items one at a time, instead of wrapping them all in
+//! a single datum.
+//!
+//! The window closes when the requesting side closes it or when the
+//! responders have pushed `capacity` items.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ptr;
+use std::sync::Arc;
+use std::thread;
+
+use super::atomic::{AtomicBool, AtomicUsize, Ordering};
+use super::{try_lock, unlock, Error, Result};
+
+/// This function creates a batch channel and returns a tuple containing
+/// its two ends. Each request can receive up to `capacity` items.
+///
+/// # Panics
+///
+/// It panics if `capacity` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan;
+///
+/// #[allow(unused_variables)]
+/// let (requester, responder) = reqchan::batch_channel::<u32>(16);
+/// ```
+pub fn batch_channel<T>(capacity: usize) -> (BatchRequester<T>, BatchResponder<T>) {
+    if capacity == 0 {
+        panic!("Cannot create a batch channel with zero capacity!");
+    }
+
+    let inner = Arc::new(Inner {
+        has_request_lock: AtomicBool::new(false),
+        has_response_lock: AtomicBool::new(false),
+        has_request: AtomicBool::new(false),
+        pushed: AtomicUsize::new(0),
+        slots: (0..capacity).map(|_| UnsafeCell::new(None)).collect(),
+    });
+
+    (
+        BatchRequester { inner: inner.clone() },
+        BatchResponder { inner: inner.clone() },
+    )
+}
+
+/// This end of the batch channel requests and receives items from its
+/// `BatchResponder`(s).
+pub struct BatchRequester<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> BatchRequester<T> {
+    /// This method tries to open a request window. If successful, it returns
+    /// a `BatchRequestContract` to drain items from the window or close it.
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `BatchRequestContract` may be active at a time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::batch_channel::<u32>(4);
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    ///
+    /// {
+    ///     let mut response_contract = responder.try_respond().ok().unwrap();
+    ///     response_contract.push(1).ok().unwrap();
+    ///     response_contract.push(2).ok().unwrap();
+    /// }
+    ///
+    /// request_contract.close();
+    ///
+    /// let mut items = Vec::new();
+    /// while request_contract.try_receive_batch(&mut items).is_ok() {}
+    /// assert_eq!(items, vec![1, 2]);
+    /// ```
+    pub fn try_request(&self) -> Result<BatchRequestContract<T>> {
+        // First, try to lock the requesting side.
+        self.inner.try_lock_request()?;
+
+        // Next, open the window.
+        self.inner.flag_request();
+
+        // Then return a `BatchRequestContract`.
+        Ok(BatchRequestContract {
+            inner: self.inner.clone(),
+            drained: 0,
+            done: false,
+        })
+    }
+}
+
+/// This is the contract returned by a successful `BatchRequester::try_request()`.
+/// It represents the caller's exclusive access to the requesting side of
+/// the batch channel. To prevent data loss, `BatchRequestContract` will panic
+/// if the user drops it before the window is closed and every item drained.
+pub struct BatchRequestContract<T> {
+    inner: Arc<Inner<T>>,
+    drained: usize,
+    done: bool,
+}
+
+impl<T> BatchRequestContract<T> {
+    /// This method moves every item the responders have pushed so far into
+    /// `items` and returns how many it moved.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Empty)` if there is nothing to drain yet, and
+    /// `Err(Error::Done)` once the window is closed, no responder is pushing
+    /// items, and every item has been drained.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - The `Vec` to append the items to
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::batch_channel::<u32>(2);
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    /// let mut items = Vec::new();
+    ///
+    /// match request_contract.try_receive_batch(&mut items) {
+    ///     Err(chan::Error::Empty) => { println!("No items yet!"); },
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// {
+    ///     let mut response_contract = responder.try_respond().ok().unwrap();
+    ///     response_contract.push(1).ok().unwrap();
+    ///     // Pushing `capacity` items closes the window.
+    ///     response_contract.push(2).ok().unwrap();
+    /// }
+    ///
+    /// assert_eq!(request_contract.try_receive_batch(&mut items).ok().unwrap(), 2);
+    ///
+    /// match request_contract.try_receive_batch(&mut items) {
+    ///     Err(chan::Error::Done) => { println!("We received everything!"); },
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn try_receive_batch(&mut self, items: &mut Vec<T>) -> Result<usize> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        // Check whether the window is finished before draining, so
+        // that no item can slip in after the last drain.
+        let finished = self.inner.is_finished();
+
+        match self.inner.drain_into(self.drained, items) {
+            0 if finished => {
+                self.done = true;
+                Err(Error::Done)
+            },
+            0 => Err(Error::Empty),
+            n => {
+                self.drained += n;
+                Ok(n)
+            },
+        }
+    }
+
+    /// This method closes the request window. Responders can no longer
+    /// push items, but the user still has to drain the items that
+    /// were already pushed.
+    pub fn close(&mut self) {
+        self.inner.unflag_request();
+    }
+
+    /// This method returns whether or not the request window is still open.
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.inner.has_request.load(Ordering::SeqCst)
+    }
+}
+
+impl<T> Drop for BatchRequestContract<T> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping BatchRequestContract without receiving data!");
+        }
+
+        self.inner.unlock_request();
+    }
+}
+
+/// This end of the batch channel pushes items in response to requests
+/// from its `BatchRequester`.
+pub struct BatchResponder<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> BatchResponder<T> {
+    /// This method tries to lock the responding side of the batch channel
+    /// while a request window is open. If successful, it returns a
+    /// `BatchResponseContract` to push items.
+    ///
+    /// Unlike `Responder::try_respond()`, it does not consume the request,
+    /// so other responders can push more items after this contract is dropped.
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `BatchResponseContract` may be active at a time.
+    pub fn try_respond(&self) -> Result<BatchResponseContract<T>> {
+        // First try to lock the responding side.
+        self.inner.try_lock_response()?;
+
+        // Next, check for an open window.
+        if self.inner.has_request.load(Ordering::SeqCst) {
+            Ok(BatchResponseContract {
+                inner: self.inner.clone(),
+            })
+        }
+        else {
+            self.inner.unlock_response();
+            Err(Error::NoRequest)
+        }
+    }
+}
+
+impl<T> Clone for BatchResponder<T> {
+    fn clone(&self) -> Self {
+        BatchResponder {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// This is the contract returned by a successful `BatchResponder::try_respond()`.
+/// It represents the caller's exclusive access to the responding side of
+/// the batch channel. Dropping it frees the responding side.
+pub struct BatchResponseContract<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> BatchResponseContract<T> {
+    /// This method tries to push an item into the request window. It returns
+    /// the item inside `Err(Full)` if the window is closed or full.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The item to push
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::batch_channel::<u32>(1);
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    ///
+    /// let mut response_contract = responder.try_respond().ok().unwrap();
+    /// response_contract.push(1).ok().unwrap();
+    ///
+    /// // The window only had room for one item.
+    /// assert_eq!(response_contract.push(2).err().unwrap().into_inner(), 2);
+    /// # drop(response_contract);
+    /// # request_contract.try_receive_batch(&mut Vec::new()).ok().unwrap();
+    /// # request_contract.try_receive_batch(&mut Vec::new()).err().unwrap();
+    /// ```
+    pub fn push(&mut self, item: T) -> ::std::result::Result<(), Full<T>> {
+        self.inner.try_push(item).map_err(Full)
+    }
+
+    /// This method returns how many more items the window can take.
+    #[inline]
+    pub fn remaining_capacity(&self) -> usize {
+        if self.inner.has_request.load(Ordering::SeqCst) {
+            self.inner.slots.len() - self.inner.pushed.load(Ordering::SeqCst)
+        }
+        else {
+            0
+        }
+    }
+}
+
+impl<T> Drop for BatchResponseContract<T> {
+    fn drop(&mut self) {
+        self.inner.unlock_response();
+    }
+}
+
+/// This is the error returned by `BatchResponseContract::push()` when the
+/// request window cannot take any more items. It contains the rejected item.
+pub struct Full<T>(pub T);
+
+impl<T> Full<T> {
+    /// This method returns the rejected item.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Full(..)")
+    }
+}
+
+#[doc(hidden)]
+struct Inner<T> {
+    has_request_lock: AtomicBool,
+    has_response_lock: AtomicBool,
+    has_request: AtomicBool,
+    pushed: AtomicUsize,
+    slots: Box<[UnsafeCell<Option<T>>]>,
+}
+
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method opens a new request window.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.has_request_lock == true
+    ///
+    /// * every slot is empty
+    #[inline]
+    fn flag_request(&self) {
+        debug_assert!(self.has_request_lock.load(Ordering::SeqCst));
+
+        self.pushed.store(0, Ordering::SeqCst);
+        self.has_request.store(true, Ordering::SeqCst);
+    }
+
+    /// This method closes the current request window.
+    #[inline]
+    fn unflag_request(&self) {
+        self.has_request.store(false, Ordering::SeqCst);
+    }
+
+    /// This method returns whether the current window is closed and no
+    /// responder can push any more items into it.
+    #[inline]
+    fn is_finished(&self) -> bool {
+        !self.has_request.load(Ordering::SeqCst)
+            && !self.has_response_lock.load(Ordering::SeqCst)
+    }
+
+    /// This method tries to push an item into the window's buffer. It
+    /// returns the item if the window is closed or full.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responding side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.has_response_lock == true
+    #[inline]
+    fn try_push(&self, item: T) -> ::std::result::Result<(), T> {
+        debug_assert!(self.has_response_lock.load(Ordering::SeqCst));
+
+        let capacity = self.slots.len();
+        let pushed = self.pushed.load(Ordering::SeqCst);
+
+        if !self.has_request.load(Ordering::SeqCst) || pushed == capacity {
+            return Err(item);
+        }
+
+        // The requester never touches slots at or past `pushed`.
+        unsafe {
+            *self.slots[pushed].get() = Some(item);
+        }
+
+        // Publish the item.
+        self.pushed.store(pushed + 1, Ordering::SeqCst);
+
+        // Close the window once it is full.
+        if pushed + 1 == capacity {
+            self.unflag_request();
+        }
+
+        Ok(())
+    }
+
+    /// This method moves every published item after the first `drained`
+    /// ones into `items` and returns how many it moved.
+    ///
+    /// # Arguments
+    ///
+    /// * drained - The number of items already drained from this window
+    ///
+    /// * items - The `Vec` to append the items to
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.has_request_lock == true
+    #[inline]
+    fn drain_into(&self, drained: usize, items: &mut Vec<T>) -> usize {
+        debug_assert!(self.has_request_lock.load(Ordering::SeqCst));
+
+        let pushed = self.pushed.load(Ordering::SeqCst);
+
+        // Growing `items` may panic, so do it before emptying any slot.
+        items.reserve(pushed - drained);
+
+        for slot in &self.slots[drained..pushed] {
+            unsafe {
+                items.push(ptr::replace(slot.get(), None).unwrap());
+            }
+        }
+
+        pushed - drained
+    }
+
+    #[inline]
+    fn try_lock_request(&self) -> Result<()> {
+        try_lock(&self.has_request_lock)
+    }
+
+    #[inline]
+    fn unlock_request(&self) {
+        unlock(&self.has_request_lock);
+    }
+
+    #[inline]
+    fn try_lock_response(&self) -> Result<()> {
+        try_lock(&self.has_response_lock)
+    }
+
+    #[inline]
+    fn unlock_response(&self) {
+        unlock(&self.has_response_lock);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants, clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic]
+    fn test_batch_channel_zero_capacity() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = batch_channel::<u32>(0);
+    }
+
+    #[test]
+    fn test_batch_responder_try_respond_no_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = batch_channel::<u32>(2);
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(resp.inner.has_response_lock.load(Ordering::SeqCst), false);
+    }
+
+    #[test]
+    fn test_batch_responder_try_respond_multiple() {
+        let (rqst, resp) = batch_channel::<u32>(2);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        {
+            #[allow(unused_variables)]
+            let resp_contract = resp.try_respond().ok().unwrap();
+
+            match resp.try_respond() {
+                Err(Error::AlreadyLocked) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        contract.close();
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_batch_response_contract_push_full() {
+        let (rqst, resp) = batch_channel::<u32>(2);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        {
+            let mut resp_contract = resp.try_respond().ok().unwrap();
+
+            assert_eq!(resp_contract.remaining_capacity(), 2);
+            resp_contract.push(1).ok().unwrap();
+            resp_contract.push(2).ok().unwrap();
+            assert_eq!(resp_contract.remaining_capacity(), 0);
+
+            match resp_contract.push(3) {
+                Err(Full(3)) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        assert_eq!(contract.is_open(), false);
+
+        let mut items = Vec::new();
+        assert_eq!(contract.try_receive_batch(&mut items).ok().unwrap(), 2);
+        assert_eq!(items, vec![1, 2]);
+
+        match contract.try_receive_batch(&mut items) {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_batch_response_contract_push_closed() {
+        let (rqst, resp) = batch_channel::<u32>(2);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+
+        contract.close();
+
+        match resp_contract.push(1) {
+            Err(Full(1)) => {},
+            _ => { assert!(false); },
+        }
+
+        // The responder still holds the lock, so the window is not finished.
+        match contract.try_receive_batch(&mut Vec::new()) {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        drop(resp_contract);
+
+        match contract.try_receive_batch(&mut Vec::new()) {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_batch_request_contract_multiple_windows() {
+        let (rqst, resp) = batch_channel::<u32>(3);
+        let mut items = Vec::new();
+
+        for i in 0..3 {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            resp.try_respond().ok().unwrap().push(i).ok().unwrap();
+            resp.try_respond().ok().unwrap().push(i * 10).ok().unwrap();
+
+            contract.close();
+
+            while contract.try_receive_batch(&mut items).is_ok() {}
+        }
+
+        assert_eq!(items, vec![0, 0, 1, 10, 2, 20]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_batch_request_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = batch_channel::<u32>(2);
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+}
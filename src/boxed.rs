@@ -0,0 +1,203 @@
+//! This module defines a channel backend for boxed data. Its datum slot
+//! is an `AtomicPtr`, so a responder publishes a datum by storing the
+//! box's pointer, and the requesting side takes it by swapping the
+//! pointer for null. Whether the slot holds a datum is told by the
+//! pointer alone, with no state change of its own.
+//!
+//! The ends are a `BackendRequester` and a `BackendResponder`, so the
+//! contracts, errors and drop behavior are exactly those of any other
+//! backend, and the backend tests run against it too.
+
+use std::marker::PhantomData;
+use std::ptr;
+use std::result;
+
+use super::atomic::{AtomicPtr, AtomicU8, Ordering};
+use super::{channel_with_backend, BackendRequester, BackendResponder, ChannelBackend, Error,
+            Result, State};
+
+/// This function creates a channel for boxed data and returns a tuple
+/// containing its two ends. It works just like `channel::<Box<U>>()`,
+/// but hands each box over with a single atomic pointer exchange.
+///
+/// `U` must be sized, since a pointer to an unsized value does not fit in
+/// an `AtomicPtr`. To send a boxed closure, send a `Task` or wrap the
+/// closure's box in a struct.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel_boxed::<u64>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(Box::new(1));
+///
+/// assert_eq!(*contract.try_receive().ok().unwrap(), 1);
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn channel_boxed<U>() -> (BackendRequester<Box<U>, BoxedBackend<U>>,
+                              BackendResponder<Box<U>, BoxedBackend<U>>)
+{
+    channel_with_backend::<Box<U>, BoxedBackend<U>>()
+}
+
+/// This is the backend of a channel created with `channel_boxed()`.
+pub struct BoxedBackend<U> {
+    // This says how far the current request got, up to its claim. Once
+    // a datum is published, `datum` says so instead.
+    state: AtomicU8,
+    // This is null unless it holds a published datum.
+    datum: AtomicPtr<U>,
+    _datum: PhantomData<Box<U>>,
+}
+
+// Only one side at a time owns a published box, so sharing the backend
+// only needs the box's contents to move between threads.
+unsafe impl<U: Send> Sync for BoxedBackend<U> {}
+
+impl<U> BoxedBackend<U> {
+    /// This method atomically moves the channel from one state to another.
+    /// If the channel is not in the expected state, it returns the state
+    /// it is in instead.
+    ///
+    /// # Arguments
+    ///
+    /// * from - The state the channel should be in
+    ///
+    /// * to - The state to move it to
+    ///
+    /// * ordering - The ordering of a successful transition
+    #[inline]
+    fn transition(&self, from: State, to: State, ordering: Ordering)
+                  -> result::Result<(), State> {
+        self.state.compare_exchange(from as u8, to as u8, ordering, Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(State::from_u8)
+    }
+}
+
+impl<U> ChannelBackend<Box<U>> for BoxedBackend<U> {
+    #[inline]
+    fn new() -> BoxedBackend<U> {
+        BoxedBackend {
+            state: AtomicU8::new(State::Idle as u8),
+            datum: AtomicPtr::new(ptr::null_mut()),
+            _datum: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn try_lock_request(&self) -> Result<()> {
+        self.transition(State::Idle, State::RequestLocked, Ordering::Acquire)
+            .map_err(|_| Error::AlreadyLocked)
+    }
+
+    #[inline]
+    fn flag_request(&self) {
+        // Only the requesting side, which holds the lock, writes here.
+        self.state.store(State::Requested as u8, Ordering::Release);
+    }
+
+    #[inline]
+    fn try_unflag_request(&self) -> Result<()> {
+        self.transition(State::Requested, State::Done, Ordering::Relaxed)
+            .map_err(|_| Error::TooLate)
+    }
+
+    #[inline]
+    fn try_claim_request(&self) -> Result<()> {
+        match self.transition(State::Requested, State::Claimed, Ordering::Acquire) {
+            Ok(()) => Ok(()),
+            // A claimed request with a datum waiting is as good as
+            // answered, just like `State::DataReady` on a regular channel.
+            Err(State::Claimed) if self.datum.load(Ordering::Relaxed).is_null() => {
+                Err(Error::AlreadyLocked)
+            },
+            Err(_) => Err(Error::NoRequest),
+        }
+    }
+
+    #[inline]
+    fn publish_datum(&self, datum: Box<U>) {
+        let datum = Box::into_raw(datum);
+
+        // Release the box's contents to the requesting side.
+        if self.datum.compare_exchange(ptr::null_mut(), datum, Ordering::Release, Ordering::Relaxed)
+            .is_err() {
+            drop(unsafe { Box::from_raw(datum) });
+            panic!("BoxedBackend found a datum already published for its claim!");
+        }
+    }
+
+    #[inline]
+    fn try_take_datum(&self) -> Result<Box<U>> {
+        // Acquire the box's contents the responder released.
+        let datum = self.datum.swap(ptr::null_mut(), Ordering::Acquire);
+
+        if datum.is_null() {
+            return Err(Error::Empty);
+        }
+
+        // The responder is done with the request, so nothing else can
+        // change the state before this store.
+        self.state.store(State::Done as u8, Ordering::Relaxed);
+
+        // The pointer came from `Box::into_raw()` in `publish_datum()`,
+        // and the swap made this the only copy of it.
+        Ok(unsafe { Box::from_raw(datum) })
+    }
+
+    #[inline]
+    fn unlock_request(&self) {
+        // Leave an unfinished request alone, just like the regular channel.
+        self.transition(State::Done, State::Idle, Ordering::Release).ok();
+    }
+}
+
+impl<U> Drop for BoxedBackend<U> {
+    fn drop(&mut self) {
+        // Free a datum nobody took, for example one that arrived after the
+        // requesting side gave up on its request.
+        let datum = self.datum.swap(ptr::null_mut(), Ordering::Acquire);
+
+        if !datum.is_null() {
+            drop(unsafe { Box::from_raw(datum) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_boxed_backend_frees_pending_datum() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (rqst, resp) = channel_boxed::<Counted>();
+
+        let contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(Box::new(Counted(drops.clone())));
+
+        // Dropping the contract without receiving the datum panics and
+        // leaves the datum in the slot.
+        assert!(panic::catch_unwind(AssertUnwindSafe(move || drop(contract))).is_err());
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+        drop((rqst, resp));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,515 @@
+//! This module implements broadcast requests, which collect one datum from
+//! every `Responder` that is alive when the request is made.
+//!
+//! The channel keeps a roster of its live responders. A broadcast copies
+//! the roster, and each responder on the copy may claim the broadcast
+//! once. Responders that are dropped before claiming it are struck from
+//! the copy, so the requester never waits on a responder that is gone.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::thread;
+
+use super::atomic::Ordering;
+use super::monitor::{ClaimTally, ResponderStats};
+use super::{ChannelEvent, Error, Inner, Link, Result, State};
+
+/// This identifies a `Responder` within its channel. Every `Responder`,
+/// including every clone, gets its own id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResponderId(usize);
+
+/// This is the contract returned by a successful `Requester::try_request_all()`.
+/// It represents the caller's exclusive access to the requesting side of
+/// the channel while it collects one datum from every live responder.
+/// To prevent data loss, `BroadcastContract` will panic if it is dropped
+/// before every expected response has been received.
+pub struct BroadcastContract<T> {
+    inner: Link<T>,
+    received: usize,
+}
+
+impl<T> BroadcastContract<T> {
+    #[inline]
+    pub(crate) fn new(inner: Link<T>) -> BroadcastContract<T> {
+        BroadcastContract {
+            inner,
+            received: 0,
+        }
+    }
+
+    /// This method attempts to receive the next datum, along with the id
+    /// of the responder that sent it. Data arrive in the order they were
+    /// sent.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it after receiving
+    /// every expected datum.
+    pub fn try_receive(&mut self) -> Result<(ResponderId, T)> {
+        if self.responses_remaining() == 0 {
+            return Err(Error::Done);
+        }
+
+        let response = match self.inner.try_pop_broadcast() {
+            Ok(response) => response,
+            Err(err) => {
+                // Nobody can claim the rest of the broadcast of a closed
+                // channel.
+                if self.inner.is_closed() {
+                    let _ = self.try_cancel();
+
+                    if self.responses_remaining() == 0 {
+                        return Err(Error::Closed);
+                    }
+                }
+
+                self.inner.count_empty_poll();
+                return Err(err);
+            },
+        };
+        self.received += 1;
+        self.inner.notify(ChannelEvent::DatumReceived);
+
+        Ok(response)
+    }
+
+    /// This method returns how many data the contract still expects. It
+    /// drops when a responder that has not claimed the broadcast is dropped.
+    #[inline]
+    pub fn responses_remaining(&self) -> usize {
+        self.inner.broadcast_expected() - self.received
+    }
+
+    /// This method attempts to cancel the broadcast for every responder
+    /// that has not claimed it yet. Responders that already claimed it can
+    /// still send their data, and the contract still has to receive them.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::TooLate)` if every live responder has already
+    /// claimed the broadcast, and `Err(Error::Done)` if the contract has
+    /// already been cancelled or received every datum.
+    pub fn try_cancel(&mut self) -> Result<()> {
+        if self.responses_remaining() == 0 {
+            return Err(Error::Done);
+        }
+
+        match self.inner.unflag_broadcast() {
+            0 => Err(Error::TooLate),
+            _ => {
+                self.inner.count_cancelled();
+                self.inner.notify(ChannelEvent::RequestCancelled);
+                Ok(())
+            },
+        }
+    }
+}
+
+impl<T> Drop for BroadcastContract<T> {
+    fn drop(&mut self) {
+        if self.responses_remaining() > 0 {
+            #[cfg(feature = "tracing")]
+            self.inner.trace_unresolved("BroadcastContract");
+            if !thread::panicking() {
+                panic!("Dropping BroadcastContract without receiving data!");
+            }
+        }
+
+        self.inner.finish_broadcast();
+        self.inner.unlock_request();
+    }
+}
+
+/// This tracks the live responders of a channel and the current broadcast.
+#[doc(hidden)]
+pub(crate) struct Roster<T> {
+    next_id: usize,
+    live: Vec<usize>,
+    // The claim tallies of the live responders.
+    tallies: Vec<(ResponderId, Arc<ClaimTally>)>,
+    // The responders that may still claim the current broadcast.
+    pending: Vec<usize>,
+    expected: usize,
+    responses: VecDeque<(ResponderId, T)>,
+}
+
+impl<T> Roster<T> {
+    /// This function creates an empty roster.
+    #[inline]
+    pub(crate) const fn new() -> Roster<T> {
+        Roster {
+            next_id: 0,
+            live: Vec::new(),
+            tallies: Vec::new(),
+            pending: Vec::new(),
+            expected: 0,
+            responses: VecDeque::new(),
+        }
+    }
+
+    /// This method adds a new responder and returns its id and claim
+    /// tally.
+    #[inline]
+    fn add(&mut self) -> (ResponderId, Arc<ClaimTally>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.live.push(id);
+
+        let tally = Arc::new(ClaimTally::new());
+        self.tallies.push((ResponderId(id), tally.clone()));
+
+        (ResponderId(id), tally)
+    }
+}
+
+/// This function removes `id` from `ids` if it is there. It returns
+/// whether or not it was.
+#[inline]
+fn strike(ids: &mut Vec<usize>, id: usize) -> bool {
+    match ids.iter().position(|&other| other == id) {
+        Some(index) => {
+            ids.swap_remove(index);
+            true
+        },
+        None => false,
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method adds a new responder to the roster and returns its id
+    /// and claim tally.
+    #[inline]
+    pub(crate) fn register_responder(&self) -> (ResponderId, Arc<ClaimTally>) {
+        let mut roster = self.roster.lock().unwrap();
+
+        self.response.responders.fetch_add(1, Ordering::Relaxed);
+        roster.add()
+    }
+
+    /// This method adds a new responder to the roster if any other is
+    /// still on it, and returns its id and claim tally.
+    #[inline]
+    pub(crate) fn try_register_responder(&self) -> Option<(ResponderId, Arc<ClaimTally>)> {
+        let mut roster = self.roster.lock().unwrap();
+
+        if roster.live.is_empty() {
+            None
+        }
+        else {
+            self.response.responders.fetch_add(1, Ordering::Relaxed);
+            Some(roster.add())
+        }
+    }
+
+    /// This method removes a dropped responder from the roster. If it had
+    /// not claimed the current broadcast yet, the broadcast stops
+    /// expecting a datum from it.
+    ///
+    /// This is the only place the number of live responders drops, so
+    /// anything that must happen when the last one goes belongs here.
+    #[inline]
+    pub(crate) fn unregister_responder(&self, id: ResponderId) {
+        let mut roster = self.roster.lock().unwrap();
+
+        if strike(&mut roster.live, id.0) {
+            self.response.responders.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        if let Some(index) = roster.tallies.iter().position(|&(other, _)| other == id) {
+            roster.tallies.swap_remove(index);
+        }
+
+        if strike(&mut roster.pending, id.0) {
+            roster.expected -= 1;
+        }
+    }
+
+    /// This method returns the number of live responders.
+    #[inline]
+    pub(crate) fn responder_count(&self) -> usize {
+        self.response.responders.load(Ordering::Relaxed)
+    }
+
+    /// This method returns the claim statistics of every live responder,
+    /// ordered by id.
+    pub(crate) fn responder_stats(&self) -> Vec<ResponderStats> {
+        let mut stats = self.roster.lock().unwrap().tallies.iter()
+            .map(|&(id, ref tally)| tally.stats(id))
+            .collect::<Vec<_>>();
+
+        stats.sort_by_key(|stats| stats.id);
+        stats
+    }
+
+    /// This method opens a broadcast to every live responder.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    pub(crate) fn flag_broadcast(&self) {
+        self.debug_check_state(&[State::RequestLocked]);
+
+        {
+            let mut roster = self.roster.lock().unwrap();
+
+            roster.pending = roster.live.clone();
+            roster.expected = roster.pending.len();
+
+            self.number_request();
+
+            #[cfg(feature = "tracing")]
+            self.trace_issued("broadcast", roster.expected);
+        }
+
+        self.request.broadcasting.store(true, Ordering::Release);
+    }
+
+    /// This method tries to claim the current broadcast on behalf of a
+    /// single `Responder`.
+    ///
+    /// # Arguments
+    ///
+    /// * id - The id of the responder
+    #[inline]
+    pub(crate) fn try_claim_broadcast(&self, id: ResponderId) -> Result<()> {
+        // Skip the roster's lock unless a broadcast is open.
+        if !self.request.broadcasting.load(Ordering::Acquire) {
+            return Err(Error::NoRequest);
+        }
+
+        let mut roster = self.roster.lock().unwrap();
+
+        if strike(&mut roster.pending, id.0) {
+            Ok(())
+        }
+        else {
+            Err(Error::NoRequest)
+        }
+    }
+
+    /// This method hands a responder's claim on the current broadcast
+    /// back, so it can claim the broadcast again.
+    ///
+    /// # Arguments
+    ///
+    /// * id - The id of the responder
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** a responder that claimed the broadcast should call it,
+    /// instead of sending a datum.
+    #[inline]
+    pub(crate) fn unclaim_broadcast(&self, id: ResponderId) {
+        // The broadcast still expects the response, even if the
+        // requesting side cancelled the rest of it since.
+        self.roster.lock().unwrap().pending.push(id.0);
+    }
+
+    /// This method adds a response to the current broadcast.
+    ///
+    /// # Arguments
+    ///
+    /// * id - The id of the responder
+    ///
+    /// * datum - The datum to add
+    #[inline]
+    pub(crate) fn push_broadcast(&self, id: ResponderId, datum: T) {
+        self.roster.lock().unwrap().responses.push_back((id, datum));
+    }
+
+    /// This method records which responder sent the datum of the current
+    /// single-response request.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that claimed the request should call it,
+    /// before it sets the datum.
+    #[inline]
+    pub(crate) fn set_answerer(&self, id: ResponderId) {
+        // The datum's Release publishes this to the requesting side.
+        self.response.answerer.store(id.0, Ordering::Relaxed);
+    }
+
+    /// This method returns which responder sent the datum of the current
+    /// single-response request.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it, after
+    /// it received the datum and before it finishes the request.
+    #[inline]
+    pub(crate) fn answerer(&self) -> ResponderId {
+        ResponderId(self.response.answerer.load(Ordering::Relaxed))
+    }
+
+    /// This method tries to take the oldest response out of the
+    /// current broadcast.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn try_pop_broadcast(&self) -> Result<(ResponderId, T)> {
+        self.roster.lock().unwrap().responses.pop_front().ok_or(Error::Empty)
+    }
+
+    /// This method returns how many responses the current broadcast
+    /// expects in total.
+    #[inline]
+    fn broadcast_expected(&self) -> usize {
+        self.roster.lock().unwrap().expected
+    }
+
+    /// This method withdraws the current broadcast from every responder
+    /// that has not claimed it. It returns how many it withdrew.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn unflag_broadcast(&self) -> usize {
+        let mut roster = self.roster.lock().unwrap();
+
+        let withdrawn = roster.pending.len();
+        roster.pending.clear();
+        roster.expected -= withdrawn;
+
+        withdrawn
+    }
+
+    /// This method closes the current broadcast.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn finish_broadcast(&self) {
+        self.request.broadcasting.store(false, Ordering::Release);
+
+        let mut roster = self.roster.lock().unwrap();
+        roster.pending.clear();
+        roster.expected = 0;
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::super::{channel, Error};
+
+    #[test]
+    fn test_requester_try_request_all() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request_all().ok().unwrap();
+        assert_eq!(contract.responses_remaining(), 2);
+
+        resp.try_respond().ok().unwrap().send(1);
+
+        // Each responder may only claim the broadcast once.
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        resp2.try_respond().ok().unwrap().send(2);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), (resp.id(), 1));
+        assert_eq!(contract.try_receive().ok().unwrap(), (resp2.id(), 2));
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_broadcast_contract_responder_dropped() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request_all().ok().unwrap();
+
+        resp.try_respond().ok().unwrap().send(1);
+
+        // `resp2` never claimed the broadcast, so it is not expected anymore.
+        drop(resp2);
+        assert_eq!(contract.responses_remaining(), 1);
+
+        // `resp` already claimed it, so dropping it changes nothing.
+        let id = resp.id();
+        drop(resp);
+        assert_eq!(contract.responses_remaining(), 1);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), (id, 1));
+        assert_eq!(contract.responses_remaining(), 0);
+    }
+
+    #[test]
+    fn test_broadcast_contract_late_clone() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request_all().ok().unwrap();
+
+        // Responders created after the broadcast are not part of it.
+        let resp2 = resp.clone();
+        match resp2.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        resp.try_respond().ok().unwrap().send(1);
+        contract.try_receive().ok().unwrap();
+    }
+
+    #[test]
+    fn test_broadcast_contract_try_cancel() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        {
+            let mut contract = rqst.try_request_all().ok().unwrap();
+
+            let resp_contract = resp.try_respond().ok().unwrap();
+
+            contract.try_cancel().ok().unwrap();
+            assert_eq!(contract.responses_remaining(), 1);
+
+            match resp2.try_respond() {
+                Err(Error::NoRequest) => {},
+                _ => { assert!(false); },
+            }
+
+            match contract.try_cancel() {
+                Err(Error::TooLate) => {},
+                _ => { assert!(false); },
+            }
+
+            resp_contract.send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        // The next request is a regular one again.
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp2.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_broadcast_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request_all().ok().unwrap();
+    }
+}
@@ -0,0 +1,605 @@
+//! This module defines a builder for channels that need options.
+//! `channel()` stays the way to create a plain channel, and every other
+//! free function that creates a shared channel with options goes through
+//! `Builder`, so the options are combined in one place.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::backoff::{self, SpinTuner};
+use super::lease::timeout_nanos;
+use super::throttle::Throttle;
+use super::validate::{Validated, ValidatorFn};
+#[cfg(feature = "testing")]
+use super::testing::{Control, Controller};
+use super::{ChannelObserver, Inner, Link, Monitor, Requester, Responder, ValidationError};
+
+/// This builds a `reqchan` with options.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// struct Count(AtomicUsize);
+///
+/// impl chan::ChannelObserver for Count {
+///     fn on_event(&self, _: chan::ChannelEvent) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let count = Arc::new(Count(AtomicUsize::new(0)));
+///
+/// let (requester, responder, monitor) = chan::Builder::new()
+///     .observer(count.clone())
+///     .build_monitored::<u32>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+/// contract.try_receive().ok().unwrap();
+///
+/// assert_eq!(count.0.load(Ordering::Relaxed), 4);
+/// assert_eq!(monitor.snapshot().responses_sent, 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct Builder<V = ()> {
+    observer: Option<Arc<dyn ChannelObserver + Send + Sync>>,
+    min_request_interval: Option<Duration>,
+    claim_timeout: Option<Duration>,
+    spin_limits: Option<(u32, u32)>,
+    #[cfg(feature = "tracing")]
+    name: Option<&'static str>,
+    #[cfg(feature = "testing")]
+    control: Option<Arc<Control>>,
+    // This is `Validated<T>` once the builder has a validator for `T`s.
+    validator: V,
+}
+
+impl Builder {
+    /// This function creates a builder with every option at its default,
+    /// which builds the same channel as `channel()`.
+    #[inline]
+    pub fn new() -> Builder {
+        Builder::default()
+    }
+
+    /// This method makes the channel check every datum with `validator`
+    /// before it reaches the requesting side. A datum `validator` rejects
+    /// is counted, reported with `ChannelEvent::DatumRejected`, and not
+    /// sent: `ResponseContract::try_send()` hands it back and ends the
+    /// contract, and the request goes back to the other responders. The
+    /// builder then only builds channels of `T`.
+    ///
+    /// # Arguments
+    ///
+    /// * `validator` - The check every datum must pass
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::Builder::new()
+    ///     .validator(|num: &u32| {
+    ///         if *num < 10 { Ok(()) } else { Err(chan::ValidationError("too large")) }
+    ///     })
+    ///     .build();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// let mut rejected = responder.try_respond().ok().unwrap();
+    /// assert_eq!(rejected.try_send(12), Err(chan::SendError(12)));
+    ///
+    /// // The request is still pending, for a datum that passes.
+    /// responder.try_respond().ok().unwrap().send(2);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    /// ```
+    pub fn validator<T, F>(self, validator: F) -> Builder<Validated<T>>
+        where F: Fn(&T) -> Result<(), ValidationError> + Send + Sync + 'static
+    {
+        Builder {
+            observer: self.observer,
+            min_request_interval: self.min_request_interval,
+            claim_timeout: self.claim_timeout,
+            spin_limits: self.spin_limits,
+            #[cfg(feature = "tracing")]
+            name: self.name,
+            #[cfg(feature = "testing")]
+            control: self.control,
+            validator: Validated(Arc::new(validator)),
+        }
+    }
+
+    /// This method creates the channel and returns a tuple containing its
+    /// two ends.
+    pub fn build<T>(self) -> (Requester<T>, Responder<T>) {
+        let inner = self.link(None);
+
+        (
+            Requester::new(inner.clone()),
+            Responder::new(inner),
+        )
+    }
+
+    /// This method creates the channel and returns a tuple containing its
+    /// two ends and a `Monitor` of it.
+    pub fn build_monitored<T>(self) -> (Requester<T>, Responder<T>, Monitor<T>) {
+        let inner = self.link(None);
+
+        (
+            Requester::new(inner.clone()),
+            Responder::new(inner.clone()),
+            Monitor::new(inner),
+        )
+    }
+}
+
+impl<T> Builder<Validated<T>> {
+    /// This method creates the channel and returns a tuple containing its
+    /// two ends.
+    pub fn build(self) -> (Requester<T>, Responder<T>) {
+        let validator = self.validator.0.clone();
+        let inner = self.link(Some(validator));
+
+        (
+            Requester::new(inner.clone()),
+            Responder::new(inner),
+        )
+    }
+
+    /// This method creates the channel and returns a tuple containing its
+    /// two ends and a `Monitor` of it.
+    pub fn build_monitored(self) -> (Requester<T>, Responder<T>, Monitor<T>) {
+        let validator = self.validator.0.clone();
+        let inner = self.link(Some(validator));
+
+        (
+            Requester::new(inner.clone()),
+            Responder::new(inner.clone()),
+            Monitor::new(inner),
+        )
+    }
+}
+
+impl<V> Builder<V> {
+
+    /// This method makes the channel report its events to `observer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `observer` - The observer of the channel's events
+    pub fn observer(mut self, observer: Arc<dyn ChannelObserver + Send + Sync>) -> Builder<V> {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// This method keeps the requesting side from issuing a request until
+    /// `interval` has passed since its previous request finished, by
+    /// being received, cancelled or collected. A request made too early
+    /// fails with `Error::Throttled`. Responders are not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The least time between two requests
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::time::Duration;
+    ///
+    /// let (requester, _responder) = chan::Builder::new()
+    ///     .min_request_interval(Duration::from_secs(60))
+    ///     .build::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// contract.try_cancel().ok().unwrap();
+    /// drop(contract);
+    ///
+    /// match requester.try_request() {
+    ///     Err(chan::Error::Throttled) => {},
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn min_request_interval(mut self, interval: Duration) -> Builder<V> {
+        self.min_request_interval = Some(interval);
+        self
+    }
+
+    /// This method lets the requesting side take back a single-response
+    /// request that a responder claimed but has not answered within
+    /// `timeout`. The requesting side checks when it polls for the datum,
+    /// and the request goes back to the other responders. The stale
+    /// `ResponseContract` cannot send any more: `try_send()` hands the
+    /// datum back and `send()` drops it. A `HeldResponse` or `Reservation`
+    /// that leaves a request unclaimed for as long is taken back too.
+    ///
+    /// That way a responder whose thread died after it claimed a request,
+    /// or that leaked its `ResponseContract` with `mem::forget()`, cannot
+    /// keep the requesting side waiting forever.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long a responder may hold a claim
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let (requester, responder) = chan::Builder::new()
+    ///     .claim_timeout(Duration::from_millis(1))
+    ///     .build::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// let mut stalled = responder.try_respond().ok().unwrap();
+    ///
+    /// // The requesting side has to see the claim before it can time it.
+    /// assert!(contract.try_receive().is_err());
+    /// thread::sleep(Duration::from_millis(2));
+    /// assert!(contract.try_receive().is_err());
+    ///
+    /// // The claim was taken back, so another responder can answer.
+    /// responder.try_respond().ok().unwrap().send(2);
+    /// assert_eq!(stalled.try_send(1), Err(chan::SendError(1)));
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn claim_timeout(mut self, timeout: Duration) -> Builder<V> {
+        self.claim_timeout = Some(timeout);
+        self
+    }
+
+    /// This method bounds how many rounds the channel's blocking helpers,
+    /// such as `SharedRequester::request()` or `PullReceiver::recv()`,
+    /// spin before they start yielding the thread. Each round spins twice
+    /// as long as the one before. The channel counts how many recent waits
+    /// ended while they were still spinning, and moves the number of
+    /// rounds up when most did and down when few did, but never outside
+    /// `min..=max`. The bounds default to 2 and 10 rounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The fewest rounds to spin
+    /// * `max` - The most rounds to spin
+    ///
+    /// # Panics
+    ///
+    /// It panics if `min` is greater than `max`, or `max` is greater
+    /// than 16.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::thread;
+    ///
+    /// // Waits on this channel hardly spin before they yield.
+    /// let (requester, responder) = chan::Builder::new()
+    ///     .spin_limits(0, 2)
+    ///     .build::<u32>();
+    /// let receiver = requester.into_receiver();
+    ///
+    /// let worker = thread::spawn(move || {
+    ///     for contract in responder.incoming() {
+    ///         contract.send(1);
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(receiver.recv().ok().unwrap(), 1);
+    ///
+    /// drop(receiver);
+    /// worker.join().unwrap();
+    /// ```
+    pub fn spin_limits(mut self, min: u32, max: u32) -> Builder<V> {
+        assert!(min <= max, "The least spin limit cannot exceed the greatest!");
+        assert!(max <= backoff::SPIN_CEILING, "A spin limit cannot exceed 16 rounds!");
+
+        self.spin_limits = Some((min, max));
+        self
+    }
+
+    /// This method sets the name of the channel in trace events. It is
+    /// only available with the `tracing` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the channel
+    #[cfg(feature = "tracing")]
+    pub fn name(mut self, name: &'static str) -> Builder<V> {
+        self.name = Some(name);
+        self
+    }
+
+    /// This method makes the channel stop at the yield points
+    /// `controller` arms. It is only available with the `testing`
+    /// feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `controller` - The controller of the channel's yield points
+    #[cfg(feature = "testing")]
+    pub fn controller(mut self, controller: &Controller) -> Builder<V> {
+        self.control = Some(controller.control.clone());
+        self
+    }
+
+    /// This method assembles the channel's state with every option
+    /// applied.
+    fn link<T>(self, validator: Option<ValidatorFn<T>>) -> Link<T> {
+        let mut inner = Inner::new();
+        inner.observer = self.observer;
+        inner.throttle = self.min_request_interval.map(Throttle::new);
+        inner.claim_timeout = self.claim_timeout.map(timeout_nanos);
+        inner.validator = validator;
+
+        if let Some((min, max)) = self.spin_limits {
+            inner.spin = SpinTuner::new(min, max);
+        }
+
+        #[cfg(feature = "tracing")]
+        if let Some(name) = self.name {
+            inner.name = name;
+        }
+
+        #[cfg(feature = "testing")]
+        {
+            inner.control = self.control;
+        }
+
+        Link::Shared(Arc::new(inner))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use super::super::ChannelEvent;
+
+    struct Log(Mutex<Vec<ChannelEvent>>);
+
+    impl ChannelObserver for Log {
+        fn on_event(&self, event: ChannelEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn test_builder_default() {
+        let (rqst, resp) = Builder::new().build::<u32>();
+
+        assert!(rqst.inner.observer.is_none());
+        #[cfg(feature = "tracing")]
+        assert_eq!(rqst.inner.name, "reqchan");
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_builder_observer() {
+        let log = Arc::new(Log(Mutex::new(Vec::new())));
+        let (rqst, resp, monitor) = Builder::new()
+            .observer(log.clone())
+            .build_monitored::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        contract.try_receive().ok().unwrap();
+
+        assert_eq!(log.0.lock().unwrap().len(), 4);
+        assert_eq!(monitor.snapshot().requests_issued, 1);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_builder_min_request_interval() {
+        use std::time::Instant;
+
+        use super::super::{Error, RetryPolicy};
+
+        let (rqst, resp) = Builder::new()
+            .min_request_interval(Duration::from_millis(20))
+            .build::<u32>();
+
+        // The first request is never held back.
+        let start = Instant::now();
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        match rqst.try_request() {
+            Err(Error::Throttled) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.request_with_retry(&RetryPolicy::new()).ok().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        contract.try_cancel().ok().unwrap();
+
+        // A channel without the option has no throttle at all.
+        let (rqst, _resp) = Builder::new().build::<u32>();
+        assert!(rqst.inner.throttle.is_none());
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_builder_claim_timeout() {
+        use std::thread;
+
+        use super::super::{Error, SendError};
+
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .build::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut stalled = resp.try_respond().ok().unwrap();
+
+        // The requesting side starts timing the claim when it first sees it.
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+        thread::sleep(Duration::from_millis(2));
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        // The stale claim gets its datum back, and another one is served.
+        let fresh = resp.try_respond().ok().unwrap();
+        assert_eq!(stalled.try_send(1), Err(SendError(1)));
+        fresh.send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        drop(contract);
+
+        // A claim that already sent cannot be taken back.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+        thread::sleep(Duration::from_millis(2));
+        resp_contract.send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+        drop(contract);
+
+        // A channel without the option never takes claims back.
+        let (rqst, resp) = Builder::new().build::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert!(!rqst.inner.try_revoke_claim(&mut Default::default()));
+        resp_contract.send(4);
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_builder_spin_limits() {
+        use std::thread;
+
+        let (rqst, resp) = Builder::new()
+            .spin_limits(1, 3)
+            .build::<u32>();
+
+        // The limit starts as close to the default as the bounds allow.
+        assert_eq!(rqst.inner.spin.limit(), 3);
+
+        // However the waits end, the limit stays within the bounds.
+        let inner = rqst.inner.clone();
+        let receiver = rqst.into_receiver();
+        let worker = thread::spawn(move || {
+            for (i, contract) in resp.incoming().enumerate() {
+                if i % 2 == 0 {
+                    thread::yield_now();
+                }
+                contract.send(i as u32);
+            }
+        });
+
+        for i in 0..1000 {
+            assert_eq!(receiver.recv().ok().unwrap(), i);
+
+            let limit = inner.spin.limit();
+            assert!((1..=3).contains(&limit));
+        }
+
+        drop(receiver);
+        worker.join().unwrap();
+
+        // A channel without the option uses the default bounds.
+        let (rqst, _resp) = Builder::new().build::<u32>();
+        assert_eq!(rqst.inner.spin.limit(), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_builder_spin_limits_inverted() {
+        Builder::new().spin_limits(4, 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_builder_spin_limits_too_high() {
+        Builder::new().spin_limits(0, 17);
+    }
+
+    #[test]
+    fn test_builder_validator() {
+        use super::super::{Error, SendError};
+
+        let log = Arc::new(Log(Mutex::new(Vec::new())));
+        let (rqst, resp, monitor) = Builder::new()
+            .observer(log.clone())
+            .validator(|num: &u32| {
+                if num.is_multiple_of(2) { Ok(()) } else { Err(ValidationError("odd")) }
+            })
+            .build_monitored();
+
+        // A rejected datum comes back, and the request stays pending.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.try_send(1), Err(SendError(1)));
+        assert_eq!(resp_contract.try_send(2), Err(SendError(2)));
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        // `send()` drops a rejected datum.
+        resp.try_respond().ok().unwrap().send(3);
+        resp.try_respond().ok().unwrap().send(4);
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+        drop(contract);
+
+        assert_eq!(monitor.snapshot().datums_rejected, 2);
+        assert_eq!(monitor.snapshot().responses_sent, 1);
+        assert_eq!(log.0.lock().unwrap().iter()
+                   .filter(|event| **event == ChannelEvent::DatumRejected(resp.id(), ValidationError("odd")))
+                   .count(), 2);
+
+        // So does a request for several responses, for the other
+        // responders.
+        let (resp2, resp3) = (resp.clone(), resp.clone());
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.try_send(5), Err(SendError(5)));
+        resp2.try_respond().ok().unwrap().send(6);
+        resp3.try_respond().ok().unwrap().send(8);
+        assert_eq!(contract.try_receive().ok().unwrap(), 6);
+        assert_eq!(contract.try_receive().ok().unwrap(), 8);
+        assert_eq!(monitor.snapshot().datums_rejected, 3);
+
+        // A channel without a validator sends anything.
+        let (rqst, resp) = Builder::new().build::<u32>();
+        assert!(rqst.inner.validator.is_none());
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_builder_name() {
+        let (rqst, _resp) = Builder::new().name("jobs").build::<u32>();
+        assert_eq!(rqst.inner.name, "jobs");
+    }
+}
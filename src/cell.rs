@@ -0,0 +1,44 @@
+//! This module picks the `UnsafeCell` the channel keeps its datum in. By
+//! default it is `std`'s. Under `cfg(loom)` it is `loom`'s, which checks
+//! every access to the datum for data races.
+
+/// This is an `UnsafeCell` that is only accessed through raw pointers.
+#[doc(hidden)]
+pub(crate) struct UnsafeCell<T> {
+    #[cfg(not(loom))]
+    cell: ::std::cell::UnsafeCell<T>,
+    #[cfg(loom)]
+    cell: ::loom::cell::UnsafeCell<T>,
+}
+
+impl<T> UnsafeCell<T> {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new(value: T) -> UnsafeCell<T> {
+            UnsafeCell {
+                #[cfg(not(loom))]
+                cell: ::std::cell::UnsafeCell::new(value),
+                #[cfg(loom)]
+                cell: ::loom::cell::UnsafeCell::new(value),
+            }
+        }
+    }
+
+    /// This method calls `f` with a raw pointer to the cell's value.
+    #[cfg(not(loom))]
+    #[inline]
+    pub(crate) fn with_mut<R, F>(&self, f: F) -> R
+        where F: FnOnce(*mut T) -> R
+    {
+        f(self.cell.get())
+    }
+
+    /// This method calls `f` with a raw pointer to the cell's value.
+    #[cfg(loom)]
+    #[inline]
+    pub(crate) fn with_mut<R, F>(&self, f: F) -> R
+        where F: FnOnce(*mut T) -> R
+    {
+        self.cell.with_mut(f)
+    }
+}
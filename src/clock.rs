@@ -0,0 +1,47 @@
+//! This module measures time for the parts of a channel that timestamp
+//! its events, such as the metrics, the watchdog and the request
+//! throttle, and for the deadlines requests carry. A timestamp is the number of nanoseconds since a process-wide
+//! epoch, so it fits in an atomic.
+
+// `wasm32-unknown-unknown` has no clock, so the deadlines callers pass
+// in are left out there.
+#![cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code, unused_imports))]
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// This function returns the moment every channel measures time from.
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// This function returns the nanoseconds since `epoch()`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[inline]
+pub(crate) fn now() -> u64 {
+    epoch().elapsed().as_nanos() as u64
+}
+
+/// This function stands in for the clock `wasm32-unknown-unknown` does
+/// not have, where `Instant::now()` panics. Time stands still at `0`, so
+/// no timestamp ever passes and every recorded latency is zero.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[inline]
+pub(crate) fn now() -> u64 {
+    0
+}
+
+/// This function converts an `Instant` to a timestamp. Instants before
+/// `epoch()` become `0`.
+#[inline]
+pub(crate) fn from_instant(instant: Instant) -> u64 {
+    instant.saturating_duration_since(epoch()).as_nanos() as u64
+}
+
+/// This function converts a timestamp back to an `Instant`.
+#[inline]
+pub(crate) fn to_instant(timestamp: u64) -> Instant {
+    epoch() + Duration::from_nanos(timestamp)
+}
@@ -0,0 +1,203 @@
+//! This module defines a duplex channel: two opposed channels wired
+//! together for RPC-style use. Each side can request data from the other
+//! and respond to the other's requests.
+
+use super::backoff::Backoff;
+use super::{channel, Error, RequestContract, Requester, Responder,
+            ResponseContract, Result};
+
+/// This function creates a duplex channel and returns its two endpoints.
+/// The first endpoint requests `T`s from the second and responds to its
+/// requests with `U`s; the second does the opposite.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// // The worker requests tasks and hands back results.
+/// let (worker, scheduler) = chan::duplex::<u32, String>();
+///
+/// let handle = thread::spawn(move || {
+///     worker.serve_once(|task| format!("task {} done", task));
+/// });
+///
+/// // Hand out a task.
+/// loop {
+///     if let Ok(contract) = scheduler.try_respond() {
+///         contract.send(7);
+///         break;
+///     }
+/// }
+///
+/// // Collect the result.
+/// let mut contract = scheduler.request();
+/// let result = loop {
+///     if let Ok(result) = contract.try_receive() {
+///         break result;
+///     }
+/// };
+///
+/// handle.join().unwrap();
+/// assert_eq!(result, "task 7 done");
+/// ```
+pub fn duplex<T, U>() -> (Endpoint<T, U>, Endpoint<U, T>) {
+    let (requester, peer_responder) = channel::<T>();
+    let (peer_requester, responder) = channel::<U>();
+
+    (
+        Endpoint { requester, responder },
+        Endpoint { requester: peer_requester, responder: peer_responder },
+    )
+}
+
+/// This is one side of a duplex channel. It requests `T`s from the other
+/// side and responds to the other side's requests with `U`s.
+pub struct Endpoint<T, U> {
+    requester: Requester<T>,
+    responder: Responder<U>,
+}
+
+impl<T, U> Endpoint<T, U> {
+    /// This method works just like `Requester::try_request()`.
+    #[inline]
+    pub fn try_request(&self) -> Result<RequestContract<T>> {
+        self.requester.try_request()
+    }
+
+    /// This method issues a request, waiting for the current one to
+    /// finish first if necessary.
+    ///
+    /// # Warning
+    ///
+    /// It waits forever if the current thread already holds a contract.
+    /// It panics if the channel is closed.
+    pub fn request(&self) -> RequestContract<T> {
+        match self.wait_request() {
+            Ok(contract) => contract,
+            Err(_) => { panic!("Cannot request from a closed channel!"); },
+        }
+    }
+
+    /// This method issues a request, waiting for the current one to
+    /// finish first if necessary, unless the channel is closed.
+    fn wait_request(&self) -> Result<RequestContract<T>> {
+        let mut backoff = Backoff::tuned(&self.requester.inner.spin);
+
+        loop {
+            match self.requester.try_request() {
+                Ok(contract) => { return Ok(contract); },
+                Err(Error::AlreadyLocked) => { backoff.snooze(); },
+                Err(Error::Closed) => { return Err(Error::Closed); },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+
+    /// This method works just like `Responder::try_respond()`.
+    #[inline]
+    pub fn try_respond(&self) -> Result<ResponseContract<U>> {
+        self.responder.try_respond()
+    }
+
+    /// This method returns the `Requester` of this endpoint.
+    #[inline]
+    pub fn requester(&self) -> &Requester<T> {
+        &self.requester
+    }
+
+    /// This method returns the `Responder` of this endpoint.
+    #[inline]
+    pub fn responder(&self) -> &Responder<U> {
+        &self.responder
+    }
+
+    /// This method splits the endpoint into its `Requester` and `Responder`.
+    #[inline]
+    pub fn into_parts(self) -> (Requester<T>, Responder<U>) {
+        (self.requester, self.responder)
+    }
+
+    /// This method requests a `T` from the other side, turns it into a
+    /// `U` with `f`, and sends the `U` in response to the other side's
+    /// next request.
+    ///
+    /// # Warning
+    ///
+    /// It waits until the other side sends a `T` and then until it
+    /// requests a `U`, so it waits forever if the other side never does.
+    /// It gives up if either channel is closed in the meantime.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function that computes the response
+    pub fn serve_once<F>(&self, f: F)
+        where F: FnOnce(T) -> U
+    {
+        let mut backoff = Backoff::tuned(&self.requester.inner.spin);
+
+        let mut contract = match self.wait_request() {
+            Ok(contract) => contract,
+            Err(_) => { return; },
+        };
+        let input = loop {
+            match contract.try_receive() {
+                Ok(input) => { break input; },
+                Err(Error::Empty) => { backoff.snooze(); },
+                Err(Error::Closed) => { return; },
+                Err(_) => unreachable!(),
+            }
+        };
+
+        let output = f(input);
+
+        let mut backoff = Backoff::tuned(&self.responder.inner.spin);
+
+        loop {
+            match self.responder.try_respond() {
+                Ok(contract) => {
+                    contract.send(output);
+                    return;
+                },
+                Err(Error::NoRequest) | Err(Error::AlreadyLocked) | Err(Error::Paused) |
+                Err(Error::SlotFull) => {
+                    backoff.snooze();
+                },
+                Err(Error::Closed) => { return; },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplex_endpoints_are_opposed() {
+        let (a, b) = duplex::<u32, &'static str>();
+
+        let mut contract = a.try_request().ok().unwrap();
+        b.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+
+        let mut contract = b.try_request().ok().unwrap();
+        a.try_respond().ok().unwrap().send("one");
+        assert_eq!(contract.try_receive().ok().unwrap(), "one");
+    }
+
+    #[test]
+    fn test_duplex_endpoint_try_respond_no_request() {
+        #[allow(unused_variables)]
+        let (a, b) = duplex::<u32, u32>();
+
+        match a.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+}
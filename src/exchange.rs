@@ -0,0 +1,399 @@
+//! This module defines an exchange channel. It works like the regular
+//! channel, but every request carries an offer of type `A`, and the
+//! responder that claims the request takes the offer and sends back a
+//! datum of type `B`. That makes it easy to trade buffers back and forth,
+//! e.g. "here is a finished result buffer; give me a new task and take the
+//! buffer back for reuse."
+//!
+//! The offer is stored before the request is flagged, so a responder that
+//! sees a request always sees its offer. An offer whose request is
+//! cancelled before anyone claims it goes back to the requester.
+
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::Arc;
+use std::thread;
+
+use super::atomic::{AtomicU8, Ordering};
+use super::{Error, Result};
+
+/// This function creates an exchange channel and returns a tuple containing
+/// its two ends. Requests offer an `A` and receive a `B` in return.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::exchange_channel::<Vec<u8>, u32>();
+///
+/// // Hand back a spent buffer and ask for a new task.
+/// let mut contract = requester.try_request_swap(vec![1, 2, 3]).ok().unwrap();
+///
+/// let mut response_contract = responder.try_respond().ok().unwrap();
+/// let mut buffer = response_contract.take_offer();
+/// buffer.clear();
+/// response_contract.send(7);
+///
+/// assert_eq!(contract.try_receive().ok().unwrap(), 7);
+/// assert!(buffer.is_empty());
+/// ```
+pub fn exchange_channel<A, B>() -> (ExchangeRequester<A, B>, ExchangeResponder<A, B>) {
+    let inner = Arc::new(Inner {
+        state: AtomicU8::new(IDLE),
+        offer: UnsafeCell::new(None),
+        datum: UnsafeCell::new(None),
+    });
+
+    (
+        ExchangeRequester { inner: inner.clone() },
+        ExchangeResponder { inner: inner.clone() },
+    )
+}
+
+/// This end of the exchange channel offers values to its
+/// `ExchangeResponder`(s) in exchange for data.
+pub struct ExchangeRequester<A, B> {
+    inner: Arc<Inner<A, B>>,
+}
+
+impl<A, B> ExchangeRequester<A, B> {
+    /// This method tries to request a datum in exchange for `offer`. If
+    /// successful, it returns an `ExchangeRequestContract` to either poll
+    /// for data or cancel the request and get the offer back.
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `ExchangeRequestContract` may be active at a time.
+    /// If another one is, it returns `Err(Error::AlreadyLocked)` and drops
+    /// `offer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `offer` - The value to hand to the responder
+    pub fn try_request_swap(&self, offer: A) -> Result<ExchangeRequestContract<A, B>> {
+        // First, try to lock the requesting side.
+        if !self.inner.transition(IDLE, LOCKED, Ordering::Acquire) {
+            return Err(Error::AlreadyLocked);
+        }
+
+        // Next, store the offer and flag the request. The Release store
+        // makes the offer visible to whichever responder claims it.
+        unsafe {
+            *self.inner.offer.get() = Some(offer);
+        }
+        self.inner.state.store(REQUESTED, Ordering::Release);
+
+        // Then return an `ExchangeRequestContract`.
+        Ok(ExchangeRequestContract {
+            inner: self.inner.clone(),
+            done: false,
+        })
+    }
+}
+
+/// This is the contract returned by a successful
+/// `ExchangeRequester::try_request_swap()`. It works just like
+/// `RequestContract`, except that cancelling it returns the offer.
+pub struct ExchangeRequestContract<A, B> {
+    inner: Arc<Inner<A, B>>,
+    done: bool,
+}
+
+impl<A, B> ExchangeRequestContract<A, B> {
+    /// This method attempts to receive the datum sent in exchange for
+    /// the offer.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it
+    /// after either receiving a datum or cancelling the request.
+    pub fn try_receive(&mut self) -> Result<B> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        if !self.inner.transition(READY, DONE, Ordering::Acquire) {
+            return Err(Error::Empty);
+        }
+
+        let datum = unsafe { ptr::replace(self.inner.datum.get(), None).unwrap() };
+        self.done = true;
+
+        Ok(datum)
+    }
+
+    /// This method attempts to cancel the request. If no responder has
+    /// claimed it yet, it returns the offer intact. Otherwise, it returns
+    /// `Err(Error::TooLate)`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::exchange_channel::<Vec<u8>, u32>();
+    ///
+    /// let mut contract = requester.try_request_swap(vec![1, 2, 3]).ok().unwrap();
+    /// assert_eq!(contract.try_cancel().ok().unwrap(), vec![1, 2, 3]);
+    ///
+    /// match responder.try_respond() {
+    ///     Err(chan::Error::NoRequest) => {},
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn try_cancel(&mut self) -> Result<A> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        // Only this contract wrote the offer, so taking it back does not
+        // need to synchronize with anything.
+        if !self.inner.transition(REQUESTED, DONE, Ordering::Relaxed) {
+            return Err(Error::TooLate);
+        }
+
+        let offer = unsafe { ptr::replace(self.inner.offer.get(), None).unwrap() };
+        self.done = true;
+
+        Ok(offer)
+    }
+}
+
+impl<A, B> Drop for ExchangeRequestContract<A, B> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping ExchangeRequestContract without receiving data!");
+        }
+
+        self.inner.state.store(IDLE, Ordering::Release);
+    }
+}
+
+/// This end of the exchange channel takes the offers of its
+/// `ExchangeRequester` and sends data in return.
+pub struct ExchangeResponder<A, B> {
+    inner: Arc<Inner<A, B>>,
+}
+
+impl<A, B> ExchangeResponder<A, B> {
+    /// This method tries to claim the pending request. If successful, it
+    /// returns an `ExchangeResponseContract` holding the request's offer.
+    pub fn try_respond(&self) -> Result<ExchangeResponseContract<A, B>> {
+        // The Acquire pairs with the Release in `try_request_swap()`, so
+        // the offer is visible once the claim succeeds.
+        if !self.inner.transition(REQUESTED, CLAIMED, Ordering::Acquire) {
+            return match self.inner.state.load(Ordering::Relaxed) {
+                CLAIMED => Err(Error::AlreadyLocked),
+                _ => Err(Error::NoRequest),
+            };
+        }
+
+        let offer = unsafe { ptr::replace(self.inner.offer.get(), None) };
+
+        Ok(ExchangeResponseContract {
+            inner: self.inner.clone(),
+            offer,
+            done: false,
+        })
+    }
+}
+
+impl<A, B> Clone for ExchangeResponder<A, B> {
+    fn clone(&self) -> Self {
+        ExchangeResponder {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// This is the contract returned by a successful
+/// `ExchangeResponder::try_respond()`. It works just like
+/// `ResponseContract`, and also holds the request's offer until the user
+/// takes it.
+pub struct ExchangeResponseContract<A, B> {
+    inner: Arc<Inner<A, B>>,
+    offer: Option<A>,
+    done: bool,
+}
+
+impl<A, B> ExchangeResponseContract<A, B> {
+    /// This method takes the offer out of the contract. If the user never
+    /// takes it, it is dropped along with the contract.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the user already took the offer.
+    pub fn take_offer(&mut self) -> A {
+        match self.offer.take() {
+            Some(offer) => offer,
+            None => panic!("The offer was already taken!"),
+        }
+    }
+
+    /// This method sends a datum to the requesting end of the channel in
+    /// exchange for the offer.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send(mut self, datum: B) {
+        unsafe {
+            *self.inner.datum.get() = Some(datum);
+        }
+
+        self.inner.state.store(READY, Ordering::Release);
+        self.done = true;
+    }
+}
+
+impl<A, B> Drop for ExchangeResponseContract<A, B> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping ExchangeResponseContract without sending data!");
+        }
+    }
+}
+
+#[doc(hidden)]
+struct Inner<A, B> {
+    state: AtomicU8,
+    // This holds the offer from when the request is flagged until a
+    // responder claims it or the requester cancels it.
+    offer: UnsafeCell<Option<A>>,
+    datum: UnsafeCell<Option<B>>,
+}
+
+const IDLE: u8 = 0;
+const LOCKED: u8 = 1;
+const REQUESTED: u8 = 2;
+const CLAIMED: u8 = 3;
+const READY: u8 = 4;
+const DONE: u8 = 5;
+
+unsafe impl<A: Send, B: Send> Sync for Inner<A, B> {}
+
+#[doc(hidden)]
+impl<A, B> Inner<A, B> {
+    /// This method atomically moves the channel from one state to another.
+    /// It returns whether or not it succeeded.
+    #[inline]
+    fn transition(&self, old: u8, new: u8, ordering: Ordering) -> bool {
+        self.state.compare_exchange(old, new, ordering, Ordering::Relaxed).is_ok()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exchange_requester_try_request_swap_multiple() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = exchange_channel::<u32, u32>();
+
+        let mut contract = rqst.try_request_swap(1).ok().unwrap();
+
+        match rqst.try_request_swap(2) {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(contract.try_cancel().ok().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_exchange_responder_try_respond() {
+        let (rqst, resp) = exchange_channel::<u32, u64>();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request_swap(3).ok().unwrap();
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+
+        match resp.clone().try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(resp_contract.take_offer(), 3);
+        resp_contract.send(4);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_exchange_response_contract_offer_not_taken() {
+        use std::rc::Rc;
+
+        let offer = Rc::new(());
+
+        {
+            let (rqst, resp) = exchange_channel::<Rc<()>, u32>();
+
+            let mut contract = rqst.try_request_swap(offer.clone()).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(5);
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert_eq!(Rc::strong_count(&offer), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_exchange_response_contract_take_offer_twice() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = exchange_channel::<u32, u32>();
+
+        let mut contract = rqst.try_request_swap(6).ok().unwrap();
+        contract.done = true;
+
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+        resp_contract.done = true;
+
+        resp_contract.take_offer();
+        resp_contract.take_offer();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_exchange_request_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = exchange_channel::<u32, u32>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request_swap(7).ok().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_exchange_response_contract_drop_without_sending_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = exchange_channel::<u32, u32>();
+
+        let mut contract = rqst.try_request_swap(8).ok().unwrap();
+        contract.done = true;
+
+        #[allow(unused_variables)]
+        let resp_contract = resp.try_respond().ok().unwrap();
+    }
+}
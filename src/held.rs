@@ -0,0 +1,533 @@
+//! This module lets a responder hold on to a channel between requests, so
+//! it keeps serving the same requester without other responders sneaking
+//! in. A responder starts holding with `ResponseContract::send_and_hold()`,
+//! or before any request with `Responder::reserve()`.
+//!
+//! Each hold gets a token no other hold used, so a holder whose hold was
+//! taken back cannot release somebody else's. On a channel with a claim
+//! timeout, the requesting side takes back a hold that leaves its request
+//! unclaimed for longer than the timeout.
+//!
+//! The hold only covers single-response requests. Requests for several
+//! responses, and broadcasts, can still be claimed by any responder.
+
+use super::atomic::Ordering;
+use super::backoff::Backoff;
+use super::{ChannelEvent, Claim, Error, Inner, Link, Responder, ResponderId, ResponseContract,
+            Result, State};
+
+/// This is returned by `ResponseContract::send_and_hold()`. While it
+/// exists, other responders cannot claim the requester's single-response
+/// requests. Dropping it releases the hold, so a requester that stops
+/// requesting can never leave the other responders locked out for longer
+/// than the holder keeps this around.
+pub struct HeldResponse<T> {
+    inner: Link<T>,
+    // The responder that holds the channel.
+    responder: ResponderId,
+    // The token of the hold, or 0 once it was handed on or released.
+    token: u64,
+}
+
+impl<T> HeldResponse<T> {
+    #[inline]
+    pub(crate) fn new(inner: Link<T>, responder: ResponderId, token: u64) -> HeldResponse<T> {
+        HeldResponse {
+            inner,
+            responder,
+            token,
+        }
+    }
+
+    /// This function creates a `HeldResponse` that holds nothing, for a
+    /// contract whose claim was revoked before it could send.
+    #[inline]
+    pub(crate) fn released(inner: Link<T>, responder: ResponderId) -> HeldResponse<T> {
+        HeldResponse {
+            inner,
+            responder,
+            token: 0,
+        }
+    }
+
+    /// This method tries to claim the requester's next request. If
+    /// successful, it returns a `ResponseContract` that takes over the
+    /// hold: sending with `ResponseContract::send()` releases it, and
+    /// sending with `ResponseContract::send_and_hold()` keeps it.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::NoRequest)` if the requester has not made
+    /// its next request yet, `Err(Error::Done)` if the hold was already
+    /// handed to a `ResponseContract`, and `Err(Error::Expired)` if the
+    /// requesting side took the hold back.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let responder2 = responder.clone();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// let mut held = responder.try_respond().ok().unwrap().send_and_hold(1);
+    /// contract.try_receive().ok().unwrap();
+    /// drop(contract);
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// // Other responders cannot claim the request.
+    /// match responder2.try_respond() {
+    ///     Err(chan::Error::AlreadyLocked) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// // Sending normally releases the hold.
+    /// held.try_next().ok().unwrap().send(2);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    /// ```
+    pub fn try_next(&mut self) -> Result<ResponseContract<T>> {
+        if self.token == 0 {
+            return Err(Error::Done);
+        }
+
+        self.inner.try_claim_held_request(self.token)?;
+        let token = self.token;
+        self.token = 0;
+        self.inner.notify(ChannelEvent::ResponseClaimed(self.responder));
+
+        Ok(ResponseContract {
+            inner: self.inner.clone(),
+            responder: self.responder,
+            amount: self.inner.get_amount(),
+            urgency: self.inner.get_urgency(),
+            sequence: self.inner.sequence(),
+            deadline: self.inner.get_deadline(),
+            tag: self.inner.get_tag(),
+            claim: Claim::Single,
+            lease: self.inner.publish_claim(),
+            held: token,
+            done: false,
+        })
+    }
+
+    /// This method waits for the requester's next request and claims it.
+    /// It works just like `HeldResponse::try_next()` otherwise.
+    ///
+    /// # Warning
+    ///
+    /// It waits forever if the requester never makes another request.
+    /// Use `HeldResponse::try_next()` to give up at some point. It panics
+    /// if the requester closes the channel, or takes the hold back, while
+    /// it waits.
+    pub fn next(mut self) -> ResponseContract<T> {
+        let inner = self.inner.clone();
+        let mut backoff = Backoff::tuned(&inner.spin);
+
+        loop {
+            match self.try_next() {
+                Ok(contract) => { return contract; },
+                Err(Error::NoRequest) | Err(Error::Paused) => { backoff.snooze(); },
+                Err(Error::Closed) => { panic!("Channel closed while waiting for a request!"); },
+                Err(Error::Expired) => { panic!("Hold taken back while waiting for a request!"); },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+
+    /// This method releases the hold, so any responder can claim the
+    /// requester's next request.
+    #[inline]
+    pub fn release(self) {}
+}
+
+impl<T> Drop for HeldResponse<T> {
+    fn drop(&mut self) {
+        if self.token != 0 {
+            self.inner.release_hold(self.token);
+        }
+    }
+}
+
+/// This is returned by `Responder::reserve()`. It holds the channel just
+/// like a `HeldResponse`, for a responder that has not answered any
+/// request yet: other responders cannot claim the requester's
+/// single-response requests while it exists, and dropping it releases
+/// the hold.
+pub struct Reservation<T> {
+    held: HeldResponse<T>,
+}
+
+impl<T> Reservation<T> {
+    /// This method tries to claim the requester's next request. It works
+    /// just like `HeldResponse::try_next()`.
+    pub fn try_claim(&mut self) -> Result<ResponseContract<T>> {
+        self.held.try_next()
+    }
+
+    /// This method waits for the requester's next request and claims it.
+    /// It returns `Err(Error::Closed)` if the requester closes the channel
+    /// while it waits, and `Err(Error::Expired)` if the requesting side
+    /// takes the hold back.
+    ///
+    /// # Warning
+    ///
+    /// It waits forever if the requester never makes another request.
+    /// Use `Reservation::try_claim()` to give up at some point.
+    pub fn wait(mut self) -> Result<ResponseContract<T>> {
+        let inner = self.held.inner.clone();
+        let mut backoff = Backoff::tuned(&inner.spin);
+
+        loop {
+            match self.try_claim() {
+                Err(Error::NoRequest) | Err(Error::Paused) => { backoff.snooze(); },
+                res => { return res; },
+            }
+        }
+    }
+
+    /// This method releases the hold, so any responder can claim the
+    /// requester's next request.
+    #[inline]
+    pub fn release(self) {}
+}
+
+impl<T> Responder<T> {
+    /// This method holds the channel for this responder before the
+    /// requester issues its next request, so this responder claims it
+    /// even if others are polling too. Other responders get
+    /// `Err(Error::AlreadyLocked)` for single-response requests until the
+    /// returned `Reservation` claims a request or is dropped.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::AlreadyLocked)` if another responder already
+    /// holds the channel. A request another responder claimed before the
+    /// reservation stays claimed. Without a claim timeout, a reservation
+    /// that is never used keeps the requester waiting until it is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let responder2 = responder.clone();
+    ///
+    /// let reservation = responder.reserve().ok().unwrap();
+    /// assert!(responder2.reserve().is_err());
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// match responder2.try_respond() {
+    ///     Err(chan::Error::AlreadyLocked) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// reservation.wait().ok().unwrap().send(1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// ```
+    pub fn reserve(&self) -> Result<Reservation<T>> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        match self.inner.try_hold() {
+            0 => Err(Error::AlreadyLocked),
+            token => Ok(Reservation {
+                held: HeldResponse::new(self.inner.clone(), self.id, token),
+            }),
+        }
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method reserves the channel's single-response requests for
+    /// the calling responder, unless another responder holds the channel.
+    /// It returns the token of the new hold, or 0 if it did not get one.
+    #[inline]
+    pub(crate) fn try_hold(&self) -> u64 {
+        let token = self.response.holds.fetch_add(1, Ordering::Relaxed) + 1;
+
+        // A responder claiming a request at the same time checks for
+        // holds again after its claim.
+        match self.response.held.compare_exchange(0, token, Ordering::SeqCst, Ordering::Relaxed) {
+            Ok(_) => token,
+            Err(_) => 0,
+        }
+    }
+
+    /// This method lets any responder claim the channel's requests again,
+    /// unless the hold with `token` already ended.
+    ///
+    /// # Arguments
+    ///
+    /// * token - The token of the hold
+    #[inline]
+    pub(crate) fn release_hold(&self, token: u64) {
+        let _ = self.response.held.compare_exchange(token, 0, Ordering::Release, Ordering::Relaxed);
+    }
+
+    /// This method returns the token of the current hold, or 0 if no
+    /// responder holds the channel.
+    #[inline]
+    pub(crate) fn hold_token(&self) -> u64 {
+        self.response.held.load(Ordering::SeqCst)
+    }
+
+    /// This method returns whether or not a responder holds the channel.
+    #[inline]
+    pub(crate) fn is_held(&self) -> bool {
+        self.hold_token() != 0
+    }
+
+    /// This method claims the current request on behalf of the responder
+    /// that holds the channel.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that holds the channel should call it.
+    ///
+    /// # Arguments
+    ///
+    /// * token - The token of the responder's hold
+    #[inline]
+    fn try_claim_held_request(&self, token: u64) -> Result<()> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if self.hold_token() != token {
+            return Err(Error::Expired);
+        }
+
+        if self.is_paused() {
+            return Err(Error::Paused);
+        }
+
+        self.transition(State::Requested, State::Claimed, Ordering::Acquire)
+            .map_err(|_| Error::NoRequest)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(unused_imports))]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::{channel, Builder, Error};
+
+    #[test]
+    fn test_held_response_locks_out_others() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut held = resp.try_respond().ok().unwrap().send_and_hold(1);
+
+        match held.try_next() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        contract.try_receive().ok().unwrap();
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match resp2.try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        // Keep holding across several requests.
+        let held = held.try_next().ok().unwrap().send_and_hold(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = held.next();
+
+        match resp2.try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        // Sending normally releases the hold.
+        resp_contract.send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp2.try_respond().ok().unwrap().send(4);
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_held_response_release() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            let held = resp.try_respond().ok().unwrap().send_and_hold(1);
+            contract.try_receive().ok().unwrap();
+
+            held.release();
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp2.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_held_response_drop_releases() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            #[allow(unused_variables)]
+            let held = resp.try_respond().ok().unwrap().send_and_hold(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp2.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_held_response_try_next_after_handing_over() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut held = resp.try_respond().ok().unwrap().send_and_hold(1);
+        contract.try_receive().ok().unwrap();
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        held.try_next().ok().unwrap().send(2);
+
+        match held.try_next() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+
+        contract.try_receive().ok().unwrap();
+    }
+
+    #[test]
+    fn test_held_response_many_not_held() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        #[allow(unused_variables)]
+        let held = resp.try_respond().ok().unwrap().send_and_hold(1);
+        contract.try_receive().ok().unwrap();
+        drop(contract);
+
+        // Any responder can claim part of a multi-response request.
+        let mut contract = rqst.try_request_many(1).ok().unwrap();
+        resp2.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_reservation_locks_out_others() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut reservation = resp.reserve().ok().unwrap();
+        match resp2.reserve() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+        match reservation.try_claim() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        match resp2.try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut held = reservation.try_claim().ok().unwrap().send_and_hold(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        // The hold carries over to the `HeldResponse`.
+        let mut contract = rqst.try_request().ok().unwrap();
+        match resp2.try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+        held.try_next().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_reservation_drop_releases() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let reservation = resp.reserve().ok().unwrap();
+        let mut contract = rqst.try_request().ok().unwrap();
+        drop(reservation);
+
+        resp2.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        resp2.reserve().ok().unwrap().release();
+        resp.close();
+        match resp.reserve() {
+            Err(Error::Closed) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_reservation_claim_timeout() {
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .build::<u32>();
+        let resp2 = resp.clone();
+
+        let mut reservation = resp.reserve().ok().unwrap();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        // The requesting side has to see the hold before it can time it.
+        assert!(contract.try_receive().is_err());
+        thread::sleep(Duration::from_millis(2));
+        assert!(contract.try_receive().is_err());
+
+        resp2.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        drop(contract);
+
+        // The stale reservation cannot claim anything any more.
+        let mut contract = rqst.try_request().ok().unwrap();
+        match reservation.try_claim() {
+            Err(Error::Expired) => {},
+            _ => { assert!(false); },
+        }
+        match reservation.wait() {
+            Err(Error::Expired) => {},
+            _ => { assert!(false); },
+        }
+        resp2.try_respond().ok().unwrap().send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    }
+}
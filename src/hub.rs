@@ -0,0 +1,195 @@
+//! This module defines a hub, which owns one channel per key and routes
+//! handles to them. Workers can ask the hub which keys have pending
+//! requests instead of polling every channel themselves.
+//!
+//! The hub only keeps the channels' state alive. It holds no `Requester`
+//! or `Responder` of its own, so it does not count as a live handle.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use super::atomic::{AtomicUsize, Ordering};
+use super::{Inner, Link, Requester, Responder};
+
+/// This struct owns a channel for each key it has seen.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let hub = chan::Hub::<&str, u32>::new();
+///
+/// let requester = hub.requester("shard-1");
+/// let responder = hub.responder("shard-1");
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+///
+/// // A worker finds the key with a pending request.
+/// let key = hub.poll_any_request().unwrap();
+/// assert_eq!(key, "shard-1");
+///
+/// responder.try_respond().ok().unwrap().send(1);
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub struct Hub<K, T> {
+    channels: Mutex<HashMap<K, Link<T>>>,
+    // Where the next `Hub::poll_any_request()` starts looking.
+    cursor: AtomicUsize,
+}
+
+impl<K: Hash + Eq + Clone, T> Hub<K, T> {
+    /// This function creates an empty hub.
+    pub fn new() -> Hub<K, T> {
+        Hub {
+            channels: Mutex::new(HashMap::new()),
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// This method returns a new `Requester` for the channel of `key`,
+    /// creating the channel if necessary.
+    ///
+    /// # Warning
+    ///
+    /// Every `Requester` of a channel shares its single outstanding
+    /// request, so only one of them can have a contract at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the channel
+    pub fn requester(&self, key: K) -> Requester<T> {
+        Requester::new(self.link(key))
+    }
+
+    /// This method returns a new `Responder` for the channel of `key`,
+    /// creating the channel if necessary.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the channel
+    pub fn responder(&self, key: K) -> Responder<T> {
+        Responder::new(self.link(key))
+    }
+
+    /// This method returns the keys whose channels have a request no
+    /// responder has claimed yet. It does not lock any channel.
+    ///
+    /// # Warning
+    ///
+    /// A request may be claimed or cancelled right after this method
+    /// returns.
+    pub fn pending_keys(&self) -> Vec<K> {
+        self.channels.lock().unwrap().iter()
+            .filter(|&(_, inner)| inner.has_pending_request())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// This method returns a key whose channel has a request no responder
+    /// has claimed yet, or `None` if there is none. Successive calls
+    /// rotate through the pending keys, so one busy key does not starve
+    /// the others.
+    ///
+    /// # Warning
+    ///
+    /// The request may be claimed or cancelled right after this method
+    /// returns.
+    pub fn poll_any_request(&self) -> Option<K> {
+        let mut pending = self.pending_keys();
+
+        if pending.is_empty() {
+            return None;
+        }
+
+        let index = self.cursor.fetch_add(1, Ordering::Relaxed) % pending.len();
+        Some(pending.swap_remove(index))
+    }
+
+    /// This method returns the state of the channel of `key`, creating
+    /// the channel if necessary.
+    #[inline]
+    fn link(&self, key: K) -> Link<T> {
+        self.channels.lock().unwrap()
+            .entry(key)
+            .or_insert_with(|| Link::Shared(Arc::new(Inner::new())))
+            .clone()
+    }
+}
+
+impl<K: Hash + Eq + Clone, T> Default for Hub<K, T> {
+    fn default() -> Self {
+        Hub::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::Error;
+
+    #[test]
+    fn test_hub_routes_by_key() {
+        let hub = Hub::<u32, u32>::new();
+
+        let rqst = hub.requester(1);
+        let resp = hub.responder(2);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        // Key 2 has its own channel, which has no request.
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        hub.responder(1).try_respond().ok().unwrap().send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_hub_pending_keys() {
+        let hub = Hub::<u32, u32>::new();
+
+        let rqst1 = hub.requester(1);
+        let rqst2 = hub.requester(2);
+        #[allow(unused_variables)]
+        let rqst3 = hub.requester(3);
+
+        let mut contract1 = rqst1.try_request().ok().unwrap();
+        let mut contract2 = rqst2.try_request_many(2).ok().unwrap();
+
+        let mut keys = hub.pending_keys();
+        keys.sort();
+        assert_eq!(keys, vec![1, 2]);
+
+        // Claimed requests are no longer pending.
+        hub.responder(1).try_respond().ok().unwrap().send(1);
+        assert_eq!(hub.pending_keys(), vec![2]);
+
+        contract1.try_receive().ok().unwrap();
+        contract2.try_cancel().ok().unwrap();
+        assert!(hub.pending_keys().is_empty());
+    }
+
+    #[test]
+    fn test_hub_poll_any_request_rotates() {
+        let hub = Hub::<u32, u32>::new();
+
+        let rqst1 = hub.requester(1);
+        let rqst2 = hub.requester(2);
+
+        let mut contract1 = rqst1.try_request().ok().unwrap();
+        let mut contract2 = rqst2.try_request().ok().unwrap();
+
+        let mut keys = vec![hub.poll_any_request().unwrap(), hub.poll_any_request().unwrap()];
+        keys.sort();
+        assert_eq!(keys, vec![1, 2]);
+
+        contract1.try_cancel().ok().unwrap();
+        contract2.try_cancel().ok().unwrap();
+        assert_eq!(hub.poll_any_request(), None);
+    }
+}
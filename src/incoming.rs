@@ -0,0 +1,145 @@
+//! This module defines a blocking iterator over the requests a responder
+//! claims, so a worker loop can be written as a `for` loop.
+
+use super::backoff::Backoff;
+use super::{Error, Responder, ResponseContract};
+
+/// This is the iterator returned by `Responder::incoming()`.
+pub struct Incoming<'a, T: 'a> {
+    responder: &'a Responder<T>,
+}
+
+impl<T> Responder<T> {
+    /// This method returns an iterator that waits for requests and claims
+    /// them. It ends once every `Requester` of the channel is gone and no
+    /// request is left to claim.
+    ///
+    /// # Warning
+    ///
+    /// Losing a request to another responder does not end the iterator;
+    /// it keeps waiting for the next request. The iterator waits forever
+    /// if a `Requester` stays alive without making requests.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::thread;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let worker = thread::spawn(move || {
+    ///     let mut next = 0;
+    ///
+    ///     for contract in responder.incoming() {
+    ///         contract.send(next);
+    ///         next += 1;
+    ///     }
+    /// });
+    ///
+    /// for i in 0..3 {
+    ///     let mut contract = requester.try_request().ok().unwrap();
+    ///
+    ///     loop {
+    ///         if let Ok(num) = contract.try_receive() {
+    ///             assert_eq!(num, i);
+    ///             break;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// // Dropping the requester ends the worker's loop.
+    /// drop(requester);
+    /// worker.join().unwrap();
+    /// ```
+    #[inline]
+    pub fn incoming(&self) -> Incoming<'_, T> {
+        Incoming { responder: self }
+    }
+}
+
+impl<'a, T> Iterator for Incoming<'a, T> {
+    type Item = ResponseContract<T>;
+
+    fn next(&mut self) -> Option<ResponseContract<T>> {
+        let mut backoff = Backoff::tuned(&self.responder.inner.spin);
+
+        loop {
+            match self.responder.try_respond() {
+                Ok(contract) => { return Some(contract); },
+                Err(Error::NoRequest) => {
+                    if !self.responder.inner.has_requesters() {
+                        // The last `Requester` may have made a request
+                        // right before it went away.
+                        return self.responder.try_respond().ok();
+                    }
+
+                    backoff.snooze();
+                },
+                Err(Error::AlreadyLocked) | Err(Error::Paused) | Err(Error::SlotFull) => {
+                    backoff.snooze();
+                },
+                Err(Error::Closed) => { return None; },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::super::channel;
+
+    #[test]
+    fn test_incoming_ends_without_requesters() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        drop(rqst);
+
+        // The request made before the requester went away is still served.
+        let mut incoming = resp.incoming();
+        incoming.next().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+
+        assert!(incoming.next().is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_incoming_keeps_waiting_after_lost_race() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        // `resp2` wins the first request.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp2.try_respond().ok().unwrap();
+
+        let worker = thread::spawn(move || {
+            resp.incoming().map(|contract| contract.send(2)).count()
+        });
+
+        resp_contract.send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        loop {
+            match contract.try_receive() {
+                Ok(num) => {
+                    assert_eq!(num, 2);
+                    break;
+                },
+                Err(_) => { thread::yield_now(); },
+            }
+        }
+
+        drop(contract);
+        drop(rqst);
+        assert_eq!(worker.join().unwrap(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! This module emits `tracing` events for a channel's key moments. It is
+//! only built with the `tracing` feature, and every call into it sits
+//! behind `#[cfg(feature = "tracing")]`, so without the feature the hot
+//! paths contain no trace of it.
+//!
+//! Every event is emitted inside a `reqchan` span carrying the channel's
+//! name and the sequence number of the current request. The requesting
+//! side numbers each request before flagging it, and the flag's Release
+//! publishes the number to whichever responder claims the request, so the
+//! events of one request share a sequence number on every thread.
+
+use super::{Builder, ChannelEvent, Inner, Requester, Responder};
+
+/// This is the name of a channel created without one.
+pub(crate) const DEFAULT_NAME: &str = "reqchan";
+
+/// This function creates a `reqchan` whose trace events carry `name`, and
+/// returns a tuple containing its two ends. It is only available with the
+/// `tracing` feature.
+///
+/// # Arguments
+///
+/// * `name` - The name of the channel in trace events
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel_named::<u32>("jobs");
+///
+/// // With a subscriber installed, each of these steps emits an event
+/// // inside a span with `channel = "jobs"` and `seq = 1`.
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+/// contract.try_receive().ok().unwrap();
+/// ```
+pub fn channel_named<T>(name: &'static str) -> (Requester<T>, Responder<T>) {
+    Builder::new().name(name).build()
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method emits the first event of a new request.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of request
+    ///
+    /// * `amount` - The amount hint or number of responses requested
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it, after
+    /// numbering the request and before flagging it.
+    pub(crate) fn trace_issued(&self, kind: &'static str, amount: usize) {
+        self.span().in_scope(|| {
+            tracing::trace!(kind, amount, "request issued");
+        });
+    }
+
+    /// This method emits the event matching a `ChannelEvent`.
+    pub(crate) fn trace(&self, event: ChannelEvent) {
+        self.span().in_scope(|| {
+            match event {
+                // `trace_issued()` already reported it with more detail.
+                ChannelEvent::RequestIssued => {},
+                ChannelEvent::RequestCancelled => {
+                    tracing::trace!("request cancelled");
+                },
+                ChannelEvent::ResponseClaimed(id) => {
+                    tracing::trace!(responder = ?id, "response claimed");
+                },
+                ChannelEvent::ResponseAborted(id) => {
+                    tracing::warn!(responder = ?id, "ResponseContract dropped unresolved");
+                },
+                ChannelEvent::DatumSent(id) => {
+                    tracing::trace!(responder = ?id, "datum sent");
+                },
+                ChannelEvent::DatumReceived => {
+                    tracing::trace!("datum received");
+                },
+                ChannelEvent::DatumRejected(id, err) => {
+                    tracing::debug!(responder = ?id, reason = err.0, "datum rejected");
+                },
+            }
+        });
+    }
+
+    /// This method reports a request contract dropped before it was
+    /// resolved.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - The name of the contract's type
+    pub(crate) fn trace_unresolved(&self, contract: &'static str) {
+        self.span().in_scope(|| {
+            tracing::warn!("{} dropped unresolved", contract);
+        });
+    }
+
+    /// This method returns a span for the channel's current request.
+    #[inline]
+    fn span(&self) -> tracing::Span {
+        tracing::trace_span!("reqchan",
+                             channel = self.name,
+                             seq = self.sequence())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use super::*;
+
+    /// This subscriber records each event as the channel name and
+    /// sequence number of the span it happened in, plus its message.
+    #[derive(Default)]
+    struct Recorder {
+        spans: Mutex<Vec<(String, u64)>>,
+        current: Mutex<Vec<u64>>,
+        events: Mutex<Vec<(String, u64, String)>>,
+    }
+
+    #[derive(Default)]
+    struct Fields {
+        channel: String,
+        seq: u64,
+        message: String,
+    }
+
+    impl Visit for Fields {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "seq" {
+                self.seq = value;
+            }
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "channel" {
+                self.channel = value.to_string();
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.message = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes) -> Id {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((fields.channel, fields.seq));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+
+            let span = *self.current.lock().unwrap().last().unwrap();
+            let (ref channel, seq) = self.spans.lock().unwrap()[span as usize - 1];
+            self.events.lock().unwrap().push((channel.clone(), seq, fields.message));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.current.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.current.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    fn test_trace_events() {
+        let recorder = Arc::new(Recorder::default());
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let (rqst, resp) = channel_named::<u32>("jobs");
+
+            {
+                let mut contract = rqst.try_request().ok().unwrap();
+                contract.try_cancel().ok().unwrap();
+            }
+
+            let mut contract = rqst.try_request_many(1).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        });
+
+        let events = recorder.events.lock().unwrap();
+        let expected = [
+            (1, "request issued"),
+            (1, "request cancelled"),
+            (2, "request issued"),
+            (2, "response claimed"),
+            (2, "datum sent"),
+            (2, "datum received"),
+        ];
+
+        assert_eq!(events.len(), expected.len());
+
+        for (event, &(seq, message)) in events.iter().zip(expected.iter()) {
+            assert_eq!(event.0, "jobs");
+            assert_eq!(event.1, seq);
+            assert_eq!(event.2, message);
+        }
+    }
+
+    #[test]
+    fn test_trace_unresolved() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let recorder = Arc::new(Recorder::default());
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let (rqst, _resp) = super::super::channel::<u32>();
+
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                #[allow(unused_variables)]
+                let contract = rqst.try_request().ok().unwrap();
+            }));
+            assert!(res.is_err());
+        });
+
+        let events = recorder.events.lock().unwrap();
+        assert_eq!(events.last().unwrap().0, DEFAULT_NAME);
+        assert_eq!(events.last().unwrap().2, "RequestContract dropped unresolved");
+    }
+}
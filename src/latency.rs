@@ -0,0 +1,232 @@
+//! This module records how long single-response requests take, from the
+//! moment a request is flagged until the requesting side receives its
+//! datum. It is only built with the `metrics` feature.
+//!
+//! Latencies go into a histogram with one bucket per power of two
+//! nanoseconds. Every bucket is an atomic counter, so recording a latency
+//! never allocates or locks. Cancelled requests are counted on their own
+//! instead of being recorded as latencies.
+
+use std::time::Duration;
+
+use super::atomic::{AtomicU64, Ordering};
+use super::clock::now;
+use super::Inner;
+
+/// This is the number of buckets in a `LatencyHistogram`.
+pub const LATENCY_BUCKETS: usize = 64;
+
+/// This is a copy of a channel's latency histogram. Bucket `i` counts the
+/// requests that took between `2^i` and `2^(i + 1)` nanoseconds, except
+/// that bucket `0` also counts those that took less than a nanosecond.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The number of requests in each bucket.
+    pub buckets: [u64; LATENCY_BUCKETS],
+    /// The number of single-response requests that were cancelled instead.
+    pub cancelled: u64,
+}
+
+impl LatencyHistogram {
+    /// This method returns the number of latencies in the histogram.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// This method returns an upper bound on the latency below which the
+    /// fraction `q` of the requests fell, or `None` if the histogram is
+    /// empty. The bound is the end of the bucket the quantile falls in, so
+    /// it overestimates by less than a factor of two.
+    ///
+    /// # Arguments
+    ///
+    /// * `q` - The quantile, from `0.0` to `1.0`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder, monitor) = chan::channel_monitored::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// contract.try_receive().ok().unwrap();
+    ///
+    /// let histogram = monitor.latency_histogram();
+    /// assert_eq!(histogram.count(), 1);
+    ///
+    /// let p99 = histogram.quantile(0.99).unwrap();
+    /// println!("p99 latency is below {:?}", p99);
+    /// ```
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+
+        if count == 0 {
+            return None;
+        }
+
+        // The rank of the latency the quantile falls on, counting from 1.
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket;
+
+            if seen >= rank {
+                return Some(Duration::from_nanos(bucket_end(i)));
+            }
+        }
+
+        unreachable!()
+    }
+}
+
+/// This is the live histogram of a channel.
+#[doc(hidden)]
+pub(crate) struct Latency {
+    // When the current request was flagged, as a `clock::now()` timestamp.
+    requested_at: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+    cancelled: AtomicU64,
+}
+
+impl Latency {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> Latency {
+            Latency {
+                requested_at: AtomicU64::new(0),
+                buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS],
+                cancelled: AtomicU64::new(0),
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method notes when the current request was flagged.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    pub(crate) fn start_latency(&self) {
+        // The request's Release publishes this to the responder, and the
+        // datum's Release publishes it back to the requesting side.
+        self.request.latency.requested_at.store(now(), Ordering::Relaxed);
+    }
+
+    /// This method records how long the current request took.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it, after
+    /// it received the datum.
+    #[inline]
+    pub(crate) fn record_latency(&self) {
+        let latency = &self.request.latency;
+        let elapsed = now().saturating_sub(latency.requested_at.load(Ordering::Relaxed));
+
+        latency.buckets[bucket(elapsed)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method returns a copy of the channel's latency histogram.
+    pub(crate) fn latency_histogram(&self) -> LatencyHistogram {
+        let latency = &self.request.latency;
+        let mut buckets = [0; LATENCY_BUCKETS];
+
+        for (copy, bucket) in buckets.iter_mut().zip(latency.buckets.iter()) {
+            *copy = bucket.load(Ordering::Relaxed);
+        }
+
+        LatencyHistogram {
+            buckets,
+            cancelled: latency.cancelled.load(Ordering::Relaxed),
+        }
+    }
+
+    /// This method counts a cancelled request instead of its latency.
+    #[inline]
+    pub(crate) fn record_cancelled(&self) {
+        self.request.latency.cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// This function returns the bucket of a latency in nanoseconds.
+#[inline]
+fn bucket(nanos: u64) -> usize {
+    match nanos {
+        0 => 0,
+        _ => 63 - nanos.leading_zeros() as usize,
+    }
+}
+
+/// This function returns the latency in nanoseconds where a bucket ends.
+#[inline]
+fn bucket_end(bucket: usize) -> u64 {
+    match bucket {
+        63 => u64::MAX,
+        _ => 1 << (bucket + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::channel_monitored;
+
+    #[test]
+    fn test_latency_bucket() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(1), 0);
+        assert_eq!(bucket(2), 1);
+        assert_eq!(bucket(3), 1);
+        assert_eq!(bucket(1024), 10);
+        assert_eq!(bucket(u64::MAX), 63);
+    }
+
+    #[test]
+    fn test_latency_histogram_quantile() {
+        let mut histogram = LatencyHistogram {
+            buckets: [0; LATENCY_BUCKETS],
+            cancelled: 0,
+        };
+
+        assert_eq!(histogram.quantile(0.5), None);
+
+        histogram.buckets[3] = 98;
+        histogram.buckets[10] = 2;
+
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_nanos(16)));
+        assert_eq!(histogram.quantile(0.98), Some(Duration::from_nanos(16)));
+        assert_eq!(histogram.quantile(0.99), Some(Duration::from_nanos(2048)));
+        assert_eq!(histogram.quantile(0.0), Some(Duration::from_nanos(16)));
+    }
+
+    #[test]
+    fn test_latency_histogram_records_requests() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            contract.try_cancel().ok().unwrap();
+        }
+
+        for i in 0..3 {
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(i);
+            contract.try_receive().ok().unwrap();
+        }
+
+        // Requests for several responses are not recorded.
+        let mut contract = rqst.try_request_many(1).ok().unwrap();
+        resp.try_respond().ok().unwrap().send(3);
+        contract.try_receive().ok().unwrap();
+
+        let histogram = monitor.latency_histogram();
+        assert_eq!(histogram.count(), 3);
+        assert_eq!(histogram.cancelled, 1);
+    }
+}
@@ -0,0 +1,370 @@
+//! This module lets the requesting side take a request back from a
+//! responder that claimed it and then stalled. It is only active on
+//! channels built with `Builder::claim_timeout()`.
+//!
+//! Each claim of a single-response request publishes a lease, a number
+//! that no earlier claim used. A responder must seal its lease before it
+//! writes the datum, and the requesting side must seal the same lease
+//! before it hands the request back to the other responders, so exactly
+//! one of them gets to act on the claim. A stale responder that finds its
+//! lease sealed by the requesting side gets its datum back. Since no two
+//! claims share a lease, that holds however many requests were issued
+//! since, so a stale datum can never answer a later request.
+//!
+//! The requesting side also takes back a hold that keeps its request
+//! unclaimed for longer than the timeout, so a `HeldResponse` or
+//! `Reservation` that is never used cannot keep it waiting forever.
+//!
+//! Requests for several responses, and broadcasts, are not covered.
+
+use std::time::Duration;
+
+use super::atomic::Ordering;
+use super::clock::now;
+use super::{Inner, State};
+
+// The claim's responder is sending its datum.
+const SENDING: u64 = 1;
+// The claim ended without a datum.
+const VACANT: u64 = 2;
+const ENDED: u64 = SENDING | VACANT;
+const NEXT: u64 = 4;
+
+/// This is the first lease of every channel. It belongs to no claim.
+pub(crate) const INITIAL_LEASE: u64 = VACANT;
+
+/// This tracks how long the requesting side has seen the same claim.
+#[doc(hidden)]
+#[derive(Default)]
+pub(crate) struct LeaseWatch {
+    lease: u64,
+    // When the requesting side first saw the lease, as a `clock::now()`
+    // timestamp.
+    since: u64,
+    hold: u64,
+    // When the requesting side first saw the hold keep its request
+    // unclaimed.
+    hold_since: u64,
+}
+
+/// This function converts a claim timeout to the form `Inner` keeps.
+#[inline]
+pub(crate) fn timeout_nanos(timeout: Duration) -> u64 {
+    timeout.as_nanos() as u64
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method gives the claim the calling responder just made a new
+    /// lease and returns it, or returns 0 if the channel has no claim
+    /// timeout.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that claimed the request should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::Claimed
+    #[inline]
+    pub(crate) fn publish_claim(&self) -> u64 {
+        self.debug_check_state(&[State::Claimed]);
+
+        if self.claim_timeout.is_none() {
+            return 0;
+        }
+
+        // Every claim ends by marking its lease, and nobody seals a
+        // marked lease, so a plain store cannot overwrite anything.
+        let lease = (self.response.lease.load(Ordering::Relaxed) & !ENDED) + NEXT;
+        self.response.lease.store(lease, Ordering::Relaxed);
+        lease
+    }
+
+    /// This method seals the claim with `lease` for sending. It returns
+    /// `false` if the requesting side took the claim back first.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder holding `lease` should call it, before it
+    /// writes the datum.
+    #[inline]
+    pub(crate) fn seal_claim(&self, lease: u64) -> bool {
+        lease == 0 || self.response.lease
+            .compare_exchange(lease, lease | SENDING, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// This method hands the claim with `lease` back, unless the
+    /// requesting side already took it back.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder holding `lease` should call it, instead of
+    /// sending a datum.
+    #[inline]
+    pub(crate) fn release_claim(&self, lease: u64) {
+        let released = lease == 0 || self.response.lease
+            .compare_exchange(lease, lease | VACANT, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok();
+
+        if released {
+            self.unclaim_request();
+        }
+    }
+
+    /// This method takes the current claim back if the requesting side
+    /// has seen it for longer than the claim timeout. It returns whether
+    /// it did.
+    ///
+    /// # Arguments
+    ///
+    /// * watch - What the requesting side has seen of the claim so far
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    pub(crate) fn try_revoke_claim(&self, watch: &mut LeaseWatch) -> bool {
+        let timeout = match self.claim_timeout {
+            Some(timeout) => timeout,
+            None => { return false; },
+        };
+
+        if self.state() != State::Claimed {
+            return false;
+        }
+
+        // A claim that has not published its lease yet cannot be timed.
+        let lease = self.response.lease.load(Ordering::Relaxed);
+        if lease & ENDED != 0 {
+            return false;
+        }
+
+        let now = now();
+
+        if lease != watch.lease {
+            watch.lease = lease;
+            watch.since = now;
+            return false;
+        }
+
+        if now.saturating_sub(watch.since) < timeout {
+            return false;
+        }
+
+        if self.response.lease
+            .compare_exchange(lease, lease | VACANT, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err() {
+            return false;
+        }
+
+        // Only the sealed claim could have left `State::Claimed`.
+        let res = self.transition(State::Claimed, State::Requested, Ordering::Release);
+        debug_assert!(res.is_ok());
+        self.wake_responders();
+
+        true
+    }
+
+    /// This method takes the current hold back if the requesting side
+    /// has seen it keep the request unclaimed for longer than the claim
+    /// timeout. It returns whether it did.
+    ///
+    /// # Arguments
+    ///
+    /// * watch - What the requesting side has seen of the hold so far
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    pub(crate) fn try_break_hold(&self, watch: &mut LeaseWatch) -> bool {
+        let timeout = match self.claim_timeout {
+            Some(timeout) => timeout,
+            None => { return false; },
+        };
+
+        let hold = self.hold_token();
+        if hold == 0 || self.state() != State::Requested {
+            watch.hold = 0;
+            return false;
+        }
+
+        let now = now();
+
+        if hold != watch.hold {
+            watch.hold = hold;
+            watch.hold_since = now;
+            return false;
+        }
+
+        if now.saturating_sub(watch.hold_since) < timeout {
+            return false;
+        }
+
+        watch.hold = 0;
+
+        // The holder may have claimed the request in the meantime, but
+        // then the claim timeout covers it instead.
+        if self.response.held
+            .compare_exchange(hold, 0, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err() {
+            return false;
+        }
+
+        self.wake_responders();
+
+        true
+    }
+}
+
+// Every test here waits out a claim timeout, which needs a clock.
+#[cfg(all(test, not(all(target_arch = "wasm32", target_os = "unknown"))))]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::mem;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+    use super::super::{Builder, ChannelEvent, ChannelObserver, Error, ResponseContract, Responder,
+                       SendError};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_claim_timeout_forgotten_contract() {
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .build::<u32>();
+        let resp2 = resp.clone();
+
+        let receiver = rqst.into_receiver();
+
+        // The responder leaks its contract and goes away, as if its
+        // thread was killed right after the claim.
+        receiver.try_recv().err().unwrap();
+        thread::spawn(move || mem::forget(resp.try_respond().ok().unwrap()))
+            .join()
+            .unwrap();
+
+        let worker = thread::spawn(move || {
+            loop {
+                match resp2.try_respond() {
+                    Ok(contract) => {
+                        contract.send(1);
+                        return;
+                    },
+                    Err(_) => { thread::yield_now(); },
+                }
+            }
+        });
+
+        // The blocking receive takes the request back and gets its datum
+        // from the other responder.
+        assert_eq!(receiver.recv().ok().unwrap(), 1);
+        worker.join().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_claim_timeout_stale_send_after_newer_exchange() {
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .build::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut stalled = resp.try_respond().ok().unwrap();
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+        thread::sleep(Duration::from_millis(2));
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        // A newer request is claimed, and the stale contract wakes up.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let fresh = resp.try_respond().ok().unwrap();
+        assert_eq!(stalled.try_send(2), Err(SendError(2)));
+
+        fresh.send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_claim_timeout_stale_send_across_renewal() {
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .build::<u32>();
+
+        let receiver = rqst.into_receiver();
+        receiver.try_recv().err().unwrap();
+
+        // A slow responder claims the first request and stalls.
+        let mut slow = resp.try_respond().ok().unwrap();
+        let first = slow.sequence();
+        receiver.try_recv().err().unwrap();
+        thread::sleep(Duration::from_millis(2));
+        receiver.try_recv().err().unwrap();
+
+        // Another responder answers it, and the receiver renews its
+        // request right away.
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(receiver.try_recv().ok().unwrap(), 1);
+        assert!(resp.has_request());
+
+        // The slow responder cannot answer the renewed request, whether
+        // it is still pending or already claimed.
+        assert_eq!(slow.try_send(2), Err(SendError(2)));
+
+        let fresh = resp.try_respond().ok().unwrap();
+        assert!(fresh.sequence() > first);
+        fresh.send(3);
+        assert_eq!(receiver.try_recv().ok().unwrap(), 3);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_claim_timeout_try_fetch() {
+        // This claims every request as soon as it is issued, and stalls.
+        struct Stall {
+            responder: Mutex<Option<Responder<u32>>>,
+            contract: Mutex<Option<ResponseContract<u32>>>,
+        }
+
+        impl ChannelObserver for Stall {
+            fn on_event(&self, event: ChannelEvent) {
+                if event == ChannelEvent::RequestIssued {
+                    if let Some(ref resp) = *self.responder.lock().unwrap() {
+                        *self.contract.lock().unwrap() = resp.try_respond().ok();
+                    }
+                }
+            }
+        }
+
+        let stall = Arc::new(Stall {
+            responder: Mutex::new(None),
+            contract: Mutex::new(None),
+        });
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .observer(stall.clone())
+            .build::<u32>();
+        *stall.responder.lock().unwrap() = Some(resp);
+
+        // The fetch takes the claim back and cancels the request.
+        assert_eq!(rqst.try_fetch().ok().unwrap(), None);
+
+        stall.responder.lock().unwrap().take().unwrap();
+        let mut stalled = stall.contract.lock().unwrap().take().unwrap();
+        assert_eq!(stalled.try_send(2), Err(SendError(2)));
+    }
+}
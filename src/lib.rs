@@ -81,17 +81,8 @@
 //! use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 //! use std::thread;
 //! use std::time::{Duration, Instant};
-//! 
-//! // Stuff to make it easier to pass around closures.
-//! trait FnBox {
-//!     fn call_box(self: Box<Self>);
-//! }
-//! impl<F: FnOnce()> FnBox for F {
-//!     fn call_box(self: Box<F>) {
-//!         (*self)()
-//!     }
-//! }
-//! type Task = Box<FnBox + Send + 'static>;
+//!
+//! use chan::Task;
 //! 
 //! // Variable used to test calling a `Task` sent between threads.
 //! let test_var = Arc::new(AtomicUsize::new(0));
@@ -130,7 +121,7 @@
 //!         match contract.try_receive() {
 //!             // `contract` received `task`.
 //!             Ok(task) => {
-//!                 task.call_box();
+//!                 task.run();
 //!                 // Notify other threads to stop.
 //!                 should_exit.store(true, Ordering::SeqCst);
 //!                 break;
@@ -147,9 +138,9 @@
 //! 
 //! // responding thread 1
 //! let responder_1_handle = thread::spawn(move || {
-//!     let mut tasks = vec![Box::new(move || {
+//!     let mut tasks = vec![Task::new(move || {
 //!         test_var2.fetch_add(1, Ordering::SeqCst);
-//!     }) as Task];
+//!     })];
 //!     
 //!     loop {
 //!         // Exit loop if `receiver` has timed out.
@@ -176,9 +167,9 @@
 //! 
 //! // responding thread 2
 //! let responder_2_handle = thread::spawn(move || {
-//!     let mut tasks = vec![Box::new(move || {
+//!     let mut tasks = vec![Task::new(move || {
 //!         test_var3.fetch_add(2, Ordering::SeqCst);
-//!     }) as Task];
+//!     })];
 //!     
 //!     loop {
 //!         // Exit loop if `receiver` has timed out.
@@ -211,11 +202,269 @@
 //! let num = test_var.load(Ordering::SeqCst);
 //! println!("Number is {}", num);
 //! ```
+//!
+//! # Portable Atomics
+//!
+//! The channel uses 64-bit atomics, which some 32-bit targets, such as
+//! `mips-unknown-linux-gnu` and `powerpc-unknown-linux-gnu`, do not have.
+//! Enabling the `portable-atomic` feature makes the channel use the
+//! `portable-atomic` crate's atomic types instead, which fall back to a
+//! lock where the target has no native instruction.
+//!
+//! The crate needs `std`, because some of the channel's state lives
+//! behind a `Mutex` and its timeouts read the clock. `no_std` targets such
+//! as Cortex-M0 (`thumbv6m-none-eabi`) are not supported, with or without
+//! the feature.
+//!
+//! ## Interrupt Safety
+//!
+//! On a target with native atomics of every width the channel uses,
+//! single-response requests only touch atomics, so these operations are
+//! safe to call from a signal or interrupt handler while the main code
+//! uses the other end of the channel:
+//!
+//! * `Requester::try_request()`, `RequestContract::try_receive()` and
+//!   `RequestContract::try_cancel()`
+//! * `Responder::try_respond()` and `ResponseContract::send()`
+//!
+//! This only holds for a channel from `channel()` with no claim timeout,
+//! validator, observer, notify hook or waiters (a `Select`, or a blocking
+//! or `async` call), and with the `metrics`, `tracing` and
+//! `debug-watchdog` features off. Each of those takes a lock, reads the
+//! clock or runs your code.
+//!
+//! Everything that takes one of the channel's `Mutex`es is **not**
+//! interrupt-safe, since the handler would deadlock if it interrupted the
+//! lock's holder. That includes requests for several responses,
+//! broadcasts, and creating, cloning or dropping a `Responder`. Create the
+//! handles before installing the handler. Neither is anything on a target
+//! where `portable-atomic` falls back to a lock, since the handler could
+//! interrupt that lock's holder too.
+//!
+//! # WebAssembly
+//!
+//! The crate builds for `wasm32-unknown-unknown`, where there is a single
+//! thread and no clock. Everything that reads the clock or waits for a
+//! deadline is left out of the build there, since `Instant::now()` panics
+//! on that target:
+//!
+//! * `Requester::try_request_ttl()` and
+//!   `Requester::try_request_with_deadline()`
+//! * `Builder::min_request_interval()` and `Builder::claim_timeout()`
+//! * `Select::wait_timeout()`
+//! * `PullReceiver::recv_timeout()`
+//! * `SharedRequester::request_coalesced()`
+//! * `DeliveryReceipt::wait_delivered()`
+//! * `RetryPolicy::timeout()` and `BackoffStrategy::Sleep`
+//!
+//! The rest of the API builds, but with one thread there is nobody else to
+//! wait for, so calls that block until the other side acts never return
+//! or panic.
+//!
+//! # Tracing
+//!
+//! Enabling the `tracing` feature makes every channel emit `tracing`
+//! events when a request is issued or cancelled, when a responder claims
+//! it or sends a datum, when the requesting side receives a datum, and
+//! when a contract is dropped unresolved. Each event sits in a `reqchan`
+//! span with the channel's name and the request's sequence number, so one
+//! request can be followed across threads. `channel_named()` sets the
+//! name; other channels are called `reqchan`. Without the feature, none
+//! of this code is compiled.
+//!
+//! # Debugging Stuck Channels
+//!
+//! A channel gets stuck when a request is in flight but neither side can
+//! move it along, for example because a contract was leaked with
+//! `mem::forget()`. Enabling the `debug-watchdog` feature adds
+//! `Monitor::stalled_for()`, which tells how long a channel has gone
+//! without progress while a request is in flight, and
+//! `Monitor::spawn_watchdog()`, which calls back with a `ChannelState`
+//! dump once a channel has been stuck for a given time.
+//!
+//! # Crossbeam
+//!
+//! Enabling the `crossbeam` feature adds `Requester::readiness_channel()`
+//! and `Responder::readiness_channel()`, which return a `crossbeam-channel`
+//! receiver that gets a message whenever a datum arrives or a request is
+//! issued, so a thread already blocking in `crossbeam_channel::select!`
+//! can wait on a `reqchan` too. A message is only a hint to poll, and each
+//! readiness channel holds at most one message.
+//!
+//! # Pool
+//!
+//! Enabling the `pool` feature adds `pool::Pool`, a small scheduler that
+//! shares tasks between worker threads the way the more complex example
+//! does: each worker owns a channel, idle workers request tasks on
+//! theirs, and busy workers answer with half of their local queues.
+//! `Pool::join()` runs every spawned task before it stops the workers,
+//! and `Pool::monitors()` returns the `Monitor` of each worker's channel.
+//!
+//! # Testing
+//!
+//! `RequestSink` and `ResponseSource` let code take either end of a
+//! channel as a trait. Enabling the `test-util` feature adds the `mock`
+//! module, whose `MockResponder` answers requests with data queued in
+//! advance and whose `MockRequester` issues requests on command and
+//! records what was sent, so such code can be tested without threads.
+//!
+//! Enabling the `testing` feature adds the `testing` module, whose
+//! `Controller` parks threads at named points in a channel's handoffs, so
+//! tests can script the races between the two sides, and the `model`
+//! module, whose `differential()` checks random sequences of operations on
+//! a real channel against a simple reference model.
 
-use std::cell::UnsafeCell;
+#[cfg(feature = "crossbeam")]
+extern crate crossbeam_channel;
+#[cfg(loom)]
+extern crate loom;
+#[cfg(feature = "portable-atomic")]
+extern crate portable_atomic;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+use std::collections::VecDeque;
+use std::hint;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::panic::{self, AssertUnwindSafe, UnwindSafe};
+use std::ptr;
 use std::result;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use backoff::{Backoff, SpinTuner};
+use cell::UnsafeCell;
+use broadcast::Roster;
+#[cfg(feature = "metrics")]
+use latency::Latency;
+use lease::LeaseWatch;
+use monitor::{ClaimTally, RequestCounters, ResponseCounters};
+use throttle::Throttle;
+use validate::ValidatorFn;
+use wake::{NotifyHook, Waiters};
+#[cfg(feature = "testing")]
+use testing::{Control, YieldPoint};
+
+// This declares a function that is `const`, except under `cfg(loom)`,
+// whose atomics cannot be created in constant functions.
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])* $vis const fn $($rest)*
+        #[cfg(loom)]
+        $(#[$attr])* $vis fn $($rest)*
+    };
+}
+
+mod any;
+mod atomic;
+mod backend;
+mod backoff;
+mod batch;
+mod boxed;
+mod broadcast;
+mod builder;
+mod cell;
+mod clock;
+mod duplex;
+mod exchange;
+mod held;
+mod hub;
+mod incoming;
+#[cfg(feature = "tracing")]
+mod instrument;
+#[cfg(feature = "metrics")]
+mod latency;
+mod lease;
+pub mod local;
+#[cfg(feature = "test-util")]
+pub mod mock;
+#[cfg(any(test, feature = "testing"))]
+pub mod model;
+mod monitor;
+mod observer;
+mod optimistic;
+mod outcome;
+mod mutex_backend;
+#[cfg(feature = "pool")]
+pub mod pool;
+mod queue;
+#[cfg(feature = "crossbeam")]
+mod readiness;
+mod receipt;
+mod receiver;
+mod retry;
+mod scoped;
+#[doc(hidden)]
+pub mod select;
+mod shared;
+mod slots;
+mod small;
+mod spsc;
+mod static_channel;
+mod storage;
+mod subscription;
+pub mod task;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod throttle;
+mod traits;
+mod validate;
+mod wake;
+#[cfg(feature = "debug-watchdog")]
+mod watchdog;
+mod weak;
+mod xchannel;
+
+pub use any::{AnyDatum, AnyRequestContract, AnyRequester, AnyResponder,
+              AnyResponseContract};
+pub use backend::{channel_with_backend, AtomicBackend, BackendRequester,
+                  BackendRequestContract, BackendResponder, BackendResponseContract,
+                  ChannelBackend};
+pub use batch::{batch_channel, BatchRequester, BatchRequestContract,
+                BatchResponder, BatchResponseContract, Full};
+pub use boxed::{channel_boxed, BoxedBackend};
+pub use broadcast::{BroadcastContract, ResponderId};
+pub use builder::Builder;
+pub use duplex::{duplex, Endpoint};
+pub use exchange::{exchange_channel, ExchangeRequester, ExchangeRequestContract,
+                   ExchangeResponder, ExchangeResponseContract};
+pub use held::{HeldResponse, Reservation};
+pub use hub::Hub;
+pub use incoming::Incoming;
+#[cfg(feature = "tracing")]
+pub use instrument::channel_named;
+#[cfg(feature = "metrics")]
+pub use latency::{LatencyHistogram, LATENCY_BUCKETS};
+pub use monitor::{channel_monitored, Monitor, ResponderStats, Snapshot};
+pub use mutex_backend::MutexBackend;
+pub use observer::{channel_with_observer, ChannelEvent, ChannelObserver};
+pub use optimistic::{channel_optimistic, OptimisticContract, OptimisticResponder};
+pub use outcome::{CancelReason, RequestOutcome};
+pub use receipt::DeliveryReceipt;
+pub use receiver::{PullIntoIter, PullIter, PullReceiver};
+pub use retry::{BackoffStrategy, RetryPolicy};
+pub use scoped::{Scoped, ScopedChannel, ScopedRequester, ScopedResponder};
+pub use select::Select;
+pub use shared::{Coalesced, SharedRequester};
+pub use slots::{channel_with_slots, SlotRequester, SlotRequestContract,
+                SlotResponder, SlotResponseContract};
+pub use small::{channel_small, SmallBackend, SmallDatum};
+pub use spsc::{channel_spsc, SpscResponder};
+pub use static_channel::StaticChannel;
+pub use storage::{channel_in, ChannelStorage};
+pub use subscription::SubscriptionContract;
+pub use task::Task;
+pub use traits::{PendingRequest, PendingResponse, RequestSink, ResponseSource};
+pub use validate::{Validated, ValidationError};
+#[cfg(feature = "debug-watchdog")]
+pub use watchdog::ChannelState;
+pub use weak::{WeakRequester, WeakResponder};
+pub use xchannel::{xchannel, XRequester, XRequestContract,
+                   XResponder, XResponseContract};
 
 /// This function creates a `reqchan` and returns a tuple containing the
 /// two ends of this bidirectional request->response channel.
@@ -228,27 +477,119 @@ use std::sync::atomic::{AtomicBool, Ordering};
 /// #[allow(unused_variables)]
 /// let (requester, responder) = reqchan::channel::<u32>(); 
 /// ```
+///
+/// # Threads
+///
+/// The ends of a channel only cross threads if its data can. A channel
+/// of `u32`s can be split across threads:
+///
+/// ```rust
+/// extern crate reqchan;
+///
+/// use std::thread;
+///
+/// let (requester, responder) = reqchan::channel::<u32>();
+/// thread::spawn(move || drop(responder)).join().unwrap();
+/// thread::spawn(move || drop(requester)).join().unwrap();
+/// ```
+///
+/// but a channel of `Rc`s cannot, since its data would cross with it:
+///
+/// ```rust,compile_fail
+/// extern crate reqchan;
+///
+/// use std::rc::Rc;
+/// use std::thread;
+///
+/// let (requester, responder) = reqchan::channel::<Rc<u8>>();
+/// thread::spawn(move || drop(responder)).join().unwrap();
+/// ```
+///
+/// ```rust,compile_fail
+/// extern crate reqchan;
+///
+/// use std::rc::Rc;
+/// use std::thread;
+///
+/// let (requester, responder) = reqchan::channel::<Rc<u8>>();
+/// thread::scope(|s| { s.spawn(|| requester.is_closed()); });
+/// ```
 pub fn channel<T>() -> (Requester<T>, Responder<T>) {
-    let inner = Arc::new(Inner {
-        has_request_lock: AtomicBool::new(false),
-        has_response_lock: AtomicBool::new(false),
-        has_request: AtomicBool::new(false),
-        has_datum: AtomicBool::new(false),
-        datum: UnsafeCell::new(None),
-    });
+    let inner = Link::Shared(Arc::new(Inner::new()));
 
     (
-        Requester { inner: inner.clone() },
-        Responder { inner: inner.clone() },
+        Requester::new(inner.clone()),
+        Responder::new(inner.clone()),
     )
 }
 
 /// This end of the channel requests and receives data from its `Responder`(s).
+///
+/// # Sharing
+///
+/// A `Requester<T>` is `Sync` whenever `T` is `Send`, so several threads
+/// may call its methods through a shared reference. The requesting side
+/// still serves one request at a time: of several threads that call
+/// `Requester::try_request()` at once, exactly one gets a
+/// `RequestContract`, and the others get `Err(Error::AlreadyLocked)`. A
+/// losing thread may retry. It gets a contract once the winner's contract
+/// is dropped, which happens after the winner received its datum or
+/// cancelled its request. A datum always goes to the contract whose
+/// request it answers, never to a thread that retries meanwhile.
+///
+/// `SharedRequester` wraps the same behaviour in a handle each thread can
+/// own, and waits for its turn in `SharedRequester::request()`.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// let (requester, responder) = chan::channel::<u32>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+///
+/// thread::scope(|s| {
+///     // The request is still open, so another thread has to wait.
+///     s.spawn(|| {
+///         match requester.try_request() {
+///             Err(chan::Error::AlreadyLocked) => {},
+///             _ => unreachable!(),
+///         }
+///     });
+/// });
+///
+/// responder.try_respond().ok().unwrap().send(1);
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// drop(contract);
+///
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         let mut contract = requester.try_request().ok().unwrap();
+///         contract.try_cancel().ok().unwrap();
+///     });
+/// });
+/// ```
 pub struct Requester<T> {
-    inner: Arc<Inner<T>>,
+    inner: Link<T>,
 }
 
 impl<T> Requester<T> {
+    #[inline]
+    fn new(inner: Link<T>) -> Requester<T> {
+        inner.add_requester();
+        Requester::adopt(inner)
+    }
+
+    /// This function wraps a link whose requester count already
+    /// includes the new `Requester`.
+    #[inline]
+    fn adopt(inner: Link<T>) -> Requester<T> {
+        Requester { inner }
+    }
+
     /// This methods tries to request item(s) from one or more `Responder`(s).
     /// If successful, it returns a `RequestContract` to either poll for data or
     /// cancel the request.
@@ -280,816 +621,5250 @@ impl<T> Requester<T> {
     /// println!("Got number {}", request_contract.try_receive().ok().unwrap());
     /// ```
     pub fn try_request(&self) -> Result<RequestContract<T>> {
-        // First, try to lock the requesting side.
-        let _ = self.inner.try_lock_request()?;
-
-        // Next, flag a request.
-        self.inner.flag_request();
-
-        // Then return a `RequestContract`.
-        Ok(RequestContract {
-            inner: self.inner.clone(),
-            done: false,
-        })
+        self.try_request_amount(1)
     }
-}
-
-/// This is the contract returned by a successful `Requester::try_request()`.
-/// It represents the caller's exclusive access to the requesting side of
-/// the channel. The user can either try to get a datum from the responding side
-/// or *attempt* to cancel the request. To prevent data loss, `RequestContract`
-/// will panic if the user has not received a datum or cancelled the request.
-pub struct RequestContract<T> {
-    inner: Arc<Inner<T>>,
-    done: bool,
-}
 
-impl<T> RequestContract<T> {
-    /// This method attempts to receive a datum from one or more responder(s).
+    /// This method checks whether a responder has a datum ready right
+    /// now. It issues a request and polls it once. If no datum arrived, it
+    /// cancels the request and returns `Ok(None)`. It never leaves a
+    /// request behind.
     ///
     /// # Warning
     ///
-    /// It returns `Err(Error::Done)` if the user called it
-    /// after either receiving a datum or cancelling the request.
+    /// If a responder claimed the request before it could be cancelled,
+    /// this method waits for the responder's datum instead of losing it,
+    /// so it can block for as long as the responder takes to send.
     ///
     /// # Example
-    /// 
+    ///
     /// ```rust
     /// extern crate reqchan as chan;
     ///
-    /// let (requester, responder) = chan::channel::<u32>(); 
-    ///
-    /// let mut request_contract = requester.try_request().ok().unwrap();
-    ///
-    /// // The responder has not responded yet. 
-    /// match request_contract.try_receive() {
-    ///     Err(chan::Error::Empty) => { println!("No Data yet!"); },
-    ///     _ => unreachable!(),
-    /// }
-    /// 
-    /// responder.try_respond().ok().unwrap().send(6);
-    /// 
-    /// // The responder has responded now.
-    /// match request_contract.try_receive() {
-    ///     Ok(num) => { println!("Number: {}", num); },
-    ///     _ => unreachable!(),
-    /// }
+    /// let (requester, responder) = chan::channel::<u32>();
     ///
-    /// // We need to issue another request to receive more data.
-    /// match request_contract.try_receive() {
-    ///     Err(chan::Error::Done) => {
-    ///         println!("We already received data!");
-    ///     },
-    ///     _ => unreachable!(),
-    /// }
+    /// // Nobody is responding.
+    /// assert_eq!(requester.try_fetch().ok().unwrap(), None);
+    /// assert!(!responder.has_request());
     /// ```
-    pub fn try_receive(&mut self) -> Result<T> {
-        // Do not try to receive anything if the contract already received data.
-        if self.done {
-            return Err(Error::Done);
+    pub fn try_fetch(&self) -> Result<Option<T>> {
+        let mut contract = self.try_request()?;
+
+        match contract.try_receive() {
+            Ok(datum) => { return Ok(Some(datum)); },
+            Err(Error::Empty) => {},
+            Err(Error::Closed) => { return Err(Error::Closed); },
+            Err(_) => unreachable!(),
         }
 
-        let datum = self.inner.try_get_datum()?;
-        self.done = true;
+        match contract.try_cancel() {
+            Ok(()) => { return Ok(None); },
+            Err(Error::TooLate) => {},
+            Err(_) => unreachable!(),
+        }
 
-        Ok(datum)
-    } 
+        // A responder committed to the request, so drain its datum.
+        let mut backoff = Backoff::tuned(&self.inner.spin);
 
-    /// This method attempts to cancel a request. This is useful for
-    /// implementing a timeout.
+        loop {
+            match contract.try_receive() {
+                Ok(datum) => { return Ok(Some(datum)); },
+                // A claim that was taken back leaves the request to be
+                // cancelled again.
+                Err(Error::Empty) if !contract.responder_claimed() => {
+                    match contract.try_cancel() {
+                        Ok(()) => { return Ok(None); },
+                        Err(Error::TooLate) => {},
+                        Err(_) => unreachable!(),
+                    }
+                },
+                Err(Error::Empty) => { backoff.snooze(); },
+                // A revoked claim may leave the request to be withdrawn.
+                Err(Error::Closed) => { return Err(Error::Closed); },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+
+    /// This method tries to request up to `n` item(s) from one or more
+    /// `Responder`(s). It works just like `Requester::try_request()`, but
+    /// it also lets the responding side know how much the requesting side
+    /// can take (e.g. how many tasks a thief wants to steal). The responder
+    /// can read the hint with `ResponseContract::amount_requested()`.
     ///
     /// # Warning
     ///
-    /// It also returns `false` if the user called it after
-    /// either receiving a datum or cancelling the request.
+    /// The amount is only a hint. Nothing stops the responder from sending
+    /// more or less than `n` item(s).
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of item(s) the requester wants
     ///
     /// # Example
     /// 
     /// ```rust
     /// extern crate reqchan as chan;
     ///
-    /// let (requester, responder) = chan::channel::<u32>(); 
+    /// let (requester, responder) = chan::channel::<Vec<u32>>(); 
     ///
-    /// {
-    ///     let mut contract = requester.try_request().ok().unwrap();
+    /// let mut request_contract = requester.try_request_amount(2).ok().unwrap();
     ///
-    ///     // We can cancel the request since `responder` has not
-    ///     // yet responded to it.
-    ///     contract.try_cancel().ok().unwrap();
+    /// let mut tasks = vec![1, 2, 3, 4, 5];
+    /// let response_contract = responder.try_respond().ok().unwrap();
     ///
-    ///     // Both contracts go out of scope here
-    /// }
+    /// // Only send as many items as the requester asked for.
+    /// let at = tasks.len() - response_contract.amount_requested();
+    /// response_contract.send(tasks.split_off(at));
     ///
-    /// {
-    ///     let mut request_contract = requester.try_request().ok().unwrap();
+    /// assert_eq!(request_contract.try_receive().ok().unwrap(), vec![4, 5]);
+    /// ```
+    pub fn try_request_amount(&self, n: usize) -> Result<RequestContract<T>> {
+        self.try_request_with(|inner| inner.set_amount(n))
+    }
+
+    /// This method tries to request item(s) just like `Requester::try_request()`,
+    /// but it marks the request as urgent. Responders that serve several
+    /// channels can check `Responder::pending_urgency()` to answer urgent
+    /// requests first.
     ///
-    ///     responder.try_respond().ok().unwrap().send(7);
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
     ///
-    ///     // It is too late to cancel the request!
-    ///     match request_contract.try_cancel() {
-    ///         Err(chan::Error::TooLate) => {
-    ///             println!("Number: {}", request_contract.try_receive().unwrap());
-    ///         },
-    ///         _ => unimplemented!(),
-    ///     }
+    /// let (requester, responder) = chan::channel::<u32>(); 
     ///
-    ///     // Both contracts go out of scope here
-    /// }
+    /// let mut request_contract = requester.try_request_urgent().ok().unwrap();
+    ///
+    /// assert_eq!(responder.pending_urgency(), Some(chan::Urgency::Urgent));
+    ///
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// assert_eq!(response_contract.urgency(), chan::Urgency::Urgent);
+    ///
+    /// response_contract.send(1);
+    /// request_contract.try_receive().ok().unwrap();
     /// ```
-    pub fn try_cancel(&mut self) -> Result<()> {
-        // Do not try to unsend if the contract already received data.
-        if self.done {
-            return Err(Error::Done);
-        }
-
-        match self.inner.try_unflag_request() {
-            Ok(()) => {
-                self.done = true;
-                Ok(())
-            },
-            Err(Error::NoRequest) => {
-                Err(Error::TooLate)
-            },
-            _ => unreachable!(),
-        }
+    pub fn try_request_urgent(&self) -> Result<RequestContract<T>> {
+        self.try_request_with_urgency(Urgency::Urgent)
     }
-}
-
-impl<T> Drop for RequestContract<T> {
-    fn drop(&mut self) {
-        if !self.done {
-            panic!("Dropping RequestContract without receiving data!");
-        }
 
-        self.inner.unlock_request();
+    /// This method tries to request item(s) with the given urgency.
+    ///
+    /// # Arguments
+    ///
+    /// * `urgency` - How urgent the request is
+    pub fn try_request_with_urgency(&self, urgency: Urgency) -> Result<RequestContract<T>> {
+        self.try_request_with(|inner| inner.set_urgency(urgency))
     }
-}
-
-/// This end of the channel sends data in response to requests from
-/// its `Requester`.
-pub struct Responder<T> {
-    inner: Arc<Inner<T>>,
-}
 
-impl<T> Responder<T> {
-    /// This method signals the intent of `Responder` to respond to a request.
-    /// If successful, it returns a `ResponseContract` to ensure the user sends
-    /// a datum.
+    /// This method tries to request item(s) that are only wanted for
+    /// `ttl`. A responder that tries to claim the request after that gets
+    /// `Err(Error::NoRequest)`, and `RequestContract::try_receive()`
+    /// returns `Err(Error::Expired)` once the time is up and no responder
+    /// claimed it, so the requesting side does not have to wake up on time
+    /// to cancel it.
     ///
     /// # Warning
     ///
-    /// Only **one** `ResponseContract` may be active at a time.
+    /// A responder that claims the request before it expires wins, even
+    /// if it sends the datum after the time is up.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl` - How long the request stays valid
     ///
     /// # Example
-    /// 
-    /// ```
-    /// extern crate reqchan as chan;
     ///
-    /// let (requester, responder) = chan::channel::<u32>(); 
+    /// ```rust
+    /// extern crate reqchan as chan;
     ///
-    /// // `requester` has not yet issued a request.
-    /// match responder.try_respond() {
-    ///     Err(chan::Error::NoRequest) => {
-    ///         println!("There is no request!");
-    ///     },
-    ///     _ => unreachable!(),
-    /// }
+    /// use std::thread;
+    /// use std::time::Duration;
     ///
-    /// let mut request_contract = requester.try_request().ok().unwrap();
+    /// let (requester, responder) = chan::channel::<u32>();
     ///
-    /// // `requester` has issued a request.
-    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// let mut contract = requester.try_request_ttl(Duration::from_millis(1)).ok().unwrap();
+    /// thread::sleep(Duration::from_millis(2));
     ///
-    /// // We cannot issue another response to the request.
     /// match responder.try_respond() {
-    ///     Err(chan::Error::AlreadyLocked) => {
-    ///         println!("We cannot issue multiple responses to a request!");
-    ///     },
+    ///     Err(chan::Error::NoRequest) => {},
+    ///     _ => unreachable!(),
+    /// }
+    /// match contract.try_receive() {
+    ///     Err(chan::Error::Expired) => {},
     ///     _ => unreachable!(),
     /// }
-    ///
-    /// response_contract.send(8);
-    /// 
-    /// println!("Number is {}", request_contract.try_receive().ok().unwrap());
     /// ```
-    pub fn try_respond(&self) -> Result<ResponseContract<T>> {
-        // First try to lock the responding side.
-        let _ = self.inner.try_lock_response()?;
-        
-        // Next, atomically check for a request and signal a response to it.
-        // If no request exists, drop the lock and return the data.
-        match self.inner.try_unflag_request() {
-            Ok(_) => {
-                Ok(ResponseContract {
-                    inner: self.inner.clone(),
-                    done: false,
-                })
-            },
-            Err(err) => {
-                self.inner.unlock_response();
-                Err(err)
-            },
-        }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn try_request_ttl(&self, ttl: Duration) -> Result<RequestContract<T>> {
+        self.try_request_with(|inner| {
+            inner.set_expiry(clock::now().saturating_add(ttl.as_nanos() as u64).max(1))
+        })
     }
-}
 
-impl<T> Clone for Responder<T> {
-    fn clone(&self) -> Self {
-        Responder {
-            inner: self.inner.clone(),
-        }
+    /// This method tries to request item(s) and tells the responders the
+    /// moment the requester stops caring about the answer, so a responder
+    /// can skip work that cannot arrive in time. Responders can read it
+    /// with `Responder::pending_deadline()` before claiming the request
+    /// and with `ResponseContract::deadline()` after.
+    ///
+    /// # Warning
+    ///
+    /// The deadline is only information. The channel does not enforce
+    /// it; use `Requester::try_request_ttl()` for that.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - When the requester stops caring about the answer
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    ///
+    /// let mut contract = requester.try_request_with_deadline(deadline).ok().unwrap();
+    /// assert!(responder.pending_deadline().is_some());
+    ///
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// let left = response_contract.deadline().unwrap() - Instant::now();
+    /// assert!(left <= Duration::from_secs(1));
+    ///
+    /// response_contract.send(1);
+    /// contract.try_receive().ok().unwrap();
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn try_request_with_deadline(&self, deadline: Instant) -> Result<RequestContract<T>> {
+        self.try_request_with(|inner| {
+            inner.set_deadline(clock::from_instant(deadline).max(1))
+        })
     }
-}
 
-/// This is the contract returned by a successful `Responder::try_response()`.
-/// It represents the caller's exclusive access to the responding side of
-/// the channel. It ensures the user sends a datum by panicking if they have not.
-pub struct ResponseContract<T> {
-    inner: Arc<Inner<T>>,
-    done: bool,
-}
+    /// This method tries to request item(s) and attaches `tag` to the
+    /// request, such as a shard index or a bitfield of flags. Responders
+    /// can read it with `Responder::pending_tag()` before claiming the
+    /// request and with `ResponseContract::tag()` after. Requests made
+    /// without a tag carry `0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag to attach
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut contract = requester.try_request_tagged(7).ok().unwrap();
+    /// assert_eq!(responder.pending_tag(), Some(7));
+    ///
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// assert_eq!(response_contract.tag(), 7);
+    ///
+    /// response_contract.send(1);
+    /// contract.try_receive().ok().unwrap();
+    /// ```
+    pub fn try_request_tagged(&self, tag: u64) -> Result<RequestContract<T>> {
+        self.try_request_with(|inner| inner.set_tag(tag))
+    }
 
-impl<T> ResponseContract<T> {
-    /// This method tries to send a datum to the requesting end of the channel.
-    /// It will then consume itself, thereby freeing the responding side of
-    /// the channel.
+    /// This method locks the requesting side, stores the request's
+    /// metadata, and flags the request.
     ///
     /// # Arguments
     ///
-    /// * `datum` - The item(s) to send
+    /// * `set_meta` - A function that overrides the default metadata
+    #[inline]
+    fn try_request_with<F>(&self, set_meta: F) -> Result<RequestContract<T>>
+        where F: FnOnce(&Inner<T>)
+    {
+        // First, try to lock the requesting side.
+        self.inner.try_lock_request()?;
+
+        // Next, store the metadata. This must happen before the
+        // request is flagged so any responder that sees the request
+        // also sees its metadata.
+        self.inner.set_amount(1);
+        self.inner.set_urgency(Urgency::Normal);
+        self.inner.set_expiry(0);
+        self.inner.set_deadline(0);
+        self.inner.set_tag(0);
+        self.inner.set_subscribed(false);
+        set_meta(&self.inner);
+
+        // Then flag a request.
+        self.inner.flag_request();
+        self.inner.count_issued();
+        self.inner.notify(ChannelEvent::RequestIssued);
+        self.inner.wake_responders();
+
+        // Then return a `RequestContract`.
+        Ok(RequestContract {
+            sequence: self.inner.sequence(),
+            inner: self.inner.clone(),
+            watch: LeaseWatch::default(),
+            done: false,
+        })
+    }
+
+    /// This method tries to request one datum from each of up to `n`
+    /// distinct `Responder`(s). The request stays open until `n` responders
+    /// have claimed it, so several responders can work on it at the same time.
+    /// If successful, it returns a `MultiRequestContract` to either poll for
+    /// data or cancel the rest of the request.
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `RequestContract` or `MultiRequestContract` may be
+    /// active at a time.
+    ///
+    /// # Panics
+    ///
+    /// It panics if `n` is zero or greater than `u32::MAX`.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of responses to collect
     ///
     /// # Example
     /// 
-    /// ```
+    /// ```rust
     /// extern crate reqchan as chan;
     ///
     /// let (requester, responder) = chan::channel::<u32>(); 
+    /// let responder2 = responder.clone();
     ///
-    /// let mut request_contract = requester.try_request().ok().unwrap();
+    /// let mut contract = requester.try_request_many(2).ok().unwrap();
     ///
-    /// let mut response_contract = responder.try_respond().ok().unwrap();
+    /// // Both responders can claim the request at the same time.
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// let response_contract2 = responder2.try_respond().ok().unwrap();
     ///
-    /// // We send data to the requesting end.
-    /// response_contract.send(9);
+    /// response_contract.send(1);
+    /// response_contract2.send(2);
     ///
-    /// println!("Number is {}", request_contract.try_receive().unwrap());
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    /// assert_eq!(contract.remaining(), 0);
     /// ```
-    pub fn send(mut self, datum: T) {
-        self.inner.set_datum(datum);
-        self.done = true;
+    pub fn try_request_many(&self, n: usize) -> Result<MultiRequestContract<T>> {
+        self.try_request_counted(n, true)
     }
-}
-
-impl<T> Drop for ResponseContract<T> {
-    fn drop(&mut self) {
-        if !self.done {
-            panic!("Dropping ResponseContract without sending data!");
-        }
 
-        self.inner.unlock_response();
+    /// This method tries to request `k` data, one per claim. Unlike
+    /// `Requester::try_request_many()`, the same `Responder` may claim the
+    /// request more than once, so any `k` claims by any responders satisfy
+    /// it. This is useful for fanning work out, e.g. "I need 4 shards
+    /// scanned; any 4 workers pick one each".
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `RequestContract` or `MultiRequestContract` may be
+    /// active at a time.
+    ///
+    /// # Panics
+    ///
+    /// It panics if `k` is zero or greater than `u32::MAX`.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - The number of data to collect
+    ///
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// let mut contract = requester.try_request_n(3).ok().unwrap();
+    ///
+    /// // A single responder can claim the request several times.
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// let response_contract2 = responder.try_respond().ok().unwrap();
+    /// assert_eq!(contract.outstanding(), 1);
+    ///
+    /// // Stop further claims, but let the claimed shards arrive.
+    /// contract.try_cancel().ok().unwrap();
+    /// assert_eq!(contract.outstanding(), 0);
+    ///
+    /// response_contract.send(1);
+    /// response_contract2.send(2);
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    /// assert_eq!(contract.remaining(), 0);
+    /// ```
+    pub fn try_request_n(&self, k: usize) -> Result<MultiRequestContract<T>> {
+        self.try_request_counted(k, false)
     }
-}
 
-#[derive(Debug)]
-pub enum Error {
-    AlreadyLocked,
-    Done,
-    Empty,
-    NoRequest,
-    TooLate,
-}
+    /// This method locks the requesting side and opens it to `n` claims.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of responses to collect
+    ///
+    /// * `distinct` - Whether each responder may only claim one response
+    #[inline]
+    fn try_request_counted(&self, n: usize, distinct: bool) -> Result<MultiRequestContract<T>> {
+        if n == 0 || n > u32::MAX as usize {
+            panic!("Cannot request {} responses!", n);
+        }
 
-pub type Result<T> = result::Result<T, Error>;
+        // First, try to lock the requesting side.
+        self.inner.try_lock_request()?;
 
-#[doc(hidden)]
-struct Inner<T> {
-    has_request_lock: AtomicBool,
-    has_response_lock: AtomicBool,
-    has_request: AtomicBool,
-    has_datum: AtomicBool,
-    datum: UnsafeCell<Option<T>>,
-}
+        // Next, open the request to `n` claims.
+        self.inner.flag_many(n, distinct);
+        self.inner.count_issued();
+        self.inner.notify(ChannelEvent::RequestIssued);
+        self.inner.wake_responders();
 
-unsafe impl<T> Sync for Inner<T> {}
+        // Then return a `MultiRequestContract`.
+        Ok(MultiRequestContract {
+            inner: self.inner.clone(),
+            expected: n,
+            received: 0,
+        })
+    }
 
-#[doc(hidden)]
-impl<T> Inner<T> {
-    /// This method indicates that the requesting side has made a request.
+    /// This method tries to request one datum from every `Responder`,
+    /// including every clone, that exists when it is called. Each of them
+    /// may claim the request once. Responders created afterwards are not
+    /// part of it, and responders dropped before claiming it are no longer
+    /// expected to respond.
     ///
     /// # Warning
     ///
-    /// **ONLY** the requesting side of the channel should call it.
+    /// Only **one** `RequestContract`, `MultiRequestContract` or
+    /// `BroadcastContract` may be active at a time.
     ///
-    /// # Invariant
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
     ///
-    /// * self.has_request_lock == true
-    #[inline]
-    fn flag_request(&self) {
-        self.has_request.store(true, Ordering::SeqCst);
-    }
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    /// let responder2 = responder.clone();
+    ///
+    /// let mut contract = requester.try_request_all().ok().unwrap();
+    /// assert_eq!(contract.responses_remaining(), 2);
+    ///
+    /// responder.try_respond().ok().unwrap().send(1);
+    ///
+    /// // `responder2` goes away without responding.
+    /// drop(responder2);
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), (responder.id(), 1));
+    /// assert_eq!(contract.responses_remaining(), 0);
+    /// ```
+    pub fn try_request_all(&self) -> Result<BroadcastContract<T>> {
+        // First, try to lock the requesting side.
+        self.inner.try_lock_request()?;
 
-    /// This method atomically checks to see if the requesting end
-    /// issued a request and unflag the request.
-    #[inline]
-    fn try_unflag_request(&self) -> Result<()> {
-        let (old, new) = (true, false);
+        // Next, open the request to every live responder.
+        self.inner.flag_broadcast();
+        self.inner.count_issued();
+        self.inner.notify(ChannelEvent::RequestIssued);
+        self.inner.wake_responders();
 
-        let res = self.has_request.compare_and_swap(old,
-                                                    new,
-                                                    Ordering::SeqCst);
-        if res == old {
-            Ok(())
-        }
-        else {
-            Err(Error::NoRequest)
-        }
+        // Then return a `BroadcastContract`.
+        Ok(BroadcastContract::new(self.inner.clone()))
     }
 
-    /// This method sets the inner datum to the specified value.
+    /// This method turns the `Requester` into a cloneable `SharedRequester`
+    /// so multiple threads can issue requests.
     ///
-    /// # Arguments
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
     ///
-    /// * datum - The datum to set
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    /// let requester = requester.into_shared();
+    /// let requester2 = requester.clone();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// // Only one clone may have an outstanding request.
+    /// match requester2.try_request() {
+    ///     Err(chan::Error::AlreadyLocked) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// responder.try_respond().ok().unwrap().send(3);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    /// ```
+    pub fn into_shared(self) -> SharedRequester<T> {
+        SharedRequester::new(self)
+    }
+
+    /// This method creates a `WeakRequester` that neither keeps the
+    /// channel alive nor counts as a live requester.
+    #[inline]
+    pub fn downgrade(&self) -> WeakRequester<T> {
+        WeakRequester::new(&self.inner)
+    }
+
+    /// This method returns the number of live `Responder`s. If it is `0`,
+    /// nobody will answer a request, so the caller may as well do the
+    /// work itself. It does not lock anything.
     ///
     /// # Warning
     ///
-    /// **ONLY** the responding side of the channel should call it.
+    /// Responders may be created or dropped right after this method
+    /// returns.
     ///
-    /// # Invariant
+    /// # Example
     ///
-    /// * self.has_response_lock == true
+    /// ```rust
+    /// extern crate reqchan as chan;
     ///
-    /// * (*self.datum.get()).is_none() == true
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// assert_eq!(requester.responder_count(), 1);
     ///
-    /// * self.has_datum == false
+    /// let responder2 = responder.clone();
+    /// assert_eq!(requester.responder_count(), 2);
+    ///
+    /// drop(responder);
+    /// drop(responder2);
+    /// assert_eq!(requester.responder_count(), 0);
+    /// ```
     #[inline]
-    fn set_datum(&self, data: T) {
-        // First update inner datum.
-        unsafe {
-            *self.datum.get() = Some(data);
-        }
-
-        // Then indicate the presence of a new datum.
-        self.has_datum.store(true, Ordering::SeqCst);
+    pub fn responder_count(&self) -> usize {
+        self.inner.responder_count()
     }
-    
-    /// This method tries to get the datum out of `Inner`.
+
+    /// This method returns the number of live `Responder`s that are not
+    /// marked busy with `Responder::set_busy()`. If it is `0`, a request
+    /// would only be answered once some worker finishes what it is doing,
+    /// so the caller may rather do the work itself. It does not lock
+    /// anything.
     ///
     /// # Warning
     ///
-    /// **ONLY** the requesting side of the channel should call it.
+    /// Responders may be created, dropped, or change their busy flags
+    /// right after this method returns.
     ///
-    /// # Invariant
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
     ///
-    /// * self.has_request_lock == true
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let responder2 = responder.clone();
+    /// assert_eq!(requester.available_responders(), 2);
     ///
-    /// * if self.has_datum == true then (*self.datum.get()).is_some() == true
+    /// responder.set_busy(true);
+    /// assert_eq!(requester.available_responders(), 1);
+    ///
+    /// // Dropping a busy responder does not leave it counted as busy.
+    /// drop(responder);
+    /// assert_eq!(requester.available_responders(), 1);
+    /// # drop(responder2);
+    /// ```
     #[inline]
-    fn try_get_datum(&self) -> Result<T> {
-        // First check to see if data exists.
-        let (old, new) = (true, false);
-
-        if self.has_datum.compare_and_swap(old,
-                                           new,
-                                           Ordering::SeqCst) == old {
-            // If so, retrieve the data and unwrap it from its Option container.
-            unsafe {
-                Ok((*self.datum.get()).take().unwrap())
-            }
-        }
-        else {
-            Err(Error::Empty)
-        }
+    pub fn available_responders(&self) -> usize {
+        self.inner.responder_count()
+            .saturating_sub(self.inner.response.busy.load(Ordering::SeqCst))
     }
 
-    // TODO: Make locks Acquire and Release
-    
-    /// This method tries to lock the requesting side of the channel.
-    /// It returns a `boolean` indicating whether or not it succeeded.
+    /// This method creates a new `Responder` for the channel, so a worker
+    /// that joins late does not need a spare clone of an existing one. It
+    /// works even after every other `Responder` is gone, and the channel
+    /// then counts as connected again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// drop(responder);
+    /// assert_eq!(requester.responder_count(), 0);
+    ///
+    /// let responder = requester.new_responder();
+    /// assert_eq!(requester.responder_count(), 1);
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// ```
     #[inline]
-    fn try_lock_request(&self) -> Result<()> {
-        let (old, new) = (false, true);
-
-        let res = self.has_request_lock.compare_and_swap(old,
-                                                         new,
-                                                         Ordering::SeqCst);
-
-        if res == old {
-            Ok(())
-        }
-        else {
-            Err(Error::AlreadyLocked)
-        }
+    pub fn new_responder(&self) -> Responder<T> {
+        Responder::new(self.inner.clone())
     }
 
-    /// This method unlocks the requesting side of the channel.
+    /// This method pauses the channel, so responders stop claiming
+    /// requests until `Requester::resume()` is called. While the channel
+    /// is paused, `Responder::try_respond()` fails with `Error::Paused`
+    /// even if a request is pending, and the blocking ways to respond,
+    /// like `Responder::incoming()`, wait for it to resume. The requester
+    /// can still issue requests; they are claimed once the channel
+    /// resumes.
+    ///
+    /// # Warning
+    ///
+    /// A request that was already claimed is not affected. Its responder
+    /// can still send the datum.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// requester.pause();
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// match responder.try_respond() {
+    ///     Err(chan::Error::Paused) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// requester.resume();
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// ```
     #[inline]
-    fn unlock_request(&self) {
-        self.has_request_lock.store(false, Ordering::SeqCst);
+    pub fn pause(&self) {
+        self.inner.request.paused.store(true, Ordering::SeqCst);
     }
 
-    /// This method tries to lock the responding side of the channel.
-    /// It returns a `boolean` indicating whether or not it succeeded.
+    /// This method resumes a channel paused with `Requester::pause()`. A
+    /// request issued while the channel was paused can be claimed right
+    /// away.
     #[inline]
-    fn try_lock_response(&self) -> Result<()> {
-        let (old, new) = (false, true);
+    pub fn resume(&self) {
+        self.inner.request.paused.store(false, Ordering::SeqCst);
 
-        let res = self.has_response_lock.compare_and_swap(old,
-                                                          new,
-                                                          Ordering::SeqCst);
-
-        if res == old {
-            Ok(())
-        }
-        else {
-            Err(Error::AlreadyLocked)
+        if self.inner.has_pending_request() {
+            self.inner.wake_responders();
         }
     }
 
-    /// This method unlocks the responding side of the channel.
+    /// This method returns whether the channel is paused.
     #[inline]
-    fn unlock_response(&self) {
-        self.has_response_lock.store(false, Ordering::SeqCst);
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::sync::Arc;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    
-    use super::*;
-
-    trait FnBox {
-        fn call_box(self: Box<Self>);
-    }
-
-    impl<F: FnOnce()> FnBox for F {
-        fn call_box(self: Box<F>) {
-            (*self)()
-        }
+    pub fn is_paused(&self) -> bool {
+        self.inner.is_paused()
     }
 
-    type Task = Box<FnBox + Send + 'static>;
+    /// This method closes the whole channel for good, so it can be shut
+    /// down without dropping every handle. Afterwards,
+    /// `Requester::try_request()` and `Responder::try_respond()` fail with
+    /// `Error::Closed`, and a request nobody claimed yet is withdrawn the
+    /// next time its contract polls, which then fails with `Error::Closed`
+    /// too. The blocking ways to respond, like `Responder::incoming()`,
+    /// stop waiting.
+    ///
+    /// # Warning
+    ///
+    /// A request that was already claimed is not affected. Its responder
+    /// can still send the datum, and the contract still has to receive it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// requester.close();
+    ///
+    /// // The exchange in flight still completes.
+    /// response_contract.send(1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// drop(contract);
+    ///
+    /// match requester.try_request() {
+    ///     Err(chan::Error::Closed) => {},
+    ///     _ => unreachable!(),
+    /// }
+    /// assert!(responder.is_closed());
+    /// ```
+    pub fn close(&self) {
+        self.inner.request.closed.store(true, Ordering::SeqCst);
+
+        // Wake whoever waits on either side, so they see it.
+        self.inner.wake_responders();
+        self.inner.wake_requester();
+    }
+
+    /// This method returns whether the channel is closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+impl<T> Drop for Requester<T> {
+    fn drop(&mut self) {
+        self.inner.remove_requester();
+    }
+}
+
+/// This is the contract returned by a successful `Requester::try_request()`.
+/// It represents the caller's exclusive access to the requesting side of
+/// the channel. The user can either try to get a datum from the responding side
+/// or *attempt* to cancel the request. To prevent data loss, `RequestContract`
+/// will panic if the user has not received a datum or cancelled the request.
+pub struct RequestContract<T> {
+    inner: Link<T>,
+    sequence: u64,
+    watch: LeaseWatch,
+    done: bool,
+}
+
+impl<T> RequestContract<T> {
+    /// This method attempts to receive a datum from one or more responder(s).
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it
+    /// after either receiving a datum or cancelling the request.
+    ///
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    ///
+    /// // The responder has not responded yet. 
+    /// match request_contract.try_receive() {
+    ///     Err(chan::Error::Empty) => { println!("No Data yet!"); },
+    ///     _ => unreachable!(),
+    /// }
+    /// 
+    /// responder.try_respond().ok().unwrap().send(6);
+    /// 
+    /// // The responder has responded now.
+    /// match request_contract.try_receive() {
+    ///     Ok(num) => { println!("Number: {}", num); },
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// // We need to issue another request to receive more data.
+    /// match request_contract.try_receive() {
+    ///     Err(chan::Error::Done) => {
+    ///         println!("We already received data!");
+    ///     },
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn try_receive(&mut self) -> Result<T> {
+        // Do not try to receive anything if the contract already received data.
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        let datum = match self.inner.try_get_datum() {
+            Ok(datum) => datum,
+            Err(err) => {
+                if self.inner.try_expire_request().is_ok() {
+                    self.done = true;
+                    return Err(Error::Expired);
+                }
+
+                // Nobody can claim the request of a closed channel.
+                if self.inner.is_closed() && self.try_cancel_with(CancelReason::Shutdown).is_ok() {
+                    return Err(Error::Closed);
+                }
+
+                self.inner.try_revoke_claim(&mut self.watch);
+                self.inner.try_break_hold(&mut self.watch);
+
+                self.inner.count_empty_poll();
+                return Err(err);
+            },
+        };
+        self.done = true;
+        self.inner.notify(ChannelEvent::DatumReceived);
+
+        Ok(datum)
+    } 
+
+    /// This method works just like `RequestContract::try_receive()`, but
+    /// also returns the id of the `Responder` that sent the datum.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let responder2 = responder.clone();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder2.try_respond().ok().unwrap().send(1);
+    ///
+    /// let (num, id) = contract.try_receive_attributed().ok().unwrap();
+    /// assert_eq!(num, 1);
+    /// assert_eq!(id, responder2.id());
+    /// ```
+    pub fn try_receive_attributed(&mut self) -> Result<(T, ResponderId)> {
+        let datum = self.try_receive()?;
+
+        // The contract still holds the requesting side's lock, so no other
+        // responder can have overwritten the id yet.
+        Ok((datum, self.inner.answerer()))
+    }
+
+    /// This method works just like `RequestContract::try_receive()`, but
+    /// lets `accept` look at the datum first. If `accept` returns `true`,
+    /// it returns the datum. Otherwise it drops the datum, issues the same
+    /// request again, and returns `Ok(None)`; the contract stays live, and
+    /// any responder, including the one that sent the rejected datum, can
+    /// answer it.
+    ///
+    /// # Arguments
+    ///
+    /// * `accept` - Whether the requesting side can use the datum
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// assert_eq!(contract.try_receive_validate(|num| *num > 1).ok().unwrap(), None);
+    ///
+    /// responder.try_respond().ok().unwrap().send(2);
+    /// assert_eq!(contract.try_receive_validate(|num| *num > 1).ok().unwrap(), Some(2));
+    /// ```
+    pub fn try_receive_validate<F>(&mut self, accept: F) -> Result<Option<T>>
+        where F: FnOnce(&T) -> bool
+    {
+        let datum = self.try_receive()?;
+
+        if accept(&datum) {
+            return Ok(Some(datum));
+        }
+
+        drop(datum);
+        self.done = false;
+        self.inner.reflag_request();
+
+        Ok(None)
+    }
+
+    /// This method returns the sequence number of the request. The
+    /// requests of a channel are numbered from `1`, in the order they
+    /// were issued, and cancelled or expired requests use up their
+    /// numbers too. The `ResponseContract` that claims the request
+    /// reports the same number, so both sides can log it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert_eq!(contract.sequence(), 1);
+    /// contract.try_cancel().ok().unwrap();
+    /// drop(contract);
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// assert_eq!(contract.sequence(), 2);
+    /// assert_eq!(response_contract.sequence(), 2);
+    ///
+    /// response_contract.send(1);
+    /// contract.try_receive().ok().unwrap();
+    /// ```
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// This method attempts to cancel a request. This is useful for
+    /// implementing a timeout.
+    ///
+    /// # Warning
+    ///
+    /// It also returns `false` if the user called it after
+    /// either receiving a datum or cancelling the request.
+    ///
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// {
+    ///     let mut contract = requester.try_request().ok().unwrap();
+    ///
+    ///     // We can cancel the request since `responder` has not
+    ///     // yet responded to it.
+    ///     contract.try_cancel().ok().unwrap();
+    ///
+    ///     // Both contracts go out of scope here
+    /// }
+    ///
+    /// {
+    ///     let mut request_contract = requester.try_request().ok().unwrap();
+    ///
+    ///     responder.try_respond().ok().unwrap().send(7);
+    ///
+    ///     // It is too late to cancel the request!
+    ///     match request_contract.try_cancel() {
+    ///         Err(chan::Error::TooLate) => {
+    ///             println!("Number: {}", request_contract.try_receive().unwrap());
+    ///         },
+    ///         _ => unimplemented!(),
+    ///     }
+    ///
+    ///     // Both contracts go out of scope here
+    /// }
+    /// ```
+    pub fn try_cancel(&mut self) -> Result<()> {
+        self.try_cancel_with(CancelReason::Unspecified)
+    }
+
+    /// This method cancels the request just like
+    /// `RequestContract::try_cancel()`, and tells responders why: once it
+    /// succeeds, `Responder::last_request_outcome()` returns
+    /// `RequestOutcome::Cancelled(reason)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - Why the request is cancelled
+    pub fn try_cancel_with(&mut self, reason: CancelReason) -> Result<()> {
+        // Do not try to unsend if the contract already received data.
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        match self.inner.try_cancel_request() {
+            Ok(()) => {
+                self.inner.record_outcome(self.sequence, RequestOutcome::Cancelled(reason));
+                self.inner.set_urgency(Urgency::Normal);
+                self.inner.count_cancelled();
+                self.inner.notify(ChannelEvent::RequestCancelled);
+                self.done = true;
+                Ok(())
+            },
+            // An expired request is already withdrawn.
+            Err(Error::NoRequest) if self.inner.try_expire_request().is_ok() => {
+                self.done = true;
+                Ok(())
+            },
+            Err(Error::NoRequest) => {
+                Err(Error::TooLate)
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// This method returns whether or not a responder has claimed the
+    /// request but not sent its datum yet. It does not lock anything or
+    /// try to receive the datum, so a timeout can use it to decide
+    /// whether to keep waiting.
+    ///
+    /// # Warning
+    ///
+    /// The responder may send its datum right after this method returns
+    /// `true`. It returns `false` once the datum has arrived, and after
+    /// the contract received it or cancelled the request.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert!(!contract.responder_claimed());
+    ///
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// assert!(contract.responder_claimed());
+    ///
+    /// response_contract.send(1);
+    /// assert!(!contract.responder_claimed());
+    ///
+    /// contract.try_receive().ok().unwrap();
+    /// ```
+    #[inline]
+    pub fn responder_claimed(&self) -> bool {
+        !self.done && self.inner.state() == State::Claimed
+    }
+
+    /// This method gives up on the datum of a claimed request without
+    /// panicking. The responder still sends it, and the channel drops it
+    /// along with itself.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** call it once no `Requester` of the channel is left to
+    /// receive the datum.
+    #[inline]
+    pub(crate) fn abandon(mut self) {
+        self.inner.lose_delivery();
+        self.done = true;
+    }
+}
+
+impl<T> Drop for RequestContract<T> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.inner.lose_delivery();
+            #[cfg(feature = "tracing")]
+            self.inner.trace_unresolved("RequestContract");
+            // A second panic would abort the process.
+            if !thread::panicking() {
+                panic!("Dropping RequestContract without receiving data!");
+            }
+        }
+
+        self.inner.finish_request();
+    }
+}
+
+/// This is the contract returned by a successful `Requester::try_request_many()`.
+/// It represents the caller's exclusive access to the requesting side of
+/// the channel while it collects one datum from each of several responders.
+/// To prevent data loss, `MultiRequestContract` will panic if it is dropped
+/// before every claimed response has been received.
+pub struct MultiRequestContract<T> {
+    inner: Link<T>,
+    expected: usize,
+    received: usize,
+}
+
+impl<T> MultiRequestContract<T> {
+    /// This method attempts to receive the next datum sent by one of the
+    /// responders. Data arrive in the order they were sent.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it after receiving
+    /// every expected datum.
+    ///
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// let mut contract = requester.try_request_many(1).ok().unwrap();
+    ///
+    /// match contract.try_receive() {
+    ///     Err(chan::Error::Empty) => { println!("No Data yet!"); },
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// responder.try_respond().ok().unwrap().send(6);
+    ///
+    /// match contract.try_receive() {
+    ///     Ok(num) => { println!("Number: {}", num); },
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// match contract.try_receive() {
+    ///     Err(chan::Error::Done) => { println!("We received everything!"); },
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn try_receive(&mut self) -> Result<T> {
+        // Do not try to receive anything if the contract already received
+        // all the data it expects.
+        if self.remaining() == 0 {
+            return Err(Error::Done);
+        }
+
+        let datum = match self.inner.try_pop_datum() {
+            Ok(datum) => datum,
+            Err(err) => {
+                // Nobody can claim the rest of the request of a closed
+                // channel.
+                if self.inner.is_closed() {
+                    let _ = self.try_cancel();
+
+                    if self.remaining() == 0 {
+                        return Err(Error::Closed);
+                    }
+                }
+
+                self.inner.count_empty_poll();
+                return Err(err);
+            },
+        };
+        self.received += 1;
+        self.inner.notify(ChannelEvent::DatumReceived);
+
+        Ok(datum)
+    }
+
+    /// This method returns how many data the contract still expects.
+    /// This includes both unclaimed responses and responses that have been
+    /// claimed but not yet received.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.expected - self.received
+    }
+
+    /// This method returns how many responses no responder has claimed
+    /// yet. It drops to `0` once the contract is cancelled.
+    #[inline]
+    pub fn outstanding(&self) -> usize {
+        self.inner.pending_many()
+    }
+
+    /// This method attempts to cancel the part of the request that no
+    /// responder has claimed yet. Responders that already claimed the request
+    /// can still send their data, and the contract still has to receive them.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::TooLate)` if every response has already
+    /// been claimed, and `Err(Error::Done)` if the contract has already
+    /// been cancelled or received every datum.
+    ///
+    /// # Example
+    /// 
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// let mut contract = requester.try_request_many(3).ok().unwrap();
+    ///
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    ///
+    /// // Stop other responders from claiming the request.
+    /// contract.try_cancel().ok().unwrap();
+    /// assert_eq!(contract.remaining(), 1);
+    ///
+    /// // The responder that already claimed it still delivers.
+    /// response_contract.send(10);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 10);
+    /// ```
+    pub fn try_cancel(&mut self) -> Result<()> {
+        if self.remaining() == 0 {
+            return Err(Error::Done);
+        }
+
+        match self.inner.unflag_many() {
+            0 => Err(Error::TooLate),
+            withdrawn => {
+                self.inner.count_cancelled();
+                self.inner.notify(ChannelEvent::RequestCancelled);
+                self.expected -= withdrawn;
+                Ok(())
+            },
+        }
+    }
+}
+
+impl<T> Drop for MultiRequestContract<T> {
+    fn drop(&mut self) {
+        if self.remaining() > 0 {
+            #[cfg(feature = "tracing")]
+            self.inner.trace_unresolved("MultiRequestContract");
+            if !thread::panicking() {
+                panic!("Dropping MultiRequestContract without receiving data!");
+            }
+        }
+
+        self.inner.unlock_request();
+    }
+}
+
+/// This end of the channel sends data in response to requests from
+/// its `Requester`.
+pub struct Responder<T> {
+    inner: Link<T>,
+    id: ResponderId,
+    // The last request from `Requester::try_request_many()` that this
+    // responder claimed.
+    claimed: AtomicU64,
+    priority: AtomicU8,
+    busy: AtomicBool,
+    closed: AtomicBool,
+    tally: Arc<ClaimTally>,
+}
+
+impl<T> Responder<T> {
+    #[inline]
+    fn new(inner: Link<T>) -> Responder<T> {
+        let (id, tally) = inner.register_responder();
+        Responder::with_id(inner, id, tally)
+    }
+
+    /// This function wraps a link whose roster already includes `id`
+    /// and its claim tally.
+    #[inline]
+    fn with_id(inner: Link<T>, id: ResponderId, tally: Arc<ClaimTally>) -> Responder<T> {
+        Responder {
+            id,
+            inner,
+            claimed: AtomicU64::new(0),
+            priority: AtomicU8::new(0),
+            busy: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            tally,
+        }
+    }
+
+    /// This method returns the id of this `Responder`. It tells which
+    /// responder sent each datum of a `BroadcastContract`.
+    #[inline]
+    pub fn id(&self) -> ResponderId {
+        self.id
+    }
+
+    /// This method returns the number of other live `Responder`s of the
+    /// channel. It does not lock anything.
+    ///
+    /// # Warning
+    ///
+    /// Responders may be created or dropped right after this method
+    /// returns.
+    #[inline]
+    pub fn sibling_count(&self) -> usize {
+        self.inner.responder_count() - 1
+    }
+
+    /// This method returns how many requests this `Responder` tried to
+    /// claim and how many it won. `Monitor::responder_stats()` returns the
+    /// same for every live responder of the channel.
+    #[inline]
+    pub fn stats(&self) -> ResponderStats {
+        self.tally.stats(self.id)
+    }
+
+    /// This method returns the urgency of the pending request, or `None`
+    /// if there is no pending request. It does not lock anything.
+    ///
+    /// # Warning
+    ///
+    /// The request may be claimed or cancelled right after this
+    /// method returns.
+    #[inline]
+    pub fn pending_urgency(&self) -> Option<Urgency> {
+        if self.inner.state() == State::Requested {
+            Some(self.inner.get_urgency())
+        }
+        else {
+            None
+        }
+    }
+
+    /// This method returns the deadline of the pending request, or `None`
+    /// if there is no pending request or it announced no deadline. It
+    /// does not lock anything.
+    ///
+    /// # Warning
+    ///
+    /// The request may be claimed or cancelled right after this
+    /// method returns.
+    #[inline]
+    pub fn pending_deadline(&self) -> Option<Instant> {
+        if self.inner.state() != State::Requested {
+            return None;
+        }
+
+        match self.inner.get_deadline() {
+            0 => None,
+            deadline => Some(clock::to_instant(deadline)),
+        }
+    }
+
+    /// This method returns the tag of the pending request, or `None` if
+    /// there is no pending request. It does not lock anything.
+    ///
+    /// # Warning
+    ///
+    /// The request may be claimed or cancelled right after this
+    /// method returns.
+    #[inline]
+    pub fn pending_tag(&self) -> Option<u64> {
+        if self.inner.state() == State::Requested {
+            Some(self.inner.get_tag())
+        }
+        else {
+            None
+        }
+    }
+
+    /// This method returns whether or not the channel looks like it has a
+    /// request this `Responder` could claim. It only loads a few atomics,
+    /// so a worker polling many channels can use it to skip quiet ones
+    /// and only call `Responder::try_respond()` on the others.
+    ///
+    /// # Warning
+    ///
+    /// The answer may be stale by the time the caller acts on it. A
+    /// request may be claimed or cancelled right after this method returns
+    /// `true`, so `Responder::try_respond()` can still fail. During a
+    /// broadcast it returns `true` even if this responder already claimed
+    /// its part.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// assert!(!responder.has_request());
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert!(responder.has_request());
+    ///
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// assert!(!responder.has_request());
+    ///
+    /// contract.try_receive().ok().unwrap();
+    /// ```
+    #[inline]
+    pub fn has_request(&self) -> bool {
+        self.inner.has_pending_request()
+            || self.inner.request.broadcasting.load(Ordering::Relaxed)
+    }
+
+    /// This method sets the priority of this `Responder`. When several
+    /// responders try to claim the same request at about the same time,
+    /// the one with the highest priority wins; responders with equal
+    /// priorities race for it as usual. The default priority is `0`.
+    ///
+    /// While any responder has a nonzero priority, every claim of a
+    /// single-response request waits through a short arbitration window
+    /// so higher-priority responders can announce themselves.
+    ///
+    /// # Warning
+    ///
+    /// A responder only wins over lower-priority ones that try to claim
+    /// the request during its arbitration window. It cannot take back a
+    /// request that was already claimed.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - The new priority
+    ///
+    /// # Example
+    /// 
+    /// ```
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// // Prefer the responder on the same NUMA node.
+    /// let local_responder = responder.clone();
+    /// local_responder.set_priority(10);
+    ///
+    /// assert_eq!(local_responder.priority(), 10);
+    /// assert_eq!(responder.priority(), 0);
+    /// ```
+    pub fn set_priority(&self, priority: u8) {
+        let old = self.priority.swap(priority, Ordering::SeqCst);
+
+        match (old, priority) {
+            (0, 0) => {},
+            (0, _) => { self.inner.response.prioritized.fetch_add(1, Ordering::SeqCst); },
+            (_, 0) => { self.inner.response.prioritized.fetch_sub(1, Ordering::SeqCst); },
+            _ => {},
+        }
+    }
+
+    /// This method returns the priority of this `Responder`.
+    #[inline]
+    pub fn priority(&self) -> u8 {
+        self.priority.load(Ordering::SeqCst)
+    }
+
+    /// This method marks this `Responder` busy or idle, so the requesting
+    /// side can tell with `Requester::available_responders()` whether any
+    /// worker is free. A worker would mark itself busy before it starts on
+    /// a task and idle once it is done. It changes nothing about which
+    /// requests this responder can claim. New responders, clones
+    /// included, start idle.
+    ///
+    /// # Arguments
+    ///
+    /// * `busy` - Whether this responder is busy
+    pub fn set_busy(&self, busy: bool) {
+        if self.busy.swap(busy, Ordering::SeqCst) != busy {
+            if busy {
+                self.inner.response.busy.fetch_add(1, Ordering::SeqCst);
+            }
+            else {
+                self.inner.response.busy.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// This method returns whether this `Responder` is marked busy.
+    #[inline]
+    pub fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::SeqCst)
+    }
+
+    /// This method closes this `Responder` for good, so it fails to claim
+    /// any more requests with `Error::Closed`. Only this clone is closed:
+    /// other responders keep answering requests, and clones made from it
+    /// afterwards start open. A contract it already holds can still send
+    /// its datum.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let responder2 = responder.clone();
+    ///
+    /// responder.close();
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// match responder.try_respond() {
+    ///     Err(chan::Error::Closed) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// responder2.try_respond().ok().unwrap().send(1);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// ```
+    #[inline]
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    /// This method returns whether this `Responder`, or the whole
+    /// channel, is closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.inner.is_closed()
+    }
+
+    /// This method creates a `WeakResponder` that neither keeps the
+    /// channel alive nor counts as a live responder. Upgrading it gives
+    /// the new `Responder` this one's current priority.
+    #[inline]
+    pub fn downgrade(&self) -> WeakResponder<T> {
+        WeakResponder::new(&self.inner, self.priority())
+    }
+
+    /// This method clones this `Responder` and gives the clone a
+    /// different priority.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - The priority of the clone
+    pub fn clone_with_priority(&self, priority: u8) -> Responder<T> {
+        let responder = Responder::new(self.inner.clone());
+        responder.set_priority(priority);
+        responder
+    }
+
+    /// This method signals the intent of `Responder` to respond to a request.
+    /// If successful, it returns a `ResponseContract` to ensure the user sends
+    /// a datum.
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `ResponseContract` may be active at a time.
+    ///
+    /// # Example
+    /// 
+    /// ```
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// // `requester` has not yet issued a request.
+    /// match responder.try_respond() {
+    ///     Err(chan::Error::NoRequest) => {
+    ///         println!("There is no request!");
+    ///     },
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    ///
+    /// // `requester` has issued a request.
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    ///
+    /// // We cannot issue another response to the request.
+    /// match responder.try_respond() {
+    ///     Err(chan::Error::AlreadyLocked) => {
+    ///         println!("We cannot issue multiple responses to a request!");
+    ///     },
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// response_contract.send(8);
+    /// 
+    /// println!("Number is {}", request_contract.try_receive().ok().unwrap());
+    /// ```
+    pub fn try_respond(&self) -> Result<ResponseContract<T>> {
+        let contract = self.try_claim()?;
+        self.tally.count_won();
+        self.inner.notify(ChannelEvent::ResponseClaimed(self.id));
+
+        Ok(contract)
+    }
+
+    /// This method tries to claim a request and, if successful, sends it
+    /// the datum `f` returns. `f` only runs after the claim succeeded.
+    ///
+    /// # Warning
+    ///
+    /// If `f` panics, the claim is handed back so another responder can
+    /// claim the request, and the panic resumes. A `Responder` may not
+    /// claim a request from `Requester::try_request_many()` twice, but it
+    /// may claim a response it handed back.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function that makes the datum
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// // `f` does not run without a request.
+    /// match responder.try_respond_with(|| unreachable!()) {
+    ///     Err(chan::Error::NoRequest) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder.try_respond_with(|| 8).ok().unwrap();
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 8);
+    /// ```
+    pub fn try_respond_with<F>(&self, f: F) -> Result<()>
+        where F: FnOnce() -> T
+    {
+        // Nothing of the channel's is borrowed across `f`, so it is safe
+        // to keep using the channel after `f` panics.
+        match self.respond_catching(AssertUnwindSafe(f))? {
+            Ok(()) => Ok(()),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// This method works just like `Responder::try_respond_with()`, but
+    /// catches a panic in `f` and returns its payload instead of resuming
+    /// it. The claim is handed back first, so the request stays pending
+    /// for another responder, or for this one to try again, and is never
+    /// lost.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function that makes the datum
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let mut contract = requester.try_request().ok().unwrap();
+    ///
+    /// let res = responder.respond_catching(|| -> u32 { panic!("plugin failed") });
+    /// assert!(res.ok().unwrap().is_err());
+    ///
+    /// // The request is still there.
+    /// responder.respond_catching(|| 8).ok().unwrap().unwrap();
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 8);
+    /// ```
+    pub fn respond_catching<F>(&self, f: F) -> Result<thread::Result<()>>
+        where F: FnOnce() -> T + UnwindSafe
+    {
+        let mut contract = self.try_respond()?;
+
+        match panic::catch_unwind(f) {
+            Ok(datum) => {
+                contract.send(datum);
+                Ok(Ok(()))
+            },
+            Err(payload) => {
+                self.unclaim(&mut contract);
+                Ok(Err(payload))
+            },
+        }
+    }
+
+    /// This method tries to claim a request, but only if `pred` accepts
+    /// its metadata. `pred` runs while this responder holds the claim, so
+    /// the metadata cannot change under it, and if `pred` declines, the
+    /// claim is handed back untouched for other responders.
+    ///
+    /// # Warning
+    ///
+    /// While `pred` runs, other responders fail to claim the request with
+    /// `Err(Error::AlreadyLocked)`, so it should be quick. If `pred`
+    /// panics, the claim is handed back and the panic resumes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred` - The function that decides whether to answer the request
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let responder2 = responder.clone();
+    ///
+    /// // Each responder serves one shard.
+    /// let mut contract = requester.try_request_tagged(2).ok().unwrap();
+    ///
+    /// match responder.try_respond_if(|meta| meta.tag == 1) {
+    ///     Err(chan::Error::Declined) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// responder2.try_respond_if(|meta| meta.tag == 2).ok().unwrap().send(4);
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 4);
+    /// ```
+    pub fn try_respond_if<F>(&self, pred: F) -> Result<ResponseContract<T>>
+        where F: Fn(&RequestMeta) -> bool
+    {
+        let mut contract = self.try_claim()?;
+        let meta = contract.meta();
+
+        match panic::catch_unwind(AssertUnwindSafe(|| pred(&meta))) {
+            Ok(true) => {},
+            Ok(false) => {
+                self.unclaim(&mut contract);
+                return Err(Error::Declined);
+            },
+            Err(payload) => {
+                self.unclaim(&mut contract);
+                panic::resume_unwind(payload);
+            },
+        }
+
+        self.tally.count_won();
+        self.inner.notify(ChannelEvent::ResponseClaimed(self.id));
+
+        Ok(contract)
+    }
+
+    /// This method hands back the claim of a contract that will not send
+    /// anything, so the request stays pending for any responder.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - The contract this responder claimed the request with
+    #[inline]
+    fn unclaim(&self, contract: &mut ResponseContract<T>) {
+        // Let this responder claim the response again.
+        if contract.claim == Claim::Many {
+            self.claimed.store(0, Ordering::SeqCst);
+        }
+
+        contract.unclaim();
+    }
+
+    /// This method tries to claim whichever kind of request is pending.
+    #[inline]
+    fn try_claim(&self) -> Result<ResponseContract<T>> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if self.inner.is_paused() {
+            return Err(Error::Paused);
+        }
+
+        // If the requester is collecting several responses, try to claim
+        // one of them. This does not need the responding side's lock.
+        if self.inner.try_claim_many(&self.claimed).is_ok() {
+            return Ok(ResponseContract {
+                inner: self.inner.clone(),
+                responder: self.id,
+                amount: 1,
+                urgency: Urgency::Normal,
+                sequence: self.inner.sequence(),
+                deadline: 0,
+                tag: 0,
+                claim: Claim::Many,
+                lease: 0,
+                held: 0,
+                done: false,
+            });
+        }
+
+        // If the requester is collecting a response from every responder,
+        // try to claim this responder's.
+        if self.inner.try_claim_broadcast(self.id).is_ok() {
+            return Ok(ResponseContract {
+                inner: self.inner.clone(),
+                responder: self.id,
+                amount: 1,
+                urgency: Urgency::Normal,
+                sequence: self.inner.sequence(),
+                deadline: 0,
+                tag: 0,
+                claim: Claim::Broadcast,
+                lease: 0,
+                held: 0,
+                done: false,
+            });
+        }
+
+        // If any responder has a priority, give higher-priority
+        // responders a chance to claim the request first.
+        let mut res = Ok(());
+        if self.inner.response.prioritized.load(Ordering::SeqCst) > 0 {
+            res = self.inner.arbitrate(self.priority());
+        }
+
+        // Otherwise, atomically check for a request and claim it.
+        if let Err(err) = res.and_then(|()| self.inner.try_claim_request()) {
+            if let Error::AlreadyLocked = err {
+                self.inner.count_contention();
+                self.tally.count_lost();
+            }
+
+            return Err(err);
+        }
+
+        Ok(ResponseContract {
+            inner: self.inner.clone(),
+            responder: self.id,
+            amount: self.inner.get_amount(),
+            urgency: self.inner.get_urgency(),
+            sequence: self.inner.sequence(),
+            deadline: self.inner.get_deadline(),
+            tag: self.inner.get_tag(),
+            claim: Claim::Single,
+            lease: self.inner.publish_claim(),
+            held: 0,
+            done: false,
+        })
+    }
+}
+
+impl<T> Clone for Responder<T> {
+    fn clone(&self) -> Self {
+        self.clone_with_priority(self.priority())
+    }
+}
+
+impl<T> Drop for Responder<T> {
+    fn drop(&mut self) {
+        self.set_priority(0);
+        self.set_busy(false);
+        self.inner.unregister_responder(self.id);
+    }
+}
+
+/// This is the contract returned by a successful `Responder::try_response()`.
+/// It represents the caller's exclusive access to the responding side of
+/// the channel. It ensures the user sends a datum by panicking if they have not.
+///
+/// # Panics
+///
+/// Dropping the contract without sending a datum panics, unless the thread
+/// is already panicking. Then the drop hands the claim back instead, just
+/// like `Responder::respond_catching()`, so a worker that panics while it
+/// holds a contract does not abort the process, and the request stays
+/// pending for another responder.
+pub struct ResponseContract<T> {
+    inner: Link<T>,
+    // The responder that claimed the request.
+    responder: ResponderId,
+    amount: usize,
+    urgency: Urgency,
+    // The sequence number of the claimed request.
+    sequence: u64,
+    // The deadline of the claimed request as a `clock::now()` timestamp,
+    // or 0 if it has none.
+    deadline: u64,
+    tag: u64,
+    claim: Claim,
+    // The lease of a single-response claim, or 0 if the channel does not
+    // time claims or the lease is already sealed.
+    lease: u64,
+    // The token of the hold this contract carries for its responder, or
+    // 0 if it carries none.
+    held: u64,
+    done: bool,
+}
+
+/// This is the kind of request a `ResponseContract` claimed.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Claim {
+    // A request from `Requester::try_request()` and friends.
+    Single,
+    // One response to `Requester::try_request_many()` or
+    // `Requester::try_request_n()`.
+    Many,
+    // This responder's response to `Requester::try_request_all()`.
+    Broadcast,
+}
+
+impl<T> ResponseContract<T> {
+    /// This method returns the amount hint of the request this contract
+    /// is responding to. It is `1` unless the requesting side called
+    /// `Requester::try_request_amount()`.
+    ///
+    /// # Example
+    /// 
+    /// ```
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<Vec<u32>>(); 
+    ///
+    /// let mut request_contract = requester.try_request_amount(3).ok().unwrap();
+    ///
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// assert_eq!(response_contract.amount_requested(), 3);
+    ///
+    /// response_contract.send(vec![1, 2, 3]);
+    /// request_contract.try_receive().ok().unwrap();
+    /// ```
+    #[inline]
+    pub fn amount_requested(&self) -> usize {
+        self.amount
+    }
+
+    /// This method returns the urgency of the request this contract
+    /// is responding to.
+    #[inline]
+    pub fn urgency(&self) -> Urgency {
+        self.urgency
+    }
+
+    /// This method returns the deadline the request this contract is
+    /// responding to announced with `Requester::try_request_with_deadline()`,
+    /// or `None` if it announced none.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.deadline {
+            0 => None,
+            deadline => Some(clock::to_instant(deadline)),
+        }
+    }
+
+    /// This method gathers the metadata of the request this contract is
+    /// responding to.
+    #[inline]
+    fn meta(&self) -> RequestMeta {
+        RequestMeta {
+            sequence: self.sequence,
+            amount: self.amount,
+            urgency: self.urgency,
+            deadline: self.deadline(),
+            tag: self.tag,
+        }
+    }
+
+    /// This method returns the tag the request this contract is
+    /// responding to carries, or `0` if it was made without one.
+    #[inline]
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// This method returns the sequence number of the request this
+    /// contract is responding to. It matches
+    /// `RequestContract::sequence()` for the same request.
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// This method tries to send a datum to the requesting end of the channel.
+    /// It will then consume itself, thereby freeing the responding side of
+    /// the channel.
+    ///
+    /// # Warning
+    ///
+    /// If the channel has a claim timeout and the requesting side already
+    /// took the claim back, this method drops the datum. So it does if the
+    /// channel's validator rejects the datum, after handing the request
+    /// back to the other responders. Use `ResponseContract::try_send()`
+    /// to get the datum back instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    ///
+    /// # Example
+    /// 
+    /// ```
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>(); 
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    ///
+    /// let mut response_contract = responder.try_respond().ok().unwrap();
+    ///
+    /// // We send data to the requesting end.
+    /// response_contract.send(9);
+    ///
+    /// println!("Number is {}", request_contract.try_receive().unwrap());
+    /// ```
+    pub fn send(mut self, datum: T) {
+        if self.done {
+            panic!("ResponseContract already sent its datum!");
+        }
+
+        // A rejected datum, or a revoked claim, has nowhere to go.
+        if let Ok(datum) = self.check(datum) {
+            let _ = self.deliver(datum);
+        }
+    }
+
+    /// This method sends a datum just like `ResponseContract::send()`, but
+    /// does not consume the contract, and hands the datum back instead of
+    /// sending it if the contract already sent one, if the requesting side
+    /// took the claim back, or if the channel's validator rejected it. A
+    /// rejected datum also ends the contract, and the request goes back to
+    /// the other responders.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut request_contract = requester.try_request().ok().unwrap();
+    /// let mut response_contract = responder.try_respond().ok().unwrap();
+    ///
+    /// assert!(response_contract.try_send(1).is_ok());
+    /// assert_eq!(response_contract.try_send(2), Err(chan::SendError(2)));
+    ///
+    /// assert_eq!(request_contract.try_receive().ok().unwrap(), 1);
+    /// ```
+    pub fn try_send(&mut self, datum: T) -> result::Result<(), SendError<T>> {
+        if self.done {
+            return Err(SendError(datum));
+        }
+
+        let datum = self.check(datum).map_err(SendError)?;
+        self.deliver(datum).map_err(SendError)
+    }
+
+    /// This method sends a datum just like `ResponseContract::send()`,
+    /// but keeps the responding side for this responder afterwards. Other
+    /// responders cannot claim the requester's single-response requests
+    /// until the returned `HeldResponse` releases the hold or is dropped.
+    ///
+    /// # Warning
+    ///
+    /// If the channel's validator rejects the datum, this method drops it
+    /// and returns a `HeldResponse` that holds nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send_and_hold(mut self, datum: T) -> HeldResponse<T> {
+        if self.done {
+            panic!("ResponseContract already sent its datum!");
+        }
+
+        // A rejected datum, or a revoked claim, has nothing to hold on to.
+        let datum = match self.check(datum) {
+            Ok(datum) => datum,
+            Err(_) => { return HeldResponse::released(self.inner.clone(), self.responder); },
+        };
+
+        if !self.seal() {
+            self.done = true;
+            return HeldResponse::released(self.inner.clone(), self.responder);
+        }
+
+        // The hold must be visible before the datum is. A responder that
+        // reserved the channel in the meantime keeps it.
+        let token = match self.held {
+            0 => self.inner.try_hold(),
+            token => token,
+        };
+        self.held = 0;
+
+        let _ = self.deliver(datum);
+
+        match token {
+            0 => HeldResponse::released(self.inner.clone(), self.responder),
+            token => HeldResponse::new(self.inner.clone(), self.responder, token),
+        }
+    }
+
+    /// This method seals the contract's lease so the requesting side can
+    /// no longer revoke the claim. It returns `false` if the requesting
+    /// side already did.
+    #[inline]
+    fn seal(&mut self) -> bool {
+        if self.inner.seal_claim(self.lease) {
+            self.lease = 0;
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// This method hands the datum to the requesting side in the way the
+    /// claimed request expects. It hands the datum back if the requesting
+    /// side revoked the claim.
+    #[inline]
+    fn deliver(&mut self, datum: T) -> result::Result<(), T> {
+        match self.claim {
+            Claim::Single => {
+                if !self.seal() {
+                    self.done = true;
+                    return Err(datum);
+                }
+
+                self.inner.set_answerer(self.responder);
+                self.inner.record_outcome(self.sequence, RequestOutcome::Served);
+
+                // The claim is sealed, so nothing else can publish a datum
+                // for it. A rejection means the channel is corrupted.
+                if self.inner.try_set_datum(datum).is_err() {
+                    self.done = true;
+                    panic!("ResponseContract found a datum already published for its claim!");
+                }
+            },
+            Claim::Many => self.inner.push_datum(datum),
+            Claim::Broadcast => self.inner.push_broadcast(self.responder, datum),
+        }
+        // The datum is out, so there is no claim left to hand back, even
+        // if an observer panics below.
+        self.done = true;
+        self.inner.count_sent();
+        self.inner.notify(ChannelEvent::DatumSent(self.responder));
+        self.inner.wake_requester();
+
+        Ok(())
+    }
+
+    /// This method hands the claim back without sending anything, so the
+    /// request stays pending for any responder.
+    #[inline]
+    fn unclaim(&mut self) {
+        match self.claim {
+            Claim::Single => self.inner.release_claim(self.lease),
+            Claim::Many => self.inner.unclaim_many(),
+            Claim::Broadcast => self.inner.unclaim_broadcast(self.responder),
+        }
+
+        self.done = true;
+    }
+}
+
+impl<T> Drop for ResponseContract<T> {
+    fn drop(&mut self) {
+        if self.held != 0 {
+            self.inner.release_hold(self.held);
+        }
+
+        if !self.done {
+            self.inner.notify(ChannelEvent::ResponseAborted(self.responder));
+
+            // A second panic would abort the process.
+            if thread::panicking() {
+                self.unclaim();
+                return;
+            }
+
+            panic!("Dropping ResponseContract without sending data!");
+        }
+    }
+}
+
+/// This is what a responder can learn about a request before it commits
+/// to answering it. `Responder::try_respond_if()` hands it to its
+/// predicate. Requests for several responses, and broadcasts, carry the
+/// defaults: an amount of `1`, `Urgency::Normal`, no deadline and a tag
+/// of `0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestMeta {
+    /// The sequence number of the request.
+    pub sequence: u64,
+    /// The amount hint of the request.
+    pub amount: usize,
+    /// The urgency of the request.
+    pub urgency: Urgency,
+    /// The deadline the request announced, if any.
+    pub deadline: Option<Instant>,
+    /// The tag the request carries.
+    pub tag: u64,
+}
+
+/// This is how urgent a request is. Responders can use it to decide
+/// which channel to serve first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Urgency {
+    Normal = 0,
+    Urgent = 1,
+}
+
+impl Urgency {
+    #[inline]
+    fn from_u8(value: u8) -> Urgency {
+        match value {
+            0 => Urgency::Normal,
+            _ => Urgency::Urgent,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    AlreadyLocked,
+    Closed,
+    Declined,
+    Done,
+    Empty,
+    Expired,
+    NoRequest,
+    Paused,
+    SlotFull,
+    Throttled,
+    Timeout,
+    TooLate,
+    WrongType,
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// This is the error `ResponseContract::try_send()` returns. It holds the
+/// datum that could not be sent.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// This is a handle's pointer to the state it shares with the
+/// other handles of its channel.
+///
+/// Shared state lives in a plain `Arc`. Its weak count backs the weak
+/// handles, and `benches/handles.rs` shows a strong-only count would
+/// barely make cloning and dropping faster.
+#[doc(hidden)]
+enum Link<T> {
+    Shared(Arc<Inner<T>>),
+    // The owner of the storage guarantees it outlives every handle.
+    Borrowed(*const Inner<T>),
+    Stored(Arc<ChannelStorage<T>>),
+}
+
+unsafe impl<T: Send> Send for Link<T> {}
+unsafe impl<T: Send> Sync for Link<T> {}
+
+impl<T> Link<T> {
+    /// This function links a handle to storage that outlives it.
+    ///
+    /// # Safety
+    ///
+    /// `inner` must outlive every handle and contract linked to it.
+    #[inline]
+    unsafe fn borrowed(inner: &Inner<T>) -> Link<T> {
+        Link::Borrowed(inner as *const Inner<T>)
+    }
+}
+
+impl<T> Clone for Link<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        match *self {
+            Link::Shared(ref inner) => Link::Shared(inner.clone()),
+            Link::Borrowed(inner) => Link::Borrowed(inner),
+            Link::Stored(ref storage) => Link::Stored(storage.clone()),
+        }
+    }
+}
+
+impl<T> Deref for Link<T> {
+    type Target = Inner<T>;
+
+    #[inline]
+    fn deref(&self) -> &Inner<T> {
+        match *self {
+            Link::Shared(ref inner) => inner,
+            Link::Borrowed(inner) => unsafe { &*inner },
+            Link::Stored(ref storage) => Link::stored(storage),
+        }
+    }
+}
+
+// Memory ordering: `state` carries every happens-before edge a
+// single-response request needs.
+//
+// * `flag_request()` (Release) -> `try_claim_request()` (Acquire): the
+//   responder sees the amount and urgency written while the requesting
+//   side was locked.
+// * `try_set_datum()` (Release) -> `try_get_datum()` (Acquire): the requester
+//   sees the datum the responder wrote.
+// * `try_get_datum()` -> `finish_request()` (Release) -> `try_lock_request()`
+//   (Acquire): the next request starts after the last datum was taken out,
+//   even if the `Requester` moved to another thread in between.
+//
+// The metadata and the datum slot are never touched outside those edges,
+// so they can use relaxed atomics and plain writes. Responder arbitration
+// compares stores to `prioritized` and `intent` made by different threads,
+// which needs a single total order, so it stays `SeqCst`.
+#[doc(hidden)]
+struct Inner<T> {
+    // The `State` of the current single-response request.
+    state: AtomicU8,
+    request: RequestSide,
+    response: ResponseSide,
+    // This holds a datum exactly when `state` is `State::DataReady`.
+    datum: UnsafeCell<MaybeUninit<T>>,
+    data: Mutex<VecDeque<T>>,
+    roster: Mutex<Roster<T>>,
+    // This is only set when the channel is created.
+    observer: Option<Arc<dyn ChannelObserver + Send + Sync>>,
+    // So are these.
+    throttle: Option<Throttle>,
+    claim_timeout: Option<u64>,
+    validator: Option<ValidatorFn<T>>,
+    // How long the blocking helpers spin while they wait on the channel.
+    spin: SpinTuner,
+    // When the channel last reported an event, as a `clock::now()`
+    // timestamp.
+    #[cfg(feature = "debug-watchdog")]
+    progress: AtomicU64,
+    // The name of the channel in trace events.
+    #[cfg(feature = "tracing")]
+    name: &'static str,
+    // Where the channel's handoffs stop for a test.
+    #[cfg(feature = "testing")]
+    control: Option<Arc<Control>>,
+}
+
+/// This is the part of `Inner` only the requesting side writes to.
+#[doc(hidden)]
+struct RequestSide {
+    amount: AtomicUsize,
+    urgency: AtomicU8,
+    // Whether the current request is from `Requester::try_request_all()`.
+    broadcasting: AtomicBool,
+    // Whether the requester paused the channel.
+    paused: AtomicBool,
+    // Whether the requester closed the channel for good.
+    closed: AtomicBool,
+    // The number of live `Requester`s.
+    requesters: AtomicUsize,
+    counters: RequestCounters,
+    // When the current request expires, as a `clock::now()` timestamp,
+    // or 0 if it does not.
+    expiry: AtomicU64,
+    // The deadline the current request announces, in the same form.
+    deadline: AtomicU64,
+    // The tag the current request carries.
+    tag: AtomicU64,
+    #[cfg(feature = "metrics")]
+    latency: Latency,
+    // Whether the current request is from `Requester::try_subscribe()`.
+    subscribed: AtomicBool,
+    // The sequence number of the current request. Every request gets the
+    // next one, even if it is cancelled or expires.
+    seq: AtomicU64,
+    // What became of the latest single-response request, stamped with
+    // its sequence number.
+    outcome: AtomicU64,
+    // How many data the requesting side took from the single-response
+    // slot, and the number of the last one it gave up on, for receipts.
+    taken: AtomicU64,
+    lost: AtomicU64,
+    wake_responders: NotifyHook,
+    waiters: Waiters,
+}
+
+/// This is the part of `Inner` the responding side writes to.
+#[doc(hidden)]
+struct ResponseSide {
+    // The current multi-response request: the top bit says whether each
+    // responder may only claim it once, the next 31 bits hold its
+    // generation, and the lower 32 bits hold how many claims remain.
+    many: AtomicU64,
+    // The number of responders with a nonzero priority.
+    prioritized: AtomicUsize,
+    // The number of responders marked busy.
+    busy: AtomicUsize,
+    // One more than the highest priority of any responder trying to
+    // claim the current request, or 0 if none are.
+    intent: AtomicU16,
+    // The token of the `HeldResponse` or `Reservation` that reserves
+    // single-response requests, or 0 if none does.
+    held: AtomicU64,
+    // The number of holds taken so far, which makes their tokens.
+    holds: AtomicU64,
+    // The id of the responder whose datum is in `datum`.
+    answerer: AtomicUsize,
+    // The number of live `Responder`s. It only changes under the
+    // roster's lock, but it can be read without it.
+    responders: AtomicUsize,
+    counters: ResponseCounters,
+    // The lease of the current single-response claim.
+    lease: AtomicU64,
+    wake_requester: NotifyHook,
+    waiters: Waiters,
+}
+
+const MANY_PENDING_MASK: u64 = 0xFFFF_FFFF;
+const ARBITRATION_SPINS: usize = 256;
+const MANY_GENERATION_SHIFT: u32 = 32;
+const MANY_GENERATION_MASK: u64 = 0x7FFF_FFFF;
+const MANY_DISTINCT: u64 = 1 << 63;
+
+/// This is the state of the channel's single-response request. Each step
+/// of a request moves the channel from one state to the next with a single
+/// atomic operation.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    // Neither side is using the channel.
+    Idle = 0,
+    // The requesting side is setting up a request or collecting
+    // several responses.
+    RequestLocked = 1,
+    // The request is waiting for a responder.
+    Requested = 2,
+    // A responder is preparing a datum for the request.
+    Claimed = 3,
+    // The datum is waiting for the requesting side.
+    DataReady = 4,
+    // The requesting side received the datum or cancelled the request.
+    Done = 5,
+    // A responder found the request's time to live had run out before
+    // anyone claimed it.
+    Expired = 6,
+}
+
+impl State {
+    #[inline]
+    fn from_u8(value: u8) -> State {
+        match value {
+            0 => State::Idle,
+            1 => State::RequestLocked,
+            2 => State::Requested,
+            3 => State::Claimed,
+            4 => State::DataReady,
+            5 => State::Done,
+            6 => State::Expired,
+            _ => unreachable!(),
+        }
+    }
+}
+
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    const_fn! {
+        /// This function creates the state of an idle channel.
+        #[inline]
+        fn new() -> Inner<T> {
+            Inner {
+                state: AtomicU8::new(State::Idle as u8),
+                request: RequestSide {
+                    amount: AtomicUsize::new(1),
+                    urgency: AtomicU8::new(Urgency::Normal as u8),
+                    broadcasting: AtomicBool::new(false),
+                    paused: AtomicBool::new(false),
+                    closed: AtomicBool::new(false),
+                    requesters: AtomicUsize::new(0),
+                    counters: RequestCounters::new(),
+                    expiry: AtomicU64::new(0),
+                    deadline: AtomicU64::new(0),
+                    tag: AtomicU64::new(0),
+                    #[cfg(feature = "metrics")]
+                    latency: Latency::new(),
+                    subscribed: AtomicBool::new(false),
+                    seq: AtomicU64::new(0),
+                    outcome: AtomicU64::new(0),
+                    taken: AtomicU64::new(0),
+                    lost: AtomicU64::new(0),
+                    wake_responders: NotifyHook::new(),
+                    waiters: Waiters::new(),
+                },
+                response: ResponseSide {
+                    many: AtomicU64::new(0),
+                    prioritized: AtomicUsize::new(0),
+                    busy: AtomicUsize::new(0),
+                    intent: AtomicU16::new(0),
+                    held: AtomicU64::new(0),
+                    holds: AtomicU64::new(0),
+                    answerer: AtomicUsize::new(0),
+                    responders: AtomicUsize::new(0),
+                    counters: ResponseCounters::new(),
+                    lease: AtomicU64::new(lease::INITIAL_LEASE),
+                    wake_requester: NotifyHook::new(),
+                    waiters: Waiters::new(),
+                },
+                datum: UnsafeCell::new(MaybeUninit::uninit()),
+                data: Mutex::new(VecDeque::new()),
+                roster: Mutex::new(Roster::new()),
+                observer: None,
+                throttle: None,
+                claim_timeout: None,
+                validator: None,
+                spin: SpinTuner::new(backoff::MIN_SPIN_LIMIT, backoff::MAX_SPIN_LIMIT),
+                #[cfg(feature = "debug-watchdog")]
+                progress: AtomicU64::new(0),
+                #[cfg(feature = "tracing")]
+                name: instrument::DEFAULT_NAME,
+                #[cfg(feature = "testing")]
+                control: None,
+            }
+        }
+    }
+
+    /// This method indicates that the requesting side has made a request.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    fn flag_request(&self) {
+        self.debug_check_state(&[State::RequestLocked]);
+
+        // Start a new arbitration window for the new request.
+        self.response.intent.store(0, Ordering::SeqCst);
+
+        #[cfg(feature = "metrics")]
+        self.start_latency();
+
+        self.number_request();
+
+        #[cfg(feature = "tracing")]
+        self.trace_issued("single", self.get_amount());
+
+        // Release the metadata to the responder that claims the request.
+        let res = self.transition(State::RequestLocked, State::Requested, Ordering::Release);
+        debug_assert!(res.is_ok());
+
+        #[cfg(feature = "testing")]
+        self.yield_at(YieldPoint::AfterFlagRequest);
+    }
+
+    /// This method issues the request the requesting side just received
+    /// a datum for again, because it could not use the datum.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::Done
+    #[inline]
+    fn reflag_request(&self) {
+        self.debug_check_state(&[State::Done]);
+
+        // The responder that sent the datum is done with the request once
+        // it published the datum, so nothing else can move the state now.
+        self.response.intent.store(0, Ordering::SeqCst);
+        self.clear_outcome(self.sequence());
+
+        let res = self.transition(State::Done, State::Requested, Ordering::Release);
+        debug_assert!(res.is_ok());
+        self.wake_responders();
+    }
+
+    /// This method gives the request about to be flagged the next
+    /// sequence number. The flag publishes it to whichever responder
+    /// claims the request.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it, before
+    /// flagging the request.
+    #[inline]
+    fn number_request(&self) {
+        // Only the requesting side writes the sequence number.
+        let seq = self.request.seq.load(Ordering::Relaxed).wrapping_add(1);
+        self.request.seq.store(seq, Ordering::Relaxed);
+    }
+
+    /// This method returns the sequence number of the current request.
+    #[inline]
+    fn sequence(&self) -> u64 {
+        self.request.seq.load(Ordering::Relaxed)
+    }
+
+    /// This method announces that a responder with the given priority
+    /// wants to claim the current request, and then waits briefly for
+    /// higher-priority responders to do the same. It returns
+    /// `Err(Error::AlreadyLocked)` if one did.
+    ///
+    /// # Arguments
+    ///
+    /// * priority - The priority of the responder
+    #[inline]
+    fn arbitrate(&self, priority: u8) -> Result<()> {
+        match self.state() {
+            State::Requested => {},
+            State::DataReady if self.is_subscribed() => { return Err(Error::SlotFull); },
+            _ => { return Err(Error::NoRequest); },
+        }
+
+        let intent = priority as u16 + 1;
+        self.response.intent.fetch_max(intent, Ordering::SeqCst);
+
+        for _ in 0..ARBITRATION_SPINS {
+            if self.response.intent.load(Ordering::SeqCst) > intent {
+                return Err(Error::AlreadyLocked);
+            }
+
+            hint::spin_loop();
+        }
+
+        if self.response.intent.load(Ordering::SeqCst) > intent {
+            Err(Error::AlreadyLocked)
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// This method sets the amount hint for the next request.
+    ///
+    /// # Arguments
+    ///
+    /// * amount - The maximum number of item(s) the requester wants
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    fn set_amount(&self, amount: usize) {
+        self.debug_check_state(&[State::RequestLocked]);
+        self.request.amount.store(amount, Ordering::Relaxed);
+    }
+
+    /// This method gets the amount hint of the current request.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responding side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::Claimed
+    #[inline]
+    fn get_amount(&self) -> usize {
+        self.request.amount.load(Ordering::Relaxed)
+    }
+
+    /// This method sets the urgency of the next request.
+    ///
+    /// # Arguments
+    ///
+    /// * urgency - How urgent the request is
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked || self.state == State::Done
+    #[inline]
+    fn set_urgency(&self, urgency: Urgency) {
+        self.debug_check_state(&[State::RequestLocked, State::Done]);
+        self.request.urgency.store(urgency as u8, Ordering::Relaxed);
+    }
+
+    /// This method gets the urgency of the current request.
+    #[inline]
+    fn get_urgency(&self) -> Urgency {
+        Urgency::from_u8(self.request.urgency.load(Ordering::Relaxed))
+    }
+
+    /// This method returns the state of the current request. It acquires
+    /// the request's metadata when it returns `State::Requested`.
+    #[inline]
+    fn state(&self) -> State {
+        State::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// This method checks that the channel is in one of the `expected`
+    /// states, which the calling method's invariant requires. It only
+    /// checks in debug builds, and compiles to nothing in release builds.
+    #[inline]
+    fn debug_check_state(&self, expected: &[State]) {
+        debug_assert!(expected.contains(&State::from_u8(self.state.load(Ordering::Relaxed))),
+                      "Channel is in {:?} instead of one of {:?}!",
+                      State::from_u8(self.state.load(Ordering::Relaxed)),
+                      expected);
+    }
+
+    /// This method checks the invariants that tie the state of the
+    /// single-response request to the rest of the channel. Tests call it
+    /// whenever no operation is under way.
+    #[cfg(test)]
+    fn check_invariants(&self) {
+        match self.state() {
+            // A multi-response request or a broadcast keeps the channel
+            // locked while it collects responses, so neither can overlap
+            // a single-response request.
+            State::Idle | State::Requested | State::Claimed | State::DataReady | State::Expired => {
+                assert_eq!(self.pending_many(), 0);
+                assert!(!self.request.broadcasting.load(Ordering::SeqCst));
+            },
+            State::RequestLocked | State::Done => {},
+        }
+
+        // An idle channel has no responses left to collect.
+        if self.state() == State::Idle {
+            assert!(self.data.lock().unwrap().is_empty());
+        }
+    }
+
+    /// This method atomically moves the channel from one state to another.
+    /// If the channel is not in the expected state, it returns the state
+    /// it is in instead.
+    ///
+    /// # Arguments
+    ///
+    /// * from - The state the channel should be in
+    ///
+    /// * to - The state to move it to
+    ///
+    /// * ordering - The ordering of a successful transition
+    #[inline]
+    fn transition(&self, from: State, to: State, ordering: Ordering)
+                  -> result::Result<(), State> {
+        // This must be a strong exchange, since callers report a failure
+        // to the user. A failed transition only picks which error to
+        // report and touches no data, so its ordering can be relaxed.
+        self.state.compare_exchange(from as u8,
+                                    to as u8,
+                                    ordering,
+                                    Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(State::from_u8)
+    }
+
+    /// This method atomically checks to see if the requesting end
+    /// issued a request and claims it for the calling responder.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responding side of the channel should call it.
+    #[inline]
+    fn try_claim_request(&self) -> Result<()> {
+        // Leave the request to the responder holding the channel.
+        if self.is_held() {
+            return Err(Error::AlreadyLocked);
+        }
+
+        // Most polls find no request. Answer those with a plain load, so
+        // idle responders never write to the state's cache line.
+        match self.state() {
+            State::Requested => {},
+            State::Claimed => { return Err(Error::AlreadyLocked); },
+            State::DataReady if self.is_subscribed() => { return Err(Error::SlotFull); },
+            _ => { return Err(Error::NoRequest); },
+        }
+
+        // Acquire the metadata the requesting side released.
+        match self.transition(State::Requested, State::Claimed, Ordering::Acquire) {
+            Ok(()) => {},
+            Err(State::Claimed) => { return Err(Error::AlreadyLocked); },
+            Err(State::DataReady) if self.is_subscribed() => { return Err(Error::SlotFull); },
+            Err(_) => { return Err(Error::NoRequest); },
+        }
+
+        #[cfg(feature = "testing")]
+        self.yield_at(YieldPoint::AfterClaimRequest);
+
+        // The check above may have seen a stale value. The claim acquired
+        // every hold taken before the request was made, so check again and
+        // hand the request back if the channel is held.
+        if self.is_held() {
+            self.unclaim_request();
+            return Err(Error::AlreadyLocked);
+        }
+
+        // The claim also acquired the request's expiry. A request that
+        // ran out is cleared instead of answered.
+        if self.is_expired() {
+            let res = self.transition(State::Claimed, State::Expired, Ordering::Relaxed);
+            debug_assert!(res.is_ok());
+            self.record_outcome(self.sequence(), RequestOutcome::Expired);
+            self.wake_requester();
+            return Err(Error::NoRequest);
+        }
+
+        Ok(())
+    }
+
+    /// This method sets when the next request expires.
+    ///
+    /// # Arguments
+    ///
+    /// * expiry - The `clock::now()` timestamp to expire at, or 0 for never
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    fn set_expiry(&self, expiry: u64) {
+        self.debug_check_state(&[State::RequestLocked]);
+        self.request.expiry.store(expiry, Ordering::Relaxed);
+    }
+
+    /// This method sets the deadline the current request announces, as a
+    /// `clock::now()` timestamp, or 0 if it announces none.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    fn set_deadline(&self, deadline: u64) {
+        self.debug_check_state(&[State::RequestLocked]);
+        self.request.deadline.store(deadline, Ordering::Relaxed);
+    }
+
+    /// This method returns the deadline the current request announces.
+    ///
+    /// # Invariant
+    ///
+    /// * The caller acquired the request's metadata
+    #[inline]
+    fn get_deadline(&self) -> u64 {
+        self.request.deadline.load(Ordering::Relaxed)
+    }
+
+    /// This method sets the tag of the current request.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    fn set_tag(&self, tag: u64) {
+        self.debug_check_state(&[State::RequestLocked]);
+        self.request.tag.store(tag, Ordering::Relaxed);
+    }
+
+    /// This method returns the tag of the current request.
+    ///
+    /// # Invariant
+    ///
+    /// * The caller acquired the request's metadata
+    #[inline]
+    fn get_tag(&self) -> u64 {
+        self.request.tag.load(Ordering::Relaxed)
+    }
+
+    /// This method returns whether the current request has a time to live
+    /// and it has run out.
+    ///
+    /// # Invariant
+    ///
+    /// * The caller acquired the request's metadata
+    #[inline]
+    fn is_expired(&self) -> bool {
+        let expiry = self.request.expiry.load(Ordering::Relaxed);
+        expiry != 0 && clock::now() >= expiry
+    }
+
+    /// This method finishes a request whose time to live ran out before
+    /// any responder claimed it. A claim that got there first wins.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn try_expire_request(&self) -> Result<()> {
+        // Only the requesting side writes the expiry, so it can read it
+        // without waiting for anything.
+        if self.request.expiry.load(Ordering::Relaxed) == 0 {
+            return Err(Error::Empty);
+        }
+
+        match self.transition(State::Expired, State::Done, Ordering::Relaxed) {
+            Ok(()) => {},
+            Err(State::Requested) if self.is_expired() => {
+                self.transition(State::Requested, State::Done, Ordering::Relaxed)
+                    .map_err(|_| Error::Empty)?;
+            },
+            Err(_) => { return Err(Error::Empty); },
+        }
+
+        self.record_outcome(self.sequence(), RequestOutcome::Expired);
+        Ok(())
+    }
+
+    /// This method hands a claimed request back, so any responder can
+    /// claim it again.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that claimed the request should call it,
+    /// instead of sending a datum.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::Claimed
+    #[inline]
+    fn unclaim_request(&self) {
+        let res = self.transition(State::Claimed, State::Requested, Ordering::Release);
+        debug_assert!(res.is_ok());
+    }
+
+    /// This method atomically checks to see if the request is still
+    /// unclaimed and withdraws it.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn try_cancel_request(&self) -> Result<()> {
+        #[cfg(feature = "testing")]
+        self.yield_at(YieldPoint::BeforeCancelRequest);
+
+        // The requesting side wrote everything it could observe here,
+        // and only it leaves `State::Done`.
+        self.transition(State::Requested, State::Done, Ordering::Relaxed)
+            .map_err(|_| Error::NoRequest)?;
+
+        #[cfg(feature = "metrics")]
+        self.record_cancelled();
+
+        Ok(())
+    }
+
+    /// This method calls `f` with a raw pointer to the datum slot. Going
+    /// through it never creates a reference to the slot, so a requesting
+    /// side reading the slot and a responding side writing it never hold
+    /// overlapping references, even for a moment. Which side may use it
+    /// at any time is decided by the channel's state alone.
+    #[inline]
+    fn with_slot<R, F>(&self, f: F) -> R
+        where F: FnOnce(*mut T) -> R
+    {
+        // `MaybeUninit<T>` has the layout of `T`.
+        self.datum.with_mut(|slot| f(slot.cast::<T>()))
+    }
+
+    /// This method sets the inner datum to the specified value. It hands
+    /// the datum back instead if the request is not claimed, so it never
+    /// overwrites a datum the requesting side has not taken yet.
+    ///
+    /// # Arguments
+    ///
+    /// * datum - The datum to set
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that claimed the request should call it.
+    #[inline]
+    fn try_set_datum(&self, data: T) -> result::Result<(), T> {
+        #[cfg(feature = "testing")]
+        self.yield_at(YieldPoint::BeforeSetDatum);
+
+        // Only the claim's owner moves the channel out of `State::Claimed`,
+        // and the slot holds nothing while it is there. Anywhere else, the
+        // slot may hold a datum, or the requesting side may be reading it.
+        if self.state.load(Ordering::Relaxed) != State::Claimed as u8 {
+            return Err(data);
+        }
+
+        // First update inner datum.
+        self.with_slot(|slot| unsafe { ptr::write(slot, data) });
+
+        // Then indicate the presence of a new datum. If the channel left
+        // `State::Claimed` after all, nobody will read the slot, so take
+        // the datum back out.
+        match self.transition(State::Claimed, State::DataReady, Ordering::Release) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(self.with_slot(|slot| unsafe { ptr::read(slot) })),
+        }
+    }
+    
+    /// This method tries to get the datum out of `Inner`.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * if self.state == State::DataReady then self.datum is initialized
+    #[inline]
+    fn try_get_datum(&self) -> Result<T> {
+        #[cfg(feature = "testing")]
+        self.yield_at(YieldPoint::BeforeTakeDatum);
+
+        // First check to see if data exists.
+        // Acquire the datum the responder released.
+        match self.transition(State::DataReady, State::Done, Ordering::Acquire) {
+            // If so, move it out. Leaving `State::DataReady` marks the
+            // slot empty again.
+            Ok(()) => {
+                #[cfg(feature = "metrics")]
+                self.record_latency();
+
+                self.count_delivery();
+
+                Ok(self.with_slot(|slot| unsafe { ptr::read(slot) }))
+            },
+            Err(_) => Err(Error::Empty),
+        }
+    }
+
+    /// This method opens a multi-response request to `n` responders.
+    /// Each request gets a new generation so a `Responder` can tell
+    /// whether it already claimed it.
+    ///
+    /// # Arguments
+    ///
+    /// * n - The number of responses to collect
+    ///
+    /// * distinct - Whether each responder may only claim one response
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    ///
+    /// * self.many & MANY_PENDING_MASK == 0
+    #[inline]
+    fn flag_many(&self, n: usize, distinct: bool) {
+        self.debug_check_state(&[State::RequestLocked]);
+        debug_assert_eq!(self.pending_many(), 0);
+
+        // Generation 0 means "never claimed" to a `Responder`, so skip it.
+        let generation = match ((self.response.many.load(Ordering::Relaxed) >> MANY_GENERATION_SHIFT)
+            .wrapping_add(1)) & MANY_GENERATION_MASK {
+            0 => 1,
+            generation => generation,
+        };
+
+        let mode = if distinct { MANY_DISTINCT } else { 0 };
+
+        self.number_request();
+
+        #[cfg(feature = "tracing")]
+        self.trace_issued("many", n);
+
+        // The responses themselves travel through the `data` mutex, so
+        // the counter does not need to publish anything else.
+        self.response.many.store(mode | (generation << MANY_GENERATION_SHIFT) | n as u64,
+                                 Ordering::Release);
+    }
+
+    /// This method returns how many responses of the current
+    /// multi-response request no responder has claimed yet.
+    #[inline]
+    fn pending_many(&self) -> usize {
+        (self.response.many.load(Ordering::Acquire) & MANY_PENDING_MASK) as usize
+    }
+
+    /// This method returns whether or not a request is waiting for a
+    /// responder to claim it.
+    #[inline]
+    fn has_pending_request(&self) -> bool {
+        self.state() == State::Requested || self.pending_many() > 0
+    }
+
+    /// This method tries to claim one response of the current
+    /// multi-response request on behalf of a single `Responder`.
+    ///
+    /// # Arguments
+    ///
+    /// * claimed - The generation of the last request the `Responder` claimed
+    #[inline]
+    fn try_claim_many(&self, claimed: &AtomicU64) -> Result<()> {
+        let mut current = self.response.many.load(Ordering::Acquire);
+
+        if current & MANY_PENDING_MASK == 0 {
+            return Err(Error::NoRequest);
+        }
+
+        // Each responder may only claim a request for distinct responses once.
+        let generation = (current >> MANY_GENERATION_SHIFT) & MANY_GENERATION_MASK;
+        if current & MANY_DISTINCT != 0
+            && claimed.swap(generation, Ordering::SeqCst) == generation {
+            return Err(Error::NoRequest);
+        }
+
+        loop {
+            if current & MANY_PENDING_MASK == 0
+                || (current >> MANY_GENERATION_SHIFT) & MANY_GENERATION_MASK != generation {
+                return Err(Error::NoRequest);
+            }
+
+            // A failed exchange hands back the value the next iteration
+            // checks, so it needs the same ordering as the initial load.
+            // The weak exchange may fail spuriously, which just retries.
+            match self.response.many.compare_exchange_weak(current,
+                                                           current - 1,
+                                                           Ordering::AcqRel,
+                                                           Ordering::Acquire) {
+                Ok(_) => { return Ok(()); },
+                Err(actual) => { current = actual; },
+            }
+        }
+    }
+
+    /// This method hands a claimed response of the current multi-response
+    /// request back, so any responder can claim it again.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** a responder that claimed a response should call it,
+    /// instead of sending a datum.
+    #[inline]
+    fn unclaim_many(&self) {
+        // The requesting side is still waiting for the response, so the
+        // request cannot have been replaced. If it cancelled the rest of
+        // the request, it can cancel this response again.
+        self.response.many.fetch_add(1, Ordering::Release);
+    }
+
+    /// This method withdraws every unclaimed response of the current
+    /// multi-response request. It returns how many it withdrew.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn unflag_many(&self) -> usize {
+        let mut current = self.response.many.load(Ordering::Relaxed);
+
+        loop {
+            let new = current & !MANY_PENDING_MASK;
+
+            // A failed exchange only feeds the next attempt, and the
+            // attempt that succeeds validates the value it started from,
+            // so the failure ordering can be relaxed.
+            match self.response.many.compare_exchange_weak(current,
+                                                           new,
+                                                           Ordering::AcqRel,
+                                                           Ordering::Relaxed) {
+                Ok(_) => { return (current & MANY_PENDING_MASK) as usize; },
+                Err(actual) => { current = actual; },
+            }
+        }
+    }
+
+    /// This method adds a datum to the multi-response buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * datum - The datum to add
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responding side of the channel should call it.
+    #[inline]
+    fn push_datum(&self, datum: T) {
+        self.data.lock().unwrap().push_back(datum);
+    }
+
+    /// This method tries to take the oldest datum out of the
+    /// multi-response buffer.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    fn try_pop_datum(&self) -> Result<T> {
+        self.data.lock().unwrap().pop_front().ok_or(Error::Empty)
+    }
+
+    /// This method tries to lock the requesting side of the channel.
+    /// It returns a `boolean` indicating whether or not it succeeded.
+    #[inline]
+    fn try_lock_request(&self) -> Result<()> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if let Some(ref throttle) = self.throttle {
+            if throttle.wait().is_some() {
+                return Err(Error::Throttled);
+            }
+        }
+
+        self.transition(State::Idle, State::RequestLocked, Ordering::Acquire)
+            .map_err(|_| Error::AlreadyLocked)
+    }
+
+    /// This method returns how much longer the throttle holds back the
+    /// next request, or `None` if nothing does.
+    #[inline]
+    fn throttled_for(&self) -> Option<Duration> {
+        self.throttle.as_ref().and_then(Throttle::wait)
+    }
+
+    /// This method returns whether the requester paused the channel.
+    #[inline]
+    fn is_paused(&self) -> bool {
+        self.request.paused.load(Ordering::SeqCst)
+    }
+
+    /// This method returns whether the requester closed the channel.
+    #[inline]
+    fn is_closed(&self) -> bool {
+        self.request.closed.load(Ordering::SeqCst)
+    }
+
+    /// This method unlocks the requesting side of the channel after it
+    /// collected several responses.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::RequestLocked
+    #[inline]
+    fn unlock_request(&self) {
+        if let Some(ref throttle) = self.throttle {
+            throttle.mark();
+        }
+
+        let res = self.transition(State::RequestLocked, State::Idle, Ordering::Release);
+        debug_assert!(res.is_ok());
+    }
+
+    /// This method unlocks the requesting side of the channel after it
+    /// received a datum or cancelled its request.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == State::Done
+    #[inline]
+    fn finish_request(&self) {
+        // The throttle only ever holds back the requesting side, so the
+        // mark can go before the unlock.
+        if let Some(ref throttle) = self.throttle {
+            if self.state.load(Ordering::Relaxed) == State::Done as u8 {
+                throttle.mark();
+            }
+        }
+
+        // Leave an unfinished request alone so no responder loses it.
+        self.transition(State::Done, State::Idle, Ordering::Release).ok();
+    }
+
+    /// This method counts a new `Requester`.
+    #[inline]
+    fn add_requester(&self) {
+        self.request.requesters.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a new `Requester` if any other is still live.
+    /// It returns whether or not it did.
+    #[inline]
+    fn try_add_requester(&self) -> bool {
+        let mut current = self.request.requesters.load(Ordering::Relaxed);
+
+        while current > 0 {
+            match self.request.requesters.compare_exchange_weak(current,
+                                                                current + 1,
+                                                                Ordering::Relaxed,
+                                                                Ordering::Relaxed) {
+                Ok(_) => { return true; },
+                Err(actual) => { current = actual; },
+            }
+        }
+
+        false
+    }
+
+    /// This method stops counting a dropped `Requester`.
+    ///
+    /// This is the only place the number of live requesters drops, so
+    /// anything that must happen when the last one goes belongs here.
+    #[inline]
+    fn remove_requester(&self) {
+        // Release every request the `Requester` made to a responder that
+        // sees it gone.
+        self.request.requesters.fetch_sub(1, Ordering::Release);
+    }
+
+    /// This method returns whether or not any `Requester` is live. Once
+    /// none is, no new request can be made.
+    #[inline]
+    fn has_requesters(&self) -> bool {
+        self.request.requesters.load(Ordering::Acquire) > 0
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        // Drop a datum nobody received.
+        if self.state() == State::DataReady {
+            self.with_slot(|slot| unsafe { ptr::drop_in_place(slot) });
+        }
+    }
+}
+
+/// This function tries to lock one side of a channel. Every channel
+/// flavor in this crate locks its sides with it, so they all follow
+/// the same locking discipline.
+#[inline]
+fn try_lock(lock: &AtomicBool) -> Result<()> {
+    let (old, new) = (false, true);
+
+    // This must be a strong exchange, since a spurious failure would
+    // report a free lock as taken. Taking the lock acquires everything
+    // the last holder did before `unlock()`. A failed attempt touches
+    // nothing the lock protects, so its ordering can be relaxed.
+    match lock.compare_exchange(old, new, Ordering::Acquire, Ordering::Relaxed) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::AlreadyLocked),
+    }
+}
+
+/// This function unlocks one side of a channel.
+#[inline]
+fn unlock(lock: &AtomicBool) {
+    lock.store(false, Ordering::Release);
+}
+
+#[cfg(test)]
+// The tests flag an unexpected outcome with `assert!(false)`.
+#[allow(clippy::assertions_on_constants, clippy::bool_assert_comparison)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    
+    use super::*;
+  
+    #[test]
+    fn test_channel() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+    }
+   
+    #[test]
+    fn test_inner_try_lock_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        match rqst.inner.try_lock_request() {
+            Ok(()) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(resp.inner.state(), State::RequestLocked);
+    }
+       
+    #[test]
+    fn test_inner_try_lock_request_multiple() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.try_lock_request().ok().unwrap();
+
+        match rqst.inner.try_lock_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_inner_try_unlock_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.state.store(State::RequestLocked as u8, Ordering::SeqCst);
+
+        rqst.inner.unlock_request();
+        
+        assert_eq!(resp.inner.state(), State::Idle);
+    }
+
+    #[test]
+    fn test_inner_finish_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.state.store(State::Done as u8, Ordering::SeqCst);
+
+        rqst.inner.finish_request();
+        
+        assert_eq!(resp.inner.state(), State::Idle);
+    }
+      
+    #[test]
+    fn test_inner_try_claim_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.state.store(State::Requested as u8, Ordering::SeqCst);
+
+        match resp.inner.try_claim_request() {
+            Ok(()) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(resp.inner.state(), State::Claimed);
+    }
+       
+    #[test]
+    fn test_inner_try_claim_request_multiple() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.state.store(State::Requested as u8, Ordering::SeqCst);
+
+        resp.inner.try_claim_request().ok().unwrap();
+
+        match resp.inner.try_claim_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_inner_try_claim_request_no_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        match resp.inner.try_claim_request() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_inner_flag_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.try_lock_request().ok().unwrap();
+        rqst.inner.flag_request();
+
+        assert_eq!(resp.inner.state(), State::Requested);
+    }
+
+    #[test]
+    fn test_inner_try_cancel_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        resp.inner.state.store(State::Requested as u8, Ordering::SeqCst);
+
+        match rqst.inner.try_cancel_request() {
+            Ok(()) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(resp.inner.state(), State::Done);
+
+        match rqst.inner.try_cancel_request() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+   
+    #[test]
+    fn test_inner_try_cancel_request_claimed() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        resp.inner.state.store(State::Requested as u8, Ordering::SeqCst);
+
+        resp.inner.try_claim_request().ok().unwrap();
+
+        match rqst.inner.try_cancel_request() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    // A fallible transition, the state it starts from, the state it ends
+    // in, and which other states make it return `Error::AlreadyLocked`.
+    struct Transition {
+        op: fn(&Inner<u32>) -> Result<()>,
+        from: State,
+        to: State,
+        locked: fn(State) -> bool,
+    }
+
+    #[test]
+    fn test_inner_transitions() {
+        let states = [State::Idle, State::RequestLocked, State::Requested,
+                      State::Claimed, State::DataReady, State::Done, State::Expired];
+
+        let table = [
+            Transition {
+                op: |inner| inner.try_lock_request(),
+                from: State::Idle,
+                to: State::RequestLocked,
+                locked: |_| true,
+            },
+            Transition {
+                op: |inner| inner.try_claim_request(),
+                from: State::Requested,
+                to: State::Claimed,
+                locked: |state| state == State::Claimed,
+            },
+            Transition {
+                op: |inner| inner.try_cancel_request(),
+                from: State::Requested,
+                to: State::Done,
+                locked: |_| false,
+            },
+            Transition {
+                op: |inner| inner.try_get_datum().map(|_| ()),
+                from: State::DataReady,
+                to: State::Done,
+                locked: |_| false,
+            },
+        ];
+
+        for &Transition { op, from, to, locked } in table.iter() {
+            for &state in states.iter() {
+                let inner = Inner::<u32>::new();
+                inner.state.store(state as u8, Ordering::SeqCst);
+                if state == State::DataReady {
+                    inner.with_slot(|slot| unsafe { ptr::write(slot, 1) });
+                }
+
+                match op(&inner) {
+                    Ok(()) => {
+                        assert_eq!(state, from);
+                        assert_eq!(inner.state(), to);
+                    },
+                    Err(Error::AlreadyLocked) => {
+                        assert!(state != from && locked(state));
+                        assert_eq!(inner.state(), state);
+                    },
+                    Err(_) => {
+                        assert!(state != from && !locked(state));
+                        assert_eq!(inner.state(), state);
+                    },
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_inner_try_claim_many_contended() {
+        let inner = Arc::new(Inner::<u32>::new());
+        inner.state.store(State::RequestLocked as u8, Ordering::SeqCst);
+        inner.flag_many(100, true);
+
+        // Every responder retries its exchange until it claims a response
+        // or none are left, so exactly 100 claims succeed.
+        let handles: Vec<_> = (0..8).map(|_| {
+            let inner = inner.clone();
+            thread::spawn(move || {
+                let mut claims = 0;
+
+                for _ in 0..50 {
+                    // Give each attempt a fresh responder.
+                    if inner.try_claim_many(&AtomicU64::new(0)).is_ok() {
+                        claims += 1;
+                    }
+                }
+
+                claims
+            })
+        }).collect();
+
+        let claims: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(claims, 100);
+        assert_eq!(inner.response.many.load(Ordering::SeqCst) & MANY_PENDING_MASK, 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_inner_unflag_many_contended() {
+        let inner = Arc::new(Inner::<u32>::new());
+        inner.state.store(State::RequestLocked as u8, Ordering::SeqCst);
+        inner.flag_many(1000, true);
+
+        let handles: Vec<_> = (0..4).map(|_| {
+            let inner = inner.clone();
+            thread::spawn(move || {
+                let mut claims = 0;
+
+                while inner.try_claim_many(&AtomicU64::new(0)).is_ok() {
+                    claims += 1;
+                }
+
+                claims
+            })
+        }).collect();
+
+        // Withdrawing races with the claims, but every response is either
+        // claimed or withdrawn exactly once.
+        let withdrawn = inner.unflag_many();
+        let claims: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(claims + withdrawn, 1000);
+        assert_eq!(inner.unflag_many(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_try_lock_contended() {
+        let lock = Arc::new(AtomicBool::new(false));
+
+        let handles: Vec<_> = (0..8).map(|_| {
+            let lock = lock.clone();
+            thread::spawn(move || try_lock(&lock).is_ok())
+        }).collect();
+
+        let winners = handles.into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|&won| won)
+            .count();
+
+        assert_eq!(winners, 1);
+
+        unlock(&lock);
+        try_lock(&lock).ok().unwrap();
+    }
+
+    #[test]
+    fn test_inner_set_datum() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let task = Task::new(move || { println!("Hello World!"); });
+
+        resp.inner.state.store(State::Claimed as u8, Ordering::SeqCst);
+
+        resp.inner.try_set_datum(task).ok().unwrap();
+
+        assert_eq!(resp.inner.state(), State::DataReady);
+
+        rqst.inner.try_get_datum().ok().unwrap();
+    }
   
     #[test]
-    fn test_channel() {
+    fn test_inner_try_get_datum_with_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let var = Arc::new(AtomicUsize::new(0));
+        let var2 = var.clone();
+        
+        let task = Task::new(move || {
+            var2.fetch_add(1, Ordering::SeqCst);
+        });
+
+        resp.inner.with_slot(|slot| unsafe { ptr::write(slot, task) });
+        resp.inner.state.store(State::DataReady as u8, Ordering::SeqCst);
+             
+        match rqst.inner.try_get_datum() {
+            Ok(t) => {
+                t.run();
+                assert_eq!(var.load(Ordering::SeqCst), 1);
+            },
+            _ => { assert!(false); },
+        }
+    }
+ 
+    // This counts how many times values of it were dropped.
+    struct Tracked(Arc<AtomicUsize>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_inner_drop_with_undelivered_datum() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        {
+            let inner = Inner::<Tracked>::new();
+            inner.state.store(State::Claimed as u8, Ordering::SeqCst);
+            inner.try_set_datum(Tracked(drops.clone())).ok().unwrap();
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_inner_drop_after_delivery() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        {
+            let inner = Inner::<Tracked>::new();
+            inner.state.store(State::Claimed as u8, Ordering::SeqCst);
+            inner.try_set_datum(Tracked(drops.clone())).ok().unwrap();
+
+            let datum = inner.try_get_datum().ok().unwrap();
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+            drop(datum);
+        }
+
+        // The received datum was dropped once, and `Inner` did not drop it again.
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_inner_drop_without_datum() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        for &state in [State::Idle, State::RequestLocked, State::Requested,
+                       State::Claimed, State::Done, State::Expired].iter() {
+            let inner = Inner::<Tracked>::new();
+            inner.state.store(state as u8, Ordering::SeqCst);
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_channel_drop_delivered_datum() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        {
+            let (rqst, resp) = channel::<Tracked>();
+
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(Tracked(drops.clone()));
+
+            let datum = contract.try_receive().ok().unwrap();
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+            drop(datum);
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_channel_drop_abandoned_datum() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        {
+            let (rqst, resp) = channel::<Tracked>();
+
+            let contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(Tracked(drops.clone()));
+
+            // The contract panics, but still lets go of the channel.
+            let res = panic::catch_unwind(AssertUnwindSafe(|| drop(contract)));
+            assert!(res.is_err());
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_channel_drop_datum_sent_after_close() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        {
+            let (rqst, resp) = channel::<Tracked>();
+
+            let mut contract = rqst.try_request().ok().unwrap();
+            let resp_contract = resp.try_respond().ok().unwrap();
+            rqst.close();
+            resp_contract.send(Tracked(drops.clone()));
+
+            // A datum claimed before the channel closed is still delivered.
+            drop(contract.try_receive().ok().unwrap());
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_catch_unwind_live_contract() {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        // A worker iteration panics while it holds a contract. The drop
+        // hands the claim back instead of panicking again.
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _resp_contract = resp.try_respond().ok().unwrap();
+            panic!("worker failed");
+        }));
+        assert!(res.is_err());
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        // The request is pending again for any responder.
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        // The requesting side can also give up on a request that lost its
+        // responder this way.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _resp_contract = resp.try_respond().ok().unwrap();
+            panic!("worker failed");
+        }));
+        assert!(res.is_err());
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_inner_check_invariants() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+        rqst.inner.check_invariants();
+
+        // Check a single-response request after every step.
+        let mut contract = rqst.try_request().ok().unwrap();
+        rqst.inner.check_invariants();
+        let response = resp.try_respond().ok().unwrap();
+        rqst.inner.check_invariants();
+        response.send(1);
+        rqst.inner.check_invariants();
+        contract.try_receive().ok().unwrap();
+        rqst.inner.check_invariants();
+        drop(contract);
+        rqst.inner.check_invariants();
+
+        // Then a multi-response request.
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+        rqst.inner.check_invariants();
+        resp.try_respond().ok().unwrap().send(2);
+        resp2.try_respond().ok().unwrap().send(3);
+        rqst.inner.check_invariants();
+        while contract.remaining() > 0 {
+            contract.try_receive().ok().unwrap();
+        }
+        drop(contract);
+        rqst.inner.check_invariants();
+
+        // Then a broadcast.
+        let mut contract = rqst.try_request_all().ok().unwrap();
+        rqst.inner.check_invariants();
+        resp.try_respond().ok().unwrap().send(4);
+        resp2.try_respond().ok().unwrap().send(5);
+        while contract.responses_remaining() > 0 {
+            contract.try_receive().ok().unwrap();
+        }
+        drop(contract);
+        rqst.inner.check_invariants();
+
+        // And a cancelled request.
+        let mut contract = rqst.try_request().ok().unwrap();
+        contract.try_cancel().ok().unwrap();
+        rqst.inner.check_invariants();
+        drop(contract);
+        rqst.inner.check_invariants();
+    }
+
+    #[test]
+    fn test_inner_try_set_datum_unclaimed() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let inner = Inner::<Tracked>::new();
+
+        // Nobody claimed a request, so the datum comes back.
+        match inner.try_set_datum(Tracked(drops.clone())) {
+            Err(datum) => { drop(datum); },
+            Ok(()) => { assert!(false); },
+        }
+        assert_eq!(inner.state(), State::Idle);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        // Publishing twice must not overwrite the first datum.
+        inner.state.store(State::Claimed as u8, Ordering::SeqCst);
+        inner.try_set_datum(Tracked(drops.clone())).ok().unwrap();
+        match inner.try_set_datum(Tracked(drops.clone())) {
+            Err(datum) => { drop(datum); },
+            Ok(()) => { assert!(false); },
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        drop(inner.try_get_datum().ok().unwrap());
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_inner_flag_request_unlocked() {
+        let inner = Inner::<u32>::new();
+        inner.flag_request();
+    }
+
+    #[test]
+    fn test_inner_try_get_datum_no_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+        
+        match rqst.inner.try_get_datum() {
+            Err(Error::Empty) => {}
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_requester_try_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_requester_try_fetch() {
+        let (rqst, resp) = channel::<u32>();
+
+        assert_eq!(rqst.try_fetch().ok().unwrap(), None);
+
+        // A responder that claims the request in time gets its datum through.
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            let resp_contract = resp.try_respond().ok().unwrap();
+
+            match rqst.try_fetch() {
+                Err(Error::AlreadyLocked) => {},
+                _ => { assert!(false); },
+            }
+
+            resp_contract.send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert!(!resp.has_request());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_requester_try_fetch_drains_claimed_request() {
+        use std::sync::Mutex;
+        use std::thread;
+        use std::time::Duration;
+
+        let (rqst, resp) = channel::<u32>();
+        let weak = resp.downgrade();
+        let sender = Mutex::new(None);
+
+        // Claim the request as soon as it is issued, before `try_fetch()`
+        // can cancel it, and send the datum a little later.
+        resp.set_notify(move || {
+            let resp_contract = weak.upgrade().unwrap().try_respond().ok().unwrap();
+
+            *sender.lock().unwrap() = Some(thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                resp_contract.send(7);
+            }));
+        });
+
+        assert_eq!(rqst.try_fetch().ok().unwrap(), Some(7));
+        assert!(!resp.has_request());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_requester_try_fetch_stress() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize};
+        use std::thread;
+
+        const FETCHES: usize = 20_000;
+
+        let (rqst, resp) = channel::<usize>();
+        let stop = Arc::new(AtomicBool::new(false));
+        let sent = Arc::new(AtomicUsize::new(0));
+
+        let handles = (0..2).map(|_| {
+            let resp = resp.clone();
+            let stop = stop.clone();
+            let sent = sent.clone();
+
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if resp.try_respond_with(|| 1).is_ok() {
+                        sent.fetch_add(1, Ordering::Relaxed);
+                    }
+                    else {
+                        thread::yield_now();
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let mut received = 0;
+
+        for _ in 0..FETCHES {
+            if let Some(num) = rqst.try_fetch().ok().unwrap() {
+                received += num;
+            }
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Every datum sent was received, and no request is left behind.
+        assert_eq!(received, sent.load(Ordering::Relaxed));
+        assert!(!resp.has_request());
+        assert!(rqst.try_request().ok().unwrap().try_cancel().is_ok());
+    }
+
+    #[test]
+    fn test_requester_try_request_amount() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request_amount(4).ok().unwrap();
+
+        assert_eq!(resp.inner.request.amount.load(Ordering::SeqCst), 4);
+        assert_eq!(resp.inner.state(), State::Requested);
+
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_requester_try_request_resets_amount() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        // Leave the amount of an earlier request behind.
+        rqst.inner.state.store(State::RequestLocked as u8, Ordering::SeqCst);
+        rqst.inner.set_amount(4);
+        rqst.inner.state.store(State::Idle as u8, Ordering::SeqCst);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        assert_eq!(resp.inner.request.amount.load(Ordering::SeqCst), 1);
+
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_requester_try_request_many() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request_many(3).ok().unwrap();
+
+        assert_eq!(resp.inner.response.many.load(Ordering::SeqCst) & MANY_PENDING_MASK, 3);
+        assert_eq!(resp.inner.state(), State::RequestLocked);
+        assert_eq!(contract.remaining(), 3);
+
+        contract.expected = 0;
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_requester_try_request_many_zero() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request_many(0);
+    }
+
+    #[test]
+    fn test_responder_try_respond_many_distinct() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request_many(3).ok().unwrap();
+
+        resp.try_respond().ok().unwrap().send(1);
+
+        // `resp` already claimed this request.
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        resp2.try_respond().ok().unwrap().send(2);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        assert_eq!(contract.remaining(), 1);
+
+        contract.try_cancel().ok().unwrap();
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_requester_try_request_n() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request_n(3).ok().unwrap();
+        assert_eq!(contract.outstanding(), 3);
+
+        // The same responder can claim the request more than once.
+        let resp_contract = resp.try_respond().ok().unwrap();
+        let resp_contract2 = resp.try_respond().ok().unwrap();
+        let resp_contract3 = resp2.try_respond().ok().unwrap();
+        assert_eq!(contract.outstanding(), 0);
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(1);
+        resp_contract2.send(2);
+        resp_contract3.send(3);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_requester_try_request_n_then_many() {
+        let (rqst, resp) = channel::<u32>();
+
+        {
+            let mut contract = rqst.try_request_n(1).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        // Distinct claims are tracked again for the next request.
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+        resp.try_respond().ok().unwrap().send(2);
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        contract.try_cancel().ok().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_multi_request_contract_try_cancel_too_late() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request_many(1).ok().unwrap();
+
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(3);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_multi_request_contract_next_generation() {
+        let (rqst, resp) = channel::<u32>();
+
+        {
+            let mut contract = rqst.try_request_many(1).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        // The same responder can claim the next request.
+        let mut contract = rqst.try_request_many(1).ok().unwrap();
+        resp.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_multi_request_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request_many(2).ok().unwrap();
+    }
+
+    #[test]
+    fn test_requester_try_request_urgent() {
+        let (rqst, resp) = channel::<Task>();
+
+        assert_eq!(resp.pending_urgency(), None);
+
+        let mut contract = rqst.try_request_urgent().ok().unwrap();
+
+        assert_eq!(resp.pending_urgency(), Some(Urgency::Urgent));
+
+        // Cancelling the request clears the urgency.
+        contract.try_cancel().ok().unwrap();
+
+        assert_eq!(resp.pending_urgency(), None);
+        assert_eq!(resp.inner.get_urgency(), Urgency::Normal);
+    }
+
+    #[test]
+    fn test_requester_try_request_resets_urgency() {
+        let (rqst, resp) = channel::<Task>();
+
+        // Leave the urgency of an earlier request behind.
+        rqst.inner.state.store(State::RequestLocked as u8, Ordering::SeqCst);
+        rqst.inner.set_urgency(Urgency::Urgent);
+        rqst.inner.state.store(State::Idle as u8, Ordering::SeqCst);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        assert_eq!(resp.pending_urgency(), Some(Urgency::Normal));
+
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_response_contract_urgency() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request_with_urgency(Urgency::Urgent).ok().unwrap();
+
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.urgency(), Urgency::Urgent);
+        assert_eq!(resp.pending_urgency(), None);
+
+        resp_contract.send(1);
+        contract.try_receive().ok().unwrap();
+    }
+
+    #[test]
+    fn test_requester_try_request_multiple() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.try_lock_request().ok().unwrap();
+
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_request_contract_try_receive() {
+        let (rqst, resp) = channel::<Task>();
+
+        let var = Arc::new(AtomicUsize::new(0));
+        let var2 = var.clone();
+
+        let task = Task::new(move || {
+            var2.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        resp.inner.try_claim_request().ok().unwrap();
+        resp.inner.try_set_datum(task).ok().unwrap();
+
+        match contract.try_receive() {
+            Ok(task) => {
+                task.run();
+            },
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(contract.done, true);
+        assert_eq!(var.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_request_contract_try_receive_no_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(contract.done, false);
+
+        contract.done = true;
+    }
+    
+    #[test]
+    fn test_request_contract_try_receive_done() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        contract.done = true;
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_request_contract_try_cancel() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match contract.try_cancel() {
+            Ok(()) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_request_contract_try_cancel_too_late() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+        
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        resp.inner.try_claim_request().ok().unwrap();
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(contract.done, false);
+
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_request_contract_try_cancel_done() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        contract.done = true;
+
+        match contract.try_cancel() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_request_contract_try_receive_attributed() {
+        let (rqst, resp) = channel::<Task>();
+        let resp2 = resp.clone();
+
+        for resp in [&resp, &resp2, &resp] {
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(Task::new(|| {}));
+
+            let (_, id) = contract.try_receive_attributed().ok().unwrap();
+            assert_eq!(id, resp.id());
+
+            match contract.try_receive_attributed() {
+                Err(Error::Done) => {},
+                _ => { assert!(false); },
+            }
+        }
+    }
+
+    #[test]
+    fn test_request_contract_responder_claimed() {
+        let (rqst, resp) = channel::<Task>();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            assert!(!contract.responder_claimed());
+
+            contract.try_cancel().ok().unwrap();
+            assert!(!contract.responder_claimed());
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+        assert!(contract.responder_claimed());
+
+        contract.done = true;
+        resp_contract.done = true;
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_request_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+
+    #[test]
+    fn test_responder_try_respond() {
+        let (rqst, resp) = channel::<Task>();
+        
+        rqst.inner.try_lock_request().ok().unwrap();
+        rqst.inner.flag_request();
+
+        let mut contract = resp.try_respond().ok().unwrap();
+
+        contract.done = true;
+    }
+
+    #[test]
+    fn test_responder_try_respond_no_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<Task>();
+        
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_responder_has_request() {
+        let (rqst, resp) = channel::<Task>();
+
+        assert!(!resp.has_request());
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            assert!(resp.has_request());
+
+            contract.try_cancel().ok().unwrap();
+            assert!(!resp.has_request());
+        }
+
+        {
+            let mut contract = rqst.try_request_many(1).ok().unwrap();
+            assert!(resp.has_request());
+
+            contract.try_cancel().ok().unwrap();
+            assert!(!resp.has_request());
+        }
+
+        let mut contract = rqst.try_request_all().ok().unwrap();
+        assert!(resp.has_request());
+
+        contract.try_cancel().ok().unwrap();
+        drop(contract);
+        assert!(!resp.has_request());
+    }
+
+    #[test]
+    fn test_responder_try_respond_multiple() {
         #[allow(unused_variables)]
         let (rqst, resp) = channel::<Task>();
+
+        rqst.inner.try_lock_request().ok().unwrap();
+        rqst.inner.flag_request();
+        resp.inner.try_claim_request().ok().unwrap();
+        
+        match resp.try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_responder_try_respond_with() {
+        let (rqst, resp) = channel::<u32>();
+
+        match resp.try_respond_with(|| { assert!(false); 0 }) {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond_with(|| 1).ok().unwrap();
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_responder_try_respond_with_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            resp.try_respond_with(|| panic!("no datum"))
+        }));
+        assert!(res.is_err());
+
+        // The request is neither stuck as claimed nor withdrawn.
+        assert!(!contract.responder_claimed());
+        resp2.try_respond_with(|| 2).ok().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_request_ttl_expires() {
+        let (rqst, resp) = channel::<u32>();
+
+        // The responder notices first.
+        {
+            let mut contract = rqst.try_request_ttl(Duration::from_millis(1)).ok().unwrap();
+            thread::sleep(Duration::from_millis(2));
+
+            match resp.try_respond() {
+                Err(Error::NoRequest) => {},
+                _ => { assert!(false); },
+            }
+            assert_eq!(rqst.inner.state(), State::Expired);
+
+            match contract.try_receive() {
+                Err(Error::Expired) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        // The requester notices first.
+        {
+            let mut contract = rqst.try_request_ttl(Duration::from_millis(1)).ok().unwrap();
+            thread::sleep(Duration::from_millis(2));
+
+            match contract.try_receive() {
+                Err(Error::Expired) => {},
+                _ => { assert!(false); },
+            }
+            match resp.try_respond() {
+                Err(Error::NoRequest) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        // Cancelling an expired request works.
+        {
+            let mut contract = rqst.try_request_ttl(Duration::from_millis(1)).ok().unwrap();
+            thread::sleep(Duration::from_millis(2));
+            assert!(resp.try_respond().is_err());
+            contract.try_cancel().ok().unwrap();
+        }
+
+        // A plain request after a timed one never expires.
+        let mut contract = rqst.try_request().ok().unwrap();
+        thread::sleep(Duration::from_millis(2));
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_request_ttl_claim_wins() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request_ttl(Duration::from_millis(1)).ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        thread::sleep(Duration::from_millis(2));
+
+        // The claim landed in time, so the request waits for its datum.
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+        assert!(contract.try_cancel().is_err());
+
+        resp_contract.send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_response_contract_try_send_twice() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut rqst_contract = rqst.try_request().ok().unwrap();
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+
+        assert_eq!(resp_contract.try_send(1), Ok(()));
+        assert_eq!(resp_contract.try_send(2), Err(SendError(2)));
+
+        // The second datum must not overwrite the first.
+        assert_eq!(rqst_contract.try_receive().ok().unwrap(), 1);
+
+        let res = panic::catch_unwind(AssertUnwindSafe(move || resp_contract.send(3)));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_responder_respond_catching() {
+        let (rqst, resp) = channel::<u32>();
+
+        match resp.respond_catching(|| 1) {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let payload = resp.respond_catching(|| -> u32 { panic!("no datum") })
+            .ok().unwrap()
+            .err().unwrap();
+        assert_eq!(*payload.downcast_ref::<&str>().unwrap(), "no datum");
+
+        // The request is still pending.
+        assert!(!contract.responder_claimed());
+        assert!(resp.has_request());
+
+        resp.respond_catching(|| 2).ok().unwrap().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_responder_try_respond_with_panic_many_and_broadcast() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        {
+            let mut contract = rqst.try_request_many(2).ok().unwrap();
+
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                resp.try_respond_with(|| panic!("no datum"))
+            }));
+            assert!(res.is_err());
+            assert_eq!(contract.outstanding(), 2);
+
+            // The responder that panicked may claim its response again.
+            resp.try_respond_with(|| 1).ok().unwrap();
+            resp2.try_respond_with(|| 2).ok().unwrap();
+            assert_eq!(contract.try_receive().ok().unwrap(), 1);
+            assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        }
+
+        let mut contract = rqst.try_request_all().ok().unwrap();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            resp.try_respond_with(|| panic!("no datum"))
+        }));
+        assert!(res.is_err());
+
+        resp2.try_respond_with(|| 3).ok().unwrap();
+        resp.try_respond_with(|| 4).ok().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), (resp2.id(), 3));
+        assert_eq!(contract.try_receive().ok().unwrap(), (resp.id(), 4));
+    }
+
+    #[test]
+    fn test_responder_count() {
+        let (rqst, resp) = channel::<Task>();
+
+        assert_eq!(rqst.responder_count(), 1);
+        assert_eq!(resp.sibling_count(), 0);
+
+        let resp2 = resp.clone();
+        let weak = resp2.downgrade();
+        assert_eq!(rqst.responder_count(), 2);
+        assert_eq!(resp.sibling_count(), 1);
+
+        drop(resp);
+        assert_eq!(rqst.responder_count(), 1);
+
+        let resp3 = weak.upgrade().unwrap();
+        assert_eq!(rqst.responder_count(), 2);
+
+        drop(resp2);
+        drop(resp3);
+        assert_eq!(rqst.responder_count(), 0);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_new_responder_after_disconnect() {
+        let (rqst, resp) = channel::<u32>();
+        let weak = resp.downgrade();
+
+        drop(resp);
+        assert_eq!(rqst.responder_count(), 0);
+        assert!(weak.upgrade().is_none());
+
+        let resp = rqst.new_responder();
+        let resp2 = rqst.new_responder();
+        assert_eq!(rqst.responder_count(), 2);
+        assert_eq!(resp.sibling_count(), 1);
+        assert!(resp.id() != resp2.id());
+
+        // The channel is connected again, so weak responders upgrade.
+        let resp3 = weak.upgrade().unwrap();
+        assert_eq!(rqst.responder_count(), 3);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp2.try_respond().ok().unwrap().send(7);
+        assert_eq!(contract.try_receive().ok().unwrap(), 7);
+
+        drop(resp);
+        drop(resp2);
+        drop(resp3);
+        assert_eq!(rqst.responder_count(), 0);
     }
-   
+
     #[test]
-    fn test_inner_try_lock_request() {
-        #[allow(unused_variables)]
+    fn test_response_contract_amount_requested() {
         let (rqst, resp) = channel::<Task>();
 
-        match rqst.inner.try_lock_request() {
-            Ok(()) => {},
-            _ => { assert!(false); },
-        }
+        let mut rqst_contract = rqst.try_request_amount(8).ok().unwrap();
+
+        let mut resp_contract = resp.try_respond().ok().unwrap();
 
-        assert_eq!(resp.inner.has_request_lock.load(Ordering::SeqCst), true);
+        assert_eq!(resp_contract.amount_requested(), 8);
+
+        rqst_contract.done = true;
+        resp_contract.done = true;
     }
-       
+
     #[test]
-    fn test_inner_try_lock_request_multiple() {
+    fn test_responder_set_priority() {
         #[allow(unused_variables)]
         let (rqst, resp) = channel::<Task>();
 
-        rqst.inner.try_lock_request().ok().unwrap();
+        resp.set_priority(3);
+        assert_eq!(resp.inner.response.prioritized.load(Ordering::SeqCst), 1);
 
-        match rqst.inner.try_lock_request() {
-            Err(Error::AlreadyLocked) => {},
-            _ => { assert!(false); },
-        }
+        let resp2 = resp.clone();
+        assert_eq!(resp2.priority(), 3);
+        assert_eq!(resp.inner.response.prioritized.load(Ordering::SeqCst), 2);
+
+        resp2.set_priority(5);
+        assert_eq!(resp.inner.response.prioritized.load(Ordering::SeqCst), 2);
+
+        let resp3 = resp.clone_with_priority(0);
+        assert_eq!(resp.inner.response.prioritized.load(Ordering::SeqCst), 2);
+
+        drop(resp2);
+        drop(resp3);
+        assert_eq!(resp.inner.response.prioritized.load(Ordering::SeqCst), 1);
+
+        resp.set_priority(0);
+        assert_eq!(resp.inner.response.prioritized.load(Ordering::SeqCst), 0);
     }
 
     #[test]
-    fn test_inner_try_unlock_request() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_responder_set_busy() {
+        let (rqst, resp) = channel::<u32>();
 
-        rqst.inner.has_request_lock.store(true, Ordering::SeqCst);
+        resp.set_busy(true);
+        resp.set_busy(true);
+        assert!(resp.is_busy());
+        assert_eq!(rqst.available_responders(), 0);
 
-        rqst.inner.unlock_request();
-        
-        assert_eq!(resp.inner.has_request_lock.load(Ordering::SeqCst), false);
+        // Clones start idle.
+        let resp2 = resp.clone();
+        assert!(!resp2.is_busy());
+        assert_eq!(rqst.available_responders(), 1);
+
+        resp.set_busy(false);
+        resp.set_busy(false);
+        assert_eq!(rqst.available_responders(), 2);
+
+        resp2.set_busy(true);
+        drop(resp2);
+        assert_eq!(rqst.available_responders(), 1);
+        assert_eq!(resp.inner.response.busy.load(Ordering::SeqCst), 0);
     }
-      
+
     #[test]
-    fn test_inner_try_lock_response() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    #[cfg_attr(miri, ignore)]
+    fn test_responder_set_busy_churn() {
+        let (rqst, resp) = channel::<u32>();
 
-        match rqst.inner.try_lock_response() {
-            Ok(()) => {},
-            _ => { assert!(false); },
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let resp = resp.clone();
+
+                thread::spawn(move || {
+                    for j in 0..200 {
+                        // Some of the clones are dropped while busy.
+                        let worker = resp.clone();
+                        worker.set_busy(true);
+
+                        if (i + j) % 3 == 0 {
+                            worker.set_busy(false);
+                        }
+
+                        resp.set_busy(j % 2 == 0);
+                    }
+
+                    resp.set_busy(true);
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
         }
 
-        assert_eq!(resp.inner.has_response_lock.load(Ordering::SeqCst), true);
+        // Every worker's own responder was dropped busy.
+        assert_eq!(rqst.responder_count(), 1);
+        assert_eq!(rqst.available_responders(), 1);
+        assert_eq!(resp.inner.response.busy.load(Ordering::SeqCst), 0);
     }
-       
+
     #[test]
-    fn test_inner_try_lock_response_multiple() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_requester_pause() {
+        let (rqst, resp) = channel::<u32>();
 
-        rqst.inner.try_lock_response().ok().unwrap();
+        rqst.pause();
+        assert!(rqst.is_paused());
 
-        match rqst.inner.try_lock_response() {
-            Err(Error::AlreadyLocked) => {},
+        // Requests can still be issued, but not claimed.
+        let mut contract = rqst.try_request().ok().unwrap();
+        match resp.try_respond() {
+            Err(Error::Paused) => {},
+            _ => { assert!(false); },
+        }
+        match resp.try_respond_with(|| unreachable!()) {
+            Err(Error::Paused) => {},
             _ => { assert!(false); },
         }
+
+        rqst.resume();
+        assert!(!rqst.is_paused());
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
     }
 
     #[test]
-    fn test_inner_try_unlock_response() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_requester_pause_claimed() {
+        let (rqst, resp) = channel::<u32>();
 
-        rqst.inner.has_response_lock.store(true, Ordering::SeqCst);
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
 
-        rqst.inner.unlock_response();
+        // Pausing does not stop a claimed request from completing.
+        rqst.pause();
+        resp_contract.send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
 
-        assert_eq!(resp.inner.has_response_lock.load(Ordering::SeqCst), false);
+        rqst.resume();
     }
 
     #[test]
-    fn test_inner_flag_request() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    #[cfg_attr(miri, ignore)]
+    fn test_requester_pause_blocks_incoming() {
+        let (rqst, resp) = channel::<u32>();
+        let served = Arc::new(AtomicUsize::new(0));
 
-        rqst.inner.flag_request();
+        rqst.pause();
+        let mut contract = rqst.try_request().ok().unwrap();
 
-        assert_eq!(resp.inner.has_request.load(Ordering::SeqCst), true);
+        let worker = {
+            let served = served.clone();
+
+            thread::spawn(move || {
+                let contract = resp.incoming().next().unwrap();
+                served.fetch_add(1, Ordering::SeqCst);
+                contract.send(3);
+            })
+        };
+
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(served.load(Ordering::SeqCst), 0);
+
+        rqst.resume();
+        worker.join().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
     }
 
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     #[test]
-    fn test_inner_try_unflag_request() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_request_deadline() {
+        let (rqst, resp) = channel::<u32>();
+        assert_eq!(resp.pending_deadline(), None);
 
-        resp.inner.has_request.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let mut contract = rqst.try_request_with_deadline(deadline).ok().unwrap();
+        assert_eq!(resp.pending_deadline(), Some(deadline));
 
-        match rqst.inner.try_unflag_request() {
-            Ok(()) => {},
-            _ => { assert!(false); },
-        }
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.deadline(), Some(deadline));
+        assert_eq!(resp.pending_deadline(), None);
+        resp_contract.send(1);
+        contract.try_receive().ok().unwrap();
+        drop(contract);
 
-        assert_eq!(resp.inner.has_request.load(Ordering::SeqCst), false);
+        // The next request does not inherit the deadline.
+        let mut contract = rqst.try_request().ok().unwrap();
+        assert_eq!(resp.pending_deadline(), None);
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.deadline(), None);
+        resp_contract.send(2);
+        contract.try_receive().ok().unwrap();
+    }
+
+    #[test]
+    fn test_responder_try_respond_if() {
+        let (rqst, resp) = channel::<u32>();
 
-        match rqst.inner.try_unflag_request() {
+        match resp.try_respond_if(|_| true) {
             Err(Error::NoRequest) => {},
             _ => { assert!(false); },
         }
-    }
-   
-    #[test]
-    fn test_inner_try_unflag_request_multiple() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
 
-        resp.inner.has_request.store(true, Ordering::SeqCst);
+        let mut contract = rqst.try_request_urgent().ok().unwrap();
 
-        rqst.inner.try_unflag_request().ok().unwrap();
+        // Declining leaves the request for anyone.
+        match resp.try_respond_if(|meta| meta.urgency == Urgency::Normal) {
+            Err(Error::Declined) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(resp.pending_urgency(), Some(Urgency::Urgent));
+        assert_eq!(resp.stats().won, 0);
 
-        match rqst.inner.try_unflag_request() {
-            Err(Error::NoRequest) => {},
+        let resp_contract = resp.try_respond_if(|meta| {
+            meta.urgency == Urgency::Urgent && meta.sequence == 1
+        }).ok().unwrap();
+        resp_contract.send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        // A declined part of a request for several responses stays open.
+        let mut contract = rqst.try_request_n(1).ok().unwrap();
+        match resp.try_respond_if(|_| false) {
+            Err(Error::Declined) => {},
             _ => { assert!(false); },
         }
+        assert_eq!(contract.outstanding(), 1);
+        resp.try_respond_if(|meta| meta.amount == 1).ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        drop(contract);
+
+        // A panicking predicate hands the claim back too.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = resp.try_respond_if(|_| panic!("predicate failed"));
+        }));
+        assert!(res.is_err());
+        resp.try_respond().ok().unwrap().send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
     }
 
     #[test]
-    fn test_inner_set_datum() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_request_tag() {
+        let (rqst, resp) = channel::<u32>();
+        assert_eq!(resp.pending_tag(), None);
 
-        let task = Box::new(move || { println!("Hello World!"); }) as Task;
+        // A cancelled request's tag does not leak into the next one.
+        let mut contract = rqst.try_request_tagged(3).ok().unwrap();
+        assert_eq!(resp.pending_tag(), Some(3));
+        contract.try_cancel().ok().unwrap();
+        drop(contract);
+        assert_eq!(resp.pending_tag(), None);
 
-        resp.inner.set_datum(task);
+        let mut contract = rqst.try_request().ok().unwrap();
+        assert_eq!(resp.pending_tag(), Some(0));
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.tag(), 0);
+        resp_contract.send(1);
+        contract.try_receive().ok().unwrap();
+        drop(contract);
 
-        assert_eq!(resp.inner.has_datum.load(Ordering::SeqCst), true);
+        let mut contract = rqst.try_request_tagged(u64::MAX).ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.tag(), u64::MAX);
+        resp_contract.send(2);
+        contract.try_receive().ok().unwrap();
     }
-  
-    #[test]
-    fn test_inner_try_get_datum_with_data() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
 
-        let var = Arc::new(AtomicUsize::new(0));
-        let var2 = var.clone();
-        
-        let task = Box::new(move || {
-            var2.fetch_add(1, Ordering::SeqCst);
-        }) as Task;
+    #[test]
+    fn test_request_contract_try_receive_validate() {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
 
-        unsafe {
-            *resp.inner.datum.get() = Some(task);
-        }
-        resp.inner.has_datum.store(true, Ordering::SeqCst);
-             
-        match rqst.inner.try_get_datum() {
-            Ok(t) => {
-                t.call_box();
-                assert_eq!(var.load(Ordering::SeqCst), 1);
-            },
+        match contract.try_receive_validate(|_| true) {
+            Err(Error::Empty) => {},
             _ => { assert!(false); },
         }
-    }
- 
-    #[test]
-    fn test_inner_try_get_datum_no_data() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
-        
-        match rqst.inner.try_get_datum() {
-            Err(Error::Empty) => {}
+
+        // A rejected datum puts the request back up for any responder.
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive_validate(|num| *num == 2).ok().unwrap(), None);
+        assert!(resp.has_request());
+        resp.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive_validate(|num| *num == 2).ok().unwrap(), Some(2));
+
+        match contract.try_receive_validate(|_| true) {
+            Err(Error::Done) => {},
             _ => { assert!(false); },
         }
     }
 
     #[test]
-    fn test_requester_try_request() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    #[cfg_attr(miri, ignore)]
+    fn test_request_contract_try_receive_validate_responders() {
+        let (rqst, resp) = channel::<usize>();
 
-        let mut contract = rqst.try_request().ok().unwrap();
+        // Each responder answers exactly once.
+        let workers: Vec<_> = (0..3).map(|n| {
+            let resp = resp.clone();
 
-        contract.done = true;
-    }
+            thread::spawn(move || {
+                let mut backoff = Backoff::new();
 
-    #[test]
-    fn test_requester_try_request_multiple() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+                loop {
+                    match resp.try_respond() {
+                        Ok(contract) => {
+                            contract.send(n);
+                            return;
+                        },
+                        Err(_) => { backoff.snooze(); },
+                    }
+                }
+            })
+        }).collect();
 
-        rqst.inner.try_lock_request().ok().unwrap();
+        // So rejecting two data has to reach the third responder.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut seen = [false; 3];
+        let mut rejected = 0;
+        let mut backoff = Backoff::new();
 
-        match rqst.try_request() {
-            Err(Error::AlreadyLocked) => {},
-            _ => { assert!(false); },
+        loop {
+            match contract.try_receive_validate(|&n| {
+                seen[n] = true;
+                rejected == 2
+            }) {
+                Ok(Some(_)) => break,
+                Ok(None) => { rejected += 1; },
+                Err(Error::Empty) => { backoff.snooze(); },
+                _ => { assert!(false); },
+            }
         }
-    }
 
-    #[test]
-    fn test_request_contract_try_receive() {
-        let (rqst, resp) = channel::<Task>();
+        assert_eq!(seen, [true; 3]);
+        assert_eq!(rqst.inner.state(), State::Done);
 
-        let var = Arc::new(AtomicUsize::new(0));
-        let var2 = var.clone();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
 
-        let task = Box::new(move || {
-            var2.fetch_add(1, Ordering::SeqCst);
-        }) as Task;
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_request_outcome() {
+        let (rqst, resp) = channel::<u32>();
+        let sibling = resp.clone();
+        assert_eq!(resp.last_request_outcome(), None);
 
         let mut contract = rqst.try_request().ok().unwrap();
+        sibling.try_respond().ok().unwrap().send(1);
+        assert_eq!(resp.last_request_outcome(), Some(RequestOutcome::Served));
 
-        resp.inner.set_datum(task);
+        // A rejected datum makes the request pending again.
+        assert_eq!(contract.try_receive_validate(|_| false).ok().unwrap(), None);
+        assert_eq!(resp.last_request_outcome(), None);
+        contract.try_cancel_with(CancelReason::Timeout).ok().unwrap();
+        assert_eq!(resp.last_request_outcome(),
+                   Some(RequestOutcome::Cancelled(CancelReason::Timeout)));
+        drop(contract);
 
+        let mut contract = rqst.try_request_ttl(Duration::from_millis(0)).ok().unwrap();
+        assert_eq!(resp.last_request_outcome(), None);
         match contract.try_receive() {
-            Ok(task) => {
-                task.call_box();
-            },
+            Err(Error::Expired) => {},
             _ => { assert!(false); },
         }
+        assert_eq!(resp.last_request_outcome(), Some(RequestOutcome::Expired));
+        drop(contract);
 
-        assert_eq!(contract.done, true);
-        assert_eq!(var.load(Ordering::SeqCst), 1);
+        // Requests for several responses are not tracked.
+        let mut contract = rqst.try_request_many(1).ok().unwrap();
+        contract.try_cancel().ok().unwrap();
+        assert_eq!(resp.last_request_outcome(), None);
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        rqst.close();
+        match contract.try_receive() {
+            Err(Error::Closed) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(resp.last_request_outcome(),
+                   Some(RequestOutcome::Cancelled(CancelReason::Shutdown)));
     }
 
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     #[test]
-    fn test_request_contract_try_receive_no_data() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_request_sequence() {
+        let (rqst, resp) = channel::<u32>();
 
+        // Cancelled and expired requests use up their numbers.
         let mut contract = rqst.try_request().ok().unwrap();
+        assert_eq!(contract.sequence(), 1);
+        contract.try_cancel().ok().unwrap();
+        drop(contract);
 
+        let mut contract = rqst.try_request_ttl(Duration::from_millis(0)).ok().unwrap();
+        assert_eq!(contract.sequence(), 2);
         match contract.try_receive() {
-            Err(Error::Empty) => {},
+            Err(Error::Expired) => {},
             _ => { assert!(false); },
         }
+        drop(contract);
 
-        assert_eq!(contract.done, false);
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(contract.sequence(), 3);
+        assert_eq!(resp_contract.sequence(), 3);
+        resp_contract.send(1);
+        contract.try_receive().ok().unwrap();
+        drop(contract);
 
-        contract.done = true;
+        // Requests for several responses are numbered too.
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        assert_eq!(resp_contract.sequence(), 4);
+        resp_contract.send(2);
+        contract.try_cancel().ok().unwrap();
+        contract.try_receive().ok().unwrap();
     }
-    
+
     #[test]
-    fn test_request_contract_try_receive_done() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_requester_close() {
+        let (rqst, resp) = channel::<u32>();
 
         let mut contract = rqst.try_request().ok().unwrap();
+        rqst.close();
+        assert!(rqst.is_closed());
+        assert!(resp.is_closed());
 
-        contract.done = true;
+        match resp.try_respond() {
+            Err(Error::Closed) => {},
+            _ => { assert!(false); },
+        }
 
+        // The unclaimed request is withdrawn.
         match contract.try_receive() {
-            Err(Error::Done) => {},
+            Err(Error::Closed) => {},
+            _ => { assert!(false); },
+        }
+        drop(contract);
+
+        match rqst.try_request() {
+            Err(Error::Closed) => {},
+            _ => { assert!(false); },
+        }
+        match rqst.try_request_many(2) {
+            Err(Error::Closed) => {},
             _ => { assert!(false); },
         }
+        assert!(resp.incoming().next().is_none());
     }
 
     #[test]
-    fn test_request_contract_try_cancel() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_requester_close_many() {
+        let (rqst, resp) = channel::<u32>();
 
-        let mut contract = rqst.try_request().ok().unwrap();
+        let mut contract = rqst.try_request_many(3).ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+        rqst.close();
 
-        match contract.try_cancel() {
-            Ok(()) => {},
+        // The claimed response still arrives; the rest are withdrawn.
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+        resp_contract.send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        match contract.try_receive() {
+            Err(Error::Done) => {},
             _ => { assert!(false); },
         }
     }
 
     #[test]
-    fn test_request_contract_try_cancel_too_late() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
-        
-        let mut contract = rqst.try_request().ok().unwrap();
+    fn test_responder_close() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
 
-        rqst.inner.try_unflag_request().ok().unwrap();
+        resp.close();
+        assert!(resp.is_closed());
+        assert!(!resp2.is_closed());
+        assert!(!rqst.is_closed());
 
-        match contract.try_cancel() {
-            Err(Error::TooLate) => {},
+        // A clone of a closed responder starts open.
+        assert!(!resp.clone().is_closed());
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        match resp.try_respond() {
+            Err(Error::Closed) => {},
             _ => { assert!(false); },
         }
-
-        assert_eq!(contract.done, false);
-
-        contract.done = true;
+        resp2.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
     }
 
     #[test]
-    fn test_request_contract_try_cancel_done() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    #[cfg_attr(miri, ignore)]
+    fn test_requester_close_races_response() {
+        for _ in 0..100 {
+            let (rqst, resp) = channel::<u32>();
+            let sent = Arc::new(AtomicUsize::new(0));
 
-        let mut contract = rqst.try_request().ok().unwrap();
+            let mut contract = rqst.try_request().ok().unwrap();
 
-        contract.done = true;
+            let worker = {
+                let sent = sent.clone();
 
-        match contract.try_cancel() {
-            Err(Error::Done) => {},
-            _ => { assert!(false); },
+                thread::spawn(move || {
+                    loop {
+                        match resp.try_respond() {
+                            Ok(contract) => {
+                                thread::yield_now();
+                                contract.send(1);
+                                sent.fetch_add(1, Ordering::SeqCst);
+                                return;
+                            },
+                            Err(Error::Closed) => { return; },
+                            Err(_) => { thread::yield_now(); },
+                        }
+                    }
+                })
+            };
+
+            thread::yield_now();
+            rqst.close();
+
+            // Either the claim won and its datum arrives, or the request
+            // was withdrawn before anyone claimed it.
+            let received = loop {
+                match contract.try_receive() {
+                    Ok(num) => { break num; },
+                    Err(Error::Closed) => { break 0; },
+                    Err(Error::Empty) => { thread::yield_now(); },
+                    _ => { assert!(false); },
+                }
+            };
+
+            worker.join().unwrap();
+            assert_eq!(received as usize, sent.load(Ordering::SeqCst));
         }
     }
 
     #[test]
-    #[should_panic]
-    fn test_request_contract_drop_without_receiving_data() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
+    fn test_responder_try_respond_low_priority_alone() {
+        let (rqst, resp) = channel::<u32>();
+        let high = resp.clone_with_priority(9);
 
-        #[allow(unused_variables)]
-        let contract = rqst.try_request().ok().unwrap();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        // No higher-priority responder is trying, so `resp` wins.
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+
+        drop(high);
     }
 
     #[test]
-    fn test_responder_try_respond() {
-        let (rqst, resp) = channel::<Task>();
-        
-        rqst.inner.flag_request();
+    fn test_responder_try_respond_high_priority_trying() {
+        let (rqst, resp) = channel::<u32>();
+        let high = resp.clone_with_priority(9);
 
-        let mut contract = resp.try_respond().ok().unwrap();
+        let mut contract = rqst.try_request().ok().unwrap();
 
-        contract.done = true;
-    }
+        // `high` announced itself first.
+        high.inner.arbitrate(high.priority()).ok().unwrap();
 
-    #[test]
-    fn test_responder_try_respond_no_request() {
-        #[allow(unused_variables)]
-        let (rqst, resp) = channel::<Task>();
-        
         match resp.try_respond() {
-            Err(Error::NoRequest) => {},
+            Err(Error::AlreadyLocked) => {},
             _ => { assert!(false); },
         }
+
+        high.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
     }
 
     #[test]
-    fn test_responder_try_respond_multiple() {
+    fn test_responder_try_respond_equal_priority() {
+        let (rqst, resp) = channel::<u32>();
+        resp.set_priority(4);
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        resp2.inner.arbitrate(resp2.priority()).ok().unwrap();
+
+        // Equal priorities do not block each other.
+        resp.try_respond().ok().unwrap().send(3);
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_inner_flag_request_resets_intent() {
         #[allow(unused_variables)]
         let (rqst, resp) = channel::<Task>();
 
-        let _ = resp.inner.try_lock_response().ok().unwrap();
-        
-        match resp.try_respond() {
-            Err(Error::AlreadyLocked) => {},
-            _ => { assert!(false); },
-        }
+        resp.inner.response.intent.store(10, Ordering::SeqCst);
+
+        rqst.inner.try_lock_request().ok().unwrap();
+        rqst.inner.flag_request();
+
+        assert_eq!(resp.inner.response.intent.load(Ordering::SeqCst), 0);
     }
 
     #[test]
     fn test_response_contract_send() {
         let (rqst, resp) = channel::<Task>();
 
+        rqst.inner.try_lock_request().ok().unwrap();
         rqst.inner.flag_request();
 
         let contract = resp.try_respond().ok().unwrap();
 
-        contract.send(Box::new(move || { println!("Hello World!"); }) as Task);
+        contract.send(Task::new(move || { println!("Hello World!"); }));
     }
 
     #[test]
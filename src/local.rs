@@ -0,0 +1,448 @@
+//! This module defines a single-threaded version of the channel. Its ends
+//! share their state through an `Rc` instead of an `Arc`, and they track it
+//! with `Cell`s instead of atomics, so they are neither `Send` nor `Sync`.
+//! A request goes through the same states as on a regular channel.
+//! It is useful for structured handoffs inside one thread, such as a game
+//! loop asking its subsystems for work.
+//!
+//! The ends and contracts have the same API, and return the same errors,
+//! as those of a regular channel. Requests for several responses and
+//! responder priorities are left out, since they only matter when
+//! responders run at the same time.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use std::result;
+use std::thread;
+
+use super::{Error, Result, State, Urgency};
+
+/// This function creates a local `reqchan` and returns a tuple containing
+/// its two ends.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan;
+///
+/// let (requester, responder) = reqchan::local::channel::<u32>();
+///
+/// let mut request_contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+///
+/// assert_eq!(request_contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub fn channel<T>() -> (LocalRequester<T>, LocalResponder<T>) {
+    let inner = Rc::new(Inner::new());
+
+    (
+        LocalRequester { inner: inner.clone() },
+        LocalResponder { inner: inner.clone() },
+    )
+}
+
+/// This end of the local channel requests and receives data from its
+/// `LocalResponder`(s). It works just like `Requester`.
+pub struct LocalRequester<T> {
+    inner: Rc<Inner<T>>,
+}
+
+impl<T> LocalRequester<T> {
+    /// This method works just like `Requester::try_request()`.
+    #[inline]
+    pub fn try_request(&self) -> Result<LocalRequestContract<T>> {
+        self.try_request_amount(1)
+    }
+
+    /// This method works just like `Requester::try_request_amount()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of item(s) the requester wants
+    #[inline]
+    pub fn try_request_amount(&self, n: usize) -> Result<LocalRequestContract<T>> {
+        self.try_request_with(|inner| inner.amount.set(n))
+    }
+
+    /// This method works just like `Requester::try_request_urgent()`.
+    #[inline]
+    pub fn try_request_urgent(&self) -> Result<LocalRequestContract<T>> {
+        self.try_request_with_urgency(Urgency::Urgent)
+    }
+
+    /// This method works just like `Requester::try_request_with_urgency()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `urgency` - How urgent the request is
+    #[inline]
+    pub fn try_request_with_urgency(&self, urgency: Urgency) -> Result<LocalRequestContract<T>> {
+        self.try_request_with(|inner| inner.urgency.set(urgency))
+    }
+
+    /// This method locks the requesting side, stores the request's
+    /// metadata, and issues the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `set_meta` - A function that overrides the default metadata
+    #[inline]
+    fn try_request_with<F>(&self, set_meta: F) -> Result<LocalRequestContract<T>>
+        where F: FnOnce(&Inner<T>)
+    {
+        // First, try to lock the requesting side.
+        self.inner.transition(State::Idle, State::RequestLocked)
+            .map_err(|_| Error::AlreadyLocked)?;
+
+        // Next, store the metadata.
+        self.inner.amount.set(1);
+        self.inner.urgency.set(Urgency::Normal);
+        set_meta(&self.inner);
+
+        // Then issue the request.
+        self.inner.transition(State::RequestLocked, State::Requested).ok();
+
+        // Then return a `LocalRequestContract`.
+        Ok(LocalRequestContract {
+            inner: self.inner.clone(),
+            done: false,
+        })
+    }
+}
+
+/// This is the contract returned by a successful `LocalRequester::try_request()`.
+/// It works just like `RequestContract`.
+pub struct LocalRequestContract<T> {
+    inner: Rc<Inner<T>>,
+    done: bool,
+}
+
+impl<T> LocalRequestContract<T> {
+    /// This method works just like `RequestContract::try_receive()`.
+    pub fn try_receive(&mut self) -> Result<T> {
+        // Do not try to receive anything if the contract already received data.
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        // Leaving `State::DataReady` marks the slot empty again.
+        self.inner.transition(State::DataReady, State::Done)
+            .map_err(|_| Error::Empty)?;
+        self.done = true;
+
+        Ok(self.inner.datum.borrow_mut().take().unwrap())
+    }
+
+    /// This method works just like `RequestContract::try_cancel()`.
+    pub fn try_cancel(&mut self) -> Result<()> {
+        // Do not try to unsend if the contract already received data.
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        if self.inner.transition(State::Requested, State::Done).is_ok() {
+            self.inner.urgency.set(Urgency::Normal);
+            self.done = true;
+            Ok(())
+        }
+        else {
+            Err(Error::TooLate)
+        }
+    }
+}
+
+impl<T> Drop for LocalRequestContract<T> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping LocalRequestContract without receiving data!");
+        }
+
+        self.inner.transition(State::Done, State::Idle).ok();
+    }
+}
+
+/// This end of the local channel sends data in response to requests from
+/// its `LocalRequester`. It works just like `Responder`.
+pub struct LocalResponder<T> {
+    inner: Rc<Inner<T>>,
+}
+
+impl<T> LocalResponder<T> {
+    /// This method works just like `Responder::pending_urgency()`.
+    #[inline]
+    pub fn pending_urgency(&self) -> Option<Urgency> {
+        if self.inner.state() == State::Requested {
+            Some(self.inner.urgency.get())
+        }
+        else {
+            None
+        }
+    }
+
+    /// This method works just like `Responder::try_respond()`.
+    pub fn try_respond(&self) -> Result<LocalResponseContract<T>> {
+        // First, try to lock the responding side.
+        try_lock(&self.inner.has_response_lock)?;
+
+        // Next, check for a request and claim it. If no request exists,
+        // drop the lock.
+        if self.inner.transition(State::Requested, State::Claimed).is_ok() {
+            Ok(LocalResponseContract {
+                inner: self.inner.clone(),
+                amount: self.inner.amount.get(),
+                urgency: self.inner.urgency.get(),
+                done: false,
+            })
+        }
+        else {
+            unlock(&self.inner.has_response_lock);
+            Err(Error::NoRequest)
+        }
+    }
+}
+
+impl<T> Clone for LocalResponder<T> {
+    fn clone(&self) -> Self {
+        LocalResponder {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// This is the contract returned by a successful `LocalResponder::try_respond()`.
+/// It works just like `ResponseContract`.
+pub struct LocalResponseContract<T> {
+    inner: Rc<Inner<T>>,
+    amount: usize,
+    urgency: Urgency,
+    done: bool,
+}
+
+impl<T> LocalResponseContract<T> {
+    /// This method works just like `ResponseContract::amount_requested()`.
+    #[inline]
+    pub fn amount_requested(&self) -> usize {
+        self.amount
+    }
+
+    /// This method works just like `ResponseContract::urgency()`.
+    #[inline]
+    pub fn urgency(&self) -> Urgency {
+        self.urgency
+    }
+
+    /// This method works just like `ResponseContract::send()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send(mut self, datum: T) {
+        *self.inner.datum.borrow_mut() = Some(datum);
+        self.inner.transition(State::Claimed, State::DataReady).ok();
+        self.done = true;
+    }
+}
+
+impl<T> Drop for LocalResponseContract<T> {
+    fn drop(&mut self) {
+        if !self.done {
+            if !thread::panicking() {
+                panic!("Dropping LocalResponseContract without sending data!");
+            }
+
+            // Hand the claim back, just like `ResponseContract`.
+            self.inner.transition(State::Claimed, State::Requested).ok();
+        }
+
+        unlock(&self.inner.has_response_lock);
+    }
+}
+
+#[doc(hidden)]
+struct Inner<T> {
+    // This holds a `State`, just like a regular channel's state.
+    state: Cell<u8>,
+    has_response_lock: Cell<bool>,
+    amount: Cell<usize>,
+    urgency: Cell<Urgency>,
+    datum: RefCell<Option<T>>,
+}
+
+impl<T> Inner<T> {
+    /// This function creates the state of an idle channel.
+    #[inline]
+    fn new() -> Inner<T> {
+        Inner {
+            state: Cell::new(State::Idle as u8),
+            has_response_lock: Cell::new(false),
+            amount: Cell::new(1),
+            urgency: Cell::new(Urgency::Normal),
+            datum: RefCell::new(None),
+        }
+    }
+
+    /// This method returns the state of the current request.
+    #[inline]
+    fn state(&self) -> State {
+        State::from_u8(self.state.get())
+    }
+
+    /// This method works just like the transitions of a regular channel,
+    /// but needs no atomics, since only one thread uses the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * from - The state the channel should be in
+    ///
+    /// * to - The state to move it to
+    #[inline]
+    fn transition(&self, from: State, to: State) -> result::Result<(), State> {
+        let current = self.state();
+
+        if current == from {
+            self.state.set(to as u8);
+            Ok(())
+        }
+        else {
+            Err(current)
+        }
+    }
+}
+
+/// This function tries to lock one side of a local channel.
+#[inline]
+fn try_lock(lock: &Cell<bool>) -> Result<()> {
+    if lock.replace(true) {
+        Err(Error::AlreadyLocked)
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// This function unlocks one side of a local channel.
+#[inline]
+fn unlock(lock: &Cell<bool>) {
+    lock.set(false);
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    #[test]
+    fn test_local_requester_try_request_multiple() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_local_responder_try_respond() {
+        let (rqst, resp) = channel::<u32>();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request_amount(4).ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match resp.clone().try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(resp_contract.amount_requested(), 4);
+        assert_eq!(resp_contract.urgency(), Urgency::Normal);
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(4);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_local_request_contract_try_cancel() {
+        let (rqst, resp) = channel::<u32>();
+
+        {
+            let mut contract = rqst.try_request_urgent().ok().unwrap();
+            assert_eq!(resp.pending_urgency(), Some(Urgency::Urgent));
+
+            contract.try_cancel().ok().unwrap();
+            assert_eq!(resp.pending_urgency(), None);
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(5);
+        assert_eq!(contract.try_receive().ok().unwrap(), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_local_request_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_local_response_contract_drop_without_sending_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        contract.done = true;
+
+        #[allow(unused_variables)]
+        let resp_contract = resp.try_respond().ok().unwrap();
+    }
+
+    #[test]
+    fn test_local_response_contract_drop_while_panicking() {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _resp_contract = resp.try_respond().ok().unwrap();
+            panic!("worker failed");
+        }));
+        assert!(res.is_err());
+
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+}
@@ -0,0 +1,424 @@
+//! This module defines fakes for the two ends of a channel, so code that
+//! takes a `RequestSink` or a `ResponseSource` can be tested without a
+//! real channel or any threads. It is only built with the `test-util`
+//! feature.
+//!
+//! `MockResponder` stands in for the responders on the other side of a
+//! requesting component: it answers each request with the next datum the
+//! test queued. `MockRequester` stands in for the requester on the other
+//! side of a responding component: it issues requests when the test says
+//! so and records what the component sends. Both are single-threaded and
+//! fully deterministic.
+//!
+//! Their contracts panic when dropped unresolved, just like the real ones,
+//! so a component that leaks a contract fails its tests too.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::thread;
+
+use super::traits::{PendingRequest, PendingResponse, RequestSink, ResponseSource};
+use super::{Error, Result};
+
+#[doc(hidden)]
+struct ResponderState<T> {
+    data: RefCell<VecDeque<T>>,
+    locked: Cell<bool>,
+    issued: Cell<usize>,
+    cancelled: Cell<usize>,
+}
+
+/// This fake answers requests with data the test queued in advance.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use chan::mock::MockResponder;
+/// use chan::{PendingRequest, RequestSink};
+///
+/// let responder = MockResponder::new();
+/// responder.enqueue(1);
+/// responder.enqueue(2);
+///
+/// for expected in 1..3 {
+///     let mut contract = responder.try_request().ok().unwrap();
+///     assert_eq!(contract.try_receive().ok().unwrap(), expected);
+/// }
+///
+/// assert_eq!(responder.requests_issued(), 2);
+/// responder.assert_all_consumed();
+/// ```
+pub struct MockResponder<T> {
+    state: Rc<ResponderState<T>>,
+}
+
+impl<T> MockResponder<T> {
+    /// This function creates a fake with no data queued.
+    pub fn new() -> MockResponder<T> {
+        MockResponder {
+            state: Rc::new(ResponderState {
+                data: RefCell::new(VecDeque::new()),
+                locked: Cell::new(false),
+                issued: Cell::new(0),
+                cancelled: Cell::new(0),
+            }),
+        }
+    }
+
+    /// This method queues `datum` for the next request. Data go out in
+    /// the order they were queued, one per request.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The datum to answer with
+    pub fn enqueue(&self, datum: T) {
+        self.state.data.borrow_mut().push_back(datum);
+    }
+
+    /// This method returns the number of requests issued so far.
+    #[inline]
+    pub fn requests_issued(&self) -> usize {
+        self.state.issued.get()
+    }
+
+    /// This method returns the number of requests cancelled so far.
+    #[inline]
+    pub fn requests_cancelled(&self) -> usize {
+        self.state.cancelled.get()
+    }
+
+    /// This method returns the number of data nobody received yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.state.data.borrow().len()
+    }
+
+    /// This method panics unless every queued datum was received.
+    pub fn assert_all_consumed(&self) {
+        let remaining = self.remaining();
+
+        if remaining > 0 {
+            panic!("MockResponder still holds {} unreceived data!", remaining);
+        }
+    }
+}
+
+impl<T> Default for MockResponder<T> {
+    fn default() -> MockResponder<T> {
+        MockResponder::new()
+    }
+}
+
+impl<T> RequestSink<T> for MockResponder<T> {
+    type Contract = MockRequestContract<T>;
+
+    fn try_request(&self) -> Result<MockRequestContract<T>> {
+        if self.state.locked.get() {
+            return Err(Error::AlreadyLocked);
+        }
+
+        self.state.locked.set(true);
+        self.state.issued.set(self.state.issued.get() + 1);
+
+        Ok(MockRequestContract {
+            state: self.state.clone(),
+            done: false,
+        })
+    }
+}
+
+/// This is the contract returned by `MockResponder::try_request()`. It
+/// works just like `RequestContract`: it receives the next queued datum,
+/// or fails with `Error::Empty` if the test queued none.
+pub struct MockRequestContract<T> {
+    state: Rc<ResponderState<T>>,
+    done: bool,
+}
+
+impl<T> PendingRequest<T> for MockRequestContract<T> {
+    fn try_receive(&mut self) -> Result<T> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        match self.state.data.borrow_mut().pop_front() {
+            Some(datum) => {
+                self.done = true;
+                Ok(datum)
+            },
+            None => Err(Error::Empty),
+        }
+    }
+
+    fn try_cancel(&mut self) -> Result<()> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        self.done = true;
+        self.state.cancelled.set(self.state.cancelled.get() + 1);
+
+        Ok(())
+    }
+}
+
+impl<T> Drop for MockRequestContract<T> {
+    fn drop(&mut self) {
+        self.state.locked.set(false);
+
+        if !self.done && !thread::panicking() {
+            panic!("Dropping MockRequestContract without receiving data!");
+        }
+    }
+}
+
+#[doc(hidden)]
+struct RequesterState<T> {
+    requests: Cell<usize>,
+    locked: Cell<bool>,
+    issued: Cell<usize>,
+    cancelled: Cell<usize>,
+    received: RefCell<Vec<T>>,
+}
+
+/// This fake issues requests when the test tells it to, and records the
+/// data sent in answer.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use chan::mock::MockRequester;
+/// use chan::{PendingResponse, ResponseSource};
+///
+/// let requester = MockRequester::new();
+///
+/// // Nothing to answer yet.
+/// assert!(requester.try_respond().is_err());
+///
+/// requester.request();
+/// requester.try_respond().ok().unwrap().send(4);
+///
+/// assert_eq!(requester.take_received(), vec![4]);
+/// requester.assert_all_consumed();
+/// ```
+pub struct MockRequester<T> {
+    state: Rc<RequesterState<T>>,
+}
+
+impl<T> MockRequester<T> {
+    /// This function creates a fake with no requests outstanding.
+    pub fn new() -> MockRequester<T> {
+        MockRequester {
+            state: Rc::new(RequesterState {
+                requests: Cell::new(0),
+                locked: Cell::new(false),
+                issued: Cell::new(0),
+                cancelled: Cell::new(0),
+                received: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// This method issues one more request. Requests pile up until they
+    /// are answered or cancelled, so a test can issue several at once.
+    pub fn request(&self) {
+        self.state.requests.set(self.state.requests.get() + 1);
+        self.state.issued.set(self.state.issued.get() + 1);
+    }
+
+    /// This method cancels the oldest unclaimed request, as if the real
+    /// requester had given up on it. It returns `false` if there was none
+    /// to cancel.
+    pub fn cancel(&self) -> bool {
+        let requests = self.state.requests.get();
+
+        // A claimed request can no longer be cancelled.
+        let claimed = self.state.locked.get() as usize;
+
+        if requests <= claimed {
+            return false;
+        }
+
+        self.state.requests.set(requests - 1);
+        self.state.cancelled.set(self.state.cancelled.get() + 1);
+
+        true
+    }
+
+    /// This method returns the number of requests issued so far.
+    #[inline]
+    pub fn requests_issued(&self) -> usize {
+        self.state.issued.get()
+    }
+
+    /// This method returns the number of requests cancelled so far.
+    #[inline]
+    pub fn requests_cancelled(&self) -> usize {
+        self.state.cancelled.get()
+    }
+
+    /// This method returns the number of requests still waiting for an
+    /// answer.
+    #[inline]
+    pub fn outstanding(&self) -> usize {
+        self.state.requests.get()
+    }
+
+    /// This method takes the data sent so far, oldest first.
+    pub fn take_received(&self) -> Vec<T> {
+        self.state.received.borrow_mut().drain(..).collect()
+    }
+
+    /// This method panics unless every request was answered or cancelled.
+    pub fn assert_all_consumed(&self) {
+        let outstanding = self.outstanding();
+
+        if outstanding > 0 {
+            panic!("MockRequester still has {} unanswered requests!", outstanding);
+        }
+    }
+}
+
+impl<T> Default for MockRequester<T> {
+    fn default() -> MockRequester<T> {
+        MockRequester::new()
+    }
+}
+
+impl<T> ResponseSource<T> for MockRequester<T> {
+    type Contract = MockResponseContract<T>;
+
+    fn try_respond(&self) -> Result<MockResponseContract<T>> {
+        if self.state.locked.get() {
+            return Err(Error::AlreadyLocked);
+        }
+
+        if self.state.requests.get() == 0 {
+            return Err(Error::NoRequest);
+        }
+
+        self.state.locked.set(true);
+
+        Ok(MockResponseContract {
+            state: self.state.clone(),
+            done: false,
+        })
+    }
+}
+
+/// This is the contract returned by `MockRequester::try_respond()`. It
+/// works just like `ResponseContract`.
+pub struct MockResponseContract<T> {
+    state: Rc<RequesterState<T>>,
+    done: bool,
+}
+
+impl<T> PendingResponse<T> for MockResponseContract<T> {
+    fn send(mut self, datum: T) {
+        self.state.received.borrow_mut().push(datum);
+        self.state.requests.set(self.state.requests.get() - 1);
+        self.done = true;
+    }
+}
+
+impl<T> Drop for MockResponseContract<T> {
+    fn drop(&mut self) {
+        self.state.locked.set(false);
+
+        if !self.done && !thread::panicking() {
+            panic!("Dropping MockResponseContract without sending data!");
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    /// This stands in for a component that pulls work from a channel.
+    fn pull_all<R: RequestSink<u32>>(requester: &R) -> Vec<u32> {
+        let mut got = Vec::new();
+
+        loop {
+            let mut contract = requester.try_request().ok().unwrap();
+
+            match contract.try_receive() {
+                Ok(num) => { got.push(num); },
+                Err(Error::Empty) => {
+                    contract.try_cancel().ok().unwrap();
+                    return got;
+                },
+                _ => { assert!(false); },
+            }
+        }
+    }
+
+    #[test]
+    fn test_mock_responder() {
+        let responder = MockResponder::new();
+
+        for num in 0..3 {
+            responder.enqueue(num);
+        }
+
+        assert_eq!(pull_all(&responder), vec![0, 1, 2]);
+        assert_eq!(responder.requests_issued(), 4);
+        assert_eq!(responder.requests_cancelled(), 1);
+        responder.assert_all_consumed();
+
+        // Only one request may be outstanding.
+        let mut contract = responder.try_request().ok().unwrap();
+        match responder.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_mock_requester_cancel() {
+        let requester = MockRequester::<u32>::new();
+
+        requester.request();
+        requester.request();
+        assert!(requester.cancel());
+
+        // The last request is claimed, so it cannot be cancelled.
+        let contract = requester.try_respond().ok().unwrap();
+        assert!(!requester.cancel());
+        contract.send(1);
+
+        match requester.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(requester.requests_issued(), 2);
+        assert_eq!(requester.requests_cancelled(), 1);
+        assert_eq!(requester.take_received(), vec![1]);
+        requester.assert_all_consumed();
+    }
+
+    #[test]
+    fn test_mock_assert_all_consumed() {
+        let responder = MockResponder::new();
+        responder.enqueue(1);
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| responder.assert_all_consumed()));
+        assert!(res.is_err());
+
+        let requester = MockRequester::<u32>::new();
+        requester.request();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| requester.assert_all_consumed()));
+        assert!(res.is_err());
+    }
+}
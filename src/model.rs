@@ -0,0 +1,463 @@
+//! This module defines a reference model of a single-response channel and
+//! a driver that checks the real channel against it. It is built for the
+//! crate's own tests, and with the `testing` feature.
+//!
+//! `Model` keeps the whole state of a channel behind one `Mutex` and
+//! spells out what each operation does, so it is easy to see that it is
+//! right. `differential()` plays a random sequence of operations on both
+//! the model and a real channel with several `Responder`s, all on the
+//! calling thread, and compares everything the two return. Loom explores
+//! how the atomic steps of a few operations interleave; this catches
+//! logic bugs in long sequences of them instead, such as an error that
+//! is wrong in a state loom's scenarios never reach.
+
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::result;
+use std::sync::Mutex;
+
+use super::{channel, Error, RequestContract, Requester, Responder, ResponseContract, Result};
+
+/// This is the state of a `Model`'s request.
+#[doc(hidden)]
+enum State<T> {
+    Idle,
+    Requested,
+    Claimed,
+    Ready(T),
+    Done,
+}
+
+/// This is everything a `Model` keeps track of.
+#[doc(hidden)]
+struct Spec<T> {
+    state: State<T>,
+    // Whether a request contract is live, and if so, whether it received
+    // a datum or cancelled its request.
+    request: Option<bool>,
+    // Whether a response contract is live.
+    response: bool,
+    closed: bool,
+}
+
+/// This is the reference model of a channel with one `Requester`. Its
+/// methods stand for the operations of the real handles and contracts,
+/// and return what those should return.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let model = chan::model::Model::<u32>::new();
+///
+/// model.try_request().ok().unwrap();
+/// model.try_respond().ok().unwrap();
+/// model.send(1);
+///
+/// assert_eq!(model.try_receive().ok().unwrap(), 1);
+/// assert!(!model.drop_request_contract());
+/// ```
+pub struct Model<T> {
+    spec: Mutex<Spec<T>>,
+}
+
+impl<T> Model<T> {
+    /// This function creates the model of an idle channel.
+    pub fn new() -> Model<T> {
+        Model {
+            spec: Mutex::new(Spec {
+                state: State::Idle,
+                request: None,
+                response: false,
+                closed: false,
+            }),
+        }
+    }
+
+    /// This method stands for `Requester::try_request()`. A request can
+    /// only be made while the last one is finished and its contract gone.
+    pub fn try_request(&self) -> Result<()> {
+        let mut spec = self.spec.lock().unwrap();
+
+        if spec.closed {
+            return Err(Error::Closed);
+        }
+
+        match spec.state {
+            State::Idle => {
+                spec.state = State::Requested;
+                spec.request = Some(false);
+                Ok(())
+            },
+            _ => Err(Error::AlreadyLocked),
+        }
+    }
+
+    /// This method stands for `Responder::try_respond()`. Exactly one
+    /// responder claims each request.
+    pub fn try_respond(&self) -> Result<()> {
+        let mut spec = self.spec.lock().unwrap();
+
+        if spec.closed {
+            return Err(Error::Closed);
+        }
+
+        match spec.state {
+            State::Requested => {
+                spec.state = State::Claimed;
+                spec.response = true;
+                Ok(())
+            },
+            State::Claimed => Err(Error::AlreadyLocked),
+            _ => Err(Error::NoRequest),
+        }
+    }
+
+    /// This method stands for `ResponseContract::send()`.
+    ///
+    /// # Panics
+    ///
+    /// It panics if no response contract is live.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The datum to send
+    pub fn send(&self, datum: T) {
+        let mut spec = self.spec.lock().unwrap();
+
+        // Only a live claim can send, and nothing but its own send ends it.
+        assert!(spec.response, "No ResponseContract to send with!");
+        spec.response = false;
+        spec.state = State::Ready(datum);
+    }
+
+    /// This method stands for `RequestContract::try_receive()`. Polling a
+    /// request nobody claimed on a closed channel withdraws it.
+    ///
+    /// # Panics
+    ///
+    /// It panics if no request contract is live.
+    pub fn try_receive(&self) -> Result<T> {
+        let mut spec = self.spec.lock().unwrap();
+
+        match spec.request {
+            Some(false) => {},
+            Some(true) => { return Err(Error::Done); },
+            None => { panic!("No RequestContract to receive with!"); },
+        }
+
+        match mem::replace(&mut spec.state, State::Done) {
+            State::Ready(datum) => {
+                spec.request = Some(true);
+                Ok(datum)
+            },
+            State::Requested if spec.closed => {
+                spec.request = Some(true);
+                Err(Error::Closed)
+            },
+            state => {
+                spec.state = state;
+                Err(Error::Empty)
+            },
+        }
+    }
+
+    /// This method stands for `RequestContract::try_cancel()`. Only a
+    /// request nobody claimed can be withdrawn.
+    ///
+    /// # Panics
+    ///
+    /// It panics if no request contract is live.
+    pub fn try_cancel(&self) -> Result<()> {
+        let mut spec = self.spec.lock().unwrap();
+
+        match spec.request {
+            Some(false) => {},
+            Some(true) => { return Err(Error::Done); },
+            None => { panic!("No RequestContract to cancel!"); },
+        }
+
+        match spec.state {
+            State::Requested => {
+                spec.state = State::Done;
+                spec.request = Some(true);
+                Ok(())
+            },
+            _ => Err(Error::TooLate),
+        }
+    }
+
+    /// This method stands for dropping the live `RequestContract`, and
+    /// returns whether that panics. A contract that neither received a
+    /// datum nor cancelled its request panics, and leaves the request
+    /// where it was, so no new one can be made.
+    ///
+    /// # Panics
+    ///
+    /// It panics if no request contract is live.
+    pub fn drop_request_contract(&self) -> bool {
+        let mut spec = self.spec.lock().unwrap();
+
+        match spec.request.take() {
+            Some(true) => {
+                spec.state = State::Idle;
+                false
+            },
+            Some(false) => true,
+            None => { panic!("No RequestContract to drop!"); },
+        }
+    }
+
+    /// This method stands for dropping the live `ResponseContract`
+    /// without sending, and returns whether that panics. It always does,
+    /// and the request stays claimed.
+    ///
+    /// # Panics
+    ///
+    /// It panics if no response contract is live.
+    pub fn drop_response_contract(&self) -> bool {
+        let mut spec = self.spec.lock().unwrap();
+
+        assert!(spec.response, "No ResponseContract to drop!");
+        spec.response = false;
+        true
+    }
+
+    /// This method stands for `Requester::close()`.
+    pub fn close(&self) {
+        self.spec.lock().unwrap().closed = true;
+    }
+
+    /// This method stands for `Responder::has_request()`.
+    pub fn has_request(&self) -> bool {
+        matches!(self.spec.lock().unwrap().state, State::Requested)
+    }
+
+    /// This method stands for `RequestContract::responder_claimed()`.
+    pub fn responder_claimed(&self) -> bool {
+        let spec = self.spec.lock().unwrap();
+
+        spec.request == Some(false) && matches!(spec.state, State::Claimed)
+    }
+}
+
+impl<T> Default for Model<T> {
+    fn default() -> Self {
+        Model::new()
+    }
+}
+
+/// This is the first difference `differential()` found between the real
+/// channel and the model. Passing `seed` to `differential()` again
+/// replays the same operations.
+#[derive(Debug)]
+pub struct Divergence {
+    /// The seed of the sequence.
+    pub seed: u64,
+    /// The index of the operation that differed.
+    pub step: usize,
+    /// The name of the operation that differed.
+    pub op: &'static str,
+    /// What the real channel returned.
+    pub real: String,
+    /// What the model returned.
+    pub model: String,
+}
+
+/// This is the generator that picks the operations, so a seed always
+/// picks the same ones on every platform.
+#[doc(hidden)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // Xorshift never leaves zero, so spread the seed and keep it odd.
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    /// This method returns a number below `n`.
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as usize % n
+    }
+}
+
+/// This holds the real channel's handles and contracts while
+/// `differential()` drives them.
+#[doc(hidden)]
+struct Ends {
+    requester: Requester<u32>,
+    responders: Vec<Responder<u32>>,
+    request: Option<RequestContract<u32>>,
+    response: Option<ResponseContract<u32>>,
+}
+
+impl Drop for Ends {
+    fn drop(&mut self) {
+        // A sequence may stop with contracts that would panic when
+        // dropped, so leak them instead.
+        mem::forget(self.request.take());
+        mem::forget(self.response.take());
+    }
+}
+
+/// This function plays `steps` random operations on a real channel and
+/// on a `Model`, and returns the first operation whose results differ.
+/// The operations only depend on `seed`, so a failing seed can be
+/// replayed on its own.
+///
+/// # Arguments
+///
+/// * `seed` - The seed that picks the operations
+///
+/// * `steps` - The number of operations to play
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// for seed in 0..10 {
+///     if let Err(divergence) = chan::model::differential(seed, 100) {
+///         panic!("{:?}", divergence);
+///     }
+/// }
+/// ```
+pub fn differential(seed: u64, steps: usize) -> result::Result<(), Divergence> {
+    let mut rng = Rng::new(seed);
+    let model = Model::new();
+
+    let (requester, responder) = channel::<u32>();
+    let mut ends = Ends {
+        requester,
+        responders: vec![responder],
+        request: None,
+        response: None,
+    };
+
+    for step in 0..steps {
+        let diverged = |op: &'static str, real: String, model: String| {
+            Err(Divergence { seed, step, op, real, model })
+        };
+        let who = rng.below(ends.responders.len());
+
+        // Requests come and go more often than the channel changes shape,
+        // and closing it ends most of what a sequence can do.
+        match rng.below(24) {
+            0..=3 => {
+                let contract = ends.requester.try_request();
+                let real = format!("{:?}", contract.as_ref().map(|_| ()));
+                let expected = format!("{:?}", model.try_request());
+
+                if real != expected {
+                    mem::forget(contract);
+                    return diverged("try_request", real, expected);
+                }
+
+                if let Ok(contract) = contract {
+                    ends.request = Some(contract);
+                }
+            },
+            4..=7 => {
+                let contract = ends.responders[who].try_respond();
+                let real = format!("{:?}", contract.as_ref().map(|_| ()));
+                let expected = format!("{:?}", model.try_respond());
+
+                if real != expected {
+                    mem::forget(contract);
+                    return diverged("try_respond", real, expected);
+                }
+
+                if let Ok(contract) = contract {
+                    ends.response = Some(contract);
+                }
+            },
+            8..=10 if ends.response.is_some() => {
+                ends.response.take().unwrap().send(step as u32);
+                model.send(step as u32);
+            },
+            11..=14 if ends.request.is_some() => {
+                let real = format!("{:?}", ends.request.as_mut().unwrap().try_receive());
+                let expected = format!("{:?}", model.try_receive());
+
+                if real != expected {
+                    return diverged("try_receive", real, expected);
+                }
+            },
+            15..=16 if ends.request.is_some() => {
+                let real = format!("{:?}", ends.request.as_mut().unwrap().try_cancel());
+                let expected = format!("{:?}", model.try_cancel());
+
+                if real != expected {
+                    return diverged("try_cancel", real, expected);
+                }
+            },
+            17 if ends.request.is_some() => {
+                let contract = ends.request.take().unwrap();
+                let real = panic::catch_unwind(AssertUnwindSafe(|| drop(contract))).is_err();
+                let expected = model.drop_request_contract();
+
+                if real != expected {
+                    return diverged("drop_request_contract", real.to_string(), expected.to_string());
+                }
+            },
+            18 if ends.response.is_some() && rng.below(4) == 0 => {
+                let contract = ends.response.take().unwrap();
+                let real = panic::catch_unwind(AssertUnwindSafe(|| drop(contract))).is_err();
+                let expected = model.drop_response_contract();
+
+                if real != expected {
+                    return diverged("drop_response_contract", real.to_string(), expected.to_string());
+                }
+            },
+            19 => {
+                let responder = ends.responders[who].clone();
+                ends.responders.push(responder);
+            },
+            20 if ends.responders.len() > 1 => {
+                ends.responders.swap_remove(who);
+            },
+            21 if rng.below(16) == 0 => {
+                ends.requester.close();
+                model.close();
+            },
+            22 if ends.request.is_some() => {
+                let real = ends.request.as_ref().unwrap().responder_claimed();
+                let expected = model.responder_claimed();
+
+                if real != expected {
+                    return diverged("responder_claimed", real.to_string(), expected.to_string());
+                }
+            },
+            _ => {
+                let real = ends.responders[who].has_request();
+                let expected = model.has_request();
+
+                if real != expected {
+                    return diverged("has_request", real.to_string(), expected.to_string());
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_differential() {
+        for seed in 0..500 {
+            if let Err(divergence) = differential(seed, 200) {
+                panic!("The channel diverged from the model: {:?}", divergence);
+            }
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! This module defines a monitor, which reads counters every channel keeps
+//! about its own use. Each counter is a relaxed atomic bumped on a path the
+//! channel takes anyway, so keeping them costs one uncontended atomic add
+//! per event. The counters the requesting side bumps live next to its
+//! other state, and so do the responding side's.
+//!
+//! Each `Responder` also keeps its own tally of the claims it won and lost,
+//! which shows whether scheduling favors some responders over others.
+
+#[cfg(feature = "debug-watchdog")]
+use std::thread;
+#[cfg(feature = "debug-watchdog")]
+use std::time::Duration;
+
+use super::atomic::{AtomicU64, Ordering};
+use super::{Builder, Inner, Link, Requester, Responder, ResponderId};
+#[cfg(feature = "metrics")]
+use super::LatencyHistogram;
+#[cfg(feature = "debug-watchdog")]
+use super::{watchdog, ChannelState};
+
+/// This function creates a `reqchan` and returns a tuple containing its
+/// two ends and a `Monitor` of it.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder, monitor) = chan::channel_monitored::<u32>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+/// contract.try_receive().ok().unwrap();
+///
+/// let snapshot = monitor.snapshot();
+/// assert_eq!(snapshot.requests_issued, 1);
+/// assert_eq!(snapshot.responses_sent, 1);
+/// ```
+pub fn channel_monitored<T>() -> (Requester<T>, Responder<T>, Monitor<T>) {
+    Builder::new().build_monitored()
+}
+
+/// This handle reads the counters of a channel. It does not count as a
+/// `Requester` or `Responder`, and it does not lock anything.
+pub struct Monitor<T> {
+    inner: Link<T>,
+}
+
+impl<T> Monitor<T> {
+    #[inline]
+    pub(crate) fn new(inner: Link<T>) -> Monitor<T> {
+        Monitor { inner }
+    }
+
+    /// This method returns the current value of every counter.
+    ///
+    /// # Warning
+    ///
+    /// The counters are read one at a time while the channel keeps
+    /// running, so the snapshot may mix values from slightly different
+    /// moments.
+    pub fn snapshot(&self) -> Snapshot {
+        let request = &self.inner.request.counters;
+        let response = &self.inner.response.counters;
+
+        Snapshot {
+            requests_issued: request.issued.load(Ordering::Relaxed),
+            requests_cancelled: request.cancelled.load(Ordering::Relaxed),
+            responses_sent: response.sent.load(Ordering::Relaxed),
+            respond_lock_contention: response.contention.load(Ordering::Relaxed),
+            receive_empty_polls: request.empty_polls.load(Ordering::Relaxed),
+            datums_rejected: response.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    /// This method returns a copy of the channel's histogram of how long
+    /// single-response requests took, from `Requester::try_request()` to
+    /// the `RequestContract::try_receive()` that returned the datum. It is
+    /// only available with the `metrics` feature.
+    ///
+    /// # Warning
+    ///
+    /// The buckets are read one at a time while the channel keeps
+    /// running, so the copy may mix values from slightly different
+    /// moments.
+    #[cfg(feature = "metrics")]
+    pub fn latency_histogram(&self) -> LatencyHistogram {
+        self.inner.latency_histogram()
+    }
+
+    /// This method returns the claim statistics of every live
+    /// `Responder`, ordered by id. It locks the channel's roster of
+    /// responders, but not the claims themselves.
+    ///
+    /// # Warning
+    ///
+    /// A dropped responder's statistics go away with it, so the totals
+    /// only match `Snapshot::responses_sent` and
+    /// `Snapshot::respond_lock_contention` while every responder that
+    /// ever claimed a request is alive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder, monitor) = chan::channel_monitored::<u32>();
+    /// let responder2 = responder.clone();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// let response_contract = responder.try_respond().ok().unwrap();
+    /// assert!(responder2.try_respond().is_err());
+    ///
+    /// response_contract.send(1);
+    /// contract.try_receive().ok().unwrap();
+    ///
+    /// let stats = monitor.responder_stats();
+    /// assert_eq!((stats[0].attempted, stats[0].won), (1, 1));
+    /// assert_eq!((stats[1].attempted, stats[1].won), (1, 0));
+    /// ```
+    pub fn responder_stats(&self) -> Vec<ResponderStats> {
+        self.inner.responder_stats()
+    }
+
+    /// This method returns how long the channel has gone without progress
+    /// while a request is in flight, or `None` if no request is in flight.
+    /// It is only available with the `debug-watchdog` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder, monitor) = chan::channel_monitored::<u32>();
+    /// assert_eq!(monitor.stalled_for(), None);
+    ///
+    /// // Someone forgets a claimed request, so it never gets a datum.
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// std::mem::forget(responder.try_respond().ok().unwrap());
+    ///
+    /// assert!(contract.try_receive().is_err());
+    /// assert!(monitor.stalled_for().is_some());
+    /// # std::mem::forget(contract);
+    /// ```
+    #[cfg(feature = "debug-watchdog")]
+    pub fn stalled_for(&self) -> Option<Duration> {
+        self.inner.stalled_for()
+    }
+
+    /// This method spawns a thread that checks the channel every quarter
+    /// of `threshold`. When the channel has gone without progress for
+    /// `threshold` while a request is in flight, it calls `callback` with
+    /// a dump of the channel's state. It calls `callback` once per stall.
+    /// The thread exits once every handle of the channel, including every
+    /// `Monitor`, is dropped. It is only available with the
+    /// `debug-watchdog` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - How long the channel may go without progress
+    ///
+    /// * `callback` - The function to call with each stall
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::time::Duration;
+    ///
+    /// let (requester, responder, monitor) = chan::channel_monitored::<u32>();
+    ///
+    /// monitor.spawn_watchdog(Duration::from_secs(10), |state| {
+    ///     eprintln!("channel stuck: {:?}", state);
+    /// });
+    /// ```
+    #[cfg(feature = "debug-watchdog")]
+    pub fn spawn_watchdog<F>(&self, threshold: Duration, callback: F) -> thread::JoinHandle<()>
+        where T: Send + 'static,
+              F: FnMut(ChannelState) + Send + 'static
+    {
+        watchdog::spawn(&self.inner, threshold, callback)
+    }
+}
+
+impl<T> Clone for Monitor<T> {
+    fn clone(&self) -> Self {
+        Monitor { inner: self.inner.clone() }
+    }
+}
+
+/// This is the value of a channel's counters at one point in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The number of requests of any kind the requesting side issued.
+    pub requests_issued: u64,
+    /// The number of requests the requesting side successfully cancelled,
+    /// including partial cancellations of multi-response requests.
+    pub requests_cancelled: u64,
+    /// The number of data responders sent.
+    pub responses_sent: u64,
+    /// The number of times `Responder::try_respond()` returned
+    /// `Err(Error::AlreadyLocked)` because another responder got there first.
+    pub respond_lock_contention: u64,
+    /// The number of times a contract polled for a datum that had not
+    /// arrived yet.
+    pub receive_empty_polls: u64,
+    /// The number of data the channel's validator rejected.
+    pub datums_rejected: u64,
+}
+
+/// These are the claim statistics of one `Responder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponderStats {
+    /// The id of the responder.
+    pub id: ResponderId,
+    /// The number of times `Responder::try_respond()` found a request and
+    /// tried to claim it. Polls that found no request do not count.
+    pub attempted: u64,
+    /// The number of those attempts that claimed the request.
+    pub won: u64,
+}
+
+/// These are the counters only the requesting side bumps.
+#[doc(hidden)]
+pub(crate) struct RequestCounters {
+    issued: AtomicU64,
+    cancelled: AtomicU64,
+    empty_polls: AtomicU64,
+}
+
+impl RequestCounters {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> RequestCounters {
+            RequestCounters {
+                issued: AtomicU64::new(0),
+                cancelled: AtomicU64::new(0),
+                empty_polls: AtomicU64::new(0),
+            }
+        }
+    }
+}
+
+/// These are the counters the responding side bumps.
+#[doc(hidden)]
+pub(crate) struct ResponseCounters {
+    sent: AtomicU64,
+    contention: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl ResponseCounters {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> ResponseCounters {
+            ResponseCounters {
+                sent: AtomicU64::new(0),
+                contention: AtomicU64::new(0),
+                rejected: AtomicU64::new(0),
+            }
+        }
+    }
+}
+
+/// These are the claim counters of one `Responder`. Only that responder
+/// bumps them, so they sit in their own allocation.
+#[doc(hidden)]
+pub(crate) struct ClaimTally {
+    won: AtomicU64,
+    lost: AtomicU64,
+}
+
+impl ClaimTally {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> ClaimTally {
+            ClaimTally {
+                won: AtomicU64::new(0),
+                lost: AtomicU64::new(0),
+            }
+        }
+    }
+
+    /// This method counts a claim the responder won.
+    #[inline]
+    pub(crate) fn count_won(&self) {
+        self.won.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a claim another responder won first.
+    #[inline]
+    pub(crate) fn count_lost(&self) {
+        self.lost.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method returns the statistics of responder `id`.
+    #[inline]
+    pub(crate) fn stats(&self, id: ResponderId) -> ResponderStats {
+        let won = self.won.load(Ordering::Relaxed);
+
+        ResponderStats {
+            id,
+            attempted: won + self.lost.load(Ordering::Relaxed),
+            won,
+        }
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method counts a request the requesting side issued.
+    #[inline]
+    pub(crate) fn count_issued(&self) {
+        self.request.counters.issued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a request the requesting side cancelled.
+    #[inline]
+    pub(crate) fn count_cancelled(&self) {
+        self.request.counters.cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a poll for a datum that had not arrived yet.
+    #[inline]
+    pub(crate) fn count_empty_poll(&self) {
+        self.request.counters.empty_polls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a datum a responder sent.
+    #[inline]
+    pub(crate) fn count_sent(&self) {
+        self.response.counters.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a responder losing the race for a request.
+    #[inline]
+    pub(crate) fn count_contention(&self) {
+        self.response.counters.contention.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// This method counts a datum the channel's validator rejected.
+    #[inline]
+    pub(crate) fn count_rejected(&self) {
+        self.response.counters.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::Error;
+
+    #[test]
+    fn test_monitor_counts_requests() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+        let resp2 = resp.clone();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            match contract.try_receive() {
+                Err(Error::Empty) => {},
+                _ => { assert!(false); },
+            }
+
+            contract.try_cancel().ok().unwrap();
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match resp2.try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(1);
+        contract.try_receive().ok().unwrap();
+
+        assert_eq!(monitor.clone().snapshot(), Snapshot {
+            requests_issued: 2,
+            requests_cancelled: 1,
+            responses_sent: 1,
+            respond_lock_contention: 1,
+            receive_empty_polls: 1,
+            datums_rejected: 0,
+        });
+    }
+
+    #[test]
+    fn test_monitor_counts_many_and_broadcast() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+
+        {
+            let mut contract = rqst.try_request_many(2).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_cancel().ok().unwrap();
+            contract.try_receive().ok().unwrap();
+        }
+
+        {
+            let mut contract = rqst.try_request_all().ok().unwrap();
+
+            match contract.try_receive() {
+                Err(Error::Empty) => {},
+                _ => { assert!(false); },
+            }
+
+            resp.try_respond().ok().unwrap().send(2);
+            contract.try_receive().ok().unwrap();
+        }
+
+        let snapshot = monitor.snapshot();
+        assert_eq!(snapshot.requests_issued, 2);
+        assert_eq!(snapshot.requests_cancelled, 1);
+        assert_eq!(snapshot.responses_sent, 2);
+        assert_eq!(snapshot.receive_empty_polls, 1);
+    }
+
+    #[test]
+    fn test_monitor_responder_stats_skewed() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+        let resp2 = resp.clone();
+        let resp3 = resp.clone();
+
+        for i in 0..10 {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            // `resp` always gets there first, except every fifth round,
+            // when `resp3` does.
+            let (winner, losers) = if i % 5 == 4 {
+                (&resp3, [&resp, &resp2])
+            }
+            else {
+                (&resp, [&resp2, &resp3])
+            };
+
+            let resp_contract = winner.try_respond().ok().unwrap();
+
+            for loser in losers.iter() {
+                match loser.try_respond() {
+                    Err(Error::AlreadyLocked) => {},
+                    _ => { assert!(false); },
+                }
+            }
+
+            resp_contract.send(i);
+            contract.try_receive().ok().unwrap();
+        }
+
+        // Polls that find no request are not attempts.
+        match resp2.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let stats = monitor.responder_stats();
+        assert_eq!(stats, vec![
+            ResponderStats { id: resp.id(), attempted: 10, won: 8 },
+            ResponderStats { id: resp2.id(), attempted: 10, won: 0 },
+            ResponderStats { id: resp3.id(), attempted: 10, won: 2 },
+        ]);
+        assert_eq!(resp3.stats(), stats[2]);
+
+        let snapshot = monitor.snapshot();
+        let won = stats.iter().map(|stats| stats.won).sum::<u64>();
+        let lost = stats.iter().map(|stats| stats.attempted - stats.won).sum::<u64>();
+        assert_eq!(won, snapshot.responses_sent);
+        assert_eq!(lost, snapshot.respond_lock_contention);
+    }
+
+    #[test]
+    fn test_monitor_responder_stats_clone_and_drop() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+
+        {
+            let mut contract = rqst.try_request_many(1).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        // A clone starts with its own, empty statistics.
+        let resp2 = resp.clone();
+        assert_eq!(resp2.stats(), ResponderStats { id: resp2.id(), attempted: 0, won: 0 });
+        assert_eq!(monitor.responder_stats().len(), 2);
+
+        drop(resp);
+
+        assert_eq!(monitor.responder_stats(), vec![resp2.stats()]);
+
+        let weak = resp2.downgrade();
+        let resp3 = weak.upgrade().unwrap();
+        assert_eq!(monitor.responder_stats().len(), 2);
+        assert_eq!(monitor.responder_stats()[1].id, resp3.id());
+    }
+}
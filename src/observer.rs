@@ -0,0 +1,291 @@
+//! This module lets users watch a channel's events with their own code,
+//! without the crate picking a logging framework for them. A channel made
+//! with `channel_with_observer()` calls its observer after each event has
+//! taken effect, so the callback never runs in the middle of one of the
+//! channel's atomic sequences.
+//!
+//! A channel without an observer only checks that it has none, which
+//! costs a predictable branch per event.
+
+use std::sync::Arc;
+
+use super::{Builder, Inner, Requester, Responder, ResponderId, ValidationError};
+
+/// This trait receives the events of a channel.
+///
+/// # Warning
+///
+/// The requesting and responding sides call it from their own threads,
+/// so events from different sides may arrive out of order. For example,
+/// `ChannelEvent::DatumReceived` can arrive before the matching
+/// `ChannelEvent::DatumSent`.
+pub trait ChannelObserver {
+    /// This method is called once for every event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event that just happened
+    fn on_event(&self, event: ChannelEvent);
+
+    /// This method is called once for every event, along with the
+    /// sequence number of the request it belongs to, as returned by
+    /// `RequestContract::sequence()`. By default it just calls
+    /// `ChannelObserver::on_event()`, so only observers that correlate
+    /// events need to implement it.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence` - The sequence number of the event's request
+    ///
+    /// * `event` - The event that just happened
+    fn on_sequenced_event(&self, sequence: u64, event: ChannelEvent) {
+        let _ = sequence;
+        self.on_event(event);
+    }
+}
+
+/// This is an event of a channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelEvent {
+    /// The requesting side issued a request of any kind.
+    RequestIssued,
+    /// The requesting side cancelled a request, or the unclaimed part of
+    /// one.
+    RequestCancelled,
+    /// A responder claimed a request.
+    ResponseClaimed(ResponderId),
+    /// A responder dropped a `ResponseContract` without sending a datum.
+    /// The drop panics right after this event, or hands the claim back if
+    /// the thread is already panicking.
+    ResponseAborted(ResponderId),
+    /// A responder sent a datum.
+    DatumSent(ResponderId),
+    /// The requesting side received a datum.
+    DatumReceived,
+    /// The channel's validator rejected a datum a responder tried to
+    /// send. The request went back to the other responders.
+    DatumRejected(ResponderId, ValidationError),
+}
+
+/// This function creates a `reqchan` that reports its events to
+/// `observer`, and returns a tuple containing its two ends.
+///
+/// # Arguments
+///
+/// * `observer` - The observer of the channel's events
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::sync::{Arc, Mutex};
+///
+/// struct Log(Mutex<Vec<chan::ChannelEvent>>);
+///
+/// impl chan::ChannelObserver for Log {
+///     fn on_event(&self, event: chan::ChannelEvent) {
+///         self.0.lock().unwrap().push(event);
+///     }
+/// }
+///
+/// let log = Arc::new(Log(Mutex::new(Vec::new())));
+/// let (requester, responder) = chan::channel_with_observer::<u32>(log.clone());
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+/// contract.try_receive().ok().unwrap();
+///
+/// assert_eq!(*log.0.lock().unwrap(), vec![
+///     chan::ChannelEvent::RequestIssued,
+///     chan::ChannelEvent::ResponseClaimed(responder.id()),
+///     chan::ChannelEvent::DatumSent(responder.id()),
+///     chan::ChannelEvent::DatumReceived,
+/// ]);
+/// ```
+pub fn channel_with_observer<T>(observer: Arc<dyn ChannelObserver + Send + Sync>)
+                                -> (Requester<T>, Responder<T>) {
+    Builder::new().observer(observer).build()
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method reports an event to the channel's observer, if any.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** call it after the event has taken effect.
+    #[inline]
+    pub(crate) fn notify(&self, event: ChannelEvent) {
+        #[cfg(feature = "tracing")]
+        self.trace(event);
+
+        #[cfg(feature = "debug-watchdog")]
+        self.stamp_progress();
+
+        if let Some(ref observer) = self.observer {
+            report(observer, self.sequence(), event);
+        }
+    }
+}
+
+/// This function calls an observer. It stays out of line so channels
+/// without an observer only pay for the check.
+#[cold]
+#[inline(never)]
+fn report(observer: &Arc<dyn ChannelObserver + Send + Sync>, sequence: u64, event: ChannelEvent) {
+    observer.on_sequenced_event(sequence, event);
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct Log(Mutex<Vec<ChannelEvent>>);
+
+    impl ChannelObserver for Log {
+        fn on_event(&self, event: ChannelEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    impl Log {
+        fn take(&self) -> Vec<ChannelEvent> {
+            self.0.lock().unwrap().drain(..).collect()
+        }
+    }
+
+    #[test]
+    fn test_observer_sequence() {
+        struct SeqLog(Mutex<Vec<(u64, ChannelEvent)>>);
+
+        impl ChannelObserver for SeqLog {
+            fn on_event(&self, _event: ChannelEvent) {
+                unreachable!();
+            }
+
+            fn on_sequenced_event(&self, sequence: u64, event: ChannelEvent) {
+                self.0.lock().unwrap().push((sequence, event));
+            }
+        }
+
+        let log = Arc::new(SeqLog(Mutex::new(Vec::new())));
+        let (rqst, resp) = channel_with_observer::<u32>(log.clone());
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            contract.try_cancel().ok().unwrap();
+        }
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert_eq!(*log.0.lock().unwrap(), vec![
+            (1, ChannelEvent::RequestIssued),
+            (1, ChannelEvent::RequestCancelled),
+            (2, ChannelEvent::RequestIssued),
+            (2, ChannelEvent::ResponseClaimed(resp.id())),
+            (2, ChannelEvent::DatumSent(resp.id())),
+            (2, ChannelEvent::DatumReceived),
+        ]);
+    }
+
+    #[test]
+    fn test_observer_cancel() {
+        let log = Arc::new(Log(Mutex::new(Vec::new())));
+        let (rqst, resp) = channel_with_observer::<u32>(log.clone());
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            contract.try_cancel().ok().unwrap();
+        }
+
+        {
+            let mut contract = rqst.try_request_many(2).ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_cancel().ok().unwrap();
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert_eq!(log.take(), vec![
+            ChannelEvent::RequestIssued,
+            ChannelEvent::RequestCancelled,
+            ChannelEvent::RequestIssued,
+            ChannelEvent::ResponseClaimed(resp.id()),
+            ChannelEvent::DatumSent(resp.id()),
+            ChannelEvent::RequestCancelled,
+            ChannelEvent::DatumReceived,
+        ]);
+    }
+
+    #[test]
+    fn test_observer_broadcast_and_held() {
+        let log = Arc::new(Log(Mutex::new(Vec::new())));
+        let (rqst, resp) = channel_with_observer::<u32>(log.clone());
+        let resp2 = resp.clone();
+
+        {
+            let mut contract = rqst.try_request_all().ok().unwrap();
+            resp2.try_respond().ok().unwrap().send(1);
+            resp.try_respond().ok().unwrap().send(2);
+            contract.try_receive().ok().unwrap();
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert_eq!(log.take(), vec![
+            ChannelEvent::RequestIssued,
+            ChannelEvent::ResponseClaimed(resp2.id()),
+            ChannelEvent::DatumSent(resp2.id()),
+            ChannelEvent::ResponseClaimed(resp.id()),
+            ChannelEvent::DatumSent(resp.id()),
+            ChannelEvent::DatumReceived,
+            ChannelEvent::DatumReceived,
+        ]);
+
+        let mut held = {
+            let mut contract = rqst.try_request().ok().unwrap();
+            let held = resp.try_respond().ok().unwrap().send_and_hold(3);
+            contract.try_receive().ok().unwrap();
+            held
+        };
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        held.try_next().ok().unwrap().send(4);
+        contract.try_receive().ok().unwrap();
+
+        let events = log.take();
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[5], ChannelEvent::ResponseClaimed(resp.id()));
+    }
+
+    #[test]
+    fn test_observer_response_aborted() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let log = Arc::new(Log(Mutex::new(Vec::new())));
+        let (rqst, resp) = channel_with_observer::<u32>(log.clone());
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            #[allow(unused_variables)]
+            let resp_contract = resp.try_respond().ok().unwrap();
+        }));
+        assert!(res.is_err());
+
+        assert_eq!(log.take().last(), Some(&ChannelEvent::ResponseAborted(resp.id())));
+
+        // The claimed request can never be answered now.
+        match contract.try_cancel() {
+            Err(super::super::Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+        contract.done = true;
+    }
+}
@@ -0,0 +1,270 @@
+//! This module defines a channel whose responders do not lock the
+//! responding side while they prepare a datum. Every responder that sees
+//! a single-response request prepares its answer, and the claim happens
+//! only when it publishes: the first to publish wins, and the others get
+//! their datum back.
+//!
+//! This pays off when preparing a datum is cheap but its cost varies, or
+//! when a responder may be descheduled while it prepares: with
+//! `channel()` the responder that claimed the request first makes the
+//! requester wait for it, while here the fastest responder serves it. The
+//! `optimistic` benchmark pits the two modes against each other with
+//! responders of uneven speed. It needs a core per responder to show the
+//! difference: on a single core, where the responders take turns, both
+//! modes came out within a few percent of each other. When preparing a
+//! datum is expensive, the work the losers throw away makes the locked
+//! mode the better choice.
+//!
+//! The requesting end is a regular `Requester`. Requests for several
+//! responses, and broadcasts, are claimed before the datum is prepared,
+//! as with `channel()`.
+
+use std::result;
+use std::sync::Arc;
+
+use super::monitor::ClaimTally;
+use super::{ChannelEvent, Claim, Error, Inner, Link, Requester, Responder, ResponderId,
+            ResponseContract, Result, SendError, State};
+
+/// This function creates a `reqchan` whose responders race to publish
+/// their data instead of claiming requests first, and returns a tuple
+/// containing its two ends.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel_optimistic::<u32>();
+/// let responder2 = responder.clone();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+///
+/// // Both responders get to prepare a datum.
+/// let first = responder.try_respond().ok().unwrap();
+/// let second = responder2.try_respond().ok().unwrap();
+///
+/// assert_eq!(second.send(2), Ok(()));
+/// assert_eq!(first.send(1), Err(1));
+///
+/// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+/// ```
+pub fn channel_optimistic<T>() -> (Requester<T>, OptimisticResponder<T>) {
+    let inner = Link::Shared(Arc::new(Inner::new()));
+
+    (
+        Requester::new(inner.clone()),
+        OptimisticResponder { responder: Responder::new(inner) },
+    )
+}
+
+/// This is the responding end of a channel from `channel_optimistic()`.
+pub struct OptimisticResponder<T> {
+    responder: Responder<T>,
+}
+
+impl<T> OptimisticResponder<T> {
+    /// This method returns an `OptimisticContract` if the requester has a
+    /// pending request. Several responders may hold a contract for the
+    /// same single-response request at once, so it never returns
+    /// `Err(Error::AlreadyLocked)`.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::NoRequest)` while another responder
+    /// publishes its datum. Holds and priorities of plain `Responder`s on
+    /// the same channel are only honoured when a contract publishes.
+    pub fn try_respond(&self) -> Result<OptimisticContract<T>> {
+        let inner = &self.responder.inner;
+
+        if self.responder.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if inner.is_paused() {
+            return Err(Error::Paused);
+        }
+
+        // Leave the claim of a single-response request until `send()`.
+        if inner.state() == State::Requested {
+            return Ok(OptimisticContract {
+                inner: inner.clone(),
+                responder: self.responder.id(),
+                tally: self.responder.tally.clone(),
+                sequence: inner.sequence(),
+                claimed: None,
+            });
+        }
+
+        match self.responder.try_respond() {
+            Ok(contract) => Ok(OptimisticContract {
+                inner: inner.clone(),
+                responder: self.responder.id(),
+                tally: self.responder.tally.clone(),
+                sequence: contract.sequence,
+                claimed: Some(contract),
+            }),
+            // The single-response request is being answered.
+            Err(Error::AlreadyLocked) => Err(Error::NoRequest),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// This method works just like `Responder::id()`.
+    #[inline]
+    pub fn id(&self) -> ResponderId {
+        self.responder.id()
+    }
+
+    /// This method works just like `Responder::has_request()`.
+    #[inline]
+    pub fn has_request(&self) -> bool {
+        self.responder.has_request()
+    }
+
+    /// This method works just like `Responder::close()`.
+    #[inline]
+    pub fn close(&self) {
+        self.responder.close()
+    }
+}
+
+impl<T> Clone for OptimisticResponder<T> {
+    fn clone(&self) -> Self {
+        OptimisticResponder { responder: self.responder.clone() }
+    }
+}
+
+/// This is the contract returned by a successful
+/// `OptimisticResponder::try_respond()`. Until it sends, it holds no lock
+/// on a single-response request, so dropping it is harmless. A contract
+/// for part of a multi-response request, or for a broadcast, did claim
+/// its response, and panics just like a `ResponseContract` if it is
+/// dropped before it sends.
+pub struct OptimisticContract<T> {
+    inner: Link<T>,
+    responder: ResponderId,
+    tally: Arc<ClaimTally>,
+    // The sequence number of the request the contract answers.
+    sequence: u64,
+    // The contract of a response claimed up front.
+    claimed: Option<ResponseContract<T>>,
+}
+
+impl<T> OptimisticContract<T> {
+    /// This method returns the sequence number of the request this
+    /// contract answers.
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// This method tries to publish `datum`. Only the first responder to
+    /// publish for a request wins; every other one gets its datum back,
+    /// as does a responder whose request was cancelled or answered in the
+    /// meantime.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send(mut self, datum: T) -> result::Result<(), T> {
+        if let Some(mut contract) = self.claimed.take() {
+            return contract.try_send(datum).map_err(|SendError(datum)| datum);
+        }
+
+        let inner = &self.inner;
+
+        // Skip the claim for a request that is already gone.
+        if inner.sequence() != self.sequence {
+            self.tally.count_lost();
+            return Err(datum);
+        }
+
+        if let Err(err) = inner.try_claim_request() {
+            if let Error::AlreadyLocked = err {
+                inner.count_contention();
+            }
+            self.tally.count_lost();
+            return Err(datum);
+        }
+
+        // The request may have been replaced since the contract saw it.
+        if inner.sequence() != self.sequence {
+            inner.unclaim_request();
+            self.tally.count_lost();
+            return Err(datum);
+        }
+
+        self.tally.count_won();
+        inner.notify(ChannelEvent::ResponseClaimed(self.responder));
+
+        let mut contract = ResponseContract {
+            inner: inner.clone(),
+            responder: self.responder,
+            amount: inner.get_amount(),
+            urgency: inner.get_urgency(),
+            sequence: self.sequence,
+            deadline: inner.get_deadline(),
+            tag: inner.get_tag(),
+            claim: Claim::Single,
+            lease: inner.publish_claim(),
+            held: 0,
+            done: false,
+        };
+
+        contract.try_send(datum).map_err(|SendError(datum)| datum)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimistic_stale_contract() {
+        let (rqst, resp) = channel_optimistic::<u32>();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let stale = resp.try_respond().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1).ok().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        // A contract for an earlier request cannot answer the next one.
+        let mut contract = rqst.try_request().ok().unwrap();
+        assert_eq!(stale.send(2), Err(2));
+
+        let fresh = resp.try_respond().ok().unwrap();
+        assert_eq!(fresh.sequence(), contract.sequence());
+        fresh.send(3).ok().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+
+        let stats = resp.responder.stats();
+        assert_eq!((stats.attempted, stats.won), (3, 2));
+    }
+
+    #[test]
+    fn test_optimistic_many() {
+        let (rqst, resp) = channel_optimistic::<u32>();
+        let resp2 = resp.clone();
+
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+
+        // Each part of the request is claimed up front.
+        let first = resp.try_respond().ok().unwrap();
+        let second = resp2.try_respond().ok().unwrap();
+        second.send(2).ok().unwrap();
+        first.send(1).ok().unwrap();
+
+        let mut received = vec![contract.try_receive().ok().unwrap(),
+                                contract.try_receive().ok().unwrap()];
+        received.sort();
+        assert_eq!(received, vec![1, 2]);
+    }
+}
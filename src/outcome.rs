@@ -0,0 +1,149 @@
+//! This module lets responders learn what became of the latest request
+//! once it is no longer pending: a responder served it, the requesting
+//! side cancelled it, or it expired. Workers that lost the race for a
+//! request can then tell "try again" from "stop".
+//!
+//! Each outcome is stamped with the sequence number of its request, so a
+//! responder never mistakes the outcome of an earlier request for that of
+//! the current one.
+//!
+//! Requests for several responses, and broadcasts, are not covered.
+
+use super::atomic::Ordering;
+use super::{Inner, Responder};
+
+/// This is why the requesting side cancelled a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CancelReason {
+    /// The requesting side gave no reason, as with
+    /// `RequestContract::try_cancel()`.
+    Unspecified,
+    /// The requesting side stopped waiting for a datum.
+    Timeout,
+    /// The requesting side is shutting down, or closed the channel.
+    Shutdown,
+}
+
+/// This is what became of a request once it was no longer pending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RequestOutcome {
+    /// A responder sent a datum for it.
+    Served,
+    /// The requesting side cancelled it.
+    Cancelled(CancelReason),
+    /// Its time to live ran out before any responder claimed it.
+    Expired,
+}
+
+const SERVED: u64 = 1;
+const EXPIRED: u64 = 2;
+const CANCELLED: u64 = 3;
+
+const CODE_BITS: u32 = 8;
+const CODE_MASK: u64 = (1 << CODE_BITS) - 1;
+
+impl RequestOutcome {
+    #[inline]
+    fn to_code(self) -> u64 {
+        match self {
+            RequestOutcome::Served => SERVED,
+            RequestOutcome::Expired => EXPIRED,
+            RequestOutcome::Cancelled(CancelReason::Unspecified) => CANCELLED,
+            RequestOutcome::Cancelled(CancelReason::Timeout) => CANCELLED + 1,
+            RequestOutcome::Cancelled(CancelReason::Shutdown) => CANCELLED + 2,
+        }
+    }
+
+    #[inline]
+    fn from_code(code: u64) -> Option<RequestOutcome> {
+        match code {
+            SERVED => Some(RequestOutcome::Served),
+            EXPIRED => Some(RequestOutcome::Expired),
+            CANCELLED => Some(RequestOutcome::Cancelled(CancelReason::Unspecified)),
+            code if code == CANCELLED + 1 => Some(RequestOutcome::Cancelled(CancelReason::Timeout)),
+            code if code == CANCELLED + 2 => Some(RequestOutcome::Cancelled(CancelReason::Shutdown)),
+            _ => None,
+        }
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method records what became of the request with `sequence`.
+    ///
+    /// # Arguments
+    ///
+    /// * sequence - The sequence number of the request
+    ///
+    /// * outcome - What became of it
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** call it for a single-response request, at the moment it
+    /// stops being pending.
+    #[inline]
+    pub(crate) fn record_outcome(&self, sequence: u64, outcome: RequestOutcome) {
+        self.request.outcome.store(sequence << CODE_BITS | outcome.to_code(), Ordering::Release);
+    }
+
+    /// This method forgets the outcome of the request with `sequence`,
+    /// because it is pending again.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    pub(crate) fn clear_outcome(&self, sequence: u64) {
+        self.request.outcome.store(sequence << CODE_BITS, Ordering::Release);
+    }
+
+    /// This method returns what became of the current request, or `None`
+    /// if it is still pending.
+    #[inline]
+    fn outcome(&self) -> Option<RequestOutcome> {
+        let outcome = self.request.outcome.load(Ordering::Acquire);
+
+        if outcome >> CODE_BITS != self.sequence() {
+            return None;
+        }
+
+        RequestOutcome::from_code(outcome & CODE_MASK)
+    }
+}
+
+impl<T> Responder<T> {
+    /// This method returns what became of the latest request the
+    /// requesting side issued, or `None` if it is still pending, if no
+    /// request was issued yet, or if it asked for several responses or
+    /// was a broadcast.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use chan::{CancelReason, RequestOutcome};
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let sibling = responder.clone();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert_eq!(responder.last_request_outcome(), None);
+    ///
+    /// sibling.try_respond().ok().unwrap().send(1);
+    /// contract.try_receive().ok().unwrap();
+    /// drop(contract);
+    /// assert_eq!(responder.last_request_outcome(), Some(RequestOutcome::Served));
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert_eq!(responder.last_request_outcome(), None);
+    ///
+    /// contract.try_cancel_with(CancelReason::Shutdown).ok().unwrap();
+    /// assert_eq!(responder.last_request_outcome(),
+    ///            Some(RequestOutcome::Cancelled(CancelReason::Shutdown)));
+    /// ```
+    #[inline]
+    pub fn last_request_outcome(&self) -> Option<RequestOutcome> {
+        self.inner.outcome()
+    }
+}
@@ -0,0 +1,362 @@
+//! This module defines `Pool`, a minimal work-sharing scheduler built the
+//! way the crate's bigger example is: every worker owns one channel, idle
+//! workers request tasks on their own channels, and busy workers answer
+//! those requests out of their local queues between tasks. It is only
+//! built with the `pool` feature.
+//!
+//! Spawned tasks go into a shared queue, which idle workers take batches
+//! from. A worker that runs out of work asks its peers for half of what
+//! they have, and parks once it has waited a while without getting any.
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::atomic::{AtomicBool, AtomicUsize, Ordering};
+use super::backoff::Backoff;
+use super::{channel_monitored, Error, Monitor, RequestContract, Requester,
+            Responder, Task};
+
+/// This is how long a parked worker sleeps before it looks around again,
+/// in case it missed a wakeup.
+const PARK_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// This is the state the pool and its workers share.
+struct Shared {
+    injector: Mutex<VecDeque<Task>>,
+    pending: AtomicUsize,
+    closing: AtomicBool,
+    parked: Vec<AtomicBool>,
+}
+
+impl Shared {
+    /// This method moves a fair share of the spawned tasks into `local`.
+    /// It returns whether it moved any.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The queue of the worker taking the tasks
+    fn take_injected(&self, local: &mut Vec<Task>) -> bool {
+        let mut injector = self.injector.lock().unwrap();
+
+        if injector.is_empty() {
+            return false;
+        }
+
+        let workers = self.parked.len();
+        let n = injector.len().div_ceil(workers);
+        local.extend(injector.drain(..n));
+
+        true
+    }
+
+    #[inline]
+    fn has_injected(&self) -> bool {
+        !self.injector.lock().unwrap().is_empty()
+    }
+
+    /// This method tells whether the pool is shutting down and every task
+    /// it was given has run.
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.closing.load(Ordering::SeqCst) && self.pending.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// This is one worker thread's end of the pool.
+struct Worker {
+    index: usize,
+    shared: Arc<Shared>,
+    requester: Requester<Vec<Task>>,
+    peers: Vec<Responder<Vec<Task>>>,
+}
+
+impl Worker {
+    fn work(self) {
+        let me = thread::current();
+        self.requester.set_notify(move || { me.unpark(); });
+
+        let mut local = Vec::new();
+        let mut contract: Option<RequestContract<Vec<Task>>> = None;
+        let mut backoff = Backoff::new();
+
+        loop {
+            // Busy workers share before running their next task.
+            if local.len() >= 2 {
+                for peer in &self.peers {
+                    let _ = peer.respond_half(&mut local);
+                }
+            }
+
+            if let Some(task) = local.pop() {
+                self.run(task);
+                backoff = Backoff::new();
+                continue;
+            }
+
+            let stolen = match contract {
+                Some(ref mut contract) => contract.try_receive().ok(),
+                None => None,
+            };
+
+            if let Some(tasks) = stolen {
+                contract = None;
+                local = tasks;
+                continue;
+            }
+
+            if self.shared.take_injected(&mut local) {
+                // Tasks that a peer already committed to will still come.
+                if let Some(mut pending) = contract.take() {
+                    if let Err(Error::TooLate) = pending.try_cancel() {
+                        contract = Some(pending);
+                    }
+                }
+                continue;
+            }
+
+            if self.shared.is_finished() {
+                break;
+            }
+
+            if contract.is_none() {
+                contract = self.requester.try_request_amount(usize::MAX).ok();
+            }
+
+            if backoff.is_completed() {
+                self.park();
+            }
+            else {
+                backoff.snooze();
+            }
+        }
+
+        // Every task has run, so no peer can have claimed the request.
+        if let Some(mut contract) = contract {
+            contract.try_cancel().ok().unwrap();
+        }
+    }
+
+    /// This method runs `task`. A task that panics only loses itself.
+    fn run(&self, task: Task) {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| task.run()));
+        self.shared.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn park(&self) {
+        let parked = &self.shared.parked[self.index];
+        parked.store(true, Ordering::SeqCst);
+
+        // A task spawned before the flag went up would not wake us.
+        if !self.shared.has_injected() && !self.shared.is_finished() {
+            thread::park_timeout(PARK_TIMEOUT);
+        }
+
+        parked.store(false, Ordering::SeqCst);
+    }
+}
+
+/// This is a fixed set of worker threads that share tasks through
+/// `reqchan`s.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let pool = chan::pool::Pool::new(4);
+/// let count = Arc::new(AtomicUsize::new(0));
+///
+/// for _ in 0..100 {
+///     let count = count.clone();
+///     pool.spawn(move || { count.fetch_add(1, Ordering::SeqCst); });
+/// }
+///
+/// // Joining runs every task that was spawned.
+/// pool.join();
+/// assert_eq!(count.load(Ordering::SeqCst), 100);
+/// ```
+pub struct Pool {
+    shared: Arc<Shared>,
+    monitors: Vec<Monitor<Vec<Task>>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl Pool {
+    /// This method starts a pool with `n_workers` threads.
+    ///
+    /// # Warning
+    ///
+    /// This method panics if `n_workers` is zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `n_workers` - The number of worker threads
+    pub fn new(n_workers: usize) -> Pool {
+        assert!(n_workers > 0, "a pool needs at least one worker");
+
+        let shared = Arc::new(Shared {
+            injector: Mutex::new(VecDeque::new()),
+            pending: AtomicUsize::new(0),
+            closing: AtomicBool::new(false),
+            parked: (0..n_workers).map(|_| AtomicBool::new(false)).collect(),
+        });
+
+        let mut requesters = Vec::with_capacity(n_workers);
+        let mut responders = Vec::with_capacity(n_workers);
+        let mut monitors = Vec::with_capacity(n_workers);
+
+        for _ in 0..n_workers {
+            let (requester, responder, monitor) = channel_monitored();
+            requesters.push(requester);
+            responders.push(responder);
+            monitors.push(monitor);
+        }
+
+        let workers = requesters.into_iter().enumerate().map(|(index, requester)| {
+            let worker = Worker {
+                index,
+                shared: shared.clone(),
+                requester,
+                peers: responders.iter()
+                    .enumerate()
+                    .filter(|&(peer, _)| peer != index)
+                    .map(|(_, responder)| responder.clone())
+                    .collect(),
+            };
+
+            thread::spawn(move || worker.work())
+        }).collect();
+
+        Pool {
+            shared,
+            monitors,
+            workers,
+        }
+    }
+
+    /// This method hands `task` to the pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `task` - The task to run, or a closure to make one from
+    pub fn spawn<F: Into<Task>>(&self, task: F) {
+        self.shared.pending.fetch_add(1, Ordering::SeqCst);
+        self.shared.injector.lock().unwrap().push_back(task.into());
+        self.unpark_one();
+    }
+
+    /// This method returns the number of worker threads.
+    #[inline]
+    pub fn worker_count(&self) -> usize {
+        self.monitors.len()
+    }
+
+    /// This method returns the monitors of the workers' channels, in the
+    /// order of the workers. A worker's channel carries the tasks its
+    /// peers give it.
+    #[inline]
+    pub fn monitors(&self) -> &[Monitor<Vec<Task>>] {
+        &self.monitors
+    }
+
+    /// This method waits until every task spawned so far has run, and then
+    /// stops the workers. Dropping the pool does the same.
+    ///
+    /// # Warning
+    ///
+    /// A task that panics is dropped with its panic, so `join()` does not
+    /// report it.
+    pub fn join(self) {}
+
+    fn unpark_one(&self) {
+        for (parked, worker) in self.shared.parked.iter().zip(&self.workers) {
+            if parked.load(Ordering::SeqCst) {
+                worker.thread().unpark();
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.shared.closing.store(true, Ordering::SeqCst);
+
+        for worker in &self.workers {
+            worker.thread().unpark();
+        }
+
+        for worker in self.workers.drain(..) {
+            // Workers catch their tasks' panics, so this cannot fail
+            // unless the pool itself is broken.
+            worker.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_pool_runs_every_task() {
+        let pool = Pool::new(3);
+        let count = Arc::new(StdAtomicUsize::new(0));
+
+        for _ in 0..1000 {
+            let count = count.clone();
+            pool.spawn(move || {
+                count.fetch_add(1, Ordering::SeqCst);
+                thread::yield_now();
+            });
+        }
+
+        pool.join();
+        assert_eq!(count.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_pool_survives_panicking_task() {
+        let pool = Pool::new(1);
+        let count = Arc::new(StdAtomicUsize::new(0));
+
+        pool.spawn(|| { panic!("task failed"); });
+
+        let count2 = count.clone();
+        pool.spawn(Task::new(move || { count2.fetch_add(1, Ordering::SeqCst); }));
+
+        pool.join();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_pool_monitors() {
+        let pool = Pool::new(4);
+        assert_eq!(pool.worker_count(), 4);
+        assert_eq!(pool.monitors().len(), 4);
+
+        for _ in 0..200 {
+            pool.spawn(|| { thread::sleep(Duration::from_micros(50)); });
+        }
+
+        let monitors = pool.monitors().to_vec();
+        pool.join();
+
+        // Every batch a worker received was sent by one of its peers.
+        for monitor in &monitors {
+            let snapshot = monitor.snapshot();
+            assert!(snapshot.responses_sent <= snapshot.requests_issued);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! This module defines helpers for responders that serve requests out of
+//! a local queue, the use the channel's contracts were designed around:
+//! a responder only takes items out of its queue once it has claimed a
+//! request, so an item never leaves the queue without going to the
+//! requester.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+
+use super::{Responder, Result};
+
+impl<T> Responder<T> {
+    /// This method sends the oldest item of `queue` if there is a request
+    /// to send it to. It returns `Ok(true)` if it sent an item, and
+    /// `Ok(false)` without claiming anything if `queue` is empty. If it
+    /// cannot claim a request, it returns the same error as
+    /// `Responder::try_respond()` and leaves `queue` untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - The queue to serve from
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::collections::VecDeque;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let mut queue = (0..3).collect::<VecDeque<_>>();
+    ///
+    /// // Nothing leaves the queue without a request.
+    /// match responder.respond_from(&mut queue) {
+    ///     Err(chan::Error::NoRequest) => {},
+    ///     _ => unreachable!(),
+    /// }
+    /// assert_eq!(queue.len(), 3);
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert!(responder.respond_from(&mut queue).ok().unwrap());
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 0);
+    /// assert_eq!(queue.len(), 2);
+    /// ```
+    pub fn respond_from(&self, queue: &mut VecDeque<T>) -> Result<bool> {
+        self.respond_from_with(queue, |queue| queue.pop_front().unwrap())
+    }
+
+    /// This method works just like `Responder::respond_from()`, but makes
+    /// the datum with `f`, which it only calls after claiming a request,
+    /// with `queue` known not to be empty. `f` may take more than one item,
+    /// or items of another type than the datum's.
+    ///
+    /// # Warning
+    ///
+    /// If `f` panics, the claim is handed back just like in
+    /// `Responder::try_respond_with()`, but whatever `f` already took out
+    /// of `queue` is lost.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - The queue to serve from
+    ///
+    /// * `f` - The function that takes the datum out of `queue`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::collections::VecDeque;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let mut stack = (0..3).collect::<VecDeque<_>>();
+    ///
+    /// // Serve the newest item instead of the oldest.
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder.respond_from_with(&mut stack, |s| s.pop_back().unwrap()).ok().unwrap();
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    /// ```
+    pub fn respond_from_with<U, F>(&self, queue: &mut VecDeque<U>, f: F) -> Result<bool>
+        where F: FnOnce(&mut VecDeque<U>) -> T
+    {
+        if queue.is_empty() {
+            return Ok(false);
+        }
+
+        self.try_respond_with(|| f(queue)).map(|()| true)
+    }
+}
+
+impl<T> Responder<Vec<T>> {
+    /// This method gives the requester the older half of `local`, as
+    /// work-stealing schedulers do with a thief, but no more items than
+    /// the request's amount hint. It returns how many items it sent. If
+    /// `local` has fewer than two items, it returns `Ok(0)` without
+    /// claiming anything, so the request is left for a responder with
+    /// more to give. If it cannot claim a request, it returns the same
+    /// error as `Responder::try_respond()` and leaves `local` untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The queue to steal from, oldest item first
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<Vec<u32>>();
+    /// let mut local = (0..10).collect::<Vec<_>>();
+    ///
+    /// let mut contract = requester.try_request_amount(usize::MAX).ok().unwrap();
+    /// assert_eq!(responder.respond_half(&mut local).ok().unwrap(), 5);
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), vec![0, 1, 2, 3, 4]);
+    /// assert_eq!(local, vec![5, 6, 7, 8, 9]);
+    /// ```
+    pub fn respond_half(&self, local: &mut Vec<T>) -> Result<usize> {
+        if local.len() < 2 {
+            return Ok(0);
+        }
+
+        let contract = self.try_respond()?;
+        let n = cmp::min(local.len() / 2, contract.amount_requested());
+
+        let newer = local.split_off(n);
+        contract.send(mem::replace(local, newer));
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::{channel, Error};
+
+    #[test]
+    fn test_respond_from_failed_claim_pops_nothing() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+        let mut queue = (0..4).collect::<VecDeque<_>>();
+
+        match resp.respond_from(&mut queue) {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let resp_contract = resp2.try_respond().ok().unwrap();
+
+        match resp.respond_from(&mut queue) {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        match resp.respond_from_with(&mut queue, |_| -> u32 { unreachable!() }) {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        assert_eq!(queue, (0..4).collect::<VecDeque<_>>());
+
+        resp_contract.send(9);
+        assert_eq!(contract.try_receive().ok().unwrap(), 9);
+    }
+
+    #[test]
+    fn test_respond_from_empty_queue_claims_nothing() {
+        let (rqst, resp) = channel::<u32>();
+        let mut queue = VecDeque::new();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        assert!(!resp.respond_from(&mut queue).ok().unwrap());
+
+        // The request is still there for someone else.
+        assert!(resp.has_request());
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_respond_half() {
+        let (rqst, resp) = channel::<Vec<u32>>();
+        let mut local = (0..9).collect::<Vec<_>>();
+
+        match resp.respond_half(&mut local) {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(local.len(), 9);
+
+        // The thief gets the older half, rounded down.
+        {
+            let mut contract = rqst.try_request_amount(100).ok().unwrap();
+            assert_eq!(resp.respond_half(&mut local).ok().unwrap(), 4);
+            assert_eq!(contract.try_receive().ok().unwrap(), vec![0, 1, 2, 3]);
+            assert_eq!(local, vec![4, 5, 6, 7, 8]);
+        }
+
+        // The amount hint caps the half.
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            assert_eq!(resp.respond_half(&mut local).ok().unwrap(), 1);
+            assert_eq!(contract.try_receive().ok().unwrap(), vec![4]);
+            assert_eq!(local, vec![5, 6, 7, 8]);
+        }
+    }
+
+    #[test]
+    fn test_respond_half_declines_small_queue() {
+        let (rqst, resp) = channel::<Vec<u32>>();
+        let mut contract = rqst.try_request_amount(4).ok().unwrap();
+
+        let mut local = Vec::new();
+        assert_eq!(resp.respond_half(&mut local).ok().unwrap(), 0);
+
+        local.push(1);
+        assert_eq!(resp.respond_half(&mut local).ok().unwrap(), 0);
+        assert_eq!(local, vec![1]);
+
+        // The request is still there for someone else.
+        assert!(resp.has_request());
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_respond_from_serves_in_order() {
+        let (rqst, resp) = channel::<u32>();
+        let mut queue = (0..3).collect::<VecDeque<_>>();
+
+        for i in 0..3 {
+            let mut contract = rqst.try_request().ok().unwrap();
+            assert!(resp.respond_from(&mut queue).ok().unwrap());
+            assert_eq!(contract.try_receive().ok().unwrap(), i);
+        }
+
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+//! This module bridges a channel into `crossbeam_channel::select!`, for
+//! threads that already block on crossbeam channels. It is only built
+//! with the `crossbeam` feature.
+//!
+//! A readiness channel is a waiter on one side of a `reqchan`. It holds
+//! at most one message, so readiness that nobody drains cannot pile up,
+//! and the `reqchan` drops it the first time it fails to send because
+//! its receiver is gone.
+
+use std::sync::Arc;
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+
+use super::wake::{Waiter, Waiters};
+use super::{Requester, Responder};
+
+/// This is the sending half of a readiness channel.
+struct Readiness(Sender<()>);
+
+impl Waiter for Readiness {
+    #[inline]
+    fn wake(&self) -> bool {
+        match self.0.try_send(()) {
+            Err(TrySendError::Disconnected(())) => false,
+            // A full channel already says the side is ready.
+            _ => true,
+        }
+    }
+}
+
+/// This function creates a readiness channel for one side of a `reqchan`.
+///
+/// # Arguments
+///
+/// * `waiters` - The waiters of the side to watch
+fn readiness_channel(waiters: &Waiters) -> Receiver<()> {
+    let (sender, receiver) = bounded(1);
+
+    // Whatever was published before the channel existed sent nothing, so
+    // the first message makes the caller check once.
+    sender.try_send(()).unwrap();
+    waiters.add(Arc::new(Readiness(sender)));
+
+    receiver
+}
+
+impl<T> Requester<T> {
+    /// This method returns a crossbeam channel that receives a message
+    /// whenever a responder sends a datum, so the requesting thread can
+    /// wait for it in `crossbeam_channel::select!`. It is only available
+    /// with the `crossbeam` feature.
+    ///
+    /// # Warning
+    ///
+    /// A message is only a hint to poll. The channel receives one right
+    /// away, and it holds at most one message, so one message can stand
+    /// for several data.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate crossbeam_channel;
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let ready = requester.readiness_channel();
+    /// let (_control_tx, control) = crossbeam_channel::unbounded::<()>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder.try_respond().ok().unwrap().send(1);
+    ///
+    /// loop {
+    ///     crossbeam_channel::select! {
+    ///         recv(ready) -> _ => {
+    ///             if let Ok(num) = contract.try_receive() {
+    ///                 assert_eq!(num, 1);
+    ///                 break;
+    ///             }
+    ///         },
+    ///         recv(control) -> _ => { unreachable!(); },
+    ///     }
+    /// }
+    /// ```
+    pub fn readiness_channel(&self) -> Receiver<()> {
+        readiness_channel(&self.inner.response.waiters)
+    }
+}
+
+impl<T> Responder<T> {
+    /// This method returns a crossbeam channel that receives a message
+    /// whenever the requesting side issues a request of any kind, so a
+    /// responding thread can wait for it in `crossbeam_channel::select!`.
+    /// It is only available with the `crossbeam` feature.
+    ///
+    /// # Warning
+    ///
+    /// A message is only a hint to poll. The channel receives one right
+    /// away, and another responder may claim the request, or the
+    /// requesting side may cancel it, before this one gets to it.
+    pub fn readiness_channel(&self) -> Receiver<()> {
+        readiness_channel(&self.inner.request.waiters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::{channel, Error};
+
+    #[test]
+    fn test_readiness_holds_one_message() {
+        let (rqst, resp) = channel::<u32>();
+        let ready = resp.readiness_channel();
+
+        // The first message is there from the start.
+        assert!(ready.try_recv().is_ok());
+        assert!(ready.try_recv().is_err());
+
+        for _ in 0..3 {
+            let mut contract = rqst.try_request().ok().unwrap();
+            contract.try_cancel().ok().unwrap();
+        }
+
+        assert_eq!(ready.len(), 1);
+
+        // The channel forgets a readiness channel nobody listens to.
+        drop(ready);
+        let mut contract = rqst.try_request().ok().unwrap();
+        assert!(rqst.inner.request.waiters.is_empty());
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_readiness_select_across_threads() {
+        let (rqst, resp) = channel::<u32>();
+        let (stop_tx, stop) = crossbeam_channel::bounded::<()>(1);
+        let ready = resp.readiness_channel();
+
+        let worker = thread::spawn(move || {
+            let mut served = 0;
+
+            loop {
+                crossbeam_channel::select! {
+                    recv(ready) -> _ => {
+                        // The message may be stale, so a failed claim is fine.
+                        match resp.try_respond() {
+                            Ok(contract) => {
+                                contract.send(served);
+                                served += 1;
+                            },
+                            Err(Error::NoRequest) => {},
+                            _ => unreachable!(),
+                        }
+                    },
+                    recv(stop) -> _ => { return served; },
+                }
+            }
+        });
+
+        let ready = rqst.readiness_channel();
+
+        for i in 0..20 {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            loop {
+                ready.recv_timeout(Duration::from_secs(5)).unwrap();
+
+                if let Ok(num) = contract.try_receive() {
+                    assert_eq!(num, i);
+                    break;
+                }
+            }
+        }
+
+        stop_tx.send(()).unwrap();
+        assert_eq!(worker.join().unwrap(), 20);
+    }
+}
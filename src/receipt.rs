@@ -0,0 +1,239 @@
+//! This module lets a responder find out whether the requesting side
+//! actually took the datum it sent, for auditing.
+//!
+//! The requesting side numbers the data it takes out of the single-response
+//! slot. A responder sending into the slot knows every earlier datum was
+//! taken, so its datum gets the next number, and its receipt resolves once
+//! the count reaches that number. A `RequestContract` dropped without
+//! receiving its datum gives up the next number for good, so the receipt
+//! of a datum nobody will take still resolves.
+//!
+//! Requests for several responses, and broadcasts, are not covered.
+
+// `wasm32-unknown-unknown` has no clock, so waiting for a delivery,
+// which times out, is left out there.
+#![cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code, unused_imports))]
+
+use std::time::{Duration, Instant};
+
+use super::atomic::Ordering;
+use super::backoff::Backoff;
+use super::{Claim, Inner, Link, ResponseContract};
+
+/// This is the handle `ResponseContract::send_with_receipt()` returns. It
+/// tells whether the requesting side took the datum.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::time::Duration;
+///
+/// let (requester, responder) = chan::channel::<u32>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// let receipt = responder.try_respond().ok().unwrap().send_with_receipt(1);
+///
+/// assert!(!receipt.is_delivered());
+/// assert!(receipt.is_pending());
+///
+/// contract.try_receive().ok().unwrap();
+/// assert!(receipt.wait_delivered(Duration::from_millis(10)));
+/// ```
+pub struct DeliveryReceipt<T> {
+    inner: Link<T>,
+    // The number of the datum among those taken from the single-response
+    // slot, or 0 if it was never sent.
+    delivery: u64,
+}
+
+impl<T> DeliveryReceipt<T> {
+    /// This method returns whether the requesting side took the datum.
+    #[inline]
+    pub fn is_delivered(&self) -> bool {
+        self.delivery != 0 && self.inner.deliveries() >= self.delivery
+    }
+
+    /// This method returns whether the datum may still be taken. Once it
+    /// returns `false`, `DeliveryReceipt::is_delivered()` never changes.
+    #[inline]
+    pub fn is_pending(&self) -> bool {
+        self.delivery != 0
+            && self.inner.deliveries() < self.delivery
+            && self.inner.lost_delivery() < self.delivery
+    }
+
+    /// This method blocks until the datum is taken, it can no longer be,
+    /// or `timeout` elapses. It returns whether the datum was taken.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn wait_delivered(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Backoff::tuned(&self.inner.spin);
+
+        while self.is_pending() && Instant::now() < deadline {
+            backoff.snooze();
+        }
+
+        self.is_delivered()
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method returns how many data the requesting side took from
+    /// the single-response slot.
+    #[inline]
+    fn deliveries(&self) -> u64 {
+        self.request.taken.load(Ordering::Acquire)
+    }
+
+    /// This method returns the number of the last datum no contract will
+    /// take, or 0 if there is none.
+    #[inline]
+    fn lost_delivery(&self) -> u64 {
+        self.request.lost.load(Ordering::Acquire)
+    }
+
+    /// This method counts a datum the requesting side took from the
+    /// single-response slot.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it, before
+    /// it flags another request.
+    #[inline]
+    pub(crate) fn count_delivery(&self) {
+        self.request.taken.fetch_add(1, Ordering::Release);
+    }
+
+    /// This method gives up the next datum of the single-response slot,
+    /// because the contract that would take it is going away.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** a `RequestContract` dropped without receiving its datum
+    /// should call it.
+    #[inline]
+    pub(crate) fn lose_delivery(&self) {
+        let next = self.request.taken.load(Ordering::Relaxed) + 1;
+        self.request.lost.store(next, Ordering::Release);
+    }
+}
+
+impl<T> ResponseContract<T> {
+    /// This method sends a datum just like `ResponseContract::send()`,
+    /// and returns a `DeliveryReceipt` that tells whether the requesting
+    /// side took it. The receipt resolves to "not delivered" if the datum
+    /// was never sent, or if the `RequestContract` waiting for it was
+    /// dropped without receiving it.
+    ///
+    /// # Warning
+    ///
+    /// Only responses to single-response requests are tracked. The
+    /// receipt of any other response reports "not delivered" at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send_with_receipt(mut self, datum: T) -> DeliveryReceipt<T> {
+        if self.done {
+            panic!("ResponseContract already sent its datum!");
+        }
+
+        // Every earlier datum of the slot was taken before the requesting
+        // side flagged the request this contract claimed.
+        let delivery = match self.claim {
+            Claim::Single => self.inner.deliveries() + 1,
+            Claim::Many | Claim::Broadcast => 0,
+        };
+
+        let sent = match self.check(datum) {
+            Ok(datum) => self.deliver(datum).is_ok(),
+            Err(_) => false,
+        };
+
+        DeliveryReceipt {
+            inner: self.inner.clone(),
+            delivery: if sent { delivery } else { 0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+
+    use super::*;
+    use super::super::{channel, Builder, ValidationError};
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_receipt_delivered_across_threads() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let receipt = resp.try_respond().ok().unwrap().send_with_receipt(1);
+
+        assert!(!receipt.wait_delivered(Duration::from_millis(1)));
+        assert!(receipt.is_pending());
+
+        let handle = thread::spawn(move || contract.try_receive().ok().unwrap());
+
+        assert!(receipt.wait_delivered(Duration::from_secs(10)));
+        assert!(!receipt.is_pending());
+        assert_eq!(handle.join().unwrap(), 1);
+
+        // The next datum gets its own receipt.
+        let mut contract = rqst.try_request().ok().unwrap();
+        let receipt2 = resp.try_respond().ok().unwrap().send_with_receipt(2);
+        assert!(receipt.is_delivered() && !receipt2.is_delivered());
+        contract.try_receive().ok().unwrap();
+        assert!(receipt2.is_delivered());
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_receipt_abandoned() {
+        let (rqst, resp) = channel::<u32>();
+
+        let contract = rqst.try_request().ok().unwrap();
+        let receipt = resp.try_respond().ok().unwrap().send_with_receipt(1);
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| drop(contract)));
+        assert!(res.is_err());
+
+        assert!(!receipt.is_pending());
+        assert!(!receipt.wait_delivered(Duration::from_secs(10)));
+
+        // The receipt outlives the channel.
+        drop((rqst, resp));
+        assert!(!receipt.is_delivered());
+    }
+
+    #[test]
+    fn test_receipt_not_sent() {
+        let (rqst, resp) = Builder::new()
+            .validator(|_: &u32| Err(ValidationError("never")))
+            .build();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let receipt = resp.try_respond().ok().unwrap().send_with_receipt(1);
+        assert!(!receipt.is_pending() && !receipt.is_delivered());
+        contract.try_cancel().ok().unwrap();
+        drop(contract);
+
+        // Other kinds of requests are not tracked.
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request_many(1).ok().unwrap();
+        let receipt = resp.try_respond().ok().unwrap().send_with_receipt(1);
+        assert!(!receipt.is_pending() && !receipt.is_delivered());
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+}
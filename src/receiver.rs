@@ -0,0 +1,339 @@
+//! This module defines `PullReceiver`, which wraps a `Requester` in the
+//! API of `std::sync::mpsc::Receiver`, so code written against `mpsc` can
+//! pull from a `reqchan` instead.
+//!
+//! The receiver keeps one request outstanding. It issues the first one on
+//! the first receive and a new one right after each datum arrives, so the
+//! responders always have something to answer. The channel counts as
+//! disconnected once every `Responder` is gone and no responder has
+//! claimed the outstanding request, or once the channel is closed.
+
+// `wasm32-unknown-unknown` has no clock, so `recv_timeout()` is left
+// out there.
+#![cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code, unused_imports))]
+
+use std::cell::RefCell;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+
+use super::backoff::Backoff;
+use super::{Error, RequestContract, Requester};
+
+/// This is a `Requester` with the receiving API of `mpsc::Receiver`.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// let (requester, responder) = chan::channel::<u32>();
+///
+/// let worker = thread::spawn(move || {
+///     for job in 0..3 {
+///         loop {
+///             if let Ok(contract) = responder.try_respond() {
+///                 contract.send(job);
+///                 break;
+///             }
+///         }
+///     }
+/// });
+///
+/// // Dropping the responder at the end of the worker ends the loop.
+/// let jobs = requester.into_receiver().into_iter().collect::<Vec<_>>();
+/// assert_eq!(jobs, vec![0, 1, 2]);
+///
+/// worker.join().unwrap();
+/// ```
+pub struct PullReceiver<T> {
+    requester: Requester<T>,
+    contract: RefCell<Option<RequestContract<T>>>,
+}
+
+
+impl<T> Requester<T> {
+    /// This method turns a `Requester` into a `PullReceiver`.
+    pub fn into_receiver(self) -> PullReceiver<T> {
+        PullReceiver {
+            requester: self,
+            contract: RefCell::new(None),
+        }
+    }
+}
+
+impl<T> PullReceiver<T> {
+    /// This method tries to receive a datum without blocking, just like
+    /// `mpsc::Receiver::try_recv()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::sync::mpsc::TryRecvError;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let receiver = requester.into_receiver();
+    ///
+    /// // The first call issues the request.
+    /// assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    ///
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// assert_eq!(receiver.try_recv(), Ok(1));
+    ///
+    /// drop(responder);
+    /// assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    /// ```
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut slot = self.contract.borrow_mut();
+
+        if slot.is_none() {
+            // A busy channel just has nothing to receive yet.
+            *slot = Some(self.requester.try_request().map_err(|err| match err {
+                Error::Closed => TryRecvError::Disconnected,
+                _ => TryRecvError::Empty,
+            })?);
+        }
+
+        let received = slot.as_mut().unwrap().try_receive();
+
+        match received {
+            Ok(datum) => {
+                // The finished contract must go before the next request.
+                *slot = None;
+                *slot = self.requester.try_request().ok();
+                Ok(datum)
+            },
+            Err(Error::Empty) => {
+                if self.requester.responder_count() > 0 {
+                    return Err(TryRecvError::Empty);
+                }
+
+                // A responder that claimed the request can still answer
+                // it after its `Responder` is gone.
+                match slot.as_mut().unwrap().try_cancel() {
+                    Ok(()) => {
+                        *slot = None;
+                        Err(TryRecvError::Disconnected)
+                    },
+                    Err(Error::TooLate) => Err(TryRecvError::Empty),
+                    Err(_) => unreachable!(),
+                }
+            },
+            Err(Error::Closed) => {
+                *slot = None;
+                Err(TryRecvError::Disconnected)
+            },
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// This method blocks until it receives a datum, just like
+    /// `mpsc::Receiver::recv()`.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut backoff = Backoff::tuned(&self.requester.inner.spin);
+
+        loop {
+            match self.try_recv() {
+                Ok(datum) => { return Ok(datum); },
+                Err(TryRecvError::Empty) => { backoff.snooze(); },
+                Err(TryRecvError::Disconnected) => { return Err(RecvError); },
+            }
+        }
+    }
+
+    /// This method blocks until it receives a datum or `timeout` elapses,
+    /// just like `mpsc::Receiver::recv_timeout()`. The request stays
+    /// outstanding after a timeout, so a later call can still receive its
+    /// datum.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Backoff::tuned(&self.requester.inner.spin);
+
+        loop {
+            match self.try_recv() {
+                Ok(datum) => { return Ok(datum); },
+                Err(TryRecvError::Empty) => {
+                    if Instant::now() >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    backoff.snooze();
+                },
+                Err(TryRecvError::Disconnected) => {
+                    return Err(RecvTimeoutError::Disconnected);
+                },
+            }
+        }
+    }
+
+    /// This method returns an iterator that blocks for each datum and
+    /// ends once the channel is disconnected, just like
+    /// `mpsc::Receiver::iter()`.
+    #[inline]
+    pub fn iter(&self) -> PullIter<'_, T> {
+        PullIter { receiver: self }
+    }
+}
+
+impl<T> Drop for PullReceiver<T> {
+    fn drop(&mut self) {
+        let mut contract = match self.contract.get_mut().take() {
+            Some(contract) => contract,
+            None => { return; },
+        };
+
+        match contract.try_cancel() {
+            Ok(()) => { return; },
+            Err(Error::TooLate) => {},
+            Err(_) => unreachable!(),
+        }
+
+        // A responder committed to the request, so drain its datum.
+        let mut backoff = Backoff::tuned(&self.requester.inner.spin);
+
+        loop {
+            match contract.try_receive() {
+                Ok(_) => { return; },
+                Err(Error::Empty) => { backoff.snooze(); },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// This is the iterator returned by `PullReceiver::iter()`.
+pub struct PullIter<'a, T: 'a> {
+    receiver: &'a PullReceiver<T>,
+}
+
+impl<'a, T> Iterator for PullIter<'a, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// This is the iterator returned by `PullReceiver::into_iter()`.
+pub struct PullIntoIter<T> {
+    receiver: PullReceiver<T>,
+}
+
+impl<T> Iterator for PullIntoIter<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PullReceiver<T> {
+    type Item = T;
+    type IntoIter = PullIter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> PullIter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for PullReceiver<T> {
+    type Item = T;
+    type IntoIter = PullIntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> PullIntoIter<T> {
+        PullIntoIter { receiver: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use super::super::channel;
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_receiver_renews_request() {
+        let (rqst, resp) = channel::<u32>();
+        let receiver = rqst.into_receiver();
+
+        assert!(!resp.has_request());
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        assert!(resp.has_request());
+
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(receiver.try_recv(), Ok(1));
+
+        // The next request went out with the datum.
+        assert!(resp.has_request());
+        resp.try_respond().ok().unwrap().send(2);
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(5)), Ok(2));
+
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(5)),
+                   Err(RecvTimeoutError::Timeout));
+
+        // Dropping the receiver cancels the outstanding request.
+        drop(receiver);
+        assert!(!resp.has_request());
+    }
+
+    #[test]
+    fn test_receiver_disconnect() {
+        let (rqst, resp) = channel::<u32>();
+        let receiver = rqst.into_receiver();
+
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        // A claimed request can still be answered after the responder
+        // itself is gone.
+        let contract = resp.try_respond().ok().unwrap();
+        drop(resp);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        contract.send(1);
+        assert_eq!(receiver.recv(), Ok(1));
+        assert_eq!(receiver.recv(), Err(RecvError));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_receiver_iter_across_threads() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let workers = vec![resp, resp2].into_iter().map(|resp| {
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    loop {
+                        match resp.try_respond() {
+                            Ok(contract) => {
+                                contract.send(1);
+                                break;
+                            },
+                            Err(_) => { thread::yield_now(); },
+                        }
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let receiver = rqst.into_receiver();
+        assert_eq!((&receiver).into_iter().sum::<u32>(), 100);
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! This module defines `RetryPolicy`, which describes how long to keep
+//! retrying a request or a response that failed only because the other
+//! side was not ready yet, so callers do not each write their own loop.
+
+use std::hint;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::backoff::Backoff;
+use super::{Error, RequestContract, Requester, ResponseContract, Responder, Result};
+
+/// This is how a `RetryPolicy` waits between attempts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackoffStrategy {
+    /// Busy-wait for one spin between attempts.
+    Spin,
+    /// Spin for a while, then yield the thread, like the crate's own
+    /// blocking helpers.
+    Adaptive,
+    /// Sleep for the given time between attempts.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    Sleep(Duration),
+}
+
+/// This describes how often, and for how long, to retry a request or a
+/// response. A policy can be built once and used for any number of calls.
+///
+/// # Warning
+///
+/// A policy with neither a maximum number of attempts nor a timeout
+/// retries forever.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::time::Duration;
+///
+/// use chan::{BackoffStrategy, RetryPolicy};
+///
+/// let fast = RetryPolicy::new().max_attempts(10).strategy(BackoffStrategy::Spin);
+/// let patient = RetryPolicy::new().timeout(Duration::from_millis(5));
+///
+/// let (requester, responder) = chan::channel::<u32>();
+///
+/// // Nobody asked for anything, so both give up.
+/// match responder.respond_with_retry(&fast) {
+///     Err(chan::Error::NoRequest) => {},
+///     _ => unreachable!(),
+/// }
+/// match responder.respond_with_retry(&patient) {
+///     Err(chan::Error::NoRequest) => {},
+///     _ => unreachable!(),
+/// }
+///
+/// let mut contract = requester.request_with_retry(&fast).ok().unwrap();
+/// responder.respond_with_retry(&fast).ok().unwrap().send(1);
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: Option<usize>,
+    strategy: BackoffStrategy,
+    timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// This function creates a policy that retries forever with the
+    /// `Adaptive` strategy.
+    #[inline]
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: None,
+            strategy: BackoffStrategy::Adaptive,
+            timeout: None,
+        }
+    }
+
+    /// This method limits the number of attempts, counting the first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of attempts
+    pub fn max_attempts(mut self, n: usize) -> RetryPolicy {
+        self.max_attempts = Some(n);
+        self
+    }
+
+    /// This method sets how to wait between attempts.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - The way to wait
+    pub fn strategy(mut self, strategy: BackoffStrategy) -> RetryPolicy {
+        self.strategy = strategy;
+        self
+    }
+
+    /// This method stops retrying once `timeout` has elapsed since the
+    /// first attempt.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to keep retrying
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn timeout(mut self, timeout: Duration) -> RetryPolicy {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// This method calls `attempt` until it succeeds, fails with an error
+    /// that retrying cannot fix, or the policy runs out. It returns the
+    /// last error in the last two cases.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt` - The function to retry
+    fn run<R, F>(&self, mut attempt: F) -> Result<R>
+        where F: FnMut() -> Result<R>
+    {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut backoff = Backoff::new();
+        let mut attempts = 0;
+
+        loop {
+            let err = match attempt() {
+                Ok(res) => { return Ok(res); },
+                Err(err @ Error::AlreadyLocked) |
+                Err(err @ Error::NoRequest) |
+                Err(err @ Error::Paused) |
+                Err(err @ Error::SlotFull) => err,
+                Err(err) => { return Err(err); },
+            };
+
+            attempts += 1;
+
+            if self.max_attempts.is_some_and(|max| attempts >= max) ||
+                deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(err);
+            }
+
+            match self.strategy {
+                BackoffStrategy::Spin => hint::spin_loop(),
+                BackoffStrategy::Adaptive => backoff.snooze(),
+                #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+                BackoffStrategy::Sleep(pause) => thread::sleep(pause),
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+impl<T> Requester<T> {
+    /// This method calls `Requester::try_request()` until it succeeds or
+    /// `policy` runs out, in which case it returns the last error. If the
+    /// channel has a request throttle, it sleeps until the throttle lets
+    /// the next request through instead of counting it as an attempt.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - How to retry
+    pub fn request_with_retry(&self, policy: &RetryPolicy) -> Result<RequestContract<T>> {
+        policy.run(|| {
+            if let Some(wait) = self.inner.throttled_for() {
+                thread::sleep(wait);
+            }
+
+            self.try_request()
+        })
+    }
+}
+
+impl<T> Responder<T> {
+    /// This method calls `Responder::try_respond()` until it claims a
+    /// request or `policy` runs out, in which case it returns the last
+    /// error.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - How to retry
+    pub fn respond_with_retry(&self, policy: &RetryPolicy) -> Result<ResponseContract<T>> {
+        policy.run(|| self.try_respond())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::channel;
+
+    #[test]
+    fn test_retry_policy_max_attempts() {
+        let policy = RetryPolicy::new().max_attempts(3);
+        let mut calls = 0;
+
+        let res: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(Error::NoRequest)
+        });
+
+        match res {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(calls, 3);
+
+        // Errors that retrying cannot fix end the loop at once.
+        calls = 0;
+        let res: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(Error::Done)
+        });
+
+        match res {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_retry_policy_timeout() {
+        let policy = RetryPolicy::new()
+            .strategy(BackoffStrategy::Sleep(Duration::from_millis(1)))
+            .timeout(Duration::from_millis(10));
+        let (_rqst, resp) = channel::<u32>();
+
+        let start = Instant::now();
+        match resp.respond_with_retry(&policy) {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_respond_with_retry_across_threads() {
+        let (rqst, resp) = channel::<u32>();
+
+        let responder = thread::spawn(move || {
+            let contract = resp.respond_with_retry(&RetryPolicy::new()).ok().unwrap();
+            contract.send(4);
+        });
+
+        thread::sleep(Duration::from_millis(1));
+
+        let mut contract = rqst.request_with_retry(&RetryPolicy::new()).ok().unwrap();
+        let mut backoff = Backoff::new();
+
+        loop {
+            match contract.try_receive() {
+                Ok(num) => {
+                    assert_eq!(num, 4);
+                    break;
+                },
+                Err(Error::Empty) => { backoff.snooze(); },
+                _ => { assert!(false); },
+            }
+        }
+
+        responder.join().unwrap();
+    }
+}
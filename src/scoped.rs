@@ -0,0 +1,602 @@
+//! This module defines a channel whose state lives in storage the caller
+//! owns, such as a local variable, instead of on the heap. Its ends borrow
+//! the storage, so the borrow checker guarantees they are gone before the
+//! storage is. That makes it easy to pass borrowed data, like slices of a
+//! local array, between scoped threads.
+//!
+//! The ends and contracts of a scoped channel work exactly like those of
+//! a regular channel. The contracts are wrapped in `Scoped` so they cannot
+//! outlive the storage either. `Scoped` forwards the contract's methods
+//! instead of handing out the contract itself, since a contract moved out
+//! of the wrapper would no longer be tied to the storage.
+
+use std::marker::PhantomData;
+use std::result;
+use std::time::Instant;
+
+use super::{BroadcastContract, CancelReason, Inner, Link, MultiRequestContract,
+            RequestContract, RequestMeta, RequestOutcome, Requester, Responder, ResponderId,
+            ResponderStats, ResponseContract, Result, SendError, Urgency};
+
+/// This is the storage for a scoped channel.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// let mut jobs = [1, 2, 3, 4];
+///
+/// {
+///     let mut channel = chan::ScopedChannel::<&mut [u32]>::new();
+///     let (requester, responder) = channel.split();
+///
+///     thread::scope(|s| {
+///         s.spawn(move || {
+///             let mut contract = requester.try_request().ok().unwrap();
+///             loop {
+///                 if let Ok(jobs) = contract.try_receive() {
+///                     for job in jobs.iter_mut() {
+///                         *job *= 10;
+///                     }
+///                     break;
+///                 }
+///             }
+///         });
+///
+///         loop {
+///             if let Ok(contract) = responder.try_respond() {
+///                 contract.send(&mut jobs[..]);
+///                 break;
+///             }
+///         }
+///     });
+/// }
+///
+/// assert_eq!(jobs, [10, 20, 30, 40]);
+/// ```
+pub struct ScopedChannel<T> {
+    inner: Inner<T>,
+}
+
+impl<T> ScopedChannel<T> {
+    const_fn! {
+        /// This function creates the storage for an idle scoped channel.
+        pub fn new() -> ScopedChannel<T> {
+            ScopedChannel {
+                inner: Inner::new(),
+            }
+        }
+    }
+
+    /// This method returns the two ends of the channel. They borrow the
+    /// storage until they and all their clones and contracts are dropped,
+    /// after which the storage can be split again.
+    pub fn split(&mut self) -> (ScopedRequester<'_, T>, ScopedResponder<'_, T>) {
+        // The ends cannot outlive the borrow of `self`.
+        let inner = unsafe { Link::borrowed(&self.inner) };
+
+        (
+            ScopedRequester {
+                requester: Requester::new(inner.clone()),
+                _scope: PhantomData,
+            },
+            ScopedResponder {
+                responder: Responder::new(inner),
+                _scope: PhantomData,
+            },
+        )
+    }
+}
+
+impl<T> Default for ScopedChannel<T> {
+    fn default() -> Self {
+        ScopedChannel::new()
+    }
+}
+
+/// This is the requesting end of a scoped channel. It works just like
+/// `Requester`.
+pub struct ScopedRequester<'a, T: 'a> {
+    requester: Requester<T>,
+    _scope: PhantomData<&'a Inner<T>>,
+}
+
+impl<'a, T> ScopedRequester<'a, T> {
+    /// This method works just like `Requester::try_request()`.
+    #[inline]
+    pub fn try_request(&self) -> Result<Scoped<'a, RequestContract<T>>> {
+        self.requester.try_request().map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::try_fetch()`.
+    #[inline]
+    pub fn try_fetch(&self) -> Result<Option<T>> {
+        self.requester.try_fetch()
+    }
+
+    /// This method works just like `Requester::try_request_amount()`.
+    #[inline]
+    pub fn try_request_amount(&self, n: usize) -> Result<Scoped<'a, RequestContract<T>>> {
+        self.requester.try_request_amount(n).map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::try_request_urgent()`.
+    #[inline]
+    pub fn try_request_urgent(&self) -> Result<Scoped<'a, RequestContract<T>>> {
+        self.requester.try_request_urgent().map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::try_request_with_urgency()`.
+    #[inline]
+    pub fn try_request_with_urgency(&self, urgency: Urgency)
+                                    -> Result<Scoped<'a, RequestContract<T>>> {
+        self.requester.try_request_with_urgency(urgency).map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::try_request_many()`.
+    #[inline]
+    pub fn try_request_many(&self, n: usize) -> Result<Scoped<'a, MultiRequestContract<T>>> {
+        self.requester.try_request_many(n).map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::try_request_n()`.
+    #[inline]
+    pub fn try_request_n(&self, k: usize) -> Result<Scoped<'a, MultiRequestContract<T>>> {
+        self.requester.try_request_n(k).map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::try_request_all()`.
+    #[inline]
+    pub fn try_request_all(&self) -> Result<Scoped<'a, BroadcastContract<T>>> {
+        self.requester.try_request_all().map(Scoped::new)
+    }
+
+    /// This method works just like `Requester::responder_count()`.
+    #[inline]
+    pub fn responder_count(&self) -> usize {
+        self.requester.responder_count()
+    }
+
+    /// This method works just like `Requester::available_responders()`.
+    #[inline]
+    pub fn available_responders(&self) -> usize {
+        self.requester.available_responders()
+    }
+
+    /// This method works just like `Requester::pause()`.
+    #[inline]
+    pub fn pause(&self) {
+        self.requester.pause();
+    }
+
+    /// This method works just like `Requester::resume()`.
+    #[inline]
+    pub fn resume(&self) {
+        self.requester.resume();
+    }
+
+    /// This method works just like `Requester::is_paused()`.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.requester.is_paused()
+    }
+
+    /// This method works just like `Requester::close()`.
+    #[inline]
+    pub fn close(&self) {
+        self.requester.close();
+    }
+
+    /// This method works just like `Requester::is_closed()`.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.requester.is_closed()
+    }
+
+    /// This method works just like `Requester::new_responder()`.
+    #[inline]
+    pub fn new_responder(&self) -> ScopedResponder<'a, T> {
+        ScopedResponder {
+            responder: self.requester.new_responder(),
+            _scope: PhantomData,
+        }
+    }
+
+    /// This method works just like `Requester::set_notify()`.
+    #[inline]
+    pub fn set_notify<F>(&self, f: F)
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.requester.set_notify(f)
+    }
+}
+
+/// This is the responding end of a scoped channel. It works just like
+/// `Responder`.
+pub struct ScopedResponder<'a, T: 'a> {
+    responder: Responder<T>,
+    _scope: PhantomData<&'a Inner<T>>,
+}
+
+impl<'a, T> ScopedResponder<'a, T> {
+    /// This method works just like `Responder::try_respond()`.
+    #[inline]
+    pub fn try_respond(&self) -> Result<Scoped<'a, ResponseContract<T>>> {
+        self.responder.try_respond().map(Scoped::new)
+    }
+
+    /// This method works just like `Responder::try_respond_if()`.
+    #[inline]
+    pub fn try_respond_if<F>(&self, pred: F) -> Result<Scoped<'a, ResponseContract<T>>>
+        where F: Fn(&RequestMeta) -> bool
+    {
+        self.responder.try_respond_if(pred).map(Scoped::new)
+    }
+
+    /// This method works just like `Responder::id()`.
+    #[inline]
+    pub fn id(&self) -> ResponderId {
+        self.responder.id()
+    }
+
+    /// This method works just like `Responder::sibling_count()`.
+    #[inline]
+    pub fn sibling_count(&self) -> usize {
+        self.responder.sibling_count()
+    }
+
+    /// This method works just like `Responder::stats()`.
+    #[inline]
+    pub fn stats(&self) -> ResponderStats {
+        self.responder.stats()
+    }
+
+    /// This method works just like `Responder::set_notify()`.
+    #[inline]
+    pub fn set_notify<F>(&self, f: F)
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.responder.set_notify(f)
+    }
+
+    /// This method works just like `Responder::pending_urgency()`.
+    #[inline]
+    pub fn pending_urgency(&self) -> Option<Urgency> {
+        self.responder.pending_urgency()
+    }
+
+    /// This method works just like `Responder::has_request()`.
+    #[inline]
+    pub fn has_request(&self) -> bool {
+        self.responder.has_request()
+    }
+
+    /// This method works just like `Responder::set_priority()`.
+    #[inline]
+    pub fn set_priority(&self, priority: u8) {
+        self.responder.set_priority(priority);
+    }
+
+    /// This method works just like `Responder::priority()`.
+    #[inline]
+    pub fn priority(&self) -> u8 {
+        self.responder.priority()
+    }
+
+    /// This method works just like `Responder::set_busy()`.
+    #[inline]
+    pub fn set_busy(&self, busy: bool) {
+        self.responder.set_busy(busy);
+    }
+
+    /// This method works just like `Responder::is_busy()`.
+    #[inline]
+    pub fn is_busy(&self) -> bool {
+        self.responder.is_busy()
+    }
+
+    /// This method works just like `Responder::close()`.
+    #[inline]
+    pub fn close(&self) {
+        self.responder.close();
+    }
+
+    /// This method works just like `Responder::is_closed()`.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.responder.is_closed()
+    }
+
+    /// This method works just like `Responder::last_request_outcome()`.
+    #[inline]
+    pub fn last_request_outcome(&self) -> Option<RequestOutcome> {
+        self.responder.last_request_outcome()
+    }
+}
+
+impl<'a, T> Clone for ScopedResponder<'a, T> {
+    fn clone(&self) -> Self {
+        ScopedResponder {
+            responder: self.responder.clone(),
+            _scope: PhantomData,
+        }
+    }
+}
+
+/// This wraps a contract of a scoped channel so it cannot outlive the
+/// channel's storage. It has the methods of the contract it wraps, but
+/// never hands out the contract itself.
+///
+/// # Example
+///
+/// Moving a scoped contract out of its wrapper, here by swapping in the
+/// contract of a regular channel, does not compile:
+///
+/// ```rust,compile_fail
+/// extern crate reqchan as chan;
+///
+/// use std::mem;
+///
+/// let (requester, _responder) = chan::channel::<u32>();
+/// let mut escaped = requester.try_request().ok().unwrap();
+///
+/// {
+///     let mut channel = chan::ScopedChannel::<u32>::new();
+///     let (scoped_requester, _scoped_responder) = channel.split();
+///     let mut contract = scoped_requester.try_request().ok().unwrap();
+///
+///     escaped = mem::replace(&mut *contract, escaped);
+/// }
+///
+/// // `escaped` would now point into storage that is gone.
+/// escaped.try_cancel().ok();
+/// ```
+pub struct Scoped<'a, C> {
+    contract: C,
+    _scope: PhantomData<&'a ()>,
+}
+
+impl<'a, C> Scoped<'a, C> {
+    #[inline]
+    fn new(contract: C) -> Scoped<'a, C> {
+        Scoped {
+            contract,
+            _scope: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Scoped<'a, RequestContract<T>> {
+    /// This method works just like `RequestContract::try_receive()`.
+    #[inline]
+    pub fn try_receive(&mut self) -> Result<T> {
+        self.contract.try_receive()
+    }
+
+    /// This method works just like
+    /// `RequestContract::try_receive_attributed()`.
+    #[inline]
+    pub fn try_receive_attributed(&mut self) -> Result<(T, ResponderId)> {
+        self.contract.try_receive_attributed()
+    }
+
+    /// This method works just like `RequestContract::try_receive_validate()`.
+    #[inline]
+    pub fn try_receive_validate<F>(&mut self, accept: F) -> Result<Option<T>>
+        where F: FnOnce(&T) -> bool
+    {
+        self.contract.try_receive_validate(accept)
+    }
+
+    /// This method works just like `RequestContract::sequence()`.
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.contract.sequence()
+    }
+
+    /// This method works just like `RequestContract::try_cancel()`.
+    #[inline]
+    pub fn try_cancel(&mut self) -> Result<()> {
+        self.contract.try_cancel()
+    }
+
+    /// This method works just like `RequestContract::try_cancel_with()`.
+    #[inline]
+    pub fn try_cancel_with(&mut self, reason: CancelReason) -> Result<()> {
+        self.contract.try_cancel_with(reason)
+    }
+
+    /// This method works just like `RequestContract::responder_claimed()`.
+    #[inline]
+    pub fn responder_claimed(&self) -> bool {
+        self.contract.responder_claimed()
+    }
+}
+
+impl<'a, T> Scoped<'a, MultiRequestContract<T>> {
+    /// This method works just like `MultiRequestContract::try_receive()`.
+    #[inline]
+    pub fn try_receive(&mut self) -> Result<T> {
+        self.contract.try_receive()
+    }
+
+    /// This method works just like `MultiRequestContract::remaining()`.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.contract.remaining()
+    }
+
+    /// This method works just like `MultiRequestContract::outstanding()`.
+    #[inline]
+    pub fn outstanding(&self) -> usize {
+        self.contract.outstanding()
+    }
+
+    /// This method works just like `MultiRequestContract::try_cancel()`.
+    #[inline]
+    pub fn try_cancel(&mut self) -> Result<()> {
+        self.contract.try_cancel()
+    }
+}
+
+impl<'a, T> Scoped<'a, BroadcastContract<T>> {
+    /// This method works just like `BroadcastContract::try_receive()`.
+    #[inline]
+    pub fn try_receive(&mut self) -> Result<(ResponderId, T)> {
+        self.contract.try_receive()
+    }
+
+    /// This method works just like
+    /// `BroadcastContract::responses_remaining()`.
+    #[inline]
+    pub fn responses_remaining(&self) -> usize {
+        self.contract.responses_remaining()
+    }
+
+    /// This method works just like `BroadcastContract::try_cancel()`.
+    #[inline]
+    pub fn try_cancel(&mut self) -> Result<()> {
+        self.contract.try_cancel()
+    }
+}
+
+// `send_and_hold()` and `send_with_receipt()` are left out: the handles
+// they return are linked to the storage too, and would outlive it.
+impl<'a, T> Scoped<'a, ResponseContract<T>> {
+    /// This method works just like `ResponseContract::amount_requested()`.
+    #[inline]
+    pub fn amount_requested(&self) -> usize {
+        self.contract.amount_requested()
+    }
+
+    /// This method works just like `ResponseContract::urgency()`.
+    #[inline]
+    pub fn urgency(&self) -> Urgency {
+        self.contract.urgency()
+    }
+
+    /// This method works just like `ResponseContract::deadline()`.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.contract.deadline()
+    }
+
+    /// This method works just like `ResponseContract::tag()`.
+    #[inline]
+    pub fn tag(&self) -> u64 {
+        self.contract.tag()
+    }
+
+    /// This method works just like `ResponseContract::sequence()`.
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.contract.sequence()
+    }
+
+    /// This method works just like `ResponseContract::send()`.
+    #[inline]
+    pub fn send(self, datum: T) {
+        self.contract.send(datum);
+    }
+
+    /// This method works just like `ResponseContract::try_send()`.
+    #[inline]
+    pub fn try_send(&mut self, datum: T) -> result::Result<(), SendError<T>> {
+        self.contract.try_send(datum)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::Error;
+
+    #[test]
+    fn test_scoped_channel_split_multiple() {
+        let mut channel = ScopedChannel::<u32>::new();
+
+        for i in 0..3 {
+            let (rqst, resp) = channel.split();
+
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(i);
+            assert_eq!(contract.try_receive().ok().unwrap(), i);
+        }
+    }
+
+    #[test]
+    fn test_scoped_contract_forwards() {
+        let mut channel = ScopedChannel::<u32>::new();
+        let (rqst, resp) = channel.split();
+
+        let mut contract = rqst.try_request_amount(2).ok().unwrap();
+        assert!(!contract.responder_claimed());
+
+        let mut resp_contract = resp.try_respond().ok().unwrap();
+        assert!(contract.responder_claimed());
+        assert_eq!(resp_contract.amount_requested(), 2);
+        assert_eq!(resp_contract.sequence(), contract.sequence());
+        resp_contract.try_send(3).ok().unwrap();
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+        drop(contract);
+
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+        assert_eq!(contract.remaining(), 2);
+        resp.try_respond().ok().unwrap().send(4);
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_scoped_responder_try_respond_no_request() {
+        let mut channel = ScopedChannel::<u32>::new();
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel.split();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_scoped_channel_drop_with_undelivered_datum() {
+        use std::mem;
+        use std::rc::Rc;
+
+        let datum = Rc::new(());
+
+        {
+            let mut channel = ScopedChannel::<Rc<()>>::new();
+            let (rqst, resp) = channel.split();
+
+            let contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(datum.clone());
+
+            // Nobody will ever receive the datum.
+            mem::forget(contract);
+        }
+
+        assert_eq!(Rc::strong_count(&datum), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_scoped_request_contract_drop_without_receiving_data() {
+        let mut channel = ScopedChannel::<u32>::new();
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel.split();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+}
@@ -0,0 +1,749 @@
+//! This module defines two ways to wait on several channels at once.
+//!
+//! `select!` suits a fixed set of operations. It turns its arms into
+//! calls to `recv()` and `respond()`, and uses `Rotation` to decide which
+//! arm to poll first and how long to keep polling. Every `select!` on a
+//! thread starts polling one arm further along than the previous one, and
+//! each round of polling inside one `select!` moves the start along too,
+//! so an arm that is always ready cannot starve the arms listed after it.
+//!
+//! `Select` suits a set of `RequestContract`s that changes at runtime.
+//! Instead of polling, it adds a `Signal` to each contract's channel,
+//! which the channel raises whenever a responder sends a datum, and
+//! blocks on that signal between checks.
+
+use std::cell::Cell;
+use std::cmp;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::backoff::Backoff;
+use super::wake::Signal;
+use super::{Error, RequestContract, Responder, ResponseContract, Result, State};
+
+/// This waits until one of several `RequestContract`s has a datum.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// let channels = (0..3).map(|_| chan::channel::<u32>()).collect::<Vec<_>>();
+///
+/// let mut contracts = channels.iter()
+///     .map(|&(ref requester, _)| requester.try_request().ok().unwrap())
+///     .collect::<Vec<_>>();
+///
+/// let (_, ref responder) = channels[1];
+/// let responder = responder.clone();
+/// let handle = thread::spawn(move || {
+///     responder.try_respond().ok().unwrap().send(1);
+/// });
+///
+/// let mut select = chan::Select::new();
+/// for contract in contracts.iter_mut() {
+///     select.add(contract);
+/// }
+///
+/// let index = select.wait();
+/// assert_eq!(index, 1);
+/// assert_eq!(select.get_mut(index).unwrap().try_receive().ok().unwrap(), 1);
+///
+/// for index in [0, 2] {
+///     select.get_mut(index).unwrap().try_cancel().ok().unwrap();
+/// }
+/// handle.join().unwrap();
+/// ```
+pub struct Select<'a, T: 'a> {
+    // Removed contracts leave a hole, so the other indexes stay valid.
+    contracts: Vec<Option<&'a mut RequestContract<T>>>,
+    signal: Arc<Signal>,
+    // The index the next check starts at.
+    start: usize,
+}
+
+impl<'a, T> Select<'a, T> {
+    /// This function creates a `Select` without any contracts.
+    pub fn new() -> Select<'a, T> {
+        Select {
+            contracts: Vec::new(),
+            signal: Arc::new(Signal::new()),
+            start: 0,
+        }
+    }
+
+    /// This method adds a contract to wait on, and returns its index. It
+    /// reuses the lowest index freed by `Select::remove()`, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - The contract to wait on
+    pub fn add(&mut self, contract: &'a mut RequestContract<T>) -> usize {
+        contract.inner.response.waiters.add(self.signal.clone());
+
+        match self.contracts.iter().position(Option::is_none) {
+            Some(index) => {
+                self.contracts[index] = Some(contract);
+                index
+            },
+            None => {
+                self.contracts.push(Some(contract));
+                self.contracts.len() - 1
+            },
+        }
+    }
+
+    /// This method stops waiting on a contract and hands it back.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The contract's index
+    pub fn remove(&mut self, index: usize) -> Option<&'a mut RequestContract<T>> {
+        let contract = self.contracts.get_mut(index).and_then(Option::take);
+
+        if let Some(ref contract) = contract {
+            contract.inner.response.waiters.remove(&self.signal);
+        }
+
+        contract
+    }
+
+    /// This method returns a contract, for example to call `try_receive()`
+    /// on it once `Select::wait()` picked it.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The contract's index
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut RequestContract<T>> {
+        match self.contracts.get_mut(index) {
+            Some(&mut Some(ref mut contract)) => Some(&mut **contract),
+            _ => None,
+        }
+    }
+
+    /// This method blocks until one of the contracts has a datum, and
+    /// returns its index. A contract that already received its datum or
+    /// cancelled its request counts as ready, since its `try_receive()`
+    /// returns right away with `Err(Error::Done)`.
+    ///
+    /// # Warning
+    ///
+    /// It blocks forever if there are no contracts.
+    pub fn wait(&mut self) -> usize {
+        self.wait_until(None).unwrap()
+    }
+
+    /// This method works just like `Select::wait()`, but returns `None`
+    /// if no contract has a datum within `timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Option<usize> {
+        self.wait_until(Some(Instant::now() + timeout))
+    }
+
+    /// This method blocks until one of the contracts has a datum or
+    /// `deadline` passes.
+    fn wait_until(&mut self, deadline: Option<Instant>) -> Option<usize> {
+        loop {
+            self.signal.lower();
+
+            if let Some(index) = self.ready() {
+                return Some(index);
+            }
+
+            if !self.signal.wait(deadline) {
+                return self.ready();
+            }
+        }
+    }
+
+    /// This method returns the index of a ready contract, checking from
+    /// `start` on so no contract starves the ones after it.
+    fn ready(&mut self) -> Option<usize> {
+        let len = self.contracts.len();
+
+        for step in 0..len {
+            let index = (self.start + step) % len;
+
+            if let Some(ref contract) = self.contracts[index] {
+                if contract.is_ready() {
+                    self.start = (index + 1) % len;
+                    return Some(index);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, T> Default for Select<'a, T> {
+    fn default() -> Select<'a, T> {
+        Select::new()
+    }
+}
+
+impl<'a, T> Drop for Select<'a, T> {
+    fn drop(&mut self) {
+        for contract in self.contracts.iter().flatten() {
+            contract.inner.response.waiters.remove(&self.signal);
+        }
+    }
+}
+
+impl<T> RequestContract<T> {
+    /// This method returns whether `try_receive()` would return right
+    /// away, with either the datum or `Err(Error::Done)`.
+    #[inline]
+    fn is_ready(&self) -> bool {
+        self.done || self.inner.state() == State::DataReady
+    }
+}
+
+thread_local! {
+    // The arm the next `select!` on this thread starts with, modulo its
+    // number of arms.
+    static NEXT_START: Cell<usize> = const { Cell::new(0) };
+}
+
+/// This is how long a `select!` waits for one of its arms.
+#[derive(Clone, Copy, Debug)]
+pub enum Wait {
+    /// It waits until an arm is ready.
+    Forever,
+    /// It polls every arm once, then gives up.
+    Never,
+    /// It gives up after the duration.
+    For(Duration),
+}
+
+/// This struct tracks one `select!` while it polls its arms.
+pub struct Rotation {
+    arms: usize,
+    start: usize,
+    // The arm the current pass is on, and whether the pass is the first
+    // one of its round.
+    next: usize,
+    first_pass: bool,
+    deadline: Option<Instant>,
+    wait: Wait,
+    backoff: Backoff<'static>,
+}
+
+impl Rotation {
+    /// This function starts a `select!`.
+    ///
+    /// # Arguments
+    ///
+    /// * `arms` - The number of arms to poll
+    ///
+    /// * `wait` - How long to wait for an arm
+    pub fn new(arms: usize, wait: Wait) -> Rotation {
+        let start = NEXT_START.with(|next| {
+            let start = next.get();
+            next.set(start.wrapping_add(1));
+            start % cmp::max(arms, 1)
+        });
+
+        let deadline = match wait {
+            Wait::For(timeout) => Some(Instant::now() + timeout),
+            _ => None,
+        };
+
+        Rotation {
+            arms,
+            start,
+            next: 0,
+            first_pass: true,
+            deadline,
+            wait,
+            backoff: Backoff::new(),
+        }
+    }
+
+    /// This method starts one of the two passes of a round. The first
+    /// pass polls the arms from `start` on, and the second the ones
+    /// before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - The number of the pass, either 0 or 1
+    #[inline]
+    pub fn pass(&mut self, pass: usize) {
+        self.next = 0;
+        self.first_pass = pass == 0;
+    }
+
+    /// This method returns whether the current pass should poll the next
+    /// arm.
+    #[inline]
+    pub fn turn(&mut self) -> bool {
+        let arm = self.next;
+        self.next += 1;
+
+        (arm >= self.start) == self.first_pass
+    }
+
+    /// This method waits before the next round, and returns `false` once
+    /// the `select!` should give up.
+    pub fn wait(&mut self) -> bool {
+        self.start = (self.start + 1) % cmp::max(self.arms, 1);
+
+        let expired = match self.wait {
+            Wait::Forever => false,
+            Wait::Never => true,
+            Wait::For(_) => Instant::now() >= self.deadline.unwrap(),
+        };
+
+        if !expired {
+            self.backoff.snooze();
+        }
+
+        !expired
+    }
+}
+
+/// This function polls a `recv` arm, which is ready once the contract
+/// has a datum or can never have one.
+///
+/// # Arguments
+///
+/// * `contract` - The contract to receive from
+#[inline]
+pub fn recv<T>(contract: &mut RequestContract<T>) -> Option<Result<T>> {
+    match contract.try_receive() {
+        Err(Error::Empty) => None,
+        result => Some(result),
+    }
+}
+
+/// This function polls a `respond` arm, which is ready once the
+/// responder has claimed a request. A paused channel is not ready.
+///
+/// # Arguments
+///
+/// * `responder` - The responder to claim a request with
+#[inline]
+pub fn respond<T>(responder: &Responder<T>) -> Option<Result<ResponseContract<T>>> {
+    match responder.try_respond() {
+        Err(Error::NoRequest) | Err(Error::AlreadyLocked) | Err(Error::Paused) |
+        Err(Error::SlotFull) => None,
+        result => Some(result),
+    }
+}
+
+/// This macro waits on several channel operations at once and runs the
+/// arm of the first one that is ready. It takes these arms:
+///
+/// * `recv(contract) -> result => body` polls a `RequestContract`. It is
+///   ready once `try_receive()` returns anything but `Error::Empty`, and
+///   binds what it returned to `result`.
+///
+/// * `respond(responder) -> result => body` polls a `Responder`. It is
+///   ready once `try_respond()` claims a request, and binds what it
+///   returned to `result`.
+///
+/// * `default => body` runs if no other arm is ready after each was
+///   polled once.
+///
+/// * `timeout(duration) => body` runs if no other arm is ready within
+///   `duration`.
+///
+/// At most one of `default` and `timeout` may be given, as the last arm.
+/// Without either, the macro waits until an arm is ready. It evaluates
+/// to the value of the arm it ran.
+///
+/// # Warning
+///
+/// The bindings after `->` must be irrefutable patterns. A `recv` arm on
+/// a contract that already received its datum is always ready, with
+/// `Err(Error::Done)`.
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate reqchan as chan;
+///
+/// use std::time::Duration;
+///
+/// fn main() {
+///     let (high, _high_responder) = chan::channel::<&str>();
+///     let (low, low_responder) = chan::channel::<&str>();
+///
+///     let mut high_contract = high.try_request().ok().unwrap();
+///     let mut low_contract = low.try_request().ok().unwrap();
+///
+///     low_responder.try_respond().ok().unwrap().send("sweep");
+///
+///     let job = select! {
+///         recv(high_contract) -> job => job.ok().unwrap(),
+///         recv(low_contract) -> job => job.ok().unwrap(),
+///         timeout(Duration::from_secs(1)) => "nothing",
+///     };
+///     assert_eq!(job, "sweep");
+///
+///     high_contract.try_cancel().ok().unwrap();
+/// }
+/// ```
+#[macro_export]
+macro_rules! select {
+    // Every arm is parsed into `(slot op target, pattern, { body })`,
+    // where each `slot` comes from a different expansion and so names a
+    // different variable.
+    (@parse ($($arms:tt)*) ()) => {
+        $crate::select!(@emit ($($arms)*) ($crate::select::Wait::Forever)
+                        { unreachable!() })
+    };
+    (@parse ($($arms:tt)*) (default => $body:expr $(,)?)) => {
+        $crate::select!(@emit ($($arms)*) ($crate::select::Wait::Never) { $body })
+    };
+    (@parse ($($arms:tt)*) (timeout($timeout:expr) => $body:expr $(,)?)) => {
+        $crate::select!(@emit ($($arms)*) ($crate::select::Wait::For($timeout))
+                        { $body })
+    };
+    (@parse ($($arms:tt)*)
+     ($op:ident($target:expr) -> $pat:pat => $body:expr, $($rest:tt)*)) => {
+        $crate::select!(@parse ($($arms)* (slot $op $target, $pat, { $body }))
+                        ($($rest)*))
+    };
+    (@parse ($($arms:tt)*)
+     ($op:ident($target:expr) -> $pat:pat => $body:block $($rest:tt)*)) => {
+        $crate::select!(@parse ($($arms)* (slot $op $target, $pat, { $body }))
+                        ($($rest)*))
+    };
+    (@parse ($($arms:tt)*)
+     ($op:ident($target:expr) -> $pat:pat => $body:expr)) => {
+        $crate::select!(@parse ($($arms)* (slot $op $target, $pat, { $body })) ())
+    };
+    (@parse $($tokens:tt)*) => {
+        compile_error!("expected `recv(..) -> .. => ..`, `respond(..) -> .. => ..`, \
+                        or a final `default` or `timeout(..)` arm")
+    };
+
+    (@emit ($(($slot:ident $op:ident $target:expr, $pat:pat, $body:tt))*)
+     ($wait:expr) $fallback:tt) => {{
+        let mut select = $crate::select::Rotation::new($crate::select!(@count $($slot)*),
+                                                     $wait);
+        $( let mut $slot = None; )*
+
+        'select: loop {
+            for pass in 0..2 {
+                select.pass(pass);
+
+                $(
+                    if select.turn() {
+                        $slot = $crate::select!(@poll $op $target);
+
+                        if $slot.is_some() {
+                            break 'select;
+                        }
+                    }
+                )*
+            }
+
+            if !select.wait() {
+                break;
+            }
+        }
+
+        $crate::select!(@run ($(($slot, $pat, $body))*) $fallback)
+    }};
+
+    (@run () $fallback:tt) => {
+        $fallback
+    };
+    (@run (($slot:ident, $pat:pat, $body:tt) $($rest:tt)*) $fallback:tt) => {
+        if let Some(result) = $slot {
+            let $pat = result;
+            $body
+        }
+        else {
+            $crate::select!(@run ($($rest)*) $fallback)
+        }
+    };
+
+    (@poll recv $target:expr) => {
+        $crate::select::recv(&mut $target)
+    };
+    (@poll respond $target:expr) => {
+        $crate::select::respond(&$target)
+    };
+
+    (@count) => { 0 };
+    (@count $head:tt $($tail:tt)*) => { 1 + $crate::select!(@count $($tail)*) };
+
+    ($($arms:tt)*) => {
+        $crate::select!(@parse () ($($arms)*))
+    };
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use super::super::channel;
+
+    #[test]
+    fn test_select_default() {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let ran = select! {
+            recv(contract) -> _ => "recv",
+            default => "default",
+        };
+        assert_eq!(ran, "default");
+
+        resp.try_respond().ok().unwrap().send(1);
+
+        let ran = select! {
+            recv(contract) -> num => {
+                assert_eq!(num.ok().unwrap(), 1);
+                "recv"
+            }
+            default => "default",
+        };
+        assert_eq!(ran, "recv");
+
+        // A contract that already received its datum is always ready.
+        select! {
+            recv(contract) -> res => {
+                match res {
+                    Err(Error::Done) => {},
+                    _ => { assert!(false); },
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_select_timeout() {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let start = Instant::now();
+        let timed_out = select! {
+            recv(contract) -> _ => false,
+            timeout(Duration::from_millis(10)) => true,
+        };
+        assert!(timed_out);
+        assert!(start.elapsed() >= Duration::from_millis(10));
+
+        resp.try_respond().ok().unwrap().send(1);
+        contract.try_receive().ok().unwrap();
+    }
+
+    #[test]
+    fn test_select_rotates() {
+        let (rqst1, resp1) = channel::<u32>();
+        let (rqst2, resp2) = channel::<u32>();
+        let mut served = [0, 0];
+
+        // Both arms are ready every time, so only the rotation decides.
+        for _ in 0..10 {
+            let mut contract1 = rqst1.try_request().ok().unwrap();
+            let mut contract2 = rqst2.try_request().ok().unwrap();
+
+            select! {
+                respond(resp1) -> contract => {
+                    contract.ok().unwrap().send(1);
+                    served[0] += 1;
+                    contract1.try_receive().ok().unwrap();
+                    contract2.try_cancel().ok().unwrap();
+                }
+                respond(resp2) -> contract => {
+                    contract.ok().unwrap().send(2);
+                    served[1] += 1;
+                    contract2.try_receive().ok().unwrap();
+                    contract1.try_cancel().ok().unwrap();
+                }
+            }
+        }
+
+        assert_eq!(served, [5, 5]);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_select_struct_wait() {
+        let (rqst1, _resp1) = channel::<u32>();
+        let (rqst2, resp2) = channel::<u32>();
+        let mut contract1 = rqst1.try_request().ok().unwrap();
+        let mut contract2 = rqst2.try_request().ok().unwrap();
+
+        {
+            let mut select = Select::new();
+            assert_eq!(select.add(&mut contract1), 0);
+            assert_eq!(select.add(&mut contract2), 1);
+
+            assert_eq!(select.wait_timeout(Duration::from_millis(5)), None);
+
+            let handle = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(5));
+                resp2.try_respond().ok().unwrap().send(2);
+            });
+
+            assert_eq!(select.wait(), 1);
+            assert_eq!(select.get_mut(1).unwrap().try_receive().ok().unwrap(), 2);
+            handle.join().unwrap();
+
+            // A contract that received its datum stays ready.
+            assert_eq!(select.wait_timeout(Duration::from_millis(5)), Some(1));
+        }
+
+        // Dropping the `Select` takes its signal off both channels.
+        assert!(rqst1.inner.response.waiters.is_empty());
+        assert!(rqst2.inner.response.waiters.is_empty());
+
+        contract1.try_cancel().ok().unwrap();
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_select_struct_remove_and_add() {
+        let (rqst1, resp1) = channel::<u32>();
+        let (rqst2, resp2) = channel::<u32>();
+        let mut contract1 = rqst1.try_request().ok().unwrap();
+        let mut contract2 = rqst2.try_request().ok().unwrap();
+
+        let mut select = Select::new();
+        select.add(&mut contract1);
+        select.add(&mut contract2);
+
+        let contract1 = select.remove(0).unwrap();
+        assert!(select.remove(0).is_none());
+        assert!(select.get_mut(0).is_none());
+        assert!(rqst1.inner.response.waiters.is_empty());
+
+        // The removed contract no longer counts.
+        resp1.try_respond().ok().unwrap().send(1);
+        assert_eq!(select.wait_timeout(Duration::from_millis(5)), None);
+
+        // Adding it back reuses its index.
+        assert_eq!(select.add(contract1), 0);
+        assert_eq!(select.wait(), 0);
+        assert_eq!(select.get_mut(0).unwrap().try_receive().ok().unwrap(), 1);
+
+        resp2.try_respond().ok().unwrap().send(2);
+        assert_eq!(select.get_mut(1).unwrap().try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_select_struct_rotates() {
+        let (rqst1, resp1) = channel::<u32>();
+        let (rqst2, resp2) = channel::<u32>();
+        let mut contract1 = rqst1.try_request().ok().unwrap();
+        let mut contract2 = rqst2.try_request().ok().unwrap();
+
+        resp1.try_respond().ok().unwrap().send(1);
+        resp2.try_respond().ok().unwrap().send(2);
+
+        let mut select = Select::new();
+        select.add(&mut contract1);
+        select.add(&mut contract2);
+
+        // Both are ready, so each wait picks the one after the last pick.
+        assert_eq!(select.wait(), 0);
+        assert_eq!(select.wait(), 1);
+        assert_eq!(select.wait(), 0);
+
+        select.get_mut(0).unwrap().try_receive().ok().unwrap();
+        select.get_mut(1).unwrap().try_receive().ok().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_select_across_threads() {
+        const COUNT: u32 = 100;
+
+        let (high_rqst, high_resp) = channel::<u32>();
+        let (low_rqst, low_resp) = channel::<u32>();
+        let (ask_rqst, ask_resp) = channel::<u32>();
+
+        let producers = vec![(high_resp, 0), (low_resp, COUNT)].into_iter().map(|(resp, base)| {
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    loop {
+                        match resp.try_respond() {
+                            Ok(contract) => {
+                                contract.send(base + i);
+                                break;
+                            },
+                            Err(_) => { thread::yield_now(); },
+                        }
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let asker = thread::spawn(move || {
+            let mut answers = Vec::new();
+
+            for _ in 0..COUNT {
+                let mut contract = ask_rqst.try_request().ok().unwrap();
+
+                loop {
+                    match contract.try_receive() {
+                        Ok(num) => {
+                            answers.push(num);
+                            break;
+                        },
+                        Err(Error::Empty) => { thread::yield_now(); },
+                        _ => unreachable!(),
+                    }
+                }
+            }
+
+            answers
+        });
+
+        let mut high_contract = high_rqst.try_request().ok().unwrap();
+        let mut low_contract = low_rqst.try_request().ok().unwrap();
+        let (mut highs, mut lows, mut asked) = (Vec::new(), Vec::new(), 0);
+
+        while highs.len() < COUNT as usize || lows.len() < COUNT as usize || asked < COUNT {
+            select! {
+                recv(high_contract) -> num => {
+                    // A finished contract stays ready with `Error::Done`.
+                    if let Ok(num) = num {
+                        highs.push(num);
+                        if highs.len() < COUNT as usize {
+                            drop(high_contract);
+                            high_contract = high_rqst.try_request().ok().unwrap();
+                        }
+                    }
+                }
+                recv(low_contract) -> num => {
+                    if let Ok(num) = num {
+                        lows.push(num);
+                        if lows.len() < COUNT as usize {
+                            drop(low_contract);
+                            low_contract = low_rqst.try_request().ok().unwrap();
+                        }
+                    }
+                }
+                respond(ask_resp) -> contract => {
+                    contract.ok().unwrap().send(asked);
+                    asked += 1;
+                }
+            }
+        }
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        assert_eq!(highs, (0..COUNT).collect::<Vec<_>>());
+        assert_eq!(lows, (COUNT..2 * COUNT).collect::<Vec<_>>());
+        assert_eq!(asker.join().unwrap(), (0..COUNT).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,599 @@
+//! This module defines `SharedRequester`, a cloneable handle to the
+//! requesting end of a channel.
+
+// `wasm32-unknown-unknown` has no clock, so coalesced requests, which
+// time out, are left out there.
+#![cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code, unused_imports))]
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::backoff::Backoff;
+use super::{BroadcastContract, Error, MultiRequestContract, RequestContract, Requester,
+            Result};
+
+/// This is a cloneable version of `Requester`. Any number of threads can
+/// hold a clone, but only **one** request may be outstanding at a time;
+/// the others get `Error::AlreadyLocked` (or wait in `SharedRequester::request()`)
+/// until the active contract is dropped. A datum is always delivered to the
+/// contract that issued the request it answers.
+///
+/// The responding side does not know or care whether the requesting
+/// side is shared.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// let (requester, responder) = chan::channel::<u32>();
+/// let requester = requester.into_shared();
+/// let requester2 = requester.clone();
+///
+/// let handle = thread::spawn(move || {
+///     let mut contract = requester2.request();
+///     loop {
+///         if let Ok(num) = contract.try_receive() {
+///             break num;
+///         }
+///     }
+/// });
+///
+/// loop {
+///     if let Ok(contract) = responder.try_respond() {
+///         contract.send(5);
+///         break;
+///     }
+/// }
+///
+/// assert_eq!(handle.join().unwrap(), 5);
+/// # drop(requester);
+/// ```
+pub struct SharedRequester<T> {
+    requester: Arc<Requester<T>>,
+    round: Arc<Mutex<Option<Arc<Round<T>>>>>,
+}
+
+impl<T> SharedRequester<T> {
+    /// This function turns a `Requester` into a `SharedRequester`.
+    pub fn new(requester: Requester<T>) -> SharedRequester<T> {
+        SharedRequester {
+            requester: Arc::new(requester),
+            round: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// This method works just like `Requester::try_request()`. It returns
+    /// `Err(Error::AlreadyLocked)` if any clone has an active contract.
+    #[inline]
+    pub fn try_request(&self) -> Result<RequestContract<T>> {
+        self.settle_round();
+        self.requester.try_request()
+    }
+
+    /// This method works just like `Requester::try_fetch()`.
+    #[inline]
+    pub fn try_fetch(&self) -> Result<Option<T>> {
+        self.settle_round();
+        self.requester.try_fetch()
+    }
+
+    /// This method works just like `Requester::try_request_amount()`.
+    #[inline]
+    pub fn try_request_amount(&self, n: usize) -> Result<RequestContract<T>> {
+        self.settle_round();
+        self.requester.try_request_amount(n)
+    }
+
+    /// This method works just like `Requester::try_request_many()`.
+    #[inline]
+    pub fn try_request_many(&self, n: usize) -> Result<MultiRequestContract<T>> {
+        self.settle_round();
+        self.requester.try_request_many(n)
+    }
+
+    /// This method works just like `Requester::try_request_n()`.
+    #[inline]
+    pub fn try_request_n(&self, k: usize) -> Result<MultiRequestContract<T>> {
+        self.settle_round();
+        self.requester.try_request_n(k)
+    }
+
+    /// This method works just like `Requester::try_request_all()`.
+    #[inline]
+    pub fn try_request_all(&self) -> Result<BroadcastContract<T>> {
+        self.settle_round();
+        self.requester.try_request_all()
+    }
+
+    /// This method works just like `Requester::responder_count()`.
+    #[inline]
+    pub fn responder_count(&self) -> usize {
+        self.requester.responder_count()
+    }
+
+    /// This method works just like `Requester::available_responders()`.
+    #[inline]
+    pub fn available_responders(&self) -> usize {
+        self.requester.available_responders()
+    }
+
+    /// This method works just like `Requester::pause()`.
+    #[inline]
+    pub fn pause(&self) {
+        self.requester.pause();
+    }
+
+    /// This method works just like `Requester::resume()`.
+    #[inline]
+    pub fn resume(&self) {
+        self.requester.resume();
+    }
+
+    /// This method works just like `Requester::is_paused()`.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.requester.is_paused()
+    }
+
+    /// This method works just like `Requester::close()`.
+    #[inline]
+    pub fn close(&self) {
+        self.requester.close();
+    }
+
+    /// This method works just like `Requester::is_closed()`.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.requester.is_closed()
+    }
+
+    /// This method issues a request, waiting for other clones to finish
+    /// their requests first if necessary.
+    ///
+    /// # Warning
+    ///
+    /// It waits forever if the current thread already holds a contract.
+    /// It panics if the channel is closed.
+    pub fn request(&self) -> RequestContract<T> {
+        let mut backoff = Backoff::tuned(&self.requester.inner.spin);
+
+        loop {
+            match self.try_request() {
+                Ok(contract) => { return contract; },
+                Err(Error::AlreadyLocked) => {
+                    self.settle_round();
+                    backoff.snooze();
+                },
+                Err(Error::Closed) => { panic!("Cannot request from a closed channel!"); },
+                Err(_) => unreachable!(),
+            }
+        }
+    }
+
+    /// This method finishes the pending round of coalesced requests if
+    /// every caller left it while a responder held the claim, and the
+    /// responder is done since. This frees the requesting side for other
+    /// requests.
+    fn settle_round(&self) {
+        let round = match *self.round.lock().unwrap() {
+            Some(ref round) => round.clone(),
+            None => { return; },
+        };
+
+        let mut state = round.state.lock().unwrap();
+
+        if state.waiters == 0 && state.try_settle() {
+            drop(state);
+            self.leave_round(&round);
+        }
+    }
+
+    /// This method stops new callers from attaching to `round`.
+    fn leave_round(&self, round: &Arc<Round<T>>) {
+        let mut current = self.round.lock().unwrap();
+
+        let finished = match *current {
+            Some(ref pending) => Arc::ptr_eq(pending, round),
+            None => false,
+        };
+
+        if finished {
+            *current = None;
+        }
+    }
+}
+
+impl<T: Clone> SharedRequester<T> {
+    /// This method requests a datum, but instead of issuing its own request
+    /// it attaches to the request of any other clone that is already waiting
+    /// in `SharedRequester::request_coalesced()`. Every attached caller gets
+    /// a clone of the one datum a responder sends.
+    ///
+    /// If `timeout` elapses first, the caller detaches and gets
+    /// `Err(Error::Timeout)`. This does not cancel the request for the
+    /// other callers. The request is only cancelled when its last caller
+    /// detaches. If a responder already claimed it, the caller still
+    /// returns at its deadline, and the request stays pending. The next
+    /// caller attaches to it and gets the responder's datum, and the next
+    /// plain request of any clone finishes it once the responder is done,
+    /// dropping the datum nobody waits for anymore.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for a datum
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let (requester, responder) = chan::channel::<String>();
+    /// let requester = requester.into_shared();
+    ///
+    /// let handles = (0..3).map(|_| {
+    ///     let requester = requester.clone();
+    ///     thread::spawn(move || {
+    ///         requester.request_coalesced(Duration::from_secs(10)).ok().unwrap()
+    ///     })
+    /// }).collect::<Vec<_>>();
+    ///
+    /// // Keep responding until every thread has its datum.
+    /// let mut served = 0;
+    /// while served < 3 {
+    ///     if let Ok(contract) = responder.try_respond() {
+    ///         contract.send(String::from("config"));
+    ///     }
+    ///     served = handles.iter().filter(|h| h.is_finished()).count();
+    /// }
+    ///
+    /// for handle in handles {
+    ///     assert_eq!(handle.join().unwrap().datum, "config");
+    /// }
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn request_coalesced(&self, timeout: Duration) -> Result<Coalesced<T>> {
+        let deadline = Instant::now() + timeout;
+        let round = self.join_round();
+
+        loop {
+            let mut state = round.state.lock().unwrap();
+
+            if let Some(ref datum) = state.datum {
+                return Ok(Coalesced {
+                    datum: datum.clone(),
+                    waiters: state.satisfied,
+                });
+            }
+
+            // Any attached caller can drive the shared request.
+            if state.contract.is_none() {
+                if let Ok(contract) = self.requester.try_request() {
+                    state.contract = Some(contract);
+                }
+            }
+
+            let mut received = match state.contract {
+                Some(ref mut contract) => contract.try_receive().ok(),
+                None => None,
+            };
+
+            let now = Instant::now();
+            if received.is_none() && now >= deadline {
+                state.waiters -= 1;
+
+                // The last caller to leave cancels the request. If a
+                // responder has already claimed it, the caller does not
+                // wait for the datum. The contract stays with the round,
+                // for the next caller or request to settle.
+                if state.waiters == 0 {
+                    let abandoned = match state.contract {
+                        Some(ref mut contract) => match contract.try_cancel() {
+                            Ok(()) | Err(Error::Done) => true,
+                            Err(Error::TooLate) => {
+                                received = contract.try_receive().ok();
+                                false
+                            },
+                            Err(_) => unreachable!(),
+                        },
+                        None => true,
+                    };
+
+                    if abandoned {
+                        state.contract = None;
+                        drop(state);
+                        self.leave_round(&round);
+                        return Err(Error::Timeout);
+                    }
+                }
+
+                if received.is_none() {
+                    return Err(Error::Timeout);
+                }
+
+                // The datum arrived just in time.
+                state.waiters += 1;
+            }
+
+            if let Some(datum) = received {
+                let result = Coalesced {
+                    datum: datum.clone(),
+                    waiters: state.waiters,
+                };
+
+                state.satisfied = state.waiters;
+                state.datum = Some(datum);
+                state.contract = None;
+                drop(state);
+
+                round.ready.notify_all();
+                self.leave_round(&round);
+
+                return Ok(result);
+            }
+
+            let wait = ::std::cmp::min(deadline - now, Duration::from_millis(1));
+            let _ = round.ready.wait_timeout(state, wait).unwrap();
+        }
+    }
+
+    /// This method attaches to the pending round of coalesced requests,
+    /// or starts a new one.
+    fn join_round(&self) -> Arc<Round<T>> {
+        let mut current = self.round.lock().unwrap();
+
+        if let Some(ref round) = *current {
+            let mut state = round.state.lock().unwrap();
+
+            if state.datum.is_none() {
+                state.waiters += 1;
+                return round.clone();
+            }
+        }
+
+        let round = Arc::new(Round {
+            state: Mutex::new(RoundState {
+                contract: None,
+                datum: None,
+                waiters: 1,
+                satisfied: 0,
+            }),
+            ready: Condvar::new(),
+        });
+
+        *current = Some(round.clone());
+
+        round
+    }
+}
+
+impl<T> Clone for SharedRequester<T> {
+    fn clone(&self) -> Self {
+        SharedRequester {
+            requester: self.requester.clone(),
+            round: self.round.clone(),
+        }
+    }
+}
+
+/// This is the result of a successful `SharedRequester::request_coalesced()`.
+#[derive(Debug)]
+pub struct Coalesced<T> {
+    /// The caller's clone of the datum.
+    pub datum: T,
+    /// How many callers were attached to the request when it was satisfied.
+    pub waiters: usize,
+}
+
+/// This is one round of coalesced requests.
+struct Round<T> {
+    state: Mutex<RoundState<T>>,
+    ready: Condvar,
+}
+
+struct RoundState<T> {
+    contract: Option<RequestContract<T>>,
+    datum: Option<T>,
+    waiters: usize,
+    satisfied: usize,
+}
+
+impl<T> RoundState<T> {
+    /// This method polls the shared request of a round every caller left,
+    /// and cancels it if the claim on it was taken back. It returns
+    /// whether the request is finished. A datum that arrived is dropped,
+    /// since nobody waits for it anymore.
+    fn try_settle(&mut self) -> bool {
+        let settled = match self.contract {
+            Some(ref mut contract) => match contract.try_receive() {
+                Ok(_) => true,
+                Err(Error::Empty) => {
+                    !contract.responder_claimed() && contract.try_cancel().is_ok()
+                },
+                Err(_) => true,
+            },
+            None => true,
+        };
+
+        if settled {
+            self.contract = None;
+        }
+
+        settled
+    }
+}
+
+impl<T> Drop for RoundState<T> {
+    fn drop(&mut self) {
+        // The last `SharedRequester` is gone, so nobody can receive the
+        // datum of a request a responder still holds. Rather than wait
+        // for the responder, leave the datum to the channel.
+        if !self.try_settle() {
+            if let Some(contract) = self.contract.take() {
+                contract.abandon();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use super::super::{channel, Builder, ChannelEvent, ChannelObserver, ResponseContract,
+                       Responder};
+
+    #[test]
+    fn test_shared_requester_try_request_multiple() {
+        let (rqst, resp) = channel::<u32>();
+        let rqst = SharedRequester::new(rqst);
+        let rqst2 = rqst.clone();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match rqst2.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        drop(contract);
+
+        // Once the contract is dropped, any clone can request.
+        let mut contract = rqst2.request();
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_shared_requester_request_coalesced_timeout() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+        let rqst = SharedRequester::new(rqst);
+
+        match rqst.request_coalesced(Duration::from_millis(1)) {
+            Err(Error::Timeout) => {},
+            _ => { assert!(false); },
+        }
+
+        // The last caller to leave cancelled the request.
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        assert!(rqst.round.lock().unwrap().is_none());
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_shared_requester_request_coalesced_claimed_after_timeout() {
+        let (rqst, resp) = channel::<u32>();
+        let rqst = SharedRequester::new(rqst);
+
+        // Attach a caller and issue the shared request.
+        let round = rqst.join_round();
+        round.state.lock().unwrap().contract = Some(rqst.try_request().ok().unwrap());
+
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        // The caller leaves, but the request was already claimed.
+        {
+            let mut state = round.state.lock().unwrap();
+            state.waiters -= 1;
+            assert!(state.contract.as_mut().unwrap().try_cancel().is_err());
+        }
+        drop(round);
+
+        resp_contract.send(7);
+
+        // The next caller gets the datum.
+        let coalesced = rqst.request_coalesced(Duration::from_secs(1)).ok().unwrap();
+        assert_eq!(coalesced.datum, 7);
+        assert_eq!(coalesced.waiters, 1);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_shared_requester_request_coalesced_timeout_claim_stalled() {
+        let (rqst, stall) = stalled_channel();
+
+        // The caller leaves at its deadline, although a responder holds
+        // the claim, and so does the next one.
+        for _ in 0..2 {
+            match rqst.request_coalesced(Duration::from_millis(1)) {
+                Err(Error::Timeout) => {},
+                _ => { assert!(false); },
+            }
+        }
+
+        // The request stays pending until the responder is done.
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+        assert!(rqst.round.lock().unwrap().is_some());
+
+        // Then the next request finishes it.
+        stall.contract.lock().unwrap().take().unwrap().send(7);
+        let resp = stall.responder.lock().unwrap().take().unwrap();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        assert!(rqst.round.lock().unwrap().is_none());
+        resp.try_respond().ok().unwrap().send(8);
+        assert_eq!(contract.try_receive().ok().unwrap(), 8);
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[test]
+    fn test_shared_requester_drop_with_claim_stalled() {
+        let (rqst, stall) = stalled_channel();
+
+        match rqst.request_coalesced(Duration::from_millis(1)) {
+            Err(Error::Timeout) => {},
+            _ => { assert!(false); },
+        }
+
+        // Dropping the requester does not wait for the responder, which
+        // can still send.
+        drop(rqst);
+        stall.contract.lock().unwrap().take().unwrap().send(7);
+        stall.responder.lock().unwrap().take().unwrap();
+    }
+
+    // This claims the first request as soon as it is issued, and stalls.
+    struct Stall {
+        armed: AtomicBool,
+        responder: Mutex<Option<Responder<u32>>>,
+        contract: Mutex<Option<ResponseContract<u32>>>,
+    }
+
+    impl ChannelObserver for Stall {
+        fn on_event(&self, event: ChannelEvent) {
+            if event == ChannelEvent::RequestIssued && self.armed.swap(false, Ordering::SeqCst) {
+                if let Some(ref resp) = *self.responder.lock().unwrap() {
+                    *self.contract.lock().unwrap() = resp.try_respond().ok();
+                }
+            }
+        }
+    }
+
+    fn stalled_channel() -> (SharedRequester<u32>, Arc<Stall>) {
+        let stall = Arc::new(Stall {
+            armed: AtomicBool::new(true),
+            responder: Mutex::new(None),
+            contract: Mutex::new(None),
+        });
+        let (rqst, resp) = Builder::new().observer(stall.clone()).build::<u32>();
+        *stall.responder.lock().unwrap() = Some(resp);
+
+        (SharedRequester::new(rqst), stall)
+    }
+}
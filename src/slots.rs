@@ -0,0 +1,434 @@
+//! This module defines a channel with several request slots, so the
+//! requesting side can keep up to `n` requests in flight at once.
+//!
+//! Each slot has its own state and datum cell, so a datum always goes to
+//! the contract of the request it answers. Responders serve requests
+//! oldest first. With a single slot, the channel behaves exactly like
+//! the regular one.
+
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::atomic::{AtomicU8, Ordering};
+use super::{Error, Result};
+
+/// This function creates a channel with `n` request slots and returns a
+/// tuple containing its two ends.
+///
+/// # Panics
+///
+/// It panics if `n` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel_with_slots::<u32>(2);
+///
+/// // Keep two requests in flight.
+/// let mut contract = requester.try_request().ok().unwrap();
+/// let mut contract2 = requester.try_request().ok().unwrap();
+///
+/// // Every slot is taken.
+/// match requester.try_request() {
+///     Err(chan::Error::AlreadyLocked) => {},
+///     _ => unreachable!(),
+/// }
+///
+/// // Responders serve the oldest request first.
+/// responder.try_respond().ok().unwrap().send(1);
+/// responder.try_respond().ok().unwrap().send(2);
+///
+/// assert_eq!(contract2.try_receive().ok().unwrap(), 2);
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub fn channel_with_slots<T>(n: usize) -> (SlotRequester<T>, SlotResponder<T>) {
+    if n == 0 {
+        panic!("Cannot create a channel with zero slots!");
+    }
+
+    let inner = Arc::new(Inner {
+        slots: (0..n).map(|_| Slot {
+            state: AtomicU8::new(IDLE),
+            datum: UnsafeCell::new(None),
+        }).collect(),
+        pending: Mutex::new(VecDeque::with_capacity(n)),
+    });
+
+    (
+        SlotRequester { inner: inner.clone() },
+        SlotResponder { inner: inner.clone() },
+    )
+}
+
+/// This end of a slotted channel requests and receives data from its
+/// `SlotResponder`(s). It works just like `Requester`, except that it can
+/// have one outstanding request per slot.
+pub struct SlotRequester<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> SlotRequester<T> {
+    /// This method tries to request a datum using any free slot. If
+    /// successful, it returns a `SlotRequestContract` bound to that slot.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::AlreadyLocked)` if every slot has an active
+    /// `SlotRequestContract`.
+    pub fn try_request(&self) -> Result<SlotRequestContract<T>> {
+        let mut pending = self.inner.pending.lock().unwrap();
+
+        // Grab the first free slot and queue it up. Taking the slot
+        // acquires everything its last contract did with it.
+        let index = self.inner.slots.iter()
+            .position(|slot| slot.transition(IDLE, REQUESTED, Ordering::Acquire))
+            .ok_or(Error::AlreadyLocked)?;
+        pending.push_back(index);
+
+        Ok(SlotRequestContract {
+            inner: self.inner.clone(),
+            index,
+            done: false,
+        })
+    }
+
+    /// This method returns the number of slots of the channel.
+    #[inline]
+    pub fn slots(&self) -> usize {
+        self.inner.slots.len()
+    }
+}
+
+/// This is the contract returned by a successful `SlotRequester::try_request()`.
+/// It works just like `RequestContract`.
+pub struct SlotRequestContract<T> {
+    inner: Arc<Inner<T>>,
+    index: usize,
+    done: bool,
+}
+
+impl<T> SlotRequestContract<T> {
+    /// This method attempts to receive the datum sent to this contract's
+    /// slot.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it
+    /// after either receiving a datum or cancelling the request.
+    pub fn try_receive(&mut self) -> Result<T> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        let datum = self.inner.slots[self.index].try_take()?;
+        self.done = true;
+
+        Ok(datum)
+    }
+
+    /// This method attempts to cancel the request. It returns
+    /// `Err(Error::TooLate)` if a responder already claimed it.
+    pub fn try_cancel(&mut self) -> Result<()> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        // Responders only claim slots while holding the queue's lock,
+        // so the slot leaves the queue in the same step it is cancelled.
+        let mut pending = self.inner.pending.lock().unwrap();
+
+        if self.inner.slots[self.index].transition(REQUESTED, DONE, Ordering::Relaxed) {
+            pending.retain(|&index| index != self.index);
+            self.done = true;
+            Ok(())
+        }
+        else {
+            Err(Error::TooLate)
+        }
+    }
+}
+
+impl<T> Drop for SlotRequestContract<T> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping SlotRequestContract without receiving data!");
+        }
+
+        self.inner.slots[self.index].state.store(IDLE, Ordering::Release);
+    }
+}
+
+/// This end of a slotted channel sends data in response to requests from
+/// its `SlotRequester`. It works just like `Responder`.
+pub struct SlotResponder<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> SlotResponder<T> {
+    /// This method tries to claim the oldest unclaimed request. If
+    /// successful, it returns a `SlotResponseContract` to ensure the user
+    /// sends a datum.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::AlreadyLocked)` if there is no unclaimed
+    /// request but a responder is still preparing a datum.
+    pub fn try_respond(&self) -> Result<SlotResponseContract<T>> {
+        let mut pending = self.inner.pending.lock().unwrap();
+
+        match pending.pop_front() {
+            Some(index) => {
+                // Only this method and `SlotRequestContract::try_cancel()`
+                // move a queued slot out of `REQUESTED`, and both hold the
+                // queue's lock, so the claim cannot fail.
+                let res = self.inner.slots[index].transition(REQUESTED, CLAIMED, Ordering::Acquire);
+                debug_assert!(res);
+
+                Ok(SlotResponseContract {
+                    inner: self.inner.clone(),
+                    index,
+                    done: false,
+                })
+            },
+            None if self.inner.slots.iter().any(|slot| slot.state.load(Ordering::Relaxed) == CLAIMED) => {
+                Err(Error::AlreadyLocked)
+            },
+            None => Err(Error::NoRequest),
+        }
+    }
+}
+
+impl<T> Clone for SlotResponder<T> {
+    fn clone(&self) -> Self {
+        SlotResponder {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// This is the contract returned by a successful `SlotResponder::try_respond()`.
+/// It works just like `ResponseContract`.
+pub struct SlotResponseContract<T> {
+    inner: Arc<Inner<T>>,
+    index: usize,
+    done: bool,
+}
+
+impl<T> SlotResponseContract<T> {
+    /// This method sends a datum to the contract whose request this
+    /// contract claimed.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send(mut self, datum: T) {
+        self.inner.slots[self.index].put(datum);
+        self.done = true;
+    }
+}
+
+impl<T> Drop for SlotResponseContract<T> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping SlotResponseContract without sending data!");
+        }
+    }
+}
+
+#[doc(hidden)]
+struct Inner<T> {
+    slots: Box<[Slot<T>]>,
+    // The indices of the slots waiting for a responder, oldest first.
+    pending: Mutex<VecDeque<usize>>,
+}
+
+const IDLE: u8 = 0;
+const REQUESTED: u8 = 1;
+const CLAIMED: u8 = 2;
+const READY: u8 = 3;
+const DONE: u8 = 4;
+
+/// This is the state of a single slot.
+#[doc(hidden)]
+struct Slot<T> {
+    state: AtomicU8,
+    datum: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Sync for Slot<T> {}
+
+#[doc(hidden)]
+impl<T> Slot<T> {
+    /// This method atomically moves the slot from one state to another.
+    /// It returns whether or not it succeeded.
+    #[inline]
+    fn transition(&self, old: u8, new: u8, ordering: Ordering) -> bool {
+        self.state.compare_exchange(old, new, ordering, Ordering::Relaxed).is_ok()
+    }
+
+    /// This method stores the datum and marks it ready.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that claimed the slot should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == CLAIMED
+    #[inline]
+    fn put(&self, datum: T) {
+        debug_assert_eq!(self.state.load(Ordering::Relaxed), CLAIMED);
+
+        unsafe {
+            *self.datum.get() = Some(datum);
+        }
+
+        self.state.store(READY, Ordering::Release);
+    }
+
+    /// This method tries to take the datum out of the slot.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the contract that owns the slot should call it.
+    #[inline]
+    fn try_take(&self) -> Result<T> {
+        if self.transition(READY, DONE, Ordering::Acquire) {
+            unsafe {
+                Ok(ptr::replace(self.datum.get(), None).unwrap())
+            }
+        }
+        else {
+            Err(Error::Empty)
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_requester_try_request_all_slots_taken() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel_with_slots::<u32>(2);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut contract2 = rqst.try_request().ok().unwrap();
+
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        // Freeing a slot makes it available again.
+        contract.try_cancel().ok().unwrap();
+        drop(contract);
+
+        let mut contract3 = rqst.try_request().ok().unwrap();
+
+        contract2.try_cancel().ok().unwrap();
+        contract3.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_slot_responder_try_respond_skips_cancelled() {
+        let (rqst, resp) = channel_with_slots::<u32>(3);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut contract2 = rqst.try_request().ok().unwrap();
+
+        contract.try_cancel().ok().unwrap();
+        drop(contract);
+
+        // The freed slot is reused, but its new request is the newest.
+        let mut contract3 = rqst.try_request().ok().unwrap();
+
+        resp.try_respond().ok().unwrap().send(2);
+        resp.try_respond().ok().unwrap().send(3);
+
+        assert_eq!(contract3.try_receive().ok().unwrap(), 3);
+        assert_eq!(contract2.try_receive().ok().unwrap(), 2);
+        assert_eq!(resp.inner.pending.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_slot_channel_single_slot() {
+        let (rqst, resp) = channel_with_slots::<u32>(1);
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        // Just like the regular channel, a second claim finds the
+        // responding side busy.
+        match resp.clone().try_respond() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(4);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 4);
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_channel_with_zero_slots() {
+        channel_with_slots::<u32>(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slot_request_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel_with_slots::<u32>(1);
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slot_response_contract_drop_without_sending_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel_with_slots::<u32>(1);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        contract.done = true;
+
+        #[allow(unused_variables)]
+        let resp_contract = resp.try_respond().ok().unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! This module defines a channel backend for data small enough to share
+//! an atomic word with the channel's state. The datum is packed into the
+//! low bytes of the word and the state into the top byte, so a responder
+//! publishes its datum with a single compare-and-swap, and there is no
+//! separate slot for the two sides to hand over.
+//!
+//! The ends are a `BackendRequester` and a `BackendResponder`, so the
+//! contracts, errors and drop behavior are exactly those of any other
+//! backend, and the backend tests run against it too.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::result;
+
+use super::atomic::{AtomicU64, Ordering};
+use super::{channel_with_backend, BackendRequester, BackendResponder, ChannelBackend, Error,
+            Result, State};
+
+// The state takes the top byte of the word, and the datum the rest.
+const STATE_SHIFT: u32 = 56;
+const DATUM_MASK: u64 = (1 << STATE_SHIFT) - 1;
+const MAX_DATUM_SIZE: usize = 7;
+
+/// This trait marks the types `channel_small()` can carry.
+///
+/// # Safety
+///
+/// Every byte of the type must be initialized, so it must not have any
+/// padding, since the channel copies a datum's bytes into an integer. It
+/// must also be at most 7 bytes long, which `channel_small()` checks when
+/// it is compiled.
+pub unsafe trait SmallDatum: Copy + Send {}
+
+unsafe impl SmallDatum for () {}
+unsafe impl SmallDatum for bool {}
+unsafe impl SmallDatum for char {}
+unsafe impl SmallDatum for u8 {}
+unsafe impl SmallDatum for u16 {}
+unsafe impl SmallDatum for u32 {}
+unsafe impl SmallDatum for i8 {}
+unsafe impl SmallDatum for i16 {}
+unsafe impl SmallDatum for i32 {}
+unsafe impl SmallDatum for f32 {}
+
+/// This function creates a channel for small `Copy` data and returns a
+/// tuple containing its two ends. It works just like `channel()`, but
+/// packs the datum into the channel's state, so a handoff takes one
+/// atomic operation fewer.
+///
+/// It fails to compile if `T` is larger than 7 bytes:
+///
+/// ```rust,compile_fail
+/// extern crate reqchan as chan;
+///
+/// #[derive(Clone, Copy)]
+/// struct Wide([u8; 8]);
+///
+/// unsafe impl chan::SmallDatum for Wide {}
+///
+/// let (requester, responder) = chan::channel_small::<Wide>();
+/// ```
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// let (requester, responder) = chan::channel_small::<u32>();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+///
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub fn channel_small<T>() -> (BackendRequester<T, SmallBackend<T>>,
+                              BackendResponder<T, SmallBackend<T>>)
+    where T: SmallDatum
+{
+    channel_with_backend::<T, SmallBackend<T>>()
+}
+
+/// This is the backend of a channel created with `channel_small()`.
+pub struct SmallBackend<T> {
+    word: AtomicU64,
+    _datum: PhantomData<fn(T) -> T>,
+}
+
+impl<T: SmallDatum> SmallBackend<T> {
+    // Referring to this fails to compile if `T` does not fit beside the
+    // state.
+    const FITS: () = assert!(mem::size_of::<T>() <= MAX_DATUM_SIZE,
+                             "channel_small() only carries data of up to 7 bytes");
+
+    /// This function returns the word of a channel in `state` that holds
+    /// no datum.
+    #[inline]
+    fn bare(state: State) -> u64 {
+        (state as u64) << STATE_SHIFT
+    }
+
+    /// This function copies the bytes of `datum` into the low bytes of a
+    /// word, in the same order on every target.
+    #[inline]
+    fn pack(datum: T) -> u64 {
+        let mut bytes = [0u8; 8];
+
+        // `SmallDatum` promises every byte of `datum` is initialized, and
+        // `FITS` that they fit below the state.
+        unsafe {
+            ptr::copy_nonoverlapping(&datum as *const T as *const u8,
+                                     bytes.as_mut_ptr(),
+                                     mem::size_of::<T>());
+        }
+
+        u64::from_le_bytes(bytes)
+    }
+
+    /// This function copies a datum back out of the low bytes of `word`.
+    #[inline]
+    fn unpack(word: u64) -> T {
+        let bytes = (word & DATUM_MASK).to_le_bytes();
+
+        // Only words `pack()` filled get here, so the bytes form a `T`.
+        unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+    }
+
+    /// This method atomically moves the channel from one state that holds
+    /// no datum to another. If the channel is not in the expected state,
+    /// it returns the state it is in instead.
+    ///
+    /// # Arguments
+    ///
+    /// * from - The state the channel should be in
+    ///
+    /// * to - The state to move it to
+    ///
+    /// * ordering - The ordering of a successful transition
+    #[inline]
+    fn transition(&self, from: State, to: State, ordering: Ordering)
+                  -> result::Result<(), State> {
+        self.word.compare_exchange(Self::bare(from),
+                                   Self::bare(to),
+                                   ordering,
+                                   Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(|word| State::from_u8((word >> STATE_SHIFT) as u8))
+    }
+}
+
+impl<T: SmallDatum> ChannelBackend<T> for SmallBackend<T> {
+    #[inline]
+    fn new() -> SmallBackend<T> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS;
+
+        SmallBackend {
+            word: AtomicU64::new(Self::bare(State::Idle)),
+            _datum: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn try_lock_request(&self) -> Result<()> {
+        self.transition(State::Idle, State::RequestLocked, Ordering::Acquire)
+            .map_err(|_| Error::AlreadyLocked)
+    }
+
+    #[inline]
+    fn flag_request(&self) {
+        // Only the requesting side, which holds the lock, writes here.
+        self.word.store(Self::bare(State::Requested), Ordering::Release);
+    }
+
+    #[inline]
+    fn try_unflag_request(&self) -> Result<()> {
+        self.transition(State::Requested, State::Done, Ordering::Relaxed)
+            .map_err(|_| Error::TooLate)
+    }
+
+    #[inline]
+    fn try_claim_request(&self) -> Result<()> {
+        match self.transition(State::Requested, State::Claimed, Ordering::Acquire) {
+            Ok(()) => Ok(()),
+            Err(State::Claimed) => Err(Error::AlreadyLocked),
+            Err(_) => Err(Error::NoRequest),
+        }
+    }
+
+    #[inline]
+    fn publish_datum(&self, datum: T) {
+        // The datum and the new state land in one exchange.
+        if self.word.compare_exchange(Self::bare(State::Claimed),
+                                      Self::bare(State::DataReady) | Self::pack(datum),
+                                      Ordering::Release,
+                                      Ordering::Relaxed).is_err() {
+            panic!("SmallBackend found a datum already published for its claim!");
+        }
+    }
+
+    #[inline]
+    fn try_take_datum(&self) -> Result<T> {
+        let word = self.word.load(Ordering::Acquire);
+
+        if word >> STATE_SHIFT != State::DataReady as u64 {
+            return Err(Error::Empty);
+        }
+
+        // Only the requesting side leaves `State::DataReady`, so nothing
+        // else can change the word before this store.
+        self.word.store(Self::bare(State::Done), Ordering::Relaxed);
+
+        Ok(Self::unpack(word))
+    }
+
+    #[inline]
+    fn unlock_request(&self) {
+        // Leave an unfinished request alone, just like the regular channel.
+        self.transition(State::Done, State::Idle, Ordering::Release).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_backend_round_trips_data() {
+        let (rqst, resp) = channel_small::<char>();
+
+        for datum in "a\u{e9}\u{1f980}".chars() {
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(datum);
+            assert_eq!(contract.try_receive().ok().unwrap(), datum);
+        }
+
+        let (rqst, resp) = channel_small::<i16>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(-2);
+        assert_eq!(contract.try_receive().ok().unwrap(), -2);
+    }
+}
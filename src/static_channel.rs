@@ -0,0 +1,160 @@
+//! This module defines a channel whose state can live in a `static`. It
+//! never allocates: its ends just point at the `static`, which outlives
+//! them by definition. That suits embedded code and services that avoid
+//! heap allocation on their hot paths.
+//!
+//! The ends are a regular `Requester` and `Responder`, so the contracts,
+//! errors and drop behavior are exactly those of a regular channel.
+
+use super::atomic::{AtomicBool, Ordering};
+use super::{Error, Inner, Link, Requester, Responder, Result};
+
+/// This is the storage for a channel that lives in a `static`.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// static CHAN: chan::StaticChannel<u32> = chan::StaticChannel::new();
+///
+/// let (requester, responder) = CHAN.split().ok().unwrap();
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+/// responder.try_respond().ok().unwrap().send(1);
+///
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub struct StaticChannel<T> {
+    inner: Inner<T>,
+    split: AtomicBool,
+}
+
+impl<T> StaticChannel<T> {
+    const_fn! {
+        /// This function creates the storage for an idle static channel.
+        pub fn new() -> StaticChannel<T> {
+            StaticChannel {
+                inner: Inner::new(),
+                split: AtomicBool::new(false),
+            }
+        }
+    }
+
+    /// This method returns the two ends of the channel. The `Responder`
+    /// can be cloned as usual.
+    ///
+    /// # Warning
+    ///
+    /// It only succeeds once, so the channel never has more than the one
+    /// `Requester` it hands out. It returns `Err(Error::AlreadyLocked)`
+    /// on every later call.
+    pub fn split(&'static self) -> Result<(Requester<T>, Responder<T>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            return Err(Error::AlreadyLocked);
+        }
+
+        // A `static` outlives every handle and contract.
+        let inner = unsafe { Link::borrowed(&self.inner) };
+
+        Ok((Requester::new(inner.clone()), Responder::new(inner)))
+    }
+}
+
+impl<T> Default for StaticChannel<T> {
+    fn default() -> Self {
+        StaticChannel::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants, clippy::bool_assert_comparison)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_static_channel_split_once() {
+        static CHAN: StaticChannel<u32> = StaticChannel::new();
+
+        #[allow(unused_variables)]
+        let (rqst, resp) = CHAN.split().ok().unwrap();
+
+        match CHAN.split() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_static_channel_request_and_cancel() {
+        static CHAN: StaticChannel<u32> = StaticChannel::new();
+
+        let (rqst, resp) = CHAN.split().ok().unwrap();
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+
+            match rqst.try_request() {
+                Err(Error::AlreadyLocked) => {},
+                _ => { assert!(false); },
+            }
+
+            contract.try_cancel().ok().unwrap();
+        }
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.clone().try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_static_channel_across_threads() {
+        static CHAN: StaticChannel<u32> = StaticChannel::new();
+
+        let (rqst, resp) = CHAN.split().ok().unwrap();
+
+        let handle = thread::spawn(move || {
+            loop {
+                if let Ok(contract) = resp.try_respond() {
+                    contract.send(3);
+                    break;
+                }
+
+                thread::yield_now();
+            }
+        });
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        loop {
+            if let Ok(num) = contract.try_receive() {
+                assert_eq!(num, 3);
+                break;
+            }
+
+            thread::yield_now();
+        }
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_static_channel_drop_request_contract_panics() {
+        static CHAN: StaticChannel<u32> = StaticChannel::new();
+
+        #[allow(unused_variables)]
+        let (rqst, resp) = CHAN.split().ok().unwrap();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! This module defines a channel whose state lives in storage the caller
+//! allocates, so it can be reused instead of freed once the channel is
+//! done. Services that open many short-lived channels can keep a few
+//! `ChannelStorage`s around and skip the allocation of each channel.
+//!
+//! The ends are a regular `Requester` and `Responder`, so the contracts,
+//! errors and drop behavior are exactly those of a regular channel.
+
+use std::sync::Arc;
+
+use super::atomic::{AtomicBool, Ordering};
+use super::{Error, Inner, Link, Requester, Responder, Result};
+
+/// This is the storage for a channel created with `channel_in()`.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::sync::Arc;
+///
+/// let mut storage = Arc::new(chan::ChannelStorage::<u32>::new());
+///
+/// for num in 0..3 {
+///     let (requester, responder) = chan::channel_in(storage.clone());
+///
+///     let mut contract = requester.try_request().ok().unwrap();
+///     responder.try_respond().ok().unwrap().send(num);
+///     assert_eq!(contract.try_receive().ok().unwrap(), num);
+///
+///     drop((contract, requester, responder));
+///     chan::ChannelStorage::try_reclaim(&mut storage).ok().unwrap();
+/// }
+/// ```
+pub struct ChannelStorage<T> {
+    inner: Inner<T>,
+    in_use: AtomicBool,
+}
+
+impl<T> ChannelStorage<T> {
+    const_fn! {
+        /// This function creates the storage for an idle channel.
+        pub fn new() -> ChannelStorage<T> {
+            ChannelStorage {
+                inner: Inner::new(),
+                in_use: AtomicBool::new(false),
+            }
+        }
+    }
+
+    /// This function makes `storage` ready for another `channel_in()`.
+    /// It resets the state of the channel, and drops any datum that was
+    /// sent but never received.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::AlreadyLocked)`, and leaves the storage
+    /// alone, while any end, contract or weak handle of the last channel
+    /// is alive, or while `storage` has other clones.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage to reclaim
+    pub fn try_reclaim(storage: &mut Arc<ChannelStorage<T>>) -> Result<()> {
+        let storage = match Arc::get_mut(storage) {
+            Some(storage) => storage,
+            None => { return Err(Error::AlreadyLocked); },
+        };
+
+        // Dropping the old state drops its stale datum.
+        storage.inner = Inner::new();
+        storage.in_use.store(false, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// This method returns whether a channel was created in the storage
+    /// since it was created or last reclaimed.
+    #[inline]
+    pub fn is_in_use(&self) -> bool {
+        self.in_use.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Default for ChannelStorage<T> {
+    fn default() -> Self {
+        ChannelStorage::new()
+    }
+}
+
+/// This function creates a `reqchan` in `storage` instead of a fresh
+/// allocation, and returns a tuple containing its two ends. Once both
+/// ends and every contract are gone, `ChannelStorage::try_reclaim()` makes
+/// the storage ready for the next channel.
+///
+/// # Panics
+///
+/// It panics if a channel was already created in `storage` and the
+/// storage was not reclaimed since.
+///
+/// # Arguments
+///
+/// * `storage` - The storage of the channel
+pub fn channel_in<T>(storage: Arc<ChannelStorage<T>>) -> (Requester<T>, Responder<T>) {
+    if storage.in_use.swap(true, Ordering::AcqRel) {
+        panic!("ChannelStorage is already in use!");
+    }
+
+    let inner = Link::Stored(storage);
+
+    (
+        Requester::new(inner.clone()),
+        Responder::new(inner),
+    )
+}
+
+impl<T> Link<T> {
+    /// This method returns the state of a channel that lives in a
+    /// `ChannelStorage`.
+    #[inline]
+    pub(crate) fn stored(storage: &ChannelStorage<T>) -> &Inner<T> {
+        &storage.inner
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    // This counts how many times values of it were dropped.
+    struct Tracked(Arc<AtomicUsize>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_channel_in_reclaim() {
+        let mut storage = Arc::new(ChannelStorage::<u32>::new());
+        assert!(!storage.is_in_use());
+
+        let (rqst, resp) = channel_in(storage.clone());
+        assert!(storage.is_in_use());
+
+        // The storage cannot host two channels at once.
+        let res = panic::catch_unwind(AssertUnwindSafe(|| { let _ = channel_in(storage.clone()); }));
+        assert!(res.is_err());
+
+        let resp2 = resp.clone();
+        drop((rqst, resp));
+        match ChannelStorage::try_reclaim(&mut storage) {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+        assert!(storage.is_in_use());
+
+        drop(resp2);
+        ChannelStorage::try_reclaim(&mut storage).ok().unwrap();
+        assert!(!storage.is_in_use());
+
+        // The new channel starts out idle.
+        let (rqst, resp) = channel_in(storage.clone());
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_channel_in_reclaim_resets_state() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut storage = Arc::new(ChannelStorage::<Tracked>::new());
+
+        {
+            let (rqst, resp) = channel_in(storage.clone());
+
+            // Leave a datum in the slot, and the channel closed.
+            let contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(Tracked(drops.clone()));
+            let res = panic::catch_unwind(AssertUnwindSafe(|| drop(contract)));
+            assert!(res.is_err());
+            rqst.close();
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        ChannelStorage::try_reclaim(&mut storage).ok().unwrap();
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (rqst, resp) = channel_in(storage.clone());
+        assert!(!rqst.is_closed() && !resp.is_closed());
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(Tracked(drops.clone()));
+        drop(contract.try_receive().ok().unwrap());
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,253 @@
+//! This module defines subscriptions: single-response requests that stay
+//! open after each datum, so a requester can keep receiving data without
+//! issuing a new request for each one.
+//!
+//! A subscription holds the slot one datum at a time. Once the requesting
+//! side takes a datum, the request is issued again for whichever responder
+//! claims it next. Until then, responders get `Error::SlotFull`.
+
+use super::atomic::Ordering;
+use super::{Error, Inner, RequestContract, Requester, Result};
+
+/// This is the contract returned by a successful `Requester::try_subscribe()`.
+/// It keeps the requesting side of the channel locked until it is closed.
+/// Dropping it closes it, but panics just like a `RequestContract` if a
+/// responder already claimed the next datum.
+pub struct SubscriptionContract<T> {
+    request: RequestContract<T>,
+    open: bool,
+}
+
+impl<T> SubscriptionContract<T> {
+    /// This method attempts to receive the next datum. Unless the
+    /// subscription was closed, the request is issued again right away.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` once the subscription is closed and
+    /// every datum in flight was received.
+    pub fn try_next(&mut self) -> Result<T> {
+        let datum = self.request.try_receive()?;
+
+        if self.open {
+            self.request.done = false;
+            self.request.inner.reflag_request();
+        }
+
+        Ok(datum)
+    }
+
+    /// This method returns whether the subscription still asks for data.
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// This method stops asking for data and wakes any responder waiting
+    /// for a request. It returns `Err(Error::TooLate)` if a responder
+    /// already claimed the next datum; `SubscriptionContract::try_next()`
+    /// has to receive it before the contract can be dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut subscription = requester.try_subscribe().ok().unwrap();
+    ///
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// assert_eq!(subscription.try_next().ok().unwrap(), 1);
+    ///
+    /// // A responder is already working on the next datum.
+    /// let contract = responder.try_respond().ok().unwrap();
+    /// match subscription.close() {
+    ///     Err(chan::Error::TooLate) => {},
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// contract.send(2);
+    /// assert_eq!(subscription.try_next().ok().unwrap(), 2);
+    /// assert!(responder.try_respond().is_err());
+    /// ```
+    pub fn close(&mut self) -> Result<()> {
+        if self.open {
+            self.open = false;
+            self.request.inner.set_subscribed(false);
+        }
+
+        let res = match self.request.try_cancel() {
+            Ok(()) | Err(Error::Done) => Ok(()),
+            Err(err) => Err(err),
+        };
+
+        self.request.inner.wake_responders();
+
+        res
+    }
+}
+
+impl<T> Drop for SubscriptionContract<T> {
+    fn drop(&mut self) {
+        // A datum in flight makes the `RequestContract` panic.
+        let _ = self.close();
+    }
+}
+
+impl<T> Requester<T> {
+    /// This method tries to open a subscription: a request that stays
+    /// open after each datum. Responders answer it with `try_respond()`
+    /// as usual, one datum at a time, and get `Err(Error::SlotFull)` while
+    /// the last datum has not been received yet.
+    ///
+    /// # Warning
+    ///
+    /// The subscription locks the requesting side until it is closed, just
+    /// like a `RequestContract`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    ///
+    /// let mut subscription = requester.try_subscribe().ok().unwrap();
+    ///
+    /// for num in 0..3 {
+    ///     responder.try_respond().ok().unwrap().send(num);
+    ///
+    ///     // The last datum was not received yet.
+    ///     match responder.try_respond() {
+    ///         Err(chan::Error::SlotFull) => {},
+    ///         _ => unreachable!(),
+    ///     }
+    ///
+    ///     assert_eq!(subscription.try_next().ok().unwrap(), num);
+    /// }
+    ///
+    /// subscription.close().ok().unwrap();
+    /// ```
+    pub fn try_subscribe(&self) -> Result<SubscriptionContract<T>> {
+        let request = self.try_request_with(|inner| inner.set_subscribed(true))?;
+
+        Ok(SubscriptionContract {
+            request,
+            open: true,
+        })
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method sets whether the current request is a subscription.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requesting side of the channel should call it.
+    #[inline]
+    pub(crate) fn set_subscribed(&self, subscribed: bool) {
+        self.request.subscribed.store(subscribed, Ordering::Relaxed);
+    }
+
+    /// This method returns whether the current request is a subscription.
+    #[inline]
+    pub(crate) fn is_subscribed(&self) -> bool {
+        self.request.subscribed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use super::super::backoff::Backoff;
+    use super::super::channel;
+
+    #[test]
+    fn test_subscription_close() {
+        let (rqst, resp) = channel::<u32>();
+
+        let mut subscription = rqst.try_subscribe().ok().unwrap();
+        assert!(subscription.is_open());
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        subscription.close().ok().unwrap();
+        assert!(!subscription.is_open());
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        match subscription.try_next() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+        drop(subscription);
+
+        // A plain request after a subscription is not one.
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_subscription_across_threads() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let workers: Vec<_> = vec![resp, resp2].into_iter().map(|resp| {
+            thread::spawn(move || {
+                let mut backoff = Backoff::new();
+                let mut sent = 0;
+
+                loop {
+                    match resp.try_respond() {
+                        Ok(contract) => {
+                            contract.send(1);
+                            sent += 1;
+                        },
+                        Err(Error::SlotFull) | Err(Error::AlreadyLocked) => { backoff.snooze(); },
+                        Err(Error::NoRequest) if resp.inner.has_requesters() => { backoff.snooze(); },
+                        Err(_) => { return sent; },
+                    }
+                }
+            })
+        }).collect();
+
+        let mut subscription = rqst.try_subscribe().ok().unwrap();
+        let mut received = 0;
+        let mut backoff = Backoff::new();
+
+        while received < 100 {
+            match subscription.try_next() {
+                Ok(num) => { received += num; },
+                Err(Error::Empty) => { backoff.snooze(); },
+                _ => { assert!(false); },
+            }
+        }
+
+        // Drain whatever a worker claimed before the subscription closed.
+        while subscription.close().is_err() {
+            if let Ok(num) = subscription.try_next() {
+                received += num;
+            }
+        }
+
+        drop(subscription);
+        drop(rqst);
+
+        let sent: u32 = workers.into_iter().map(|worker| worker.join().unwrap()).sum();
+        assert_eq!(sent, received);
+    }
+}
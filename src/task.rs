@@ -0,0 +1,141 @@
+//! This module defines `Task`, a closure that can be sent through a
+//! channel and run once on the other side. Channels of work items are the
+//! most common use of `reqchan`, and since a boxed `FnOnce` could not be
+//! called by value before Rust 1.35, every user used to write the same
+//! `FnBox` helper trait to pass closures around.
+
+use std::fmt;
+
+/// This is a closure that runs once, on whichever thread receives it.
+///
+/// A `Task` is not `UnwindSafe`, since the closure may capture anything.
+/// To catch a panic from `Task::run()`, wrap the call in `AssertUnwindSafe`
+/// once you know the closure's captures are fine to use after a panic:
+/// `panic::catch_unwind(AssertUnwindSafe(|| task.run()))`.
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate reqchan as chan;
+///
+/// use chan::Task;
+///
+/// fn main() {
+///     let (requester, responder) = chan::channel::<Task>();
+///
+///     let mut contract = requester.try_request().ok().unwrap();
+///     responder.try_respond().ok().unwrap().send(task!(println!("Hello World!")));
+///
+///     contract.try_receive().ok().unwrap().run();
+/// }
+/// ```
+pub struct Task(Box<dyn FnOnce() + Send + 'static>);
+
+impl Task {
+    /// This function wraps a closure in a `Task`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure to run
+    #[inline]
+    pub fn new<F>(f: F) -> Task
+        where F: FnOnce() + Send + 'static
+    {
+        Task(Box::new(f))
+    }
+
+    /// This method runs the task, consuming it.
+    #[inline]
+    pub fn run(self) {
+        (self.0)()
+    }
+}
+
+impl<F> From<F> for Task
+    where F: FnOnce() + Send + 'static
+{
+    #[inline]
+    fn from(f: F) -> Task {
+        Task::new(f)
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Task")
+    }
+}
+
+/// This macro creates a `Task` whose closure runs the given statements,
+/// moving whatever they capture.
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate reqchan;
+///
+/// fn main() {
+///     let name = String::from("World");
+///     let task = task! {
+///         let greeting = format!("Hello {}!", name);
+///         println!("{}", greeting);
+///     };
+///
+///     task.run();
+/// }
+/// ```
+#[macro_export]
+macro_rules! task {
+    ($($body:tt)*) => {
+        $crate::Task::new(move || { $($body)* })
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use super::super::atomic::{AtomicUsize, Ordering};
+    use super::super::channel;
+
+    #[test]
+    fn test_task_run() {
+        let count = Arc::new(AtomicUsize::new(0));
+
+        let count2 = count.clone();
+        Task::new(move || { count2.fetch_add(1, Ordering::SeqCst); }).run();
+
+        let count2 = count.clone();
+        let task: Task = (move || { count2.fetch_add(2, Ordering::SeqCst); }).into();
+        task.run();
+
+        let count2 = count.clone();
+        task!(count2.fetch_add(4, Ordering::SeqCst);).run();
+
+        assert_eq!(count.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_task_across_threads() {
+        let (rqst, resp) = channel::<Task>();
+        let count = Arc::new(AtomicUsize::new(0));
+        let count2 = count.clone();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let handle = thread::spawn(move || {
+            resp.try_respond().ok().unwrap().send(task! {
+                count2.fetch_add(1, Ordering::SeqCst);
+            });
+        });
+        handle.join().unwrap();
+
+        contract.try_receive().ok().unwrap().run();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,474 @@
+//! This module lets tests force the interleavings that races between the
+//! two sides of a channel depend on. It is only built with the `testing`
+//! feature.
+//!
+//! A channel made with `channel_with_controller()` stops at named
+//! `YieldPoint`s in its handoffs, and asks its `Controller` whether to go
+//! on. A test arms a point, runs one side on another thread until that
+//! thread is parked there, acts on the other side, and then lets the
+//! parked thread go. That turns a race normal tests hit once in a blue
+//! moon into a script, such as "request; respond up to the claim; cancel;
+//! finish responding".
+//!
+//! A channel without a controller only checks that it has none at each
+//! point.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+use super::{Builder, Inner, Requester, Responder};
+
+/// This is a point in a channel's handoffs where a `Controller` can park
+/// the thread that reaches it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum YieldPoint {
+    /// The requesting side just flagged a single-response request.
+    AfterFlagRequest,
+    /// A responder just claimed a single-response request, before it
+    /// checks whether it may keep it.
+    AfterClaimRequest,
+    /// A responder is about to write its datum to the slot.
+    BeforeSetDatum,
+    /// The requesting side is about to withdraw its request.
+    BeforeCancelRequest,
+    /// The requesting side is about to take the datum from the slot.
+    BeforeTakeDatum,
+}
+
+/// This decides where the threads using its channels stop.
+///
+/// # Warning
+///
+/// Dropping it releases every parked thread and disarms every point.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::thread;
+///
+/// use chan::testing::{channel_with_controller, Controller, YieldPoint};
+///
+/// let controller = Controller::new();
+/// let (requester, responder) = channel_with_controller::<u32>(&controller);
+///
+/// let mut contract = requester.try_request().ok().unwrap();
+///
+/// // Let the responder claim the request, but stop it right after.
+/// controller.pause_at(YieldPoint::AfterClaimRequest);
+/// let handle = thread::spawn(move || {
+///     responder.try_respond().ok().unwrap().send(1);
+/// });
+/// controller.wait_paused(YieldPoint::AfterClaimRequest);
+///
+/// // The claim already happened, so the request cannot be cancelled.
+/// match contract.try_cancel() {
+///     Err(chan::Error::TooLate) => {},
+///     _ => unreachable!(),
+/// }
+///
+/// controller.resume(YieldPoint::AfterClaimRequest);
+/// handle.join().unwrap();
+/// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+/// ```
+pub struct Controller {
+    pub(crate) control: Arc<Control>,
+}
+
+impl Controller {
+    /// This function creates a controller with no point armed.
+    pub fn new() -> Controller {
+        Controller {
+            control: Arc::new(Control {
+                points: Mutex::new(Points {
+                    armed: Vec::new(),
+                    parked: Vec::new(),
+                    hits: HashMap::new(),
+                    released: false,
+                }),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    /// This method makes the next thread that reaches `point` park there
+    /// until `Controller::resume()` lets it go. Arming a point twice parks
+    /// the next two threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - Where to park
+    pub fn pause_at(&self, point: YieldPoint) {
+        self.control.arm(point, None);
+    }
+
+    /// This method works just like `Controller::pause_at()`, but only
+    /// parks `thread`. Other threads pass `point` as usual.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread` - The thread to park
+    ///
+    /// * `point` - Where to park it
+    pub fn pause_thread_at(&self, thread: ThreadId, point: YieldPoint) {
+        self.control.arm(point, Some(thread));
+    }
+
+    /// This method blocks until a thread is parked at `point`, and returns
+    /// that thread's id.
+    ///
+    /// # Warning
+    ///
+    /// It blocks forever if no thread ever reaches an armed `point`.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - Where to look for a parked thread
+    pub fn wait_paused(&self, point: YieldPoint) -> ThreadId {
+        let mut points = self.control.points.lock().unwrap();
+
+        loop {
+            if let Some(&(thread, _)) = points.parked.iter().find(|&&(_, at)| at == point) {
+                return thread;
+            }
+
+            points = self.control.changed.wait(points).unwrap();
+        }
+    }
+
+    /// This method lets every thread parked at `point` go on.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - Where the threads are parked
+    pub fn resume(&self, point: YieldPoint) {
+        let mut points = self.control.points.lock().unwrap();
+        points.parked.retain(|&(_, at)| at != point);
+        self.control.changed.notify_all();
+    }
+
+    /// This method lets `thread` go on, wherever it is parked.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread` - The thread to let go
+    pub fn resume_thread(&self, thread: ThreadId) {
+        let mut points = self.control.points.lock().unwrap();
+        points.parked.retain(|&(parked, _)| parked != thread);
+        self.control.changed.notify_all();
+    }
+
+    /// This method returns how many times any thread reached `point`.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - The point to count
+    pub fn hits(&self, point: YieldPoint) -> usize {
+        let points = self.control.points.lock().unwrap();
+        points.hits.get(&point).cloned().unwrap_or(0)
+    }
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Controller::new()
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        let mut points = self.control.points.lock().unwrap();
+        points.released = true;
+        points.armed.clear();
+        points.parked.clear();
+        self.control.changed.notify_all();
+    }
+}
+
+/// This function creates a `reqchan` whose handoffs stop where
+/// `controller` says, and returns a tuple containing its two ends.
+///
+/// # Arguments
+///
+/// * `controller` - The controller of the channel's yield points
+pub fn channel_with_controller<T>(controller: &Controller) -> (Requester<T>, Responder<T>) {
+    Builder::new().controller(controller).build()
+}
+
+/// This is the state a `Controller` shares with its channels.
+#[doc(hidden)]
+pub(crate) struct Control {
+    points: Mutex<Points>,
+    changed: Condvar,
+}
+
+struct Points {
+    // The points to park at, and the thread to park, if only one.
+    armed: Vec<(YieldPoint, Option<ThreadId>)>,
+    parked: Vec<(ThreadId, YieldPoint)>,
+    hits: HashMap<YieldPoint, usize>,
+    // Whether the `Controller` is gone.
+    released: bool,
+}
+
+impl Control {
+    fn arm(&self, point: YieldPoint, thread: Option<ThreadId>) {
+        let mut points = self.points.lock().unwrap();
+
+        if !points.released {
+            points.armed.push((point, thread));
+        }
+    }
+
+    /// This method counts the calling thread reaching `point`, and parks
+    /// it there if `point` is armed for it.
+    fn reach(&self, point: YieldPoint) {
+        let me = thread::current().id();
+        let mut points = self.points.lock().unwrap();
+
+        *points.hits.entry(point).or_insert(0) += 1;
+
+        let armed = points.armed.iter().position(|&(at, thread)| {
+            at == point && thread.is_none_or(|thread| thread == me)
+        });
+
+        if let Some(index) = armed {
+            points.armed.remove(index);
+            points.parked.push((me, point));
+            self.changed.notify_all();
+
+            while points.parked.iter().any(|&(parked, _)| parked == me) {
+                points = self.changed.wait(points).unwrap();
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method lets the channel's controller, if any, park the
+    /// calling thread at `point`.
+    ///
+    /// # Warning
+    ///
+    /// **NEVER** call it while holding one of the channel's locks.
+    #[inline]
+    pub(crate) fn yield_at(&self, point: YieldPoint) {
+        if let Some(ref control) = self.control {
+            control.reach(point);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use super::super::Error;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_cancel_after_claim() {
+        let controller = Controller::new();
+        let (rqst, resp) = channel_with_controller::<u32>(&controller);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        controller.pause_at(YieldPoint::AfterClaimRequest);
+        let responder = thread::spawn(move || {
+            resp.try_respond().ok().unwrap().send(1);
+        });
+        controller.wait_paused(YieldPoint::AfterClaimRequest);
+
+        // The claim won, so the cancel is too late.
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        controller.resume(YieldPoint::AfterClaimRequest);
+        responder.join().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_claim_after_cancel() {
+        let controller = Controller::new();
+        let (rqst, resp) = channel_with_controller::<u32>(&controller);
+
+        let contract = rqst.try_request().ok().unwrap();
+
+        controller.pause_at(YieldPoint::BeforeCancelRequest);
+        let requester = thread::spawn(move || {
+            let mut contract = contract;
+            let res = contract.try_cancel();
+            (res, contract)
+        });
+        controller.wait_paused(YieldPoint::BeforeCancelRequest);
+
+        // The responder claims first, so the cancel has to fail.
+        let response = resp.try_respond().ok().unwrap();
+        controller.resume(YieldPoint::BeforeCancelRequest);
+
+        let (res, mut contract) = requester.join().unwrap();
+        match res {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        response.send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_cancel_wins_before_claim() {
+        let controller = Controller::new();
+        let (rqst, resp) = channel_with_controller::<u32>(&controller);
+
+        controller.pause_at(YieldPoint::AfterFlagRequest);
+        let requester = thread::spawn(move || {
+            let mut contract = rqst.try_request().ok().unwrap();
+            contract.try_cancel().ok().unwrap();
+        });
+        controller.wait_paused(YieldPoint::AfterFlagRequest);
+
+        // The request is out, but the cancel runs before any claim.
+        assert!(resp.has_request());
+        controller.resume(YieldPoint::AfterFlagRequest);
+        requester.join().unwrap();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+        assert_eq!(controller.hits(YieldPoint::AfterClaimRequest), 0);
+        assert_eq!(controller.hits(YieldPoint::BeforeCancelRequest), 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_close_after_claim() {
+        let controller = Controller::new();
+        let (rqst, resp) = channel_with_controller::<u32>(&controller);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        controller.pause_at(YieldPoint::BeforeSetDatum);
+        let responder = thread::spawn(move || {
+            resp.try_respond().ok().unwrap().send(1);
+            resp
+        });
+        controller.wait_paused(YieldPoint::BeforeSetDatum);
+
+        // Closing does not withdraw a claimed request.
+        rqst.close();
+        controller.resume(YieldPoint::BeforeSetDatum);
+
+        let resp = responder.join().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        match resp.try_respond() {
+            Err(Error::Closed) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sealed_claim_outlives_claim_timeout() {
+        use std::time::Duration;
+
+        let controller = Controller::new();
+        let (rqst, resp) = Builder::new()
+            .claim_timeout(Duration::from_millis(1))
+            .controller(&controller)
+            .build::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        controller.pause_at(YieldPoint::BeforeSetDatum);
+        let responder = thread::spawn(move || {
+            resp.try_respond().ok().unwrap().send(1);
+        });
+        controller.wait_paused(YieldPoint::BeforeSetDatum);
+
+        // The responder sealed its claim before it stalled, so the
+        // requesting side cannot take the request back.
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+        thread::sleep(Duration::from_millis(2));
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        controller.resume(YieldPoint::BeforeSetDatum);
+        responder.join().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_pause_thread_at() {
+        let controller = Controller::new();
+        let (rqst, resp) = channel_with_controller::<u32>(&controller);
+        let resp2 = resp.clone();
+
+        let (go, wait) = mpsc::channel();
+        let other = thread::spawn(move || {
+            wait.recv().unwrap();
+            resp2.try_respond().ok().unwrap().send(2);
+        });
+        controller.pause_thread_at(other.thread().id(), YieldPoint::BeforeSetDatum);
+
+        // Other threads pass the point.
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+        assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        assert_eq!(controller.hits(YieldPoint::BeforeSetDatum), 1);
+        drop(contract);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        go.send(()).unwrap();
+        assert_eq!(controller.wait_paused(YieldPoint::BeforeSetDatum), other.thread().id());
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        controller.resume_thread(other.thread().id());
+        other.join().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_drop_controller_releases() {
+        let controller = Controller::new();
+        let (rqst, resp) = channel_with_controller::<u32>(&controller);
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        controller.pause_at(YieldPoint::BeforeSetDatum);
+        let responder = thread::spawn(move || {
+            resp.try_respond().ok().unwrap().send(3);
+        });
+        controller.wait_paused(YieldPoint::BeforeSetDatum);
+
+        drop(controller);
+        responder.join().unwrap();
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+    }
+}
@@ -0,0 +1,55 @@
+//! This module defines the request throttle a channel can be built with.
+//! It keeps a requesting loop from issuing requests faster than a set
+//! interval, which only matters when something polls far too eagerly.
+//! Channels built without one keep no throttle state at all.
+
+use std::time::Duration;
+
+use super::atomic::{AtomicU64, Ordering};
+use super::clock::now;
+
+/// This tracks when the requesting side last finished a request.
+#[doc(hidden)]
+pub(crate) struct Throttle {
+    interval: u64,
+    // When the last request finished, as a `clock::now()` timestamp, or
+    // 0 if none has yet.
+    finished: AtomicU64,
+}
+
+impl Throttle {
+    #[inline]
+    pub(crate) fn new(interval: Duration) -> Throttle {
+        Throttle {
+            interval: interval.as_nanos() as u64,
+            finished: AtomicU64::new(0),
+        }
+    }
+
+    /// This method returns how much longer the requesting side has to
+    /// wait before its next request, or `None` if it may issue it now.
+    #[inline]
+    pub(crate) fn wait(&self) -> Option<Duration> {
+        let finished = self.finished.load(Ordering::Relaxed);
+
+        if finished == 0 {
+            return None;
+        }
+
+        let ready = finished.saturating_add(self.interval);
+        let now = now();
+
+        if now < ready {
+            Some(Duration::from_nanos(ready - now))
+        }
+        else {
+            None
+        }
+    }
+
+    /// This method records that the requesting side finished a request.
+    #[inline]
+    pub(crate) fn mark(&self) {
+        self.finished.store(now().max(1), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,185 @@
+//! This module defines traits for the two ends of a channel and their
+//! contracts, so code that only issues requests, or only answers them, can
+//! take any implementation instead of a concrete `Requester` or
+//! `Responder`. Tests can then hand it a mock instead of spinning up a
+//! channel and the threads around it.
+//!
+//! The ends name their contracts with associated types, so the real ones
+//! return their usual contracts and nothing gets boxed. The end traits are
+//! object safe once the contract type is fixed, as in
+//! `dyn ResponseSource<T, Contract = ResponseContract<T>>`.
+
+use super::{RequestContract, Requester, ResponseContract, Responder, Result};
+
+/// This trait is implemented by anything that issues requests, like a
+/// `Requester`.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use chan::{PendingRequest, RequestSink};
+///
+/// // This only needs something to request from.
+/// fn fetch<R: RequestSink<u32>>(requester: &R) -> Option<u32> {
+///     let mut contract = requester.try_request().ok()?;
+///
+///     match contract.try_receive() {
+///         Ok(num) => Some(num),
+///         Err(_) => {
+///             contract.try_cancel().ok().unwrap();
+///             None
+///         },
+///     }
+/// }
+///
+/// let (requester, responder) = chan::channel::<u32>();
+/// assert_eq!(fetch(&requester), None);
+/// # drop(responder);
+/// ```
+pub trait RequestSink<T> {
+    /// This is the contract a successful request returns.
+    type Contract: PendingRequest<T>;
+
+    /// This method works just like `Requester::try_request()`.
+    fn try_request(&self) -> Result<Self::Contract>;
+}
+
+/// This trait is implemented by the contract of an outstanding request,
+/// like a `RequestContract`.
+pub trait PendingRequest<T> {
+    /// This method works just like `RequestContract::try_receive()`.
+    fn try_receive(&mut self) -> Result<T>;
+
+    /// This method works just like `RequestContract::try_cancel()`.
+    fn try_cancel(&mut self) -> Result<()>;
+}
+
+/// This trait is implemented by anything that answers requests, like a
+/// `Responder`.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan as chan;
+///
+/// use std::cell::Cell;
+///
+/// use chan::{PendingResponse, ResponseSource};
+///
+/// // This only needs something to respond through.
+/// fn serve<R: ResponseSource<u32>>(responder: &R, num: u32) -> bool {
+///     match responder.try_respond() {
+///         Ok(contract) => {
+///             contract.send(num);
+///             true
+///         },
+///         Err(_) => false,
+///     }
+/// }
+///
+/// // A mock that records what it was sent.
+/// struct Recorder<'a>(&'a Cell<Option<u32>>);
+/// struct Record<'a>(&'a Cell<Option<u32>>);
+///
+/// impl<'a> ResponseSource<u32> for Recorder<'a> {
+///     type Contract = Record<'a>;
+///
+///     fn try_respond(&self) -> chan::Result<Record<'a>> {
+///         Ok(Record(self.0))
+///     }
+/// }
+///
+/// impl<'a> PendingResponse<u32> for Record<'a> {
+///     fn send(self, datum: u32) {
+///         self.0.set(Some(datum));
+///     }
+/// }
+///
+/// let sent = Cell::new(None);
+/// assert!(serve(&Recorder(&sent), 3));
+/// assert_eq!(sent.get(), Some(3));
+/// ```
+pub trait ResponseSource<T> {
+    /// This is the contract a successful claim returns.
+    type Contract: PendingResponse<T>;
+
+    /// This method works just like `Responder::try_respond()`.
+    fn try_respond(&self) -> Result<Self::Contract>;
+}
+
+/// This trait is implemented by the contract of a claimed request, like a
+/// `ResponseContract`.
+pub trait PendingResponse<T> {
+    /// This method works just like `ResponseContract::send()`.
+    fn send(self, datum: T);
+}
+
+impl<T> RequestSink<T> for Requester<T> {
+    type Contract = RequestContract<T>;
+
+    #[inline]
+    fn try_request(&self) -> Result<RequestContract<T>> {
+        Requester::try_request(self)
+    }
+}
+
+impl<T> PendingRequest<T> for RequestContract<T> {
+    #[inline]
+    fn try_receive(&mut self) -> Result<T> {
+        RequestContract::try_receive(self)
+    }
+
+    #[inline]
+    fn try_cancel(&mut self) -> Result<()> {
+        RequestContract::try_cancel(self)
+    }
+}
+
+impl<T> ResponseSource<T> for Responder<T> {
+    type Contract = ResponseContract<T>;
+
+    #[inline]
+    fn try_respond(&self) -> Result<ResponseContract<T>> {
+        Responder::try_respond(self)
+    }
+}
+
+impl<T> PendingResponse<T> for ResponseContract<T> {
+    #[inline]
+    fn send(self, datum: T) {
+        ResponseContract::send(self, datum)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+    use super::super::{channel, Error};
+
+    fn relay<Q, R>(requester: &Q, responder: &dyn ResponseSource<u32, Contract = R>) -> u32
+        where Q: RequestSink<u32>,
+              R: PendingResponse<u32>
+    {
+        let mut contract = requester.try_request().ok().unwrap();
+        responder.try_respond().ok().unwrap().send(5);
+        contract.try_receive().ok().unwrap()
+    }
+
+    #[test]
+    fn test_traits_on_real_ends() {
+        let (rqst, resp) = channel::<u32>();
+        assert_eq!(relay(&rqst, &resp), 5);
+
+        // The trait methods report the same errors.
+        match ResponseSource::try_respond(&resp) {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+
+        let mut contract = RequestSink::try_request(&rqst).ok().unwrap();
+        PendingRequest::try_cancel(&mut contract).ok().unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! This module lets a channel check every datum before it reaches the
+//! requesting side, so a responder that sends garbage is caught where it
+//! sends it instead of wherever the requester trips over it. It is only
+//! active on channels built with `Builder::validator()`.
+//!
+//! A rejected datum goes back to its responder, and the claim goes back
+//! to the channel, so the request stays pending for any responder.
+
+use std::result;
+use std::sync::Arc;
+
+use super::{ChannelEvent, Claim, Inner, ResponseContract};
+
+/// This is the error a validator returns for a datum it rejects. It says
+/// why, and is reported with `ChannelEvent::DatumRejected`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationError(pub &'static str);
+
+/// This is a validator, as the channel keeps it.
+pub(crate) type ValidatorFn<T> = Arc<dyn Fn(&T) -> result::Result<(), ValidationError> + Send + Sync>;
+
+/// This marks a `Builder` that has a validator for `T`s. Such a builder
+/// only builds channels of `T`.
+pub struct Validated<T>(pub(crate) ValidatorFn<T>);
+
+impl<T> Clone for Validated<T> {
+    fn clone(&self) -> Self {
+        Validated(self.0.clone())
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method runs the channel's validator, if any, on `datum`.
+    #[inline]
+    fn validate(&self, datum: &T) -> result::Result<(), ValidationError> {
+        match self.validator {
+            Some(ref validator) => validator(datum),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> ResponseContract<T> {
+    /// This method checks `datum` before the contract sends it. If the
+    /// channel's validator rejects it, the claim is handed back, the
+    /// contract is done, and the datum comes back.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The datum about to be sent
+    #[inline]
+    pub(crate) fn check(&mut self, datum: T) -> result::Result<T, T> {
+        let err = match self.inner.validate(&datum) {
+            Ok(()) => { return Ok(datum); },
+            Err(err) => err,
+        };
+
+        match self.claim {
+            Claim::Single => self.inner.release_claim(self.lease),
+            Claim::Many => self.inner.unclaim_many(),
+            Claim::Broadcast => self.inner.unclaim_broadcast(self.responder),
+        }
+
+        self.done = true;
+        self.inner.count_rejected();
+        self.inner.notify(ChannelEvent::DatumRejected(self.responder, err));
+
+        Err(datum)
+    }
+}
@@ -0,0 +1,401 @@
+//! This module lets users wake the other side of a channel with their own
+//! code, for threads that can neither park nor poll, such as a GUI event
+//! loop that only wakes up when something posts an event to its queue.
+//!
+//! Each side of a channel has one hook, which the other side calls right
+//! after publishing something. A side without a hook only checks a flag,
+//! so the hooks cost one load per request or datum until someone sets one.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use super::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+use super::{Inner, Requester, Responder};
+
+/// This is a replaceable callback.
+#[doc(hidden)]
+pub(crate) struct NotifyHook {
+    // Whether `hook` has ever been set, so calls can skip the lock.
+    set: AtomicBool,
+    hook: Mutex<Option<Arc<dyn Fn() + Send + Sync>>>,
+}
+
+impl NotifyHook {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> NotifyHook {
+            NotifyHook {
+                set: AtomicBool::new(false),
+                hook: Mutex::new(None),
+            }
+        }
+    }
+
+    /// This method replaces the callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The new callback
+    fn replace(&self, hook: Arc<dyn Fn() + Send + Sync>) {
+        *self.hook.lock().unwrap() = Some(hook);
+        self.set.store(true, Ordering::Release);
+    }
+
+    /// This method calls the callback, if any.
+    #[inline]
+    fn call(&self) {
+        if self.set.load(Ordering::Acquire) {
+            self.call_slow();
+        }
+    }
+
+    /// This method calls the callback outside the lock, so the callback
+    /// may replace itself. It stays out of line so sides without a
+    /// callback only pay for the check.
+    #[cold]
+    #[inline(never)]
+    fn call_slow(&self) {
+        let hook = self.hook.lock().unwrap().clone();
+
+        if let Some(hook) = hook {
+            hook();
+        }
+    }
+}
+
+/// This is a flag one thread blocks on until others raise it.
+#[doc(hidden)]
+pub(crate) struct Signal {
+    raised: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Signal {
+    #[inline]
+    pub(crate) fn new() -> Signal {
+        Signal {
+            raised: Mutex::new(false),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// This method raises the flag and wakes the blocked thread.
+    fn raise(&self) {
+        *self.raised.lock().unwrap() = true;
+        self.cond.notify_one();
+    }
+
+    /// This method lowers the flag.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** call it before checking whatever the flag signals, so a
+    /// raise that happens after the check is not lost.
+    pub(crate) fn lower(&self) {
+        *self.raised.lock().unwrap() = false;
+    }
+
+    /// This method blocks until the flag is raised, and returns `false`
+    /// if `deadline` passed first.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - When to give up, if ever
+    pub(crate) fn wait(&self, deadline: Option<Instant>) -> bool {
+        let mut raised = self.raised.lock().unwrap();
+
+        while !*raised {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    raised = self.cond.wait_timeout(raised, deadline - now).unwrap().0;
+                },
+                None => { raised = self.cond.wait(raised).unwrap(); },
+            }
+        }
+
+        true
+    }
+}
+
+/// This is something a channel wakes whenever it publishes a request or
+/// a datum.
+#[doc(hidden)]
+pub(crate) trait Waiter: Send + Sync {
+    /// This method wakes whoever is waiting. It returns `false` once
+    /// nobody can be waiting anymore, so the channel can drop it.
+    fn wake(&self) -> bool;
+}
+
+impl Waiter for Signal {
+    #[inline]
+    fn wake(&self) -> bool {
+        self.raise();
+        true
+    }
+}
+
+/// This is the set of waiters one side of a channel wakes.
+#[doc(hidden)]
+pub(crate) struct Waiters {
+    // The number of waiters in `waiters`, so wakes can skip the lock.
+    count: AtomicUsize,
+    waiters: Mutex<Vec<Arc<dyn Waiter>>>,
+}
+
+impl Waiters {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> Waiters {
+            Waiters {
+                count: AtomicUsize::new(0),
+                waiters: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    /// This method adds a waiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `waiter` - The waiter to add
+    pub(crate) fn add(&self, waiter: Arc<dyn Waiter>) {
+        let mut waiters = self.waiters.lock().unwrap();
+        waiters.push(waiter);
+        self.count.store(waiters.len(), Ordering::Relaxed);
+        drop(waiters);
+
+        // Pairs with the fence in `wake()`: either the publisher sees the
+        // new waiter, or the waiter's next check sees what was published.
+        fence(Ordering::SeqCst);
+    }
+
+    /// This method removes one copy of a waiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `waiter` - The waiter to remove
+    pub(crate) fn remove<W>(&self, waiter: &Arc<W>)
+        where W: Waiter + ?Sized
+    {
+        let target = Arc::as_ptr(waiter) as *const u8;
+        let mut waiters = self.waiters.lock().unwrap();
+
+        if let Some(index) = waiters.iter().position(|w| Arc::as_ptr(w) as *const u8 == target) {
+            waiters.swap_remove(index);
+        }
+        self.count.store(waiters.len(), Ordering::Relaxed);
+    }
+
+    /// This method returns whether there are no waiters.
+    #[cfg(test)]
+    #[cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code))]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count.load(Ordering::Relaxed) == 0
+    }
+
+    /// This method wakes every waiter.
+    #[inline]
+    fn wake(&self) {
+        fence(Ordering::SeqCst);
+
+        if self.count.load(Ordering::Relaxed) > 0 {
+            self.wake_slow();
+        }
+    }
+
+    /// This method wakes every waiter and drops the ones nobody waits on
+    /// anymore. It stays out of line so channels nobody waits on only pay
+    /// for the check.
+    #[cold]
+    #[inline(never)]
+    fn wake_slow(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        waiters.retain(|waiter| waiter.wake());
+        self.count.store(waiters.len(), Ordering::Relaxed);
+    }
+}
+
+impl<T> Requester<T> {
+    /// This method sets the function the channel calls whenever a
+    /// responder sends a datum, so the requesting thread can be woken
+    /// through its own event loop. It replaces any function set before.
+    ///
+    /// # Warning
+    ///
+    /// The function runs on the responder's thread right after the datum
+    /// is published, so it must be cheap and must not block. It may be
+    /// called spuriously: a requesting thread that polls on its own can
+    /// take the datum before the function runs. Treat a call as a hint to
+    /// poll, not as a promise that a datum is waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to call
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::sync::mpsc;
+    ///
+    /// // This stands in for a GUI event queue.
+    /// let (post, events) = mpsc::channel();
+    /// let post = std::sync::Mutex::new(post);
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// requester.set_notify(move || { post.lock().unwrap().send("datum").unwrap(); });
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// responder.try_respond().ok().unwrap().send(1);
+    ///
+    /// assert_eq!(events.recv().unwrap(), "datum");
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// ```
+    pub fn set_notify<F>(&self, f: F)
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.inner.response.wake_requester.replace(Arc::new(f));
+    }
+}
+
+impl<T> Responder<T> {
+    /// This method sets the function the channel calls whenever the
+    /// requesting side issues a request of any kind, so a responding
+    /// thread can be woken through its own event loop. The channel has one
+    /// such function for all its responders, so setting it from any
+    /// responder replaces the function set before.
+    ///
+    /// # Warning
+    ///
+    /// The function runs on the requesting thread right after the request
+    /// is published, so it must be cheap and must not block. It may be
+    /// called spuriously: another responder may claim the request, or the
+    /// requesting side may cancel it, before the woken one gets to it.
+    /// Treat a call as a hint to poll, not as a promise that a request is
+    /// waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to call
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// let woken = Arc::new(AtomicUsize::new(0));
+    /// let woken2 = woken.clone();
+    ///
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// responder.set_notify(move || { woken2.fetch_add(1, Ordering::Relaxed); });
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// assert_eq!(woken.load(Ordering::Relaxed), 1);
+    ///
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// contract.try_receive().ok().unwrap();
+    /// ```
+    pub fn set_notify<F>(&self, f: F)
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.inner.request.wake_responders.replace(Arc::new(f));
+    }
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method calls the responding side's notify hook and wakes
+    /// its waiters.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** call it after the request has been published.
+    #[inline]
+    pub(crate) fn wake_responders(&self) {
+        self.request.wake_responders.call();
+        self.request.waiters.wake();
+    }
+
+    /// This method calls the requesting side's notify hook and wakes its
+    /// waiters, such as a `Select` waiting on the channel.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** call it after the datum has been published.
+    #[inline]
+    pub(crate) fn wake_requester(&self) {
+        self.response.wake_requester.call();
+        self.response.waiters.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::atomic::AtomicUsize;
+    use super::super::channel;
+
+    #[test]
+    fn test_wake_every_kind_of_request() {
+        let (rqst, resp) = channel::<u32>();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let data = Arc::new(AtomicUsize::new(0));
+
+        let requests2 = requests.clone();
+        resp.set_notify(move || { requests2.fetch_add(1, Ordering::Relaxed); });
+        let data2 = data.clone();
+        rqst.set_notify(move || { data2.fetch_add(1, Ordering::Relaxed); });
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        {
+            let mut contract = rqst.try_request_many(2).ok().unwrap();
+            resp.clone().try_respond().ok().unwrap().send(2);
+            resp.clone().try_respond().ok().unwrap().send(3);
+            contract.try_receive().ok().unwrap();
+            contract.try_receive().ok().unwrap();
+        }
+
+        {
+            let mut contract = rqst.try_request_all().ok().unwrap();
+            resp.try_respond().ok().unwrap().send(4);
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert_eq!(requests.load(Ordering::Relaxed), 3);
+        assert_eq!(data.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_wake_replace() {
+        let (rqst, resp) = channel::<u32>();
+        let first = Arc::new(AtomicUsize::new(0));
+        let second = Arc::new(AtomicUsize::new(0));
+
+        let first2 = first.clone();
+        resp.set_notify(move || { first2.fetch_add(1, Ordering::Relaxed); });
+
+        // Responders share one hook, so the latest one replaces it.
+        let resp2 = resp.clone();
+        let second2 = second.clone();
+        resp2.set_notify(move || { second2.fetch_add(1, Ordering::Relaxed); });
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        contract.try_cancel().ok().unwrap();
+
+        assert_eq!(first.load(Ordering::Relaxed), 0);
+        assert_eq!(second.load(Ordering::Relaxed), 1);
+    }
+}
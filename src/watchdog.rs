@@ -0,0 +1,201 @@
+//! This module detects stuck channels. It is only built with the
+//! `debug-watchdog` feature.
+//!
+//! A channel gets stuck when a request is in flight but neither side can
+//! move it along, for example because someone leaked a contract with
+//! `mem::forget()`. The requester then polls `Error::Empty` and the
+//! responders poll `Error::NoRequest` or `Error::AlreadyLocked` forever.
+//! To catch that, the channel timestamps every event it reports to its
+//! observer, and `Monitor` checks how long ago the last one happened while
+//! a request is in flight. Polls that fail do not count as progress.
+
+use std::thread;
+use std::time::Duration;
+
+use super::atomic::Ordering;
+use super::clock::now;
+use super::weak::WeakLink;
+use super::{Inner, Link, State};
+
+/// This is a dump of a channel's state, for finding out why it is stuck.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelState {
+    /// The stage of the single-response request, for display: `"idle"`,
+    /// `"request locked"`, `"requested"`, `"claimed"`, `"data ready"`,
+    /// `"expired"` or `"done"`. Requests for several responses and broadcasts keep it
+    /// at `"request locked"`.
+    pub stage: &'static str,
+    /// The number of responses of the current multi-response request no
+    /// responder has claimed yet.
+    pub pending_many: usize,
+    /// Whether a broadcast is open.
+    pub broadcasting: bool,
+    /// Whether a `HeldResponse` reserves single-response requests.
+    pub held: bool,
+    /// The number of live `Requester`s.
+    pub requesters: usize,
+    /// The number of live `Responder`s.
+    pub responders: usize,
+    /// How long the channel has gone without progress.
+    pub stalled_for: Duration,
+}
+
+/// This function spawns the thread behind `Monitor::spawn_watchdog()`.
+///
+/// # Arguments
+///
+/// * `inner` - The channel to watch
+///
+/// * `threshold` - How long the channel may go without progress
+///
+/// * `callback` - The function to call with each stall
+pub(crate) fn spawn<T, F>(inner: &Link<T>, threshold: Duration, mut callback: F)
+                          -> thread::JoinHandle<()>
+    where T: Send + 'static,
+          F: FnMut(ChannelState) + Send + 'static
+{
+    let inner = WeakLink::new(inner);
+    let period = threshold / 4;
+
+    thread::spawn(move || {
+        // The last progress the watchdog reported a stall after.
+        let mut reported = None;
+
+        loop {
+            thread::sleep(period);
+
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => { return; },
+            };
+
+            let progress = inner.progress.load(Ordering::Relaxed);
+
+            match inner.stalled_for() {
+                Some(stalled_for) if stalled_for >= threshold
+                    && reported != Some(progress) => {
+                    reported = Some(progress);
+                    callback(inner.channel_state(stalled_for));
+                },
+                _ => {},
+            }
+        }
+    })
+}
+
+#[doc(hidden)]
+impl<T> Inner<T> {
+    /// This method notes that the channel made progress.
+    #[inline]
+    pub(crate) fn stamp_progress(&self) {
+        self.progress.store(now(), Ordering::Relaxed);
+    }
+
+    /// This method returns how long the channel has gone without progress
+    /// while a request is in flight.
+    pub(crate) fn stalled_for(&self) -> Option<Duration> {
+        match self.state() {
+            State::Idle => None,
+            _ => {
+                let progress = self.progress.load(Ordering::Relaxed);
+                Some(Duration::from_nanos(now().saturating_sub(progress)))
+            },
+        }
+    }
+
+    /// This method returns a dump of the channel's state.
+    ///
+    /// # Arguments
+    ///
+    /// * `stalled_for` - How long the channel has gone without progress
+    fn channel_state(&self, stalled_for: Duration) -> ChannelState {
+        ChannelState {
+            stage: stage_name(self.state()),
+            pending_many: self.pending_many(),
+            broadcasting: self.request.broadcasting.load(Ordering::Relaxed),
+            held: self.is_held(),
+            requesters: self.request.requesters.load(Ordering::Relaxed),
+            responders: self.responder_count(),
+            stalled_for,
+        }
+    }
+}
+
+/// This function returns the display name of a `State`.
+fn stage_name(state: State) -> &'static str {
+    match state {
+        State::Idle => "idle",
+        State::RequestLocked => "request locked",
+        State::Requested => "requested",
+        State::Claimed => "claimed",
+        State::Expired => "expired",
+        State::DataReady => "data ready",
+        State::Done => "done",
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use std::mem;
+    use std::sync::mpsc;
+
+    use super::*;
+    use super::super::{channel_monitored, Error};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_watchdog_stalled_for() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+
+        assert_eq!(monitor.stalled_for(), None);
+
+        {
+            let mut contract = rqst.try_request().ok().unwrap();
+            assert!(monitor.stalled_for().is_some());
+
+            resp.try_respond().ok().unwrap().send(1);
+            contract.try_receive().ok().unwrap();
+        }
+
+        assert_eq!(monitor.stalled_for(), None);
+
+        // A failed poll is not progress.
+        let mut contract = rqst.try_request().ok().unwrap();
+        thread::sleep(Duration::from_millis(5));
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        assert!(monitor.stalled_for().unwrap() >= Duration::from_millis(5));
+
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_watchdog_reports_leaked_contract_once() {
+        let (rqst, resp, monitor) = channel_monitored::<u32>();
+        let (sender, receiver) = mpsc::channel();
+
+        monitor.spawn_watchdog(Duration::from_millis(20), move |state| {
+            sender.send(state).unwrap();
+        });
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        mem::forget(resp.try_respond().ok().unwrap());
+
+        let state = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(state.stage, "claimed");
+        assert_eq!(state.requesters, 1);
+        assert_eq!(state.responders, 1);
+        assert!(state.stalled_for >= Duration::from_millis(20));
+
+        // The same stall is not reported again.
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+        contract.done = true;
+    }
+}
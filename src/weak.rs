@@ -0,0 +1,269 @@
+//! This module defines weak handles to a channel. They do not keep the
+//! channel alive and do not count as live requesters or responders, so
+//! they can sit in long-lived registries without hiding the fact that the
+//! real workers are gone.
+//!
+//! A weak handle upgrades to a strong one only while at least one strong
+//! handle of the same kind exists.
+
+use std::sync::{Arc, Weak};
+
+use super::{ChannelStorage, Inner, Link, Requester, Responder};
+
+/// This is a weak version of `Requester`, created with
+/// `Requester::downgrade()`.
+pub struct WeakRequester<T> {
+    inner: WeakLink<T>,
+}
+
+impl<T> WeakRequester<T> {
+    #[inline]
+    pub(crate) fn new(inner: &Link<T>) -> WeakRequester<T> {
+        WeakRequester {
+            inner: WeakLink::new(inner),
+        }
+    }
+
+    /// This method tries to turn this handle back into a `Requester`. It
+    /// returns `None` if every `Requester` of the channel was dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// #[allow(unused_variables)]
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let weak = requester.downgrade();
+    ///
+    /// assert!(weak.upgrade().is_some());
+    ///
+    /// drop(requester);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Requester<T>> {
+        let inner = self.inner.upgrade()?;
+
+        if inner.try_add_requester() {
+            Some(Requester::adopt(inner))
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl<T> Clone for WeakRequester<T> {
+    fn clone(&self) -> Self {
+        WeakRequester {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// This is a weak version of `Responder`, created with
+/// `Responder::downgrade()`.
+pub struct WeakResponder<T> {
+    inner: WeakLink<T>,
+    priority: u8,
+}
+
+impl<T> WeakResponder<T> {
+    #[inline]
+    pub(crate) fn new(inner: &Link<T>, priority: u8) -> WeakResponder<T> {
+        WeakResponder {
+            inner: WeakLink::new(inner),
+            priority,
+        }
+    }
+
+    /// This method tries to turn this handle back into a `Responder` with
+    /// the priority the original had when it was downgraded. It returns
+    /// `None` if every `Responder` of the channel was dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// #[allow(unused_variables)]
+    /// let (requester, responder) = chan::channel::<u32>();
+    /// let weak = responder.downgrade();
+    ///
+    /// assert!(weak.upgrade().is_some());
+    ///
+    /// drop(responder);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Responder<T>> {
+        let inner = self.inner.upgrade()?;
+        let (id, tally) = inner.try_register_responder()?;
+
+        let responder = Responder::with_id(inner, id, tally);
+        responder.set_priority(self.priority);
+
+        Some(responder)
+    }
+}
+
+impl<T> Clone for WeakResponder<T> {
+    fn clone(&self) -> Self {
+        WeakResponder {
+            inner: self.inner.clone(),
+            priority: self.priority,
+        }
+    }
+}
+
+/// This is a weak version of `Link`.
+#[doc(hidden)]
+pub(crate) enum WeakLink<T> {
+    Shared(Weak<Inner<T>>),
+    // The owner of the storage guarantees it outlives every handle.
+    Borrowed(*const Inner<T>),
+    Stored(Weak<ChannelStorage<T>>),
+}
+
+unsafe impl<T: Send> Send for WeakLink<T> {}
+unsafe impl<T: Send> Sync for WeakLink<T> {}
+
+impl<T> WeakLink<T> {
+    #[inline]
+    pub(crate) fn new(link: &Link<T>) -> WeakLink<T> {
+        match *link {
+            Link::Shared(ref inner) => WeakLink::Shared(Arc::downgrade(inner)),
+            Link::Borrowed(inner) => WeakLink::Borrowed(inner),
+            Link::Stored(ref storage) => WeakLink::Stored(Arc::downgrade(storage)),
+        }
+    }
+
+    /// This method returns a strong `Link`, or `None` if the channel's
+    /// state was already freed.
+    #[inline]
+    pub(crate) fn upgrade(&self) -> Option<Link<T>> {
+        match *self {
+            WeakLink::Shared(ref inner) => inner.upgrade().map(Link::Shared),
+            WeakLink::Borrowed(inner) => Some(Link::Borrowed(inner)),
+            WeakLink::Stored(ref storage) => storage.upgrade().map(Link::Stored),
+        }
+    }
+}
+
+impl<T> Clone for WeakLink<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        match *self {
+            WeakLink::Shared(ref inner) => WeakLink::Shared(inner.clone()),
+            WeakLink::Borrowed(inner) => WeakLink::Borrowed(inner),
+            WeakLink::Stored(ref storage) => WeakLink::Stored(storage.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::super::{channel, Error};
+
+    #[test]
+    fn test_weak_responder_upgrade_after_all_strong_dropped() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        let weak = resp.downgrade();
+
+        drop(resp);
+        let resp3 = weak.upgrade().unwrap();
+
+        drop(resp2);
+        drop(resp3);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_weak_responder_upgrade_keeps_priority() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = channel::<u32>();
+        resp.set_priority(3);
+
+        let weak = resp.downgrade();
+        let resp2 = weak.upgrade().unwrap();
+
+        assert_eq!(resp2.priority(), 3);
+        assert!(resp2.id() != resp.id());
+    }
+
+    #[test]
+    fn test_weak_responder_does_not_count_as_live() {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+
+        #[allow(unused_variables)]
+        let weak = resp2.downgrade();
+        drop(resp2);
+
+        // The broadcast only waits on the strong responder.
+        let mut contract = rqst.try_request_all().ok().unwrap();
+        assert_eq!(contract.responses_remaining(), 1);
+
+        resp.try_respond().ok().unwrap().send(1);
+        contract.try_receive().ok().unwrap();
+    }
+
+    #[test]
+    fn test_weak_responder_broadcast_all_dropped() {
+        let (rqst, resp) = channel::<u32>();
+
+        let weak = resp.downgrade();
+
+        let mut contract = rqst.try_request_all().ok().unwrap();
+        assert_eq!(contract.responses_remaining(), 1);
+
+        // Dropping the last strong responder releases the broadcast,
+        // even though a weak handle still exists.
+        drop(resp);
+        assert_eq!(contract.responses_remaining(), 0);
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+
+        drop(contract);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_weak_requester_upgrade() {
+        let (rqst, resp) = channel::<u32>();
+
+        let weak = rqst.downgrade();
+        let rqst2 = weak.upgrade().unwrap();
+
+        drop(rqst);
+
+        // The upgraded handle still works.
+        let mut contract = rqst2.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(2);
+        assert_eq!(contract.try_receive().ok().unwrap(), 2);
+        drop(contract);
+
+        drop(rqst2);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_weak_handles_do_not_keep_channel_alive() {
+        let (rqst, resp) = channel::<u32>();
+
+        let weak_rqst = rqst.downgrade();
+        let weak_resp = resp.downgrade();
+
+        drop(rqst);
+        drop(resp);
+
+        assert!(weak_rqst.upgrade().is_none());
+        assert!(weak_resp.upgrade().is_none());
+    }
+}
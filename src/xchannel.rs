@@ -0,0 +1,416 @@
+//! This module defines a multi-producer, multi-consumer version of the
+//! channel. Both ends can be cloned, and a request from any `XRequester`
+//! can be served by any `XResponder`.
+//!
+//! Each request gets its own slot. Requests wait in a queue until a
+//! responder claims them, oldest first. As with the regular channel,
+//! exactly one responder wins each request, and the requester must either
+//! receive the datum or cancel the request.
+
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::atomic::{AtomicBool, AtomicU8, Ordering};
+use super::{try_lock, unlock, Error, Result};
+
+/// This function creates an `xchannel` and returns a tuple containing
+/// its two ends. Both ends can be cloned.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate reqchan;
+///
+/// #[allow(unused_variables)]
+/// let (requester, responder) = reqchan::xchannel::<u32>();
+/// ```
+pub fn xchannel<T>() -> (XRequester<T>, XResponder<T>) {
+    let inner = Arc::new(Inner {
+        pending: Mutex::new(VecDeque::new()),
+    });
+
+    (
+        XRequester::new(inner.clone()),
+        XResponder { inner: inner.clone() },
+    )
+}
+
+/// This end of the `xchannel` requests and receives data from any
+/// `XResponder`. Each clone may have one outstanding request.
+pub struct XRequester<T> {
+    inner: Arc<Inner<T>>,
+    has_request_lock: Arc<AtomicBool>,
+}
+
+impl<T> XRequester<T> {
+    #[inline]
+    fn new(inner: Arc<Inner<T>>) -> XRequester<T> {
+        XRequester {
+            inner,
+            has_request_lock: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// This method tries to request a datum from any `XResponder`.
+    /// If successful, it returns an `XRequestContract` to either poll for
+    /// data or cancel the request.
+    ///
+    /// # Warning
+    ///
+    /// Only **one** `XRequestContract` per `XRequester` may be active at a
+    /// time. Clone the `XRequester` to issue more requests at once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::xchannel::<u32>();
+    /// let requester2 = requester.clone();
+    ///
+    /// // Both requesters can have a request outstanding.
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// let mut contract2 = requester2.try_request().ok().unwrap();
+    ///
+    /// // Responders serve the oldest request first.
+    /// responder.try_respond().ok().unwrap().send(1);
+    /// responder.try_respond().ok().unwrap().send(2);
+    ///
+    /// assert_eq!(contract.try_receive().ok().unwrap(), 1);
+    /// assert_eq!(contract2.try_receive().ok().unwrap(), 2);
+    /// ```
+    pub fn try_request(&self) -> Result<XRequestContract<T>> {
+        // First, try to lock this requester.
+        try_lock(&self.has_request_lock)?;
+
+        // Next, queue up a new request.
+        let slot = Arc::new(Slot {
+            state: AtomicU8::new(REQUESTED),
+            datum: UnsafeCell::new(None),
+        });
+        self.inner.pending.lock().unwrap().push_back(slot.clone());
+
+        // Then return an `XRequestContract`.
+        Ok(XRequestContract {
+            slot,
+            has_request_lock: self.has_request_lock.clone(),
+            done: false,
+        })
+    }
+}
+
+impl<T> Clone for XRequester<T> {
+    fn clone(&self) -> Self {
+        XRequester::new(self.inner.clone())
+    }
+}
+
+/// This is the contract returned by a successful `XRequester::try_request()`.
+/// It works just like `RequestContract`.
+pub struct XRequestContract<T> {
+    slot: Arc<Slot<T>>,
+    has_request_lock: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl<T> XRequestContract<T> {
+    /// This method attempts to receive a datum from the responder that
+    /// claimed the request.
+    ///
+    /// # Warning
+    ///
+    /// It returns `Err(Error::Done)` if the user called it
+    /// after either receiving a datum or cancelling the request.
+    pub fn try_receive(&mut self) -> Result<T> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        let datum = self.slot.try_take()?;
+        self.done = true;
+
+        Ok(datum)
+    }
+
+    /// This method attempts to cancel the request. It returns
+    /// `Err(Error::TooLate)` if a responder already claimed it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate reqchan as chan;
+    ///
+    /// let (requester, responder) = chan::xchannel::<u32>();
+    ///
+    /// let mut contract = requester.try_request().ok().unwrap();
+    /// contract.try_cancel().ok().unwrap();
+    ///
+    /// // Responders skip cancelled requests.
+    /// match responder.try_respond() {
+    ///     Err(chan::Error::NoRequest) => {},
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn try_cancel(&mut self) -> Result<()> {
+        if self.done {
+            return Err(Error::Done);
+        }
+
+        if self.slot.transition(REQUESTED, CANCELLED) {
+            self.done = true;
+            Ok(())
+        }
+        else {
+            Err(Error::TooLate)
+        }
+    }
+}
+
+impl<T> Drop for XRequestContract<T> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping XRequestContract without receiving data!");
+        }
+
+        unlock(&self.has_request_lock);
+    }
+}
+
+/// This end of the `xchannel` sends data in response to requests from
+/// any `XRequester`.
+pub struct XResponder<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> XResponder<T> {
+    /// This method tries to claim the oldest unclaimed request. If
+    /// successful, it returns an `XResponseContract` to ensure the user
+    /// sends a datum.
+    ///
+    /// Unlike `Responder::try_respond()`, it never returns
+    /// `Err(Error::AlreadyLocked)`, since several responders can serve
+    /// different requests at the same time.
+    pub fn try_respond(&self) -> Result<XResponseContract<T>> {
+        let mut pending = self.inner.pending.lock().unwrap();
+
+        // Skip over any requests that were cancelled.
+        while let Some(slot) = pending.pop_front() {
+            if slot.transition(REQUESTED, CLAIMED) {
+                return Ok(XResponseContract {
+                    slot,
+                    done: false,
+                });
+            }
+        }
+
+        Err(Error::NoRequest)
+    }
+}
+
+impl<T> Clone for XResponder<T> {
+    fn clone(&self) -> Self {
+        XResponder {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// This is the contract returned by a successful `XResponder::try_respond()`.
+/// It works just like `ResponseContract`.
+pub struct XResponseContract<T> {
+    slot: Arc<Slot<T>>,
+    done: bool,
+}
+
+impl<T> XResponseContract<T> {
+    /// This method sends a datum to the requester whose request this
+    /// contract claimed.
+    ///
+    /// # Arguments
+    ///
+    /// * `datum` - The item(s) to send
+    pub fn send(mut self, datum: T) {
+        self.slot.put(datum);
+        self.done = true;
+    }
+}
+
+impl<T> Drop for XResponseContract<T> {
+    fn drop(&mut self) {
+        if !self.done && !thread::panicking() {
+            panic!("Dropping XResponseContract without sending data!");
+        }
+    }
+}
+
+#[doc(hidden)]
+struct Inner<T> {
+    pending: Mutex<VecDeque<Arc<Slot<T>>>>,
+}
+
+const REQUESTED: u8 = 0;
+const CLAIMED: u8 = 1;
+const READY: u8 = 2;
+const CANCELLED: u8 = 3;
+const TAKEN: u8 = 4;
+
+/// This is the state of a single request.
+#[doc(hidden)]
+struct Slot<T> {
+    state: AtomicU8,
+    datum: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Sync for Slot<T> {}
+
+#[doc(hidden)]
+impl<T> Slot<T> {
+    /// This method atomically moves the slot from one state to another.
+    /// It returns whether or not it succeeded.
+    #[inline]
+    fn transition(&self, old: u8, new: u8) -> bool {
+        // This must be a strong exchange, since a spurious failure would
+        // skip or lose a request. A failed attempt touches nothing, so its
+        // ordering only has to match the loads of the other side.
+        self.state.compare_exchange(old, new, Ordering::SeqCst, Ordering::SeqCst).is_ok()
+    }
+
+    /// This method stores the datum and marks it ready.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the responder that claimed the slot should call it.
+    ///
+    /// # Invariant
+    ///
+    /// * self.state == CLAIMED
+    #[inline]
+    fn put(&self, datum: T) {
+        debug_assert_eq!(self.state.load(Ordering::Relaxed), CLAIMED);
+
+        unsafe {
+            *self.datum.get() = Some(datum);
+        }
+
+        self.state.store(READY, Ordering::SeqCst);
+    }
+
+    /// This method tries to take the datum out of the slot.
+    ///
+    /// # Warning
+    ///
+    /// **ONLY** the requester that owns the slot should call it.
+    #[inline]
+    fn try_take(&self) -> Result<T> {
+        if self.transition(READY, TAKEN) {
+            unsafe {
+                Ok(ptr::replace(self.datum.get(), None).unwrap())
+            }
+        }
+        else {
+            Err(Error::Empty)
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xrequester_try_request_multiple() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = xchannel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        match rqst.try_request() {
+            Err(Error::AlreadyLocked) => {},
+            _ => { assert!(false); },
+        }
+
+        contract.try_cancel().ok().unwrap();
+    }
+
+    #[test]
+    fn test_xresponder_try_respond_no_request() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = xchannel::<u32>();
+
+        match resp.try_respond() {
+            Err(Error::NoRequest) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    fn test_xresponder_try_respond_skips_cancelled() {
+        let (rqst, resp) = xchannel::<u32>();
+        let rqst2 = rqst.clone();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut contract2 = rqst2.try_request().ok().unwrap();
+
+        contract.try_cancel().ok().unwrap();
+
+        resp.try_respond().ok().unwrap().send(2);
+
+        assert_eq!(contract2.try_receive().ok().unwrap(), 2);
+        assert_eq!(resp.inner.pending.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_xrequest_contract_try_cancel_too_late() {
+        let (rqst, resp) = xchannel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let resp_contract = resp.try_respond().ok().unwrap();
+
+        match contract.try_cancel() {
+            Err(Error::TooLate) => {},
+            _ => { assert!(false); },
+        }
+
+        match contract.try_receive() {
+            Err(Error::Empty) => {},
+            _ => { assert!(false); },
+        }
+
+        resp_contract.send(3);
+
+        assert_eq!(contract.try_receive().ok().unwrap(), 3);
+
+        match contract.try_receive() {
+            Err(Error::Done) => {},
+            _ => { assert!(false); },
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_xrequest_contract_drop_without_receiving_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = xchannel::<u32>();
+
+        #[allow(unused_variables)]
+        let contract = rqst.try_request().ok().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_xresponse_contract_drop_without_sending_data() {
+        #[allow(unused_variables)]
+        let (rqst, resp) = xchannel::<u32>();
+
+        let mut contract = rqst.try_request().ok().unwrap();
+        contract.done = true;
+
+        #[allow(unused_variables)]
+        let resp_contract = resp.try_respond().ok().unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! These tests model-check the channel's handoffs with `loom`. Run them
+//! with:
+//!
+//! ```sh
+//! RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//! ```
+//!
+//! Only the atomics and the datum slot are modeled. The locks behind
+//! broadcasts and the responder roster stay `std`'s, so no test may use
+//! them from two threads at once: threads hand their `Responder`s back
+//! to be dropped on the main thread.
+
+#![cfg(loom)]
+
+extern crate loom;
+
+extern crate reqchan;
+use reqchan::*;
+
+use loom::thread;
+
+#[test]
+fn test_loom_handoff() {
+    loom::model(|| {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let responder = thread::spawn(move || {
+            resp.try_respond().ok().unwrap().send(1);
+            resp
+        });
+
+        // The datum is either not there yet, or there in full.
+        let early = match contract.try_receive() {
+            Ok(num) => Some(num),
+            Err(Error::Empty) => None,
+            _ => unreachable!(),
+        };
+
+        responder.join().unwrap();
+
+        let num = match early {
+            Some(num) => num,
+            None => contract.try_receive().ok().unwrap(),
+        };
+        assert_eq!(num, 1);
+    });
+}
+
+#[test]
+fn test_loom_cancel_races_claim() {
+    loom::model(|| {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let responder = thread::spawn(move || {
+            let sent = match resp.try_respond() {
+                Ok(contract) => {
+                    contract.send(1);
+                    true
+                },
+                Err(Error::NoRequest) => false,
+                _ => unreachable!(),
+            };
+
+            (resp, sent)
+        });
+
+        let cancelled = match contract.try_cancel() {
+            Ok(()) => true,
+            Err(Error::TooLate) => false,
+            _ => unreachable!(),
+        };
+
+        let (_resp, sent) = responder.join().unwrap();
+
+        // Exactly one of them gets the request.
+        assert!(cancelled != sent);
+        if sent {
+            assert_eq!(contract.try_receive().ok().unwrap(), 1);
+        }
+    });
+}
+
+#[test]
+fn test_loom_two_responders_race() {
+    loom::model(|| {
+        let (rqst, resp) = channel::<u32>();
+        let resp2 = resp.clone();
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        let responders: Vec<_> = vec![(resp, 1), (resp2, 2)].into_iter().map(|(resp, num)| {
+            thread::spawn(move || {
+                let sent = match resp.try_respond() {
+                    Ok(contract) => {
+                        contract.send(num);
+                        Some(num)
+                    },
+                    Err(Error::AlreadyLocked) | Err(Error::NoRequest) => None,
+                    _ => unreachable!(),
+                };
+
+                (resp, sent)
+            })
+        }).collect();
+
+        let (_resps, sent): (Vec<_>, Vec<_>) = responders.into_iter()
+            .map(|responder| responder.join().unwrap())
+            .unzip();
+        let sent: Vec<_> = sent.into_iter().flatten().collect();
+
+        assert_eq!(sent.len(), 1);
+        assert_eq!(contract.try_receive().ok().unwrap(), sent[0]);
+    });
+}
+
+#[test]
+fn test_loom_contract_drop_unlocks() {
+    loom::model(|| {
+        let (rqst, resp) = channel::<u32>();
+        let mut contract = rqst.try_request().ok().unwrap();
+        resp.try_respond().ok().unwrap().send(1);
+
+        let requester = thread::spawn(move || {
+            assert_eq!(contract.try_receive().ok().unwrap(), 1);
+            drop(contract);
+        });
+
+        // The requesting side is either still locked, or free for good.
+        let mut early = match rqst.try_request() {
+            Ok(contract) => Some(contract),
+            Err(Error::AlreadyLocked) => None,
+            _ => unreachable!(),
+        };
+
+        requester.join().unwrap();
+
+        let mut contract = match early.take() {
+            Some(contract) => contract,
+            None => rqst.try_request().ok().unwrap(),
+        };
+        contract.try_cancel().ok().unwrap();
+    });
+}
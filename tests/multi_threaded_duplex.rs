@@ -0,0 +1,85 @@
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+#[test]
+fn test_multi_threaded_duplex_round_trips() {
+    // The worker requests tasks and hands back their squares.
+    let (worker, scheduler) = duplex::<u64, u64>();
+
+    let handle = thread::spawn(move || {
+        for _ in 0..100 {
+            worker.serve_once(|task| task * task);
+        }
+    });
+
+    let mut results = Vec::new();
+
+    for task in 0..100 {
+        // Hand out the next task.
+        loop {
+            match scheduler.try_respond() {
+                Ok(contract) => {
+                    contract.send(task);
+                    break;
+                },
+                Err(Error::NoRequest) => { thread::yield_now(); },
+                _ => unreachable!(),
+            }
+        }
+
+        // Then request its result.
+        let mut contract = scheduler.request();
+
+        loop {
+            match contract.try_receive() {
+                Ok(result) => {
+                    results.push(result);
+                    break;
+                },
+                Err(Error::Empty) => { thread::yield_now(); },
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    handle.join().unwrap();
+
+    assert_eq!(results, (0..100).map(|task| task * task).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_multi_threaded_duplex_both_sides_serve() {
+    let (a, b) = duplex::<u32, String>();
+
+    // Each side serves the other once, in opposite directions.
+    let handle = thread::spawn(move || {
+        b.serve_once(|name: String| name.len() as u32);
+        b
+    });
+
+    loop {
+        match a.try_respond() {
+            Ok(contract) => {
+                contract.send("four".to_string());
+                break;
+            },
+            Err(Error::NoRequest) => { thread::yield_now(); },
+            _ => unreachable!(),
+        }
+    }
+
+    let mut contract = a.request();
+    let len = loop {
+        match contract.try_receive() {
+            Ok(len) => { break len; },
+            Err(Error::Empty) => { thread::yield_now(); },
+            _ => unreachable!(),
+        }
+    };
+    drop(contract);
+
+    handle.join().unwrap();
+    assert_eq!(len, 4);
+}
@@ -0,0 +1,58 @@
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+#[test]
+fn test_multi_threaded_exchange_one_requester_one_responder() {
+    let (rqst, resp) = exchange_channel::<Vec<usize>, usize>();
+
+    // The responder hands out tasks and recycles the returned buffers.
+    let handle = thread::spawn(move || {
+        let mut recycled = Vec::new();
+
+        for task in 0..100 {
+            loop {
+                match resp.try_respond() {
+                    Ok(mut contract) => {
+                        recycled.push(contract.take_offer());
+                        contract.send(task);
+                        break;
+                    },
+                    Err(Error::NoRequest) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        recycled
+    });
+
+    let mut buffer = Vec::new();
+    let mut tasks = Vec::new();
+
+    for _ in 0..100 {
+        let mut contract = rqst.try_request_swap(buffer).ok().unwrap();
+
+        loop {
+            match contract.try_receive() {
+                Ok(task) => {
+                    tasks.push(task);
+                    break;
+                },
+                Err(Error::Empty) => { thread::yield_now(); },
+                _ => unreachable!(),
+            }
+        }
+
+        buffer = vec![tasks.len()];
+    }
+
+    let recycled = handle.join().unwrap();
+
+    assert_eq!(tasks, (0..100).collect::<Vec<_>>());
+    assert!(recycled[0].is_empty());
+    for (i, buffer) in recycled.iter().enumerate().skip(1) {
+        assert_eq!(buffer, &vec![i]);
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants)]
+
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
@@ -5,18 +7,6 @@ use std::thread;
 extern crate reqchan;
 use reqchan::*;
 
-trait FnBox {
-    fn call_box(self: Box<Self>);
-}
-
-impl<F: FnOnce()> FnBox for F {
-    fn call_box(self: Box<F>) {
-        (*self)()
-    }
-}
-
-type Task = Box<FnBox + Send + 'static>;
-
 #[test]
 fn test_request_receive_threaded() {
     let (rqst, resp) = channel::<Task>();
@@ -28,9 +18,9 @@ fn test_request_receive_threaded() {
         loop {
             match resp.try_respond() {
                 Ok(contract) => {
-                    contract.send(Box::new(move || {
+                    contract.send(Task::new(move || {
                         var2.fetch_add(1, Ordering::SeqCst);
-                    }) as Task);
+                    }));
                     break;
                 },
                 Err(Error::NoRequest) => {},
@@ -45,7 +35,7 @@ fn test_request_receive_threaded() {
     loop {
         match contract.try_receive() {
             Ok(task) => {
-                task.call_box();
+                task.run();
                 break;
             },
             Err(Error::Empty) => {},
@@ -60,6 +50,7 @@ fn test_request_receive_threaded() {
 }
 
 //#[test]
+#[allow(dead_code)]
 fn test_request_threaded_receive() {
     let (rqst, resp) = channel::<Task>();
 
@@ -72,7 +63,7 @@ fn test_request_threaded_receive() {
         loop {
             match contract.try_receive() {
                 Ok(task) => {
-                    task.call_box();
+                    task.run();
                     break;
                 },
                 Err(Error::Empty) => {},
@@ -85,9 +76,9 @@ fn test_request_threaded_receive() {
     loop {
         match resp.try_respond() {
             Ok(contract) => {
-                contract.send(Box::new(move || {
+                contract.send(Task::new(move || {
                     var2.fetch_add(1, Ordering::SeqCst);
-                }) as Task);
+                }));
                 break;
             },
             Err(Error::NoRequest) => {},
@@ -114,7 +105,7 @@ fn test_request_threaded_receive_threaded() {
         loop {
             match contract.try_receive() {
                 Ok(task) => {
-                    task.call_box();
+                    task.run();
                     break;
                 },
                 Err(Error::Empty) => {},
@@ -128,9 +119,9 @@ fn test_request_threaded_receive_threaded() {
         loop {
             match resp.try_respond() {
                 Ok(contract) => {
-                    contract.send(Box::new(move || {
+                    contract.send(Task::new(move || {
                         var2.fetch_add(1, Ordering::SeqCst);
-                    }) as Task);
+                    }));
                     break;
                 },
                 Err(Error::NoRequest) => {},
@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants)]
+
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread;
@@ -5,18 +7,6 @@ use std::thread;
 extern crate reqchan;
 use reqchan::*;
 
-trait FnBox {
-    fn call_box(self: Box<Self>);
-}
-
-impl<F: FnOnce()> FnBox for F {
-    fn call_box(self: Box<F>) {
-        (*self)()
-    }
-}
-
-type Task = Box<FnBox + Send + 'static>;
-
 #[test]
 fn test_multi_threaded_one_requester_two_responders() {
     let (rqst, resp) = channel::<Task>();
@@ -36,7 +26,7 @@ fn test_multi_threaded_one_requester_two_responders() {
         loop {
             match contract.try_receive() {
                 Ok(task) => {
-                    task.call_box();
+                    task.run();
                     exit.store(true, Ordering::SeqCst);
                     break;
                 },
@@ -55,9 +45,9 @@ fn test_multi_threaded_one_requester_two_responders() {
             
             match resp.try_respond() {
                 Ok(contract) => {
-                    contract.send(Box::new(move || {
+                    contract.send(Task::new(move || {
                         var2.fetch_add(1, Ordering::SeqCst);
-                    }) as Task);
+                    }));
                     break;
                 },
                 Err(Error::NoRequest) => {},
@@ -75,9 +65,9 @@ fn test_multi_threaded_one_requester_two_responders() {
             
             match resp2.try_respond() {
                 Ok(contract) => {
-                    contract.send(Box::new(move || {
+                    contract.send(Task::new(move || {
                         var3.fetch_add(2, Ordering::SeqCst);
-                    }) as Task);
+                    }));
                     break;
                 },
                 Err(Error::NoRequest) => {},
@@ -94,3 +84,485 @@ fn test_multi_threaded_one_requester_two_responders() {
     let num = var.load(Ordering::SeqCst);
     assert!(num > 0);
 }
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_request_many() {
+    let (rqst, resp) = channel::<usize>();
+    let resp2 = resp.clone();
+
+    let handle1 = thread::spawn(move || {
+        let mut contract = rqst.try_request_many(2).ok().unwrap();
+        let mut sum = 0;
+
+        while contract.remaining() > 0 {
+            match contract.try_receive() {
+                Ok(num) => { sum += num; },
+                Err(Error::Empty) => {},
+                _ => unreachable!(),
+            }
+        }
+
+        sum
+    });
+
+    let respond = |resp: Responder<usize>, num: usize| {
+        thread::spawn(move || {
+            loop {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        contract.send(num);
+                        break;
+                    },
+                    // The other responder may be checking for a
+                    // single-response request at the same time.
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {},
+                    _ => unreachable!(),
+                }
+            }
+        })
+    };
+
+    let handle2 = respond(resp, 1);
+    let handle3 = respond(resp2, 2);
+
+    handle2.join().unwrap();
+    handle3.join().unwrap();
+
+    // Each responder contributed exactly one datum.
+    assert_eq!(handle1.join().unwrap(), 3);
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_batch() {
+    let (rqst, resp) = batch_channel::<usize>(8);
+    let resp2 = resp.clone();
+
+    let handle1 = thread::spawn(move || {
+        let mut contract = rqst.try_request().ok().unwrap();
+        let mut items = Vec::new();
+
+        loop {
+            match contract.try_receive_batch(&mut items) {
+                Ok(_) | Err(Error::Empty) => {},
+                Err(Error::Done) => { break; },
+                _ => unreachable!(),
+            }
+        }
+
+        items
+    });
+
+    let push = |resp: BatchResponder<usize>, base: usize| {
+        thread::spawn(move || {
+            let mut next = 0;
+
+            while next < 4 {
+                match resp.try_respond() {
+                    Ok(mut contract) => {
+                        // Push a single item per claim to interleave
+                        // with the other responder.
+                        contract.push(base + next).ok().unwrap();
+                        next += 1;
+                    },
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {},
+                    _ => unreachable!(),
+                }
+            }
+        })
+    };
+
+    let handle2 = push(resp, 0);
+    let handle3 = push(resp2, 100);
+
+    handle2.join().unwrap();
+    handle3.join().unwrap();
+
+    let mut items = handle1.join().unwrap();
+    items.sort();
+
+    assert_eq!(items, vec![0, 1, 2, 3, 100, 101, 102, 103]);
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_handoff() {
+    const ROUNDS: usize = 5000;
+
+    let (rqst, resp) = channel::<Vec<usize>>();
+    let resp2 = resp.clone();
+
+    let exit = Arc::new(AtomicBool::new(false));
+
+    // The requester checks every datum it gets, so a datum published
+    // before it was fully written would show up as a bad round.
+    let handle1 = {
+        let exit = exit.clone();
+
+        thread::spawn(move || {
+            for round in 0..ROUNDS {
+                let mut contract = rqst.try_request_amount(round + 1).ok().unwrap();
+
+                loop {
+                    match contract.try_receive() {
+                        Ok(datum) => {
+                            assert_eq!(datum, vec![round; round % 16 + 1]);
+                            break;
+                        },
+                        Err(Error::Empty) => { thread::yield_now(); },
+                        _ => unreachable!(),
+                    }
+                }
+            }
+
+            exit.store(true, Ordering::SeqCst);
+        })
+    };
+
+    let respond = |resp: Responder<Vec<usize>>, exit: Arc<AtomicBool>| {
+        thread::spawn(move || {
+            while !exit.load(Ordering::SeqCst) {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        // The amount tells the responder which round it serves.
+                        let round = contract.amount_requested() - 1;
+                        contract.send(vec![round; round % 16 + 1]);
+                    },
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {
+                        thread::yield_now();
+                    },
+                    _ => unreachable!(),
+                }
+            }
+        })
+    };
+
+    let handle2 = respond(resp, exit.clone());
+    let handle3 = respond(resp2, exit.clone());
+
+    handle1.join().unwrap();
+    handle2.join().unwrap();
+    handle3.join().unwrap();
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_copy_payload() {
+    const ROUNDS: usize = 20000;
+
+    // A 64-byte `Copy` payload whose words must always agree.
+    #[derive(Clone, Copy)]
+    struct Stats([u64; 8]);
+
+    let (rqst, resp) = channel::<Stats>();
+    let resp2 = resp.clone();
+
+    let exit = Arc::new(AtomicBool::new(false));
+
+    // The requester also cancels some requests, so claims keep racing
+    // with cancels, but every datum it gets must be whole.
+    let handle1 = {
+        let exit = exit.clone();
+
+        thread::spawn(move || {
+            let mut received = 0;
+            let mut polls = 0;
+
+            while received < ROUNDS {
+                let mut contract = rqst.try_request_amount(received + 1).ok().unwrap();
+
+                loop {
+                    polls += 1;
+
+                    match contract.try_receive() {
+                        Ok(Stats(words)) => {
+                            assert!(words.iter().all(|&word| word == received as u64));
+                            received += 1;
+                            break;
+                        },
+                        Err(Error::Empty) => {},
+                        _ => unreachable!(),
+                    }
+
+                    if polls % 3 == 0 && contract.try_cancel().is_ok() {
+                        break;
+                    }
+
+                    thread::yield_now();
+                }
+            }
+
+            exit.store(true, Ordering::SeqCst);
+        })
+    };
+
+    let respond = |resp: Responder<Stats>, exit: Arc<AtomicBool>| {
+        thread::spawn(move || {
+            while !exit.load(Ordering::SeqCst) {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        let round = (contract.amount_requested() - 1) as u64;
+                        contract.send(Stats([round; 8]));
+                    },
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {
+                        thread::yield_now();
+                    },
+                    _ => unreachable!(),
+                }
+            }
+        })
+    };
+
+    let handle2 = respond(resp, exit.clone());
+    let handle3 = respond(resp2, exit.clone());
+
+    handle1.join().unwrap();
+    handle2.join().unwrap();
+    handle3.join().unwrap();
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_request_n() {
+    let (rqst, resp) = channel::<usize>();
+    let resp2 = resp.clone();
+
+    let exit = Arc::new(AtomicBool::new(false));
+
+    // Ask for 4 shards to be scanned by whichever responders get to them.
+    let handle1 = {
+        let exit = exit.clone();
+
+        thread::spawn(move || {
+            let mut contract = rqst.try_request_n(4).ok().unwrap();
+            let mut shards = Vec::new();
+
+            while contract.remaining() > 0 {
+                match contract.try_receive() {
+                    Ok(shard) => { shards.push(shard); },
+                    Err(Error::Empty) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+
+            exit.store(true, Ordering::SeqCst);
+            shards
+        })
+    };
+
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let respond = |resp: Responder<usize>| {
+        let exit = exit.clone();
+        let next = next.clone();
+
+        thread::spawn(move || {
+            while !exit.load(Ordering::SeqCst) {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        contract.send(next.fetch_add(1, Ordering::SeqCst));
+                    },
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {
+                        thread::yield_now();
+                    },
+                    _ => unreachable!(),
+                }
+            }
+        })
+    };
+
+    let handle2 = respond(resp);
+    let handle3 = respond(resp2);
+
+    let mut shards = handle1.join().unwrap();
+    handle2.join().unwrap();
+    handle3.join().unwrap();
+
+    shards.sort();
+    assert_eq!(shards, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_request_all() {
+    let (rqst, resp) = channel::<usize>();
+    let resp2 = resp.clone();
+    let id = resp.id();
+
+    let mut contract = rqst.try_request_all().ok().unwrap();
+
+    // One responder answers the broadcast.
+    let handle1 = thread::spawn(move || {
+        loop {
+            match resp.try_respond() {
+                Ok(contract) => {
+                    contract.send(1);
+                    break;
+                },
+                Err(Error::NoRequest) => { thread::yield_now(); },
+                _ => unreachable!(),
+            }
+        }
+    });
+
+    // The other goes away without answering it.
+    let handle2 = thread::spawn(move || {
+        drop(resp2);
+    });
+
+    let mut responses = Vec::new();
+
+    while contract.responses_remaining() > 0 {
+        match contract.try_receive() {
+            Ok(response) => { responses.push(response); },
+            Err(Error::Empty) => { thread::yield_now(); },
+            _ => unreachable!(),
+        }
+    }
+
+    handle1.join().unwrap();
+    handle2.join().unwrap();
+
+    assert_eq!(responses, vec![(id, 1)]);
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_held() {
+    let (rqst, resp) = channel::<usize>();
+    let resp2 = resp.clone();
+
+    let holding = Arc::new(AtomicBool::new(false));
+    let exit = Arc::new(AtomicBool::new(false));
+
+    // This responder wins the first request and holds on to the channel.
+    let handle1 = {
+        let holding = holding.clone();
+
+        thread::spawn(move || {
+            let mut held = loop {
+                match resp.try_respond() {
+                    Ok(contract) => { break contract.send_and_hold(1); },
+                    Err(Error::NoRequest) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            };
+
+            holding.store(true, Ordering::SeqCst);
+
+            for _ in 0..99 {
+                held = held.next().send_and_hold(1);
+            }
+        })
+    };
+
+    // This one keeps trying, but only wins once the hold is released.
+    let handle2 = {
+        let holding = holding.clone();
+        let exit = exit.clone();
+
+        thread::spawn(move || {
+            while !holding.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+
+            while !exit.load(Ordering::SeqCst) {
+                match resp2.try_respond() {
+                    Ok(contract) => { contract.send(2); },
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {
+                        thread::yield_now();
+                    },
+                    _ => unreachable!(),
+                }
+            }
+        })
+    };
+
+    let mut received = Vec::new();
+
+    for _ in 0..100 {
+        let mut contract = rqst.try_request().ok().unwrap();
+
+        loop {
+            match contract.try_receive() {
+                Ok(datum) => {
+                    received.push(datum);
+                    break;
+                },
+                Err(Error::Empty) => { thread::yield_now(); },
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    // The holder drops its hold after its last datum.
+    handle1.join().unwrap();
+
+    let mut contract = rqst.try_request().ok().unwrap();
+
+    loop {
+        match contract.try_receive() {
+            Ok(datum) => {
+                received.push(datum);
+                break;
+            },
+            Err(Error::Empty) => { thread::yield_now(); },
+            _ => unreachable!(),
+        }
+    }
+
+    exit.store(true, Ordering::SeqCst);
+    handle2.join().unwrap();
+
+    assert!(received[..100].iter().all(|&datum| datum == 1));
+    assert_eq!(received[100], 2);
+}
+
+#[test]
+fn test_multi_threaded_one_requester_two_responders_idle() {
+    const POLLS: usize = 10000;
+
+    let (rqst, resp) = channel::<usize>();
+    let resp2 = resp.clone();
+
+    // While there is no request, polling responders must not get in each
+    // other's way, so neither of them may ever see the channel locked.
+    let poll = |resp: Responder<usize>| {
+        thread::spawn(move || {
+            for _ in 0..POLLS {
+                match resp.try_respond() {
+                    Err(Error::NoRequest) => {},
+                    _ => unreachable!(),
+                }
+            }
+
+            resp
+        })
+    };
+
+    let handle1 = poll(resp);
+    let handle2 = poll(resp2);
+
+    let resp = handle1.join().unwrap();
+    let resp2 = handle2.join().unwrap();
+
+    // Once a request comes in, exactly one of them gets it.
+    let mut contract = rqst.try_request().ok().unwrap();
+
+    let respond = |resp: Responder<usize>, num: usize| {
+        thread::spawn(move || {
+            match resp.try_respond() {
+                Ok(contract) => {
+                    contract.send(num);
+                    true
+                },
+                Err(Error::NoRequest) | Err(Error::AlreadyLocked) => false,
+                _ => unreachable!(),
+            }
+        })
+    };
+
+    let handle1 = respond(resp, 1);
+    let handle2 = respond(resp2, 2);
+
+    let won1 = handle1.join().unwrap();
+    let won2 = handle2.join().unwrap();
+    assert!(won1 != won2);
+
+    let num = contract.try_receive().ok().unwrap();
+    assert_eq!(num, if won1 { 1 } else { 2 });
+}
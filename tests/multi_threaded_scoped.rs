@@ -0,0 +1,65 @@
+use std::cmp;
+use std::mem;
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+#[test]
+fn test_multi_threaded_scoped_slices() {
+    let mut jobs = [0u32; 64];
+
+    {
+        let mut channel = ScopedChannel::<&mut [u32]>::new();
+        let (rqst, resp) = channel.split();
+
+        thread::scope(|s| {
+            // The worker asks for up to 16 jobs at a time and stops
+            // when it gets an empty slice.
+            s.spawn(move || {
+                loop {
+                    let mut contract = rqst.try_request_amount(16).ok().unwrap();
+
+                    let chunk = loop {
+                        match contract.try_receive() {
+                            Ok(chunk) => { break chunk; },
+                            Err(Error::Empty) => { thread::yield_now(); },
+                            _ => unreachable!(),
+                        }
+                    };
+
+                    if chunk.is_empty() {
+                        break;
+                    }
+
+                    for job in chunk.iter_mut() {
+                        *job += 1;
+                    }
+                }
+            });
+
+            let mut rest: &mut [u32] = &mut jobs;
+
+            loop {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        let n = cmp::min(contract.amount_requested(), rest.len());
+                        let (chunk, tail) = mem::take(&mut rest).split_at_mut(n);
+                        rest = tail;
+
+                        let last = chunk.is_empty();
+                        contract.send(chunk);
+
+                        if last {
+                            break;
+                        }
+                    },
+                    Err(Error::NoRequest) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+        });
+    }
+
+    assert!(jobs.iter().all(|&job| job == 1));
+}
@@ -0,0 +1,108 @@
+use std::sync::Barrier;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+fn assert_sync<T: Sync>(_: &T) {}
+
+#[test]
+fn test_multi_threaded_shared_reference_one_winner() {
+    let (rqst, resp) = channel::<u32>();
+    assert_sync(&rqst);
+
+    let threads = 4;
+    let barrier = Barrier::new(threads);
+    let winners = AtomicUsize::new(0);
+
+    // Every thread tries once, at about the same time, and keeps the
+    // request open until every thread has tried.
+    let contracts: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads).map(|_| {
+            s.spawn(|| {
+                barrier.wait();
+                let res = rqst.try_request();
+                if res.is_ok() {
+                    winners.fetch_add(1, Ordering::SeqCst);
+                }
+                barrier.wait();
+
+                match res {
+                    Ok(contract) => Some(contract),
+                    Err(Error::AlreadyLocked) => None,
+                    _ => unreachable!(),
+                }
+            })
+        }).collect();
+
+        handles.into_iter().filter_map(|handle| handle.join().unwrap()).collect()
+    });
+
+    assert_eq!(winners.load(Ordering::SeqCst), 1);
+    assert_eq!(contracts.len(), 1);
+
+    resp.try_respond().ok().unwrap().send(7);
+    for mut contract in contracts {
+        assert_eq!(contract.try_receive().ok().unwrap(), 7);
+    }
+}
+
+#[test]
+fn test_multi_threaded_shared_reference_retry() {
+    let (rqst, resp) = channel::<usize>();
+
+    let threads = 4;
+    let rounds = 25;
+
+    thread::scope(|s| {
+        let responder = s.spawn(|| {
+            let mut served = 0;
+
+            while served < threads * rounds {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        let tag = contract.amount_requested();
+                        contract.send(tag);
+                        served += 1;
+                    },
+                    Err(Error::NoRequest) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+        });
+
+        // Losers retry until they get their turn, and each thread only
+        // ever receives the datum answering its own request.
+        for id in 0..threads {
+            let rqst = &rqst;
+
+            s.spawn(move || {
+                for i in 0..rounds {
+                    let tag = id * 1000 + i + 1;
+
+                    let mut contract = loop {
+                        match rqst.try_request_amount(tag) {
+                            Ok(contract) => { break contract; },
+                            Err(Error::AlreadyLocked) => { thread::yield_now(); },
+                            _ => unreachable!(),
+                        }
+                    };
+
+                    loop {
+                        match contract.try_receive() {
+                            Ok(datum) => {
+                                assert_eq!(datum, tag);
+                                break;
+                            },
+                            Err(Error::Empty) => { thread::yield_now(); },
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+            });
+        }
+
+        responder.join().unwrap();
+    });
+}
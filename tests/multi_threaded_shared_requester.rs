@@ -0,0 +1,170 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+#[test]
+fn test_multi_threaded_shared_requester_one_responder() {
+    let (rqst, resp) = channel::<usize>();
+    let rqst = rqst.into_shared();
+
+    let exit = Arc::new(AtomicBool::new(false));
+    let exit2 = exit.clone();
+
+    // The responder echoes the amount hint, which each requesting thread
+    // uses to tag its own requests.
+    let responder = thread::spawn(move || {
+        let mut served = 0;
+
+        while !exit2.load(Ordering::SeqCst) {
+            match resp.try_respond() {
+                Ok(contract) => {
+                    let tag = contract.amount_requested();
+                    contract.send(tag);
+                    served += 1;
+                },
+                Err(Error::NoRequest) => { thread::yield_now(); },
+                _ => unreachable!(),
+            }
+        }
+
+        served
+    });
+
+    let requesters = (1..5).map(|id| {
+        let rqst = rqst.clone();
+
+        thread::spawn(move || {
+            for i in 0..100 {
+                let tag = id * 1000 + i;
+
+                let mut contract = loop {
+                    match rqst.try_request_amount(tag) {
+                        Ok(contract) => { break contract; },
+                        Err(Error::AlreadyLocked) => { thread::yield_now(); },
+                        _ => unreachable!(),
+                    }
+                };
+
+                loop {
+                    match contract.try_receive() {
+                        Ok(datum) => {
+                            assert_eq!(datum, tag);
+                            break;
+                        },
+                        Err(Error::Empty) => {},
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    for handle in requesters {
+        handle.join().unwrap();
+    }
+
+    exit.store(true, Ordering::SeqCst);
+
+    assert_eq!(responder.join().unwrap(), 400);
+}
+
+#[test]
+fn test_multi_threaded_shared_requester_request_waits() {
+    let (rqst, resp) = channel::<usize>();
+    let rqst = rqst.into_shared();
+
+    let requesters = (0..4).map(|id| {
+        let rqst = rqst.clone();
+
+        thread::spawn(move || {
+            let mut contract = rqst.request();
+
+            loop {
+                match contract.try_receive() {
+                    Ok(datum) => { return (id, datum); },
+                    Err(Error::Empty) => {},
+                    _ => unreachable!(),
+                }
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    let mut sent = 0;
+    while sent < 4 {
+        if let Ok(contract) = resp.try_respond() {
+            contract.send(sent);
+            sent += 1;
+        }
+    }
+
+    let mut received = requesters.into_iter()
+        .map(|handle| handle.join().unwrap().1)
+        .collect::<Vec<_>>();
+    received.sort();
+
+    assert_eq!(received, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_multi_threaded_shared_requester_request_coalesced() {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    let (rqst, resp) = channel::<usize>();
+    let rqst = rqst.into_shared();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let done2 = done.clone();
+
+    let responder = thread::spawn(move || {
+        let mut sent = 0;
+
+        while !done2.load(Ordering::SeqCst) {
+            // Give requesters time to pile onto the same request.
+            thread::sleep(Duration::from_millis(10));
+
+            if let Ok(contract) = resp.try_respond() {
+                contract.send(sent);
+                sent += 1;
+            }
+        }
+
+        sent
+    });
+
+    let requesters = (0..8).map(|_| {
+        let rqst = rqst.clone();
+
+        thread::spawn(move || {
+            let coalesced = rqst.request_coalesced(Duration::from_secs(30)).ok().unwrap();
+            (coalesced.datum, coalesced.waiters)
+        })
+    }).collect::<Vec<_>>();
+
+    let results = requesters.into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    done.store(true, Ordering::SeqCst);
+    let sent = responder.join().unwrap();
+
+    // Every caller attached to a request reports the same waiter count,
+    // and that count matches the number of callers that got its datum.
+    let mut rounds = HashMap::new();
+    for &(datum, waiters) in &results {
+        let entry = rounds.entry(datum).or_insert((0, waiters));
+        entry.0 += 1;
+        assert_eq!(entry.1, waiters);
+    }
+
+    for &(count, waiters) in rounds.values() {
+        assert_eq!(count, waiters);
+    }
+
+    assert!(rounds.len() <= sent);
+}
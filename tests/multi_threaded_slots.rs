@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+#[test]
+fn test_multi_threaded_slots_one_requester_one_responder_in_order() {
+    let (rqst, resp) = channel_with_slots::<usize>(4);
+
+    let handle = thread::spawn(move || {
+        for i in 0..200 {
+            loop {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        contract.send(i);
+                        break;
+                    },
+                    Err(Error::NoRequest) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+        }
+    });
+
+    // Keep up to 4 requests in flight, and receive them in order.
+    let mut in_flight = VecDeque::new();
+    let mut received = Vec::new();
+
+    while received.len() < 200 {
+        while in_flight.len() + received.len() < 200 {
+            match rqst.try_request() {
+                Ok(contract) => { in_flight.push_back(contract); },
+                Err(Error::AlreadyLocked) => { break; },
+                _ => unreachable!(),
+            }
+        }
+
+        match in_flight.front_mut().unwrap().try_receive() {
+            Ok(datum) => {
+                received.push(datum);
+                in_flight.pop_front();
+            },
+            Err(Error::Empty) => { thread::yield_now(); },
+            _ => unreachable!(),
+        }
+    }
+
+    handle.join().unwrap();
+
+    assert_eq!(received, (0..200).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_multi_threaded_slots_one_requester_two_responders() {
+    let (rqst, resp) = channel_with_slots::<usize>(3);
+
+    let exit = Arc::new(AtomicBool::new(false));
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let responders = (0..2).map(|_| {
+        let resp = resp.clone();
+        let exit = exit.clone();
+        let next = next.clone();
+
+        thread::spawn(move || {
+            while !exit.load(Ordering::SeqCst) {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        contract.send(next.fetch_add(1, Ordering::SeqCst));
+                    },
+                    Err(Error::NoRequest) | Err(Error::AlreadyLocked) => {
+                        thread::yield_now();
+                    },
+                    _ => unreachable!(),
+                }
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    let mut in_flight = Vec::new();
+    let mut received = Vec::new();
+
+    while received.len() < 300 {
+        if in_flight.len() + received.len() < 300 {
+            if let Ok(contract) = rqst.try_request() {
+                in_flight.push(contract);
+            }
+        }
+
+        let mut i = 0;
+        while i < in_flight.len() {
+            match in_flight[i].try_receive() {
+                Ok(datum) => {
+                    received.push(datum);
+                    in_flight.swap_remove(i);
+                },
+                Err(Error::Empty) => { i += 1; },
+                _ => unreachable!(),
+            }
+        }
+
+        thread::yield_now();
+    }
+
+    exit.store(true, Ordering::SeqCst);
+
+    for handle in responders {
+        handle.join().unwrap();
+    }
+
+    // Every datum reached exactly one contract.
+    received.sort();
+    assert_eq!(received, (0..300).collect::<Vec<_>>());
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+extern crate reqchan;
+use reqchan::*;
+
+#[test]
+fn test_multi_threaded_three_requesters_three_responders() {
+    let (rqst, resp) = xchannel::<(usize, usize)>();
+
+    let exit = Arc::new(AtomicBool::new(false));
+
+    let responders = (0..3).map(|id| {
+        let resp = resp.clone();
+        let exit = exit.clone();
+
+        thread::spawn(move || {
+            let mut sent = 0;
+
+            while !exit.load(Ordering::SeqCst) {
+                match resp.try_respond() {
+                    Ok(contract) => {
+                        contract.send((id, sent));
+                        sent += 1;
+                    },
+                    Err(Error::NoRequest) => { thread::yield_now(); },
+                    _ => unreachable!(),
+                }
+            }
+
+            sent
+        })
+    }).collect::<Vec<_>>();
+
+    let requesters = (0..3).map(|_| {
+        let rqst = rqst.clone();
+
+        thread::spawn(move || {
+            let mut received = Vec::new();
+
+            for _ in 0..100 {
+                let mut contract = rqst.try_request().ok().unwrap();
+
+                loop {
+                    match contract.try_receive() {
+                        Ok(datum) => {
+                            received.push(datum);
+                            break;
+                        },
+                        Err(Error::Empty) => { thread::yield_now(); },
+                        _ => unreachable!(),
+                    }
+                }
+            }
+
+            received
+        })
+    }).collect::<Vec<_>>();
+
+    let mut received = requesters.into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    exit.store(true, Ordering::SeqCst);
+
+    let sent = responders.into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    // Every datum sent was received exactly once.
+    received.sort();
+    let mut expected = sent.iter()
+        .enumerate()
+        .flat_map(|(id, &n)| (0..n).map(move |i| (id, i)))
+        .collect::<Vec<_>>();
+    expected.sort();
+
+    assert_eq!(received.len(), 300);
+    assert_eq!(received, expected);
+}
@@ -1,20 +1,15 @@
+#![allow(clippy::assertions_on_constants)]
+
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 extern crate reqchan;
 use reqchan::*;
 
-trait FnBox {
-    fn call_box(self: Box<Self>);
-}
-
-impl<F: FnOnce()> FnBox for F {
-    fn call_box(self: Box<F>) {
-        (*self)()
-    }
-}
-
-type Task = Box<FnBox + Send + 'static>;
+#[cfg(target_arch = "wasm32")]
+extern crate wasm_bindgen_test;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
 
 #[test]
 fn test_multiple_requests() {
@@ -29,13 +24,13 @@ fn test_multiple_requests() {
         let mut rqst_con = rqst.try_request().ok().unwrap();
         let resp_con = resp.try_respond().ok().unwrap();
 
-        resp_con.send(Box::new(move || {
+        resp_con.send(Task::new(move || {
             var2.fetch_add(1, Ordering::SeqCst);
-        }) as Task);
+        }));
 
         match rqst_con.try_receive() {
             Ok(task) => {
-                task.call_box();
+                task.run();
             },
             _ => { assert!(false); },
         }
@@ -50,13 +45,13 @@ fn test_multiple_requests() {
         let mut rqst_con = rqst.try_request().ok().unwrap();
         let resp_con = resp.try_respond().ok().unwrap();
 
-        resp_con.send(Box::new(move || {
+        resp_con.send(Task::new(move || {
             var3.fetch_add(1, Ordering::SeqCst);
-        }) as Task);
+        }));
 
         match rqst_con.try_receive() {
             Ok(task) => {
-                task.call_box();
+                task.run();
             },
             _ => { assert!(false); },
         }
@@ -83,13 +78,13 @@ fn test_multiple_responders() {
         _ => { assert!(false); },
     }
 
-    resp_con.send(Box::new(move || {
+    resp_con.send(Task::new(move || {
         var2.fetch_add(1, Ordering::SeqCst);
-    }) as Task);
+    }));
 
     match rqst_con.try_receive() {
         Ok(task) => {
-            task.call_box();
+            task.run();
         },
         _ => { assert!(false); },
     }
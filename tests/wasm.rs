@@ -0,0 +1,44 @@
+//! This is a smoke test of the channel on `wasm32-unknown-unknown`, where
+//! there is a single thread. Run it with
+//! `cargo test --target wasm32-unknown-unknown --test wasm`, which needs
+//! `wasm-bindgen-test-runner` from `wasm-bindgen-cli`.
+#![cfg(target_arch = "wasm32")]
+
+extern crate reqchan;
+extern crate wasm_bindgen_test;
+
+use reqchan::*;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_request_respond_receive() {
+    let (rqst, resp) = channel::<u32>();
+
+    let mut contract = rqst.try_request().ok().unwrap();
+
+    match contract.try_receive() {
+        Err(Error::Empty) => {},
+        _ => { assert!(false); },
+    }
+
+    resp.try_respond().ok().unwrap().send(5);
+    assert_eq!(contract.try_receive().ok().unwrap(), 5);
+}
+
+#[wasm_bindgen_test]
+fn test_cancel_then_request_again() {
+    let (rqst, resp) = channel::<u32>();
+
+    let mut contract = rqst.try_request().ok().unwrap();
+    contract.try_cancel().ok().unwrap();
+    drop(contract);
+
+    match resp.try_respond() {
+        Err(Error::NoRequest) => {},
+        _ => { assert!(false); },
+    }
+
+    let mut contract = rqst.try_request().ok().unwrap();
+    resp.clone().try_respond().ok().unwrap().send(6);
+    assert_eq!(contract.try_receive().ok().unwrap(), 6);
+}